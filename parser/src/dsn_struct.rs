@@ -14,9 +14,18 @@ pub struct Layer {
 
 pub struct Boundary(pub Vec<FloatVec2>);
 
+/// a copper pour of a net, the area is filled after routing
+pub struct Plane {
+    pub net_name: String,
+    pub layer_name: String,
+    pub points: Vec<FloatVec2>,
+}
+
 pub struct Structure {
     pub layers: Vec<Layer>,
     pub boundary: Boundary,
+    pub vias: Vec<String>, // padstack names of the vias the router may use
+    pub planes: Vec<Plane>,
 }
 pub enum PlacementLayer {
     Front,
@@ -79,6 +88,7 @@ pub struct PadStack {
     pub name: String,
     pub shape: Shape,
     pub through_hole: bool,
+    pub layers: Vec<String>, // the layer of every shape, a via spans from the first to the last
 }
 
 pub struct Library {
@@ -104,9 +114,25 @@ pub struct Net {
     pub pins: Vec<Pin2>,
 }
 
+pub struct Pair {
+    pub positive_net: String,
+    pub negative_net: String,
+    pub gap: Option<f32>, // falls back to the clearance of the netclass
+}
+
+pub struct LengthGroup {
+    pub name: String,
+    pub net_names: Vec<String>,
+    pub max_length: Option<f32>,
+    pub min_length: Option<f32>,
+    pub tolerance: Option<f32>, // the nets are matched to each other only if this is given
+}
+
 pub struct Network {
     pub nets: Vec<Net>,
     pub netclasses: HashMap<String, Netclass>,
+    pub pairs: Vec<Pair>,
+    pub length_groups: Vec<LengthGroup>,
 }
 
 pub struct DsnStruct {
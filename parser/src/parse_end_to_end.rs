@@ -15,6 +15,7 @@ pub fn parse_struct_to_end(dsn_struct: &DsnStruct) -> Result<PcbProblem, String>
     let display_format = dsn_to_display(dsn_struct)?;
     let extra_info = ExtraInfo {
         net_name_to_source_pad: HashMap::new(),
+        zone_net_layers: Vec::new(),
     };
     let pcb_problem = Converter::convert(&display_format, &extra_info)?;
     Ok(pcb_problem)
@@ -133,6 +134,7 @@ pub fn parse_end_to_end(dsn_file_content: String) -> Result<PcbProblem, String>
     let display_format = dsn_to_display(&dsn_struct)?;
     let extra_info = ExtraInfo {
         net_name_to_source_pad: HashMap::new(),
+        zone_net_layers: Vec::new(),
     };
     let pcb_problem = Converter::convert(&display_format, &extra_info)?;
     Ok(pcb_problem)
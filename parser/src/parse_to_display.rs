@@ -8,10 +8,14 @@ use cgmath::{Deg, Matrix2, Rad, Vector2};
 use core::{f32, net};
 use shared::collider::PolygonCollider;
 use shared::pad::{Pad, PadLayer, PadName, PadShape};
-use shared::pcb_problem::{NetClassName, NetName};
+use shared::hyperparameters::DIFF_PAIR_MAX_UNCOUPLED_LENGTH;
+use shared::pcb_problem::{DiffPair, LengthConstraint, NetClassName, NetName};
 use shared::prim_shape::Line;
 use shared::vec2::{FixedVec2, FloatVec2};
-use std::collections::HashMap;
+use shared::via_type::{ViaKind, ViaType};
+use shared::zone::Zone;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
 
 fn calculate_boundary_and_scale(
     boundary: &Boundary,
@@ -416,6 +420,56 @@ fn pins_to_pads_and_scale(
 //         .ok_or_else(|| format!("Net '{}' doesn't belong to any netclass", net_name))
 // }
 
+/// the span of the via is taken from the layers of its shapes, layers that are not signal layers by name mean all of them
+/// a via that spans less than two layers, with all its shapes on one layer, is no usable via and gives None
+fn via_type_and_scale(
+    dsn: &DsnStruct,
+    via_name: &String,
+    clearance: f32,
+    scale_down_factor: f32,
+) -> Result<Option<ViaType>, String> {
+    let pad_stack = dsn
+        .library
+        .pad_stacks
+        .get(via_name)
+        .ok_or_else(|| format!("Via '{}' not found", via_name))?;
+    let diameter = match &pad_stack.shape {
+        Shape::Circle { diameter } => *diameter,
+        _ => {
+            return Err(format!("Invalid via '{}': not circular", via_name));
+        }
+    };
+    let layer_names = dsn.get_layer_names();
+    let num_layers = layer_names.len();
+    let mut min_layer = num_layers - 1;
+    let mut max_layer = 0;
+    for layer_name in pad_stack.layers.iter() {
+        match layer_names.iter().position(|name| name == layer_name) {
+            Some(layer) => {
+                min_layer = usize::min(min_layer, layer);
+                max_layer = usize::max(max_layer, layer);
+            }
+            None => {
+                min_layer = 0;
+                max_layer = num_layers - 1;
+            }
+        }
+    }
+    if min_layer >= max_layer {
+        return Ok(None);
+    }
+    let mut via_type = ViaType {
+        name: via_name.clone(),
+        min_layer,
+        max_layer,
+        diameter: diameter / scale_down_factor,
+        clearance,
+        cost: 0.0,
+    };
+    via_type.cost = ViaType::default_cost(via_type.kind(num_layers));
+    Ok(Some(via_type))
+}
+
 fn parse_net_info_and_scale(
     dsn: &DsnStruct,
     scale_down_factor: f32,
@@ -427,28 +481,33 @@ fn parse_net_info_and_scale(
             net_to_net_class.insert(net_name.clone(), netclass);
         }
     }
-    let mut net_to_via_diameter_scaled: HashMap<String, f32> = HashMap::new();
+    let mut net_to_via_types: HashMap<String, Vec<ViaType>> = HashMap::new();
     for (net_name, netclass) in &net_to_net_class {
-        let pad_stack = dsn
-            .library
-            .pad_stacks
-            .get(&netclass.via_name)
-            .ok_or_else(|| {
-                format!(
-                    "Via '{}' not found for net '{}'",
-                    netclass.via_name, net_name
-                )
-            })?;
-        let via_diameter = match &pad_stack.shape {
-            Shape::Circle { diameter } => *diameter as f32,
-            _ => {
-                return Err(format!(
-                    "Invalid via '{}' for net '{}': not circular",
-                    netclass.via_name, net_name
-                ));
+        let clearance = netclass.clearance / scale_down_factor;
+        // the netclass via first, then the blind, buried and micro vias the structure allows
+        let mut via_names: Vec<&String> = vec![&netclass.via_name];
+        for via_name in dsn.structure.vias.iter() {
+            if !via_names.contains(&via_name) {
+                via_names.push(via_name);
             }
-        };
-        net_to_via_diameter_scaled.insert(net_name.clone(), via_diameter / scale_down_factor);
+        }
+        let mut via_types: Vec<ViaType> = Vec::new();
+        for via_name in via_names {
+            let Some(via_type) = via_type_and_scale(dsn, via_name, clearance, scale_down_factor)
+                .map_err(|e| format!("{} for net '{}'", e, net_name))?
+            else {
+                continue;
+            };
+            // a second through via would only duplicate the first one
+            let num_layers = dsn.structure.layers.len();
+            let is_through = |via_type: &ViaType| via_type.kind(num_layers) == ViaKind::Through;
+            if !is_through(&via_type) || !via_types.iter().any(is_through) {
+                via_types.push(via_type);
+            }
+        }
+        // the router takes the first via type that fits, so the cheapest comes first
+        via_types.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        net_to_via_types.insert(net_name.clone(), via_types);
     }
     for all_nets in dsn.network.nets.iter() {
         let net_class = net_to_net_class
@@ -456,9 +515,9 @@ fn parse_net_info_and_scale(
             .ok_or_else(|| format!("Net '{}' doesn't belong to any netclass", all_nets.name))?;
         let net_name = all_nets.name.clone();
         let pads = pins_to_pads_and_scale(&all_nets.pins, &dsn, scale_down_factor)?;
-        let via_diameter_scaled = *net_to_via_diameter_scaled
-            .get(&net_name)
-            .ok_or_else(|| format!("Via diameter not found for net '{}'", net_name))?;
+        let via_types = net_to_via_types
+            .remove(&net_name)
+            .ok_or_else(|| format!("Via types not found for net '{}'", net_name))?;
         net_info.insert(
             NetName(net_name.clone()),
             DisplayNetInfo {
@@ -467,13 +526,140 @@ fn parse_net_info_and_scale(
                 net_class_name: NetClassName(net_class.net_class_name.clone()),
                 default_trace_width: net_class.width / scale_down_factor,
                 default_trace_clearance: net_class.clearance / scale_down_factor,
-                via_diameter: via_diameter_scaled,
+                via_types,
             },
         );
     }
     Ok(net_info)
 }
 
+/// pairs declared in the network come first, the remaining nets are paired by the _P/_N suffix of their names
+fn parse_diff_pairs_and_scale(
+    dsn: &DsnStruct,
+    net_info: &HashMap<NetName, DisplayNetInfo>,
+    scale_down_factor: f32,
+) -> Result<Vec<DiffPair>, String> {
+    let max_uncoupled_length = DIFF_PAIR_MAX_UNCOUPLED_LENGTH.load(Ordering::Relaxed) as f32;
+    let mut diff_pairs: Vec<DiffPair> = Vec::new();
+    let mut paired_nets: HashSet<NetName> = HashSet::new();
+    for pair in dsn.network.pairs.iter() {
+        let positive_net = NetName(pair.positive_net.clone());
+        let negative_net = NetName(pair.negative_net.clone());
+        let positive_net_info = net_info
+            .get(&positive_net)
+            .ok_or_else(|| format!("Net '{}' of a pair not found", pair.positive_net))?;
+        if !net_info.contains_key(&negative_net) {
+            return Err(format!("Net '{}' of a pair not found", pair.negative_net));
+        }
+        if paired_nets.contains(&positive_net) || paired_nets.contains(&negative_net) {
+            return Err(format!(
+                "Net '{}' or '{}' belongs to more than one pair",
+                pair.positive_net, pair.negative_net
+            ));
+        }
+        let gap = match pair.gap {
+            Some(gap) => gap / scale_down_factor,
+            None => positive_net_info.default_trace_clearance,
+        };
+        paired_nets.insert(positive_net.clone());
+        paired_nets.insert(negative_net.clone());
+        diff_pairs.push(DiffPair {
+            positive_net,
+            negative_net,
+            gap,
+            max_uncoupled_length,
+        });
+    }
+    let mut net_names: Vec<&NetName> = net_info.keys().collect();
+    net_names.sort();
+    for positive_net in net_names {
+        let base_name = match positive_net.0.strip_suffix("_P") {
+            Some(base_name) => base_name,
+            None => continue,
+        };
+        let negative_net = NetName(format!("{}_N", base_name));
+        if !net_info.contains_key(&negative_net)
+            || paired_nets.contains(positive_net)
+            || paired_nets.contains(&negative_net)
+        {
+            continue;
+        }
+        paired_nets.insert(positive_net.clone());
+        paired_nets.insert(negative_net.clone());
+        diff_pairs.push(DiffPair {
+            positive_net: positive_net.clone(),
+            negative_net,
+            gap: net_info[positive_net].default_trace_clearance,
+            max_uncoupled_length,
+        });
+    }
+    Ok(diff_pairs)
+}
+
+fn parse_length_constraints_and_scale(
+    dsn: &DsnStruct,
+    net_info: &HashMap<NetName, DisplayNetInfo>,
+    scale_down_factor: f32,
+) -> Result<Vec<LengthConstraint>, String> {
+    let mut length_constraints: Vec<LengthConstraint> = Vec::new();
+    for length_group in dsn.network.length_groups.iter() {
+        let mut net_names: Vec<NetName> = Vec::new();
+        for net_name in length_group.net_names.iter() {
+            let net_name = NetName(net_name.clone());
+            if !net_info.contains_key(&net_name) {
+                return Err(format!(
+                    "Net '{}' of length group {} not found",
+                    net_name.0, length_group.name
+                ));
+            }
+            net_names.push(net_name);
+        }
+        let min_length = length_group.min_length.unwrap_or(0.0) / scale_down_factor;
+        let max_length = length_group.max_length.map(|max_length| max_length / scale_down_factor);
+        if let Some(max_length) = max_length
+            && max_length < min_length
+        {
+            return Err(format!(
+                "Length group {} has a max length below its min length",
+                length_group.name
+            ));
+        }
+        length_constraints.push(LengthConstraint {
+            name: length_group.name.clone(),
+            net_names,
+            min_length,
+            max_length,
+            match_tolerance: length_group.tolerance.map(|tolerance| tolerance / scale_down_factor),
+        });
+    }
+    Ok(length_constraints)
+}
+
+fn parse_zones_and_scale(
+    dsn: &DsnStruct,
+    net_info: &HashMap<NetName, DisplayNetInfo>,
+    scale_down_factor: f32,
+) -> Result<Vec<Zone>, String> {
+    let layer_names = dsn.get_layer_names();
+    let mut zones: Vec<Zone> = Vec::new();
+    for plane in dsn.structure.planes.iter() {
+        let net_name = NetName(plane.net_name.clone());
+        if !net_info.contains_key(&net_name) {
+            return Err(format!("Net '{}' of a plane not found", net_name.0));
+        }
+        let layer = layer_names
+            .iter()
+            .position(|layer_name| *layer_name == plane.layer_name)
+            .ok_or_else(|| format!("Layer '{}' of the plane of net {} not found", plane.layer_name, net_name.0))?;
+        zones.push(Zone {
+            net_name,
+            layer,
+            outline: plane.points.iter().map(|point| *point / scale_down_factor).collect(),
+        });
+    }
+    Ok(zones)
+}
+
 pub fn dsn_to_display(dsn: &DsnStruct) -> Result<DisplayFormat, String> {
     let unit = &dsn.resolution.unit;
     let scale_down_factor: f32 = match unit.as_str() {
@@ -493,6 +679,9 @@ pub fn dsn_to_display(dsn: &DsnStruct) -> Result<DisplayFormat, String> {
     let obstacle_polygons: Vec<PolygonCollider> = Vec::new();
     let net_info: HashMap<NetName, DisplayNetInfo> =
         parse_net_info_and_scale(&dsn, scale_down_factor)?;
+    let diff_pairs = parse_diff_pairs_and_scale(dsn, &net_info, scale_down_factor)?;
+    let length_constraints = parse_length_constraints_and_scale(dsn, &net_info, scale_down_factor)?;
+    let zones = parse_zones_and_scale(dsn, &net_info, scale_down_factor)?;
 
     let display_format = DisplayFormat {
         width,
@@ -502,6 +691,9 @@ pub fn dsn_to_display(dsn: &DsnStruct) -> Result<DisplayFormat, String> {
         obstacle_lines,
         obstacle_polygons,
         nets: net_info,
+        diff_pairs,
+        length_constraints,
+        zones,
         scale_down_factor,
     };
     Ok(display_format)
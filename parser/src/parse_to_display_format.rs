@@ -3,9 +3,11 @@ use std::collections::HashMap;
 use shared::{
     collider::PolygonCollider,
     pad::{Pad, PadName},
-    pcb_problem::{NetClassName, NetName},
+    pcb_problem::{DiffPair, LengthConstraint, NetClassName, NetName},
     prim_shape::Line,
     vec2::FloatVec2,
+    via_type::ViaType,
+    zone::Zone,
 };
 
 pub struct DisplayNetInfo {
@@ -16,7 +18,7 @@ pub struct DisplayNetInfo {
     // unwrap netclass information to each net for convenience
     pub default_trace_width: f32, // may be overridden by individual pads in the next pass
     pub default_trace_clearance: f32, // may be overridden by individual pads in the next pass
-    pub via_diameter: f32,        // obtained from via name, and accessed through padstacks
+    pub via_types: Vec<ViaType>,  // the netclass via plus the vias listed in the structure, cheapest first
 }
 
 pub struct DisplayFormat {
@@ -27,10 +29,15 @@ pub struct DisplayFormat {
     pub obstacle_lines: Vec<Line>,               // Lines that represent obstacles in the PCB
     pub obstacle_polygons: Vec<PolygonCollider>, // Polygons that represent obstacles in the PCB
     pub nets: HashMap<NetName, DisplayNetInfo>,  // NetID to DisplayNetInfo
+    pub diff_pairs: Vec<DiffPair>,               // declared in the network or matched by the _P/_N naming rule
+    pub length_constraints: Vec<LengthConstraint>, // declared as length groups in the network
+    pub zones: Vec<Zone>,                        // declared as planes in the structure
     pub scale_down_factor: f32, // Scale down factor to convert specctra dsn units to float units
 }
 
 pub struct ExtraInfo {
     // for nets with 3 or more pads, choose the pad specified below as the source pad. If it's not specified, generate a warning and choose the first one.
     pub net_name_to_source_pad: HashMap<NetName, PadName>, // net name to source pad name
+    // nets to pour on whole layers, a zone over the board outline is added for every pair besides the planes of the DSN
+    pub zone_net_layers: Vec<(NetName, usize)>,
 }
@@ -3,6 +3,7 @@ use shared::{
     pad::{Pad, PadName},
     pcb_problem::{Connection, ConnectionID, NetInfo, NetName, PcbProblem},
     vec2::FloatVec2,
+    zone::Zone,
 };
 
 // convert_to_problem.rs
@@ -10,7 +11,7 @@ use crate::{
     parse_to_display_format::{DisplayFormat, ExtraInfo},
     prim_mst::prim_mst,
 };
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
 
 pub struct Converter;
 
@@ -59,7 +60,7 @@ impl Converter {
                 prim_mst(pad_positions)
             };
 
-            let mut connections: HashMap<ConnectionID, Rc<Connection>> = HashMap::new();
+            let mut connections: HashMap<ConnectionID, Arc<Connection>> = HashMap::new();
             for (start, end) in connection_pairs.iter() {
                 let connection_id = connection_id_generator.next().unwrap();
                 let connection = Connection {
@@ -68,7 +69,7 @@ impl Converter {
                     start_pad: start.clone(),
                     end_pad: end.clone(),
                 };
-                connections.insert(connection_id, Rc::new(connection));
+                connections.insert(connection_id, Arc::new(connection));
             }
             let color = distinct_color_generator.next().unwrap();
             let net_info = NetInfo {
@@ -77,11 +78,34 @@ impl Converter {
                 pads,
                 trace_width: display_net.default_trace_width,
                 trace_clearance: display_net.default_trace_clearance,
-                via_diameter: display_net.via_diameter,
+                via_types: display_net.via_types.clone(),
                 connections,
             };
             nets.insert(net_name.clone(), net_info);
         }
+        let mut zones: Vec<Zone> = display_format.zones.clone();
+        for (net_name, layer) in extra_info.zone_net_layers.iter() {
+            if !nets.contains_key(net_name) {
+                return Err(format!("Net '{}' of a zone not found", net_name.0));
+            }
+            if *layer >= display_format.num_layers {
+                return Err(format!("Layer {} of the zone of net {} not found", layer, net_name.0));
+            }
+            // the zone covers the whole board
+            let half_width = display_format.width / 2.0;
+            let half_height = display_format.height / 2.0;
+            let center = display_format.center;
+            zones.push(Zone {
+                net_name: net_name.clone(),
+                layer: *layer,
+                outline: vec![
+                    FloatVec2::new(center.x - half_width, center.y - half_height),
+                    FloatVec2::new(center.x + half_width, center.y - half_height),
+                    FloatVec2::new(center.x + half_width, center.y + half_height),
+                    FloatVec2::new(center.x - half_width, center.y + half_height),
+                ],
+            });
+        }
         let problem = PcbProblem {
             width: display_format.width,
             height: display_format.height,
@@ -91,6 +115,9 @@ impl Converter {
             obstacle_border_outlines: Vec::new(),
             obstacle_polygons: Vec::new(),
             nets, // netname, netinfo
+            diff_pairs: display_format.diff_pairs.clone(),
+            length_constraints: display_format.length_constraints.clone(),
+            zones,
             //connection_id_generator: Box::new((0..).map(ConnectionID)),
             scale_down_factor: display_format.scale_down_factor,
         };
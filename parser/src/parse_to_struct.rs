@@ -6,8 +6,8 @@ use shared::vec2::FloatVec2;
 
 use crate::{
     dsn_struct::{
-        Boundary, Component, ComponentInst, DsnStruct, Image, Layer, Library, Net, Netclass,
-        Network, PadStack, Pin, Pin2, Placement, PlacementLayer, Resolution, Shape, Structure,
+        Boundary, Component, ComponentInst, DsnStruct, Image, Layer, LengthGroup, Library, Net, Netclass,
+        Network, PadStack, Pair, Pin, Pin2, Placement, Plane, PlacementLayer, Resolution, Shape, Structure,
     },
     s_expr::SExpr,
 };
@@ -93,6 +93,62 @@ fn parse_boundary(s_expr: &Vec<SExpr>) -> Result<Boundary, String> {
     Ok(boundary)
 }
 
+fn parse_plane(s_expr: &Vec<SExpr>) -> Result<Plane, String> {
+    // (plane GND (polygon B.Cu 0  x1 y1 x2 y2 ...))
+    let net_name = s_expr
+        .get(1)
+        .ok_or("Expected net name as the second item of the plane")?
+        .as_atom()
+        .ok_or("Plane net name must be an atom")?
+        .to_string();
+    let polygon = s_expr
+        .get(2)
+        .ok_or("Expected a polygon as the third item of the plane")?
+        .as_list()
+        .ok_or("Expected a list as the third item of the plane")?;
+    let shape_type = polygon
+        .first()
+        .and_then(|x| x.as_atom())
+        .ok_or("Expected the shape type of the plane")?;
+    if shape_type != "polygon" {
+        return Err(format!("Unsupported plane shape: {}", shape_type));
+    }
+    let layer_name = polygon
+        .get(1)
+        .ok_or("Expected the layer of the plane polygon")?
+        .as_atom()
+        .ok_or("Plane layer must be an atom")?
+        .to_string();
+    let numbers = polygon
+        .iter()
+        .skip(3)
+        .map(|item| {
+            item.as_atom()
+                .ok_or("Expected an atom in the plane polygon".to_string())?
+                .parse::<f32>()
+                .map_err(|e| format!("Failed to parse plane polygon number: {}", e))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    if numbers.len() % 2 == 1 {
+        return Err("Expected an even number of items in the plane polygon".to_string());
+    }
+    let points: Vec<FloatVec2> = numbers
+        .chunks(2)
+        .map(|chunk| FloatVec2 {
+            x: chunk[0],
+            y: chunk[1],
+        })
+        .collect();
+    if points.len() < 3 {
+        return Err(format!("Plane of net {} has less than three points", net_name));
+    }
+    Ok(Plane {
+        net_name,
+        layer_name,
+        points,
+    })
+}
+
 fn parse_structure(s_expr: &Vec<SExpr>) -> Result<Structure, String> {
     // Placeholder for structure parsing logic
     // This function should parse the structure part of the S-expression
@@ -111,6 +167,8 @@ fn parse_structure(s_expr: &Vec<SExpr>) -> Result<Structure, String> {
     }
     let mut layers: Vec<Layer> = Vec::new();
     let mut boundary: Option<Boundary> = None;
+    let mut vias: Vec<String> = Vec::new();
+    let mut planes: Vec<Plane> = Vec::new();
     for item in s_expr.iter().skip(1) {
         let expr_list = item.as_list().ok_or(format!(
             "Expected a list in the structure scope, found: {:?}",
@@ -131,13 +189,19 @@ fn parse_structure(s_expr: &Vec<SExpr>) -> Result<Structure, String> {
                 boundary = Some(parse_boundary(expr_list)?);
             }
             "via" => {
-                continue;
+                // (via "Via[0-1]_800:400_um" "Via[1-2]_300:150_um" ...)
+                for via_name in expr_list.iter().skip(1) {
+                    let via_name = via_name
+                        .as_atom()
+                        .ok_or("Expected via names to be atoms")?;
+                    vias.push(via_name.to_string());
+                }
             }
             "rule" => {
                 continue;
             }
             "plane" => {
-                continue;
+                planes.push(parse_plane(expr_list)?);
             }
             "keepout" => {
                 continue; // to do
@@ -148,7 +212,12 @@ fn parse_structure(s_expr: &Vec<SExpr>) -> Result<Structure, String> {
         }
     }
     let boundary = boundary.ok_or("Expected a boundary in the structure scope")?;
-    Ok(Structure { layers, boundary })
+    Ok(Structure {
+        layers,
+        boundary,
+        vias,
+        planes,
+    })
 }
 
 fn parse_placement(s_expr: &Vec<SExpr>) -> Result<Placement, String> {
@@ -525,6 +594,7 @@ fn parse_padstack(s_expr: &Vec<SExpr>) -> Result<PadStack, String> {
 
     let mut shapes = None;
     let mut shape_num = 0;
+    let mut layers: Vec<String> = Vec::new();
     for item in s_expr.iter().skip(2) {
         let expr_list = item.as_list().ok_or(format!(
             "Expected a list in the padstack scope, found: {:?}",
@@ -539,6 +609,14 @@ fn parse_padstack(s_expr: &Vec<SExpr>) -> Result<PadStack, String> {
         match first_item.as_str() {
             "shape" => {
                 shape_num += 1;
+                // (shape (circle F.Cu 800))
+                let layer = expr_list
+                    .get(1)
+                    .and_then(|shape| shape.as_list())
+                    .and_then(|shape| shape.get(1))
+                    .and_then(|layer| layer.as_atom())
+                    .ok_or("Expected a layer name in the padstack shape")?;
+                layers.push(layer.to_string());
                 if shapes.is_none() {
                     shapes = Some(parse_shape(expr_list)?);
                 }
@@ -557,6 +635,7 @@ fn parse_padstack(s_expr: &Vec<SExpr>) -> Result<PadStack, String> {
         name: padstack_name,
         shape,
         through_hole,
+        layers,
     })
 }
 
@@ -783,6 +862,141 @@ fn parse_netclass(s_expr: &Vec<SExpr>) -> Result<Netclass, String> {
     })
 }
 
+fn parse_pair(s_expr: &Vec<SExpr>) -> Result<Pair, String> {
+    // (pair (nets "USB_D_P" "USB_D_N") (rule (gap 150)))
+    let mut net_names: Vec<String> = Vec::new();
+    let mut gap: Option<f32> = None;
+    for item in s_expr.iter().skip(1) {
+        if let SExpr::List(list) = item {
+            match list.first().and_then(|x| x.as_atom()).map(|s| s.as_str()) {
+                Some("nets") => {
+                    for net_name in list.iter().skip(1) {
+                        let net_name = net_name
+                            .as_atom()
+                            .ok_or("Net name in pair must be an atom")?;
+                        net_names.push(net_name.to_string());
+                    }
+                }
+                Some("rule") => {
+                    for rule_item in list.iter().skip(1) {
+                        if let Some(rule_list) = rule_item.as_list() {
+                            if rule_list.first().and_then(|x| x.as_atom()).map(|s| s.as_str())
+                                == Some("gap")
+                            {
+                                let temp_gap = rule_list
+                                    .get(1)
+                                    .ok_or("Missing gap value")?
+                                    .as_atom()
+                                    .ok_or("Gap must be a number")?
+                                    .parse::<f32>()
+                                    .map_err(|e| format!("Invalid gap: {}", e))?;
+                                gap = Some(temp_gap);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if net_names.len() != 2 {
+        return Err(format!(
+            "Expected exactly two nets in a pair, found: {}",
+            net_names.len()
+        ));
+    }
+    let negative_net = net_names.pop().unwrap();
+    let positive_net = net_names.pop().unwrap();
+    Ok(Pair {
+        positive_net,
+        negative_net,
+        gap,
+    })
+}
+
+fn parse_length_group(s_expr: &Vec<SExpr>) -> Result<LengthGroup, String> {
+    // (length_group DDR_DQ (nets "DQ0" "DQ1" "DQ2") (rule (length 60000 40000) (tolerance 250)))
+    // a max length of -1 means no maximum
+    let name = s_expr
+        .get(1)
+        .ok_or("Expected length group name as the second item")?
+        .as_atom()
+        .ok_or("Length group name must be an atom")?
+        .to_string();
+    let mut net_names: Vec<String> = Vec::new();
+    let mut max_length: Option<f32> = None;
+    let mut min_length: Option<f32> = None;
+    let mut tolerance: Option<f32> = None;
+    for item in s_expr.iter().skip(2) {
+        if let SExpr::List(list) = item {
+            match list.first().and_then(|x| x.as_atom()).map(|s| s.as_str()) {
+                Some("nets") => {
+                    for net_name in list.iter().skip(1) {
+                        let net_name = net_name
+                            .as_atom()
+                            .ok_or("Net name in length group must be an atom")?;
+                        net_names.push(net_name.to_string());
+                    }
+                }
+                Some("rule") => {
+                    for rule_item in list.iter().skip(1) {
+                        if let Some(rule_list) = rule_item.as_list() {
+                            match rule_list
+                                .first()
+                                .and_then(|x| x.as_atom())
+                                .map(|s| s.as_str())
+                            {
+                                Some("length") => {
+                                    let temp_max_length = rule_list
+                                        .get(1)
+                                        .ok_or("Missing max length value")?
+                                        .as_atom()
+                                        .ok_or("Max length must be a number")?
+                                        .parse::<f32>()
+                                        .map_err(|e| format!("Invalid max length: {}", e))?;
+                                    if temp_max_length >= 0.0 {
+                                        max_length = Some(temp_max_length);
+                                    }
+                                    if let Some(temp_min_length) = rule_list.get(2) {
+                                        let temp_min_length = temp_min_length
+                                            .as_atom()
+                                            .ok_or("Min length must be a number")?
+                                            .parse::<f32>()
+                                            .map_err(|e| format!("Invalid min length: {}", e))?;
+                                        min_length = Some(temp_min_length);
+                                    }
+                                }
+                                Some("tolerance") => {
+                                    let temp_tolerance = rule_list
+                                        .get(1)
+                                        .ok_or("Missing tolerance value")?
+                                        .as_atom()
+                                        .ok_or("Tolerance must be a number")?
+                                        .parse::<f32>()
+                                        .map_err(|e| format!("Invalid tolerance: {}", e))?;
+                                    tolerance = Some(temp_tolerance);
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if net_names.is_empty() {
+        return Err(format!("Length group {} has no nets", name));
+    }
+    Ok(LengthGroup {
+        name,
+        net_names,
+        max_length,
+        min_length,
+        tolerance,
+    })
+}
+
 fn parse_network(s_expr: &Vec<SExpr>) -> Result<Network, String> {
     // Placeholder for network parsing logic
     // This function should parse the network part of the S-expression
@@ -803,6 +1017,8 @@ fn parse_network(s_expr: &Vec<SExpr>) -> Result<Network, String> {
 
     let mut nets: Vec<Net> = Vec::new();
     let mut netclasses: HashMap<String, Netclass> = HashMap::new();
+    let mut pairs: Vec<Pair> = Vec::new();
+    let mut length_groups: Vec<LengthGroup> = Vec::new();
 
     for item in s_expr.iter().skip(1) {
         let expr_list = item.as_list().ok_or(format!(
@@ -824,13 +1040,26 @@ fn parse_network(s_expr: &Vec<SExpr>) -> Result<Network, String> {
                 let netclass = parse_netclass(expr_list)?;
                 netclasses.insert(netclass.net_class_name.clone(), netclass);
             }
+            "pair" => {
+                let pair = parse_pair(expr_list)?;
+                pairs.push(pair);
+            }
+            "length_group" => {
+                let length_group = parse_length_group(expr_list)?;
+                length_groups.push(length_group);
+            }
             _ => {
                 return Err(format!("Unknown network item: {}", first_item));
             }
         }
     }
 
-    Ok(Network { nets, netclasses })
+    Ok(Network {
+        nets,
+        netclasses,
+        pairs,
+        length_groups,
+    })
 }
 
 pub fn parse_s_expr_to_struct(s_expr: &SExpr) -> Result<DsnStruct, String> {
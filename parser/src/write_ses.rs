@@ -40,25 +40,17 @@ fn generate_placement(dsn: &DsnStruct) -> Result<String, String> {
 pub struct ViaSES {
     name: String,
     shape: String,
-    through_hole: bool,
+    layers: Vec<String>, // one shape per layer the via spans
     diameter: f32,
 }
 
 impl ViaSES {
-    fn to_ses_string(&self, layers: &[String]) -> String {
+    fn to_ses_string(&self) -> String {
         let shape = &self.shape;
         let dia_int = self.diameter.round() as i32;
         let mut s = format!("      (padstack \"{}\"\n", self.name);
 
-        if self.through_hole {
-            for layer in layers {
-                s += &format!(
-                    "        (shape\n          ({} {} {} 0 0)\n        )\n",
-                    shape, layer, dia_int
-                );
-            }
-        } else {
-            let layer = &layers[0];
+        for layer in &self.layers {
             s += &format!(
                 "        (shape\n          ({} {} {} 0 0)\n        )\n",
                 shape, layer, dia_int
@@ -72,16 +64,24 @@ impl ViaSES {
 }
 
 fn via_info(dsn: &DsnStruct) -> Vec<ViaSES> {
+    let layer_names = dsn.get_layer_names();
     dsn.library
         .pad_stacks
         .iter()
         .filter_map(|(name, pad)| {
-            if name.starts_with("Via") {
+            // blind, buried and micro vias are not always named after the kicad convention
+            if name.starts_with("Via") || dsn.structure.vias.contains(name) {
                 if let Shape::Circle { diameter } = pad.shape {
+                    // a layer that is not a signal layer by name stands for all of them
+                    let layers = if pad.layers.iter().all(|layer| layer_names.contains(layer)) {
+                        pad.layers.clone()
+                    } else {
+                        layer_names.clone()
+                    };
                     Some(ViaSES {
                         name: name.clone(),
                         shape: "circle".to_string(),
-                        through_hole: pad.through_hole,
+                        layers,
                         diameter,
                     })
                 } else {
@@ -98,15 +98,6 @@ fn extract_fixed_vec2(v: &FixedVec2) -> (f32, f32) {
     (v.x.to_num::<f32>(), v.y.to_num::<f32>())
 }
 
-fn find_via_name(netname: &String, dsn: &DsnStruct) -> Option<String> {
-    for netclass in dsn.network.netclasses.values() {
-        if netclass.net_names.contains(netname) {
-            return Some(netclass.via_name.clone());
-        }
-    }
-    None
-}
-
 fn generate_network(
     dsn: &DsnStruct,
     solution: &PcbSolution,
@@ -122,15 +113,18 @@ fn generate_network(
     for trace in solution.determined_traces.values() {
         nets.entry(&trace.net_name.0).or_default().push(trace);
     }
+    // a net can have a zone fill without any trace
+    for zone_fill in solution.zone_fills.iter() {
+        nets.entry(&zone_fill.net_name.0).or_default();
+    }
 
     for (net_name, traces) in nets {
         writeln!(ses, "  (net \"{}\"", net_name).unwrap();
-        let via_name = find_via_name(&net_name, &dsn).unwrap_or("default_via".to_string());
 
         for trace in traces {
             for via in &trace.trace_path.vias {
                 let (x, y) = extract_fixed_vec2(&via.position);
-                writeln!(ses, "    (via {} {} {})", via_name, x * scale_down_factor, y * scale_down_factor).unwrap();
+                writeln!(ses, "    (via {} {} {})", via.padstack, x * scale_down_factor, y * scale_down_factor).unwrap();
             }
             for segment in &trace.trace_path.segments {
                 let (start_x, start_y) = extract_fixed_vec2(&segment.start);
@@ -147,6 +141,68 @@ fn generate_network(
                     end_y * scale_down_factor
                 ).unwrap();
             }
+            // a qarc goes counterclockwise from its first point to its second
+            for arc in &trace.trace_path.arcs {
+                let (first, second) = if arc.counterclockwise { (arc.start, arc.end) } else { (arc.end, arc.start) };
+                let (first_x, first_y) = extract_fixed_vec2(&first);
+                let (second_x, second_y) = extract_fixed_vec2(&second);
+                let layer_name = layers[arc.layer].as_str();
+                writeln!(
+                    ses,
+                    "        (wire\n          (qarc {} {}\n            {} {}\n            {} {}\n            {} {}))",
+                    layer_name,
+                    arc.width * scale_down_factor,
+                    first_x * scale_down_factor,
+                    first_y * scale_down_factor,
+                    second_x * scale_down_factor,
+                    second_y * scale_down_factor,
+                    arc.center.x * scale_down_factor,
+                    arc.center.y * scale_down_factor
+                ).unwrap();
+            }
+        }
+        // teardrops are plain copper polygons of the net, with no aperture
+        for teardrop in solution.teardrops.iter().filter(|teardrop| teardrop.net_name.0 == *net_name) {
+            let layer_name = layers[teardrop.layer].as_str();
+            write!(ses, "        (wire\n          (polygon {} 0", layer_name).unwrap();
+            for vertex in teardrop.outline.iter() {
+                write!(
+                    ses,
+                    "\n            {} {}",
+                    vertex.x * scale_down_factor,
+                    vertex.y * scale_down_factor
+                ).unwrap();
+            }
+            writeln!(ses, "))").unwrap();
+        }
+        // zone fills are written as one polygon per island, the holes in it as windows of the wire
+        for zone_fill in solution.zone_fills.iter().filter(|zone_fill| zone_fill.net_name.0 == *net_name) {
+            let layer_name = layers[zone_fill.layer].as_str();
+            for outline in zone_fill.outlines.iter() {
+                write!(ses, "        (wire\n          (polygon {} 0", layer_name).unwrap();
+                for vertex in outline.boundary.iter() {
+                    write!(
+                        ses,
+                        "\n            {} {}",
+                        vertex.x * scale_down_factor,
+                        vertex.y * scale_down_factor
+                    ).unwrap();
+                }
+                write!(ses, ")").unwrap();
+                for hole in outline.holes.iter() {
+                    write!(ses, "\n          (window\n            (polygon {} 0", layer_name).unwrap();
+                    for vertex in hole.iter() {
+                        write!(
+                            ses,
+                            "\n              {} {}",
+                            vertex.x * scale_down_factor,
+                            vertex.y * scale_down_factor
+                        ).unwrap();
+                    }
+                    write!(ses, "))").unwrap();
+                }
+                writeln!(ses, ")").unwrap();
+            }
         }
         writeln!(ses, "    )").unwrap();
     }
@@ -186,7 +242,7 @@ pub fn write_ses_to_string(dsn: &DsnStruct, solution: &PcbSolution) -> Result<St
     writeln!(ses, "    (library_out").unwrap();
     let vias = via_info(&dsn);
     for via in &vias {
-        let via_str = via.to_ses_string(&layer_names);
+        let via_str = via.to_ses_string();
         write!(ses, "{}", via_str).unwrap();
     }
     writeln!(ses, "    )").unwrap();
@@ -0,0 +1,82 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use parser::{
+    parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end},
+    write_ses::write_ses_to_string,
+};
+use router::{
+    command_flags::{CommandFlag, TARGET_COMMAND_LEVEL},
+    display_injection::DisplayInjection,
+    pcb_problem_solve::{resume_pcb_problem, solve_pcb_problem_with_checkpoints, SolverAlgorithm},
+};
+
+const USAGE: &str = "usage: router <board.dsn> [naive|bayesian|negotiated|exact|mcts] [--resume]";
+
+fn parse_algorithm(name: &str) -> Result<SolverAlgorithm, String> {
+    match name {
+        "naive" => Ok(SolverAlgorithm::NaiveBacktrack),
+        "bayesian" => Ok(SolverAlgorithm::BayesianBacktrack),
+        "negotiated" => Ok(SolverAlgorithm::NegotiatedCongestion),
+        "exact" => Ok(SolverAlgorithm::ExactSelection),
+        "mcts" => Ok(SolverAlgorithm::MonteCarloOrder),
+        _ => Err(format!("Unknown algorithm {}\n{}", name, USAGE)),
+    }
+}
+
+/// routes a board from the command line, the same way the desktop app does for a board opened from disk:
+/// the search is checkpointed next to the board, and --resume goes on with the search of that checkpoint
+/// the session is written next to the board as well
+fn run() -> Result<(), String> {
+    let mut board_path: Option<PathBuf> = None;
+    let mut algorithm = SolverAlgorithm::BayesianBacktrack;
+    let mut resume = false;
+    for argument in std::env::args().skip(1) {
+        if argument == "--resume" {
+            resume = true;
+        } else if board_path.is_none() {
+            board_path = Some(PathBuf::from(argument));
+        } else {
+            algorithm = parse_algorithm(&argument)?;
+        }
+    }
+    let board_path = board_path.ok_or(USAGE.to_string())?;
+    let dsn_file_content = std::fs::read_to_string(&board_path)
+        .map_err(|e| format!("Failed to read {}: {}", board_path.to_string_lossy(), e))?;
+    let dsn_struct = parse_start_to_dsn_struct(dsn_file_content)?;
+    let pcb_problem = parse_struct_to_end(&dsn_struct)?;
+
+    // there is no display to step through, the search runs on its own
+    TARGET_COMMAND_LEVEL.store(CommandFlag::Auto.get_level(), Ordering::Relaxed);
+    let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+    let checkpoint_path = board_path.with_extension("checkpoint.json");
+    let solution = if resume {
+        resume_pcb_problem(&pcb_problem, &checkpoint_path, &mut display_injection)?
+    } else {
+        solve_pcb_problem_with_checkpoints(&pcb_problem, algorithm, &checkpoint_path, &mut display_injection)?
+    };
+    println!(
+        "Routed {} connections with seed {}, {} are unrouted",
+        solution.determined_traces.len(),
+        solution.seed,
+        solution.unrouted_connections.len()
+    );
+
+    let ses_path = board_path.with_extension("ses");
+    let ses = write_ses_to_string(&dsn_struct, &solution)?;
+    std::fs::write(&ses_path, ses).map_err(|e| format!("Failed to write {}: {}", ses_path.to_string_lossy(), e))?;
+    println!("Session written to {}", ses_path.to_string_lossy());
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
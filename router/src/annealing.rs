@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::Ordering,
+    time::Instant,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng};
+use shared::{
+    hyperparameters::{
+        ANNEALING_COOLING, ANNEALING_ITERATIONS, ANNEALING_MAX_RIPUP, ANNEALING_START_TEMPERATURE, ANNEALING_TIME_LIMIT,
+        SCORE_CLEARANCE_MARGIN, SCORE_CLEARANCE_WEIGHT, SCORE_LENGTH_WEIGHT, SCORE_VIA_WEIGHT,
+    },
+    pcb_problem::{Connection, ConnectionID, FixedTrace, PcbProblem},
+    trace_path::{TracePath, TraceSegment, Via},
+    trace_scorer::{ClearanceMarginTerm, ScoringContext, TraceScorer},
+};
+
+use crate::{
+    backjumping::blocking_traces,
+    bayesian_backtrack_algo::TraceCache,
+    command_flags::CommandFlag,
+    deterministic_rand::create_deterministic_rng,
+    display_injection::{display_when_necessary, DisplayInjection},
+    obstacles::build_pad_obstacles,
+    pareto_front::ParetoFront,
+    partial_solution::astar_model_for_connection,
+};
+
+/// the global objective, with the weights of the trace scorer
+struct Objective {
+    length_weight: f64,
+    via_weight: f64,
+    clearance_weight: f64,
+    close_pads_term: ClearanceMarginTerm, // built once so the pads of the problem are indexed once
+}
+
+impl Objective {
+    fn from_settings() -> Self {
+        Objective {
+            length_weight: SCORE_LENGTH_WEIGHT.load(Ordering::Relaxed),
+            via_weight: SCORE_VIA_WEIGHT.load(Ordering::Relaxed),
+            clearance_weight: SCORE_CLEARANCE_WEIGHT.load(Ordering::Relaxed),
+            close_pads_term: ClearanceMarginTerm::new(SCORE_CLEARANCE_MARGIN.load(Ordering::Relaxed) as f32),
+        }
+    }
+
+    fn value(&self, metrics: &Metrics) -> f64 {
+        self.length_weight * metrics.total_length
+            + self.via_weight * metrics.num_vias as f64
+            + self.clearance_weight * metrics.num_close_pairs as f64
+    }
+}
+
+/// the objective terms of a set of traces
+#[derive(Debug, Clone, Copy, Default)]
+struct Metrics {
+    total_length: f64,
+    num_vias: usize,
+    num_close_pairs: usize, // pads and traces of other nets within the clearance margin of a trace, a pair of traces counted once
+}
+
+impl Metrics {
+    fn add(&mut self, other: &Metrics) {
+        self.total_length += other.total_length;
+        self.num_vias += other.num_vias;
+        self.num_close_pairs += other.num_close_pairs;
+    }
+}
+
+/// the trace with its clearance grown by the margin, it collides with whatever is closer than the clearance plus the margin
+fn grown_trace(trace_path: &TracePath, margin: f32) -> TracePath {
+    let mut grown_trace = trace_path.clone();
+    grown_trace.segments = trace_path
+        .segments
+        .iter()
+        .map(|segment| TraceSegment { clearance: segment.clearance + margin, ..segment.clone() })
+        .collect();
+    grown_trace.vias = trace_path.vias.iter().map(|via| Via { clearance: via.clearance + margin, ..via.clone() }).collect();
+    grown_trace
+}
+
+/// whether two traces of different nets are within the clearance margin of each other
+fn is_close(fixed_trace: &FixedTrace, other: &FixedTrace, margin: f32) -> bool {
+    fixed_trace.net_name != other.net_name && grown_trace(&fixed_trace.trace_path, margin).collides_with(&other.trace_path)
+}
+
+/// the terms of the traces of the connections, with the close traces that involve them
+fn metrics_of(
+    problem: &PcbProblem,
+    connection_ids: &[ConnectionID],
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    objective: &Objective,
+) -> Metrics {
+    let mut metrics = Metrics::default();
+    for connection_id in connection_ids {
+        let fixed_trace = &fixed_traces[connection_id];
+        let context = ScoringContext { problem, net_name: &fixed_trace.net_name };
+        metrics.add(&Metrics {
+            total_length: fixed_trace.trace_path.total_length,
+            num_vias: fixed_trace.trace_path.vias.len(),
+            num_close_pairs: objective.close_pads_term.raw_score(&fixed_trace.trace_path, &context) as usize,
+        });
+        // a pair within the connections is counted from its smaller id only
+        metrics.num_close_pairs += fixed_traces
+            .iter()
+            .filter(|(other_id, _)| !connection_ids.contains(other_id) || *other_id > connection_id)
+            .filter(|(_, other)| is_close(fixed_trace, other, objective.close_pads_term.margin))
+            .count();
+    }
+    metrics
+}
+
+/// the connections rerouted together, the first at random and the rest among the traces close to it
+fn pick_connections(
+    movable_connections: &[ConnectionID],
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    max_ripup: usize,
+    clearance_margin: f32,
+    rng: &mut StdRng,
+) -> Vec<ConnectionID> {
+    let first = movable_connections[rng.random_range(0..movable_connections.len())];
+    let num_ripped = rng.random_range(1..=max_ripup.clamp(1, movable_connections.len()));
+    let mut neighbours: Vec<ConnectionID> = movable_connections
+        .iter()
+        .filter(|connection_id| **connection_id != first)
+        .filter(|connection_id| is_close(&fixed_traces[&first], &fixed_traces[connection_id], clearance_margin))
+        .cloned()
+        .collect();
+    if neighbours.len() < num_ripped - 1 {
+        neighbours = movable_connections.iter().filter(|connection_id| **connection_id != first).cloned().collect();
+    }
+    neighbours.shuffle(rng);
+    let mut picked = vec![first];
+    picked.extend(neighbours.into_iter().take(num_ripped - 1));
+    picked
+}
+
+/// a new trace for the connection around the fixed traces, a cached trace that fits or a fresh A* trace,
+/// none when neither is found
+fn reroute(
+    problem: &PcbProblem,
+    connection: &Connection,
+    old_trace: &TracePath,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    trace_cache: &mut TraceCache,
+    rng: &mut StdRng,
+    display_injection: &mut DisplayInjection,
+) -> Option<TracePath> {
+    let cached_traces = trace_cache.traces.entry(connection.connection_id).or_default();
+    let fitting_traces: Vec<&TracePath> = cached_traces
+        .iter()
+        .filter(|cached_trace| cached_trace.anchors != old_trace.anchors)
+        .filter(|cached_trace| blocking_traces(cached_trace, &connection.net_name, fixed_traces).is_empty())
+        .collect();
+    if !fitting_traces.is_empty() && rng.random_bool(0.5) {
+        return Some(fitting_traces[rng.random_range(0..fitting_traces.len())].clone());
+    }
+    let fitting_trace = fitting_traces.first().map(|fitting_trace| (*fitting_trace).clone());
+    let obstacles = build_pad_obstacles(problem, &[&connection.net_name], fixed_traces);
+    match astar_model_for_connection(problem, connection, obstacles).run(display_injection) {
+        // the optimized A* trace can still touch a fixed trace, a move must never leave a collision behind
+        Ok(result) if !blocking_traces(&result.trace_path, &connection.net_name, fixed_traces).is_empty() => fitting_trace,
+        Ok(result) => {
+            if !cached_traces.iter().any(|cached_trace| cached_trace.anchors == result.trace_path.anchors) {
+                cached_traces.push(result.trace_path.clone());
+            }
+            Some(result.trace_path)
+        }
+        Err(_) => fitting_trace,
+    }
+}
+
+/// simulated annealing over a complete solution, every move rips up one or a few connections and reroutes them against the rest,
+/// a worse move is accepted with a chance that shrinks with the temperature; the objective weighs the total length, the vias and the
+/// pads and traces within the clearance margin like the trace scorer does
+/// the prerouted traces are never ripped up, the best solution seen is kept, and the pass stops after ANNEALING_ITERATIONS moves,
+/// ANNEALING_TIME_LIMIT seconds or a stop; every accepted routing is offered to the Pareto front, if one is collected
+pub fn anneal_solution(
+    problem: &PcbProblem,
+    determined_traces: &mut HashMap<ConnectionID, FixedTrace>,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    trace_cache: &mut TraceCache,
+    mut pareto_front: Option<&mut ParetoFront>,
+    display_injection: &mut DisplayInjection,
+) {
+    let mut movable_connections: Vec<ConnectionID> =
+        determined_traces.keys().filter(|connection_id| !prerouted_traces.contains_key(connection_id)).cloned().collect();
+    movable_connections.sort();
+    if movable_connections.is_empty() {
+        return;
+    }
+    let connections: HashMap<ConnectionID, _> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.iter())
+        .map(|(connection_id, connection)| (*connection_id, connection.clone()))
+        .collect();
+    let objective = Objective::from_settings();
+    let num_iterations = ANNEALING_ITERATIONS.load(Ordering::Relaxed);
+    let time_limit = ANNEALING_TIME_LIMIT.load(Ordering::Relaxed);
+    let start_temperature = ANNEALING_START_TEMPERATURE.load(Ordering::Relaxed);
+    let cooling = ANNEALING_COOLING.load(Ordering::Relaxed);
+    let max_ripup = ANNEALING_MAX_RIPUP.load(Ordering::Relaxed);
+    let mut rng = create_deterministic_rng();
+    let start = Instant::now();
+
+    let all_connections: Vec<ConnectionID> = determined_traces.keys().cloned().collect();
+    let initial_metrics = metrics_of(problem, &all_connections, determined_traces, &objective);
+    let mut current_traces = determined_traces.clone();
+    let mut current_value = objective.value(&initial_metrics);
+    let mut best_value = current_value;
+    let mut temperature = start_temperature;
+    let mut num_accepted = 0;
+    let mut num_moves = 0;
+    for _ in 0..num_iterations {
+        if display_injection.is_stopped() {
+            println!("Stop requested, ending the annealing pass");
+            break;
+        }
+        if time_limit > 0.0 && start.elapsed().as_secs_f64() > time_limit {
+            println!("Annealing time limit of {:.2} s reached", time_limit);
+            break;
+        }
+        num_moves += 1;
+        let ripped_connections =
+            pick_connections(&movable_connections, &current_traces, max_ripup, objective.close_pads_term.margin, &mut rng);
+        let old_value = objective.value(&metrics_of(problem, &ripped_connections, &current_traces, &objective));
+        let mut new_traces = current_traces.clone();
+        for connection_id in ripped_connections.iter() {
+            new_traces.remove(connection_id);
+        }
+        let mut reroute_order = ripped_connections.clone();
+        reroute_order.shuffle(&mut rng);
+        let mut is_rerouted = true;
+        for connection_id in reroute_order {
+            let connection = &connections[&connection_id];
+            let old_trace = &current_traces[&connection_id].trace_path;
+            let Some(trace_path) =
+                reroute(problem, connection, old_trace, &new_traces, trace_cache, &mut rng, display_injection)
+            else {
+                is_rerouted = false;
+                break;
+            };
+            new_traces.insert(
+                connection_id,
+                FixedTrace { net_name: connection.net_name.clone(), connection_id, trace_path },
+            );
+        }
+        temperature *= cooling;
+        if !is_rerouted {
+            continue;
+        }
+        let new_value = objective.value(&metrics_of(problem, &ripped_connections, &new_traces, &objective));
+        let delta = new_value - old_value;
+        if delta > 0.0 && rng.random::<f64>() >= (-delta / temperature.max(f64::MIN_POSITIVE)).exp() {
+            continue;
+        }
+        num_accepted += 1;
+        current_traces = new_traces;
+        current_value += delta;
+        if let Some(pareto_front) = pareto_front.as_deref_mut() {
+            pareto_front.offer(problem, &current_traces);
+        }
+        if current_value < best_value - 1e-9 {
+            best_value = current_value;
+            *determined_traces = current_traces.clone();
+            display_when_necessary(determined_traces, problem, CommandFlag::ProbaModelResult, display_injection, true);
+        }
+    }
+
+    let final_metrics = metrics_of(problem, &all_connections, determined_traces, &objective);
+    println!(
+        "Annealing accepted {} of {} moves in {:.2} s: length {:.2} -> {:.2}, vias {} -> {}, close pairs {} -> {}, objective {:.2} -> {:.2}",
+        num_accepted,
+        num_moves,
+        start.elapsed().as_secs_f64(),
+        initial_metrics.total_length,
+        final_metrics.total_length,
+        initial_metrics.num_vias,
+        final_metrics.num_vias,
+        initial_metrics.num_close_pairs,
+        final_metrics.num_close_pairs,
+        objective.value(&initial_metrics),
+        objective.value(&final_metrics),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::AtomicBool,
+            Arc,
+        },
+    };
+
+    use shared::pcb_problem::{ConnectionID, FixedTrace};
+
+    use super::{anneal_solution, metrics_of, Objective};
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        pcb_problem_solve::SolverAlgorithm,
+        test_pcb_problem::examples::{assert_no_collisions, example_problem, route_example, AutoCommandLevel},
+    };
+
+    #[test]
+    fn annealing_never_ends_worse_and_leaves_the_prerouted_traces() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let solution = route_example(&problem, SolverAlgorithm::NaiveBacktrack);
+        assert!(solution.unrouted_connections.is_empty());
+        let mut determined_traces = solution.determined_traces;
+        let first_connection_id = *determined_traces.keys().min().unwrap();
+        let prerouted_traces: HashMap<ConnectionID, FixedTrace> =
+            HashMap::from([(first_connection_id, determined_traces[&first_connection_id].clone())]);
+        let all_connections: Vec<ConnectionID> = determined_traces.keys().cloned().collect();
+        let objective = Objective::from_settings();
+        let initial_value = objective.value(&metrics_of(&problem, &all_connections, &determined_traces, &objective));
+
+        let _auto_command_level = AutoCommandLevel::hold();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        anneal_solution(
+            &problem,
+            &mut determined_traces,
+            &prerouted_traces,
+            &mut TraceCache::default(),
+            None,
+            &mut display_injection,
+        );
+
+        let mut connections = determined_traces.keys().cloned().collect::<Vec<_>>();
+        let mut expected_connections = all_connections.clone();
+        connections.sort();
+        expected_connections.sort();
+        assert_eq!(connections, expected_connections);
+        assert_no_collisions(&determined_traces);
+        let final_value = objective.value(&metrics_of(&problem, &all_connections, &determined_traces, &objective));
+        assert!(final_value <= initial_value + 1e-6);
+        let as_json = |fixed_trace: &FixedTrace| serde_json::to_string(fixed_trace).unwrap();
+        assert_eq!(as_json(&determined_traces[&first_connection_id]), as_json(&prerouted_traces[&first_connection_id]));
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     cmp::Reverse,
     collections::{BinaryHeap, HashMap, HashSet},
     rc::Rc,
-    sync::{Arc, Mutex, atomic::Ordering},
+    sync::{Arc, atomic::Ordering},
 };
 
 use fixed::traits::Fixed;
@@ -17,7 +17,7 @@ use crate::{
 use shared::{
     binary_heap_item::BinaryHeapItem,
     collider::{BorderCollider, Collider},
-    hyperparameters::{ASTAR_STRIDE, ASTAR_MAX_EXPANSIONS, VIA_COST},
+    hyperparameters::{ASTAR_STRIDE, ASTAR_MAX_EXPANSIONS},
     octile_distance::octile_distance_fixed,
     pad::PadLayer,
     pcb_render_model::{
@@ -25,19 +25,29 @@ use shared::{
     },
     prim_shape::{CircleShape, PrimShape, RectangleShape},
     trace_path::{
-        self, AStarNodeDirection, Direction, TraceAnchor, TraceAnchors, TracePath, TraceSegment, Via
+        self, AStarNodeDirection, Direction, TraceAnchor, TraceAnchors, TraceArc, TracePath, TraceSegment, Via
     },
     vec2::{FixedPoint, FixedVec2, FloatVec2},
+    via_type::ViaType,
 };
 
+/// extra cost on top of length and via cost, consulted by A* for every move
+/// the negotiated congestion solver uses it to steer traces away from congested cells
+pub trait AStarCostMap {
+    fn planar_cost(&self, start: FixedVec2, end: FixedVec2, layer: usize) -> f64;
+    fn via_cost(&self, position: FixedVec2, min_layer: usize, max_layer: usize) -> f64; // the layers the via spans
+    // post processing treats congested segments as collisions, so it won't pull a trace back into them
+    fn is_congested(&self, start: FixedVec2, end: FixedVec2, layer: usize) -> bool;
+}
+
 pub struct AStarModel {
     pub width: f32,
     pub height: f32,
     pub center: FloatVec2,
-    pub obstacle_shapes: Rc<HashMap<usize, Vec<PrimShape>>>,
-    pub obstacle_clearance_shapes: Rc<HashMap<usize, Vec<PrimShape>>>,
-    pub obstacle_colliders: Rc<HashMap<usize, QuadTreeNode>>,
-    pub obstacle_clearance_colliders: Rc<HashMap<usize, QuadTreeNode>>,
+    pub obstacle_shapes: Arc<HashMap<usize, Vec<PrimShape>>>,
+    pub obstacle_clearance_shapes: Arc<HashMap<usize, Vec<PrimShape>>>,
+    pub obstacle_colliders: Arc<HashMap<usize, QuadTreeNode>>,
+    pub obstacle_clearance_colliders: Arc<HashMap<usize, QuadTreeNode>>,
     pub start: FixedVec2,
     pub end: FixedVec2,
     pub start_layers: PadLayer,
@@ -45,9 +55,21 @@ pub struct AStarModel {
     pub num_layers: usize,
     pub trace_width: f32,
     pub trace_clearance: f32,
-    pub via_diameter: f32,
-    pub border_colliders_cache: RefCell<Option<Rc<Vec<Collider>>>>,
-    pub border_shapes_cache: RefCell<Option<Rc<Vec<PrimShape>>>>,
+    pub via_types: Vec<ViaType>, // the catalog A* picks from when changing layers, sorted by cost
+    pub border_colliders_cache: RefCell<Option<Arc<Vec<Collider>>>>,
+    pub border_shapes_cache: RefCell<Option<Arc<Vec<PrimShape>>>>,
+    pub cost_map: Option<Rc<dyn AStarCostMap>>, // None for plain shortest path
+    pub blocking_colliders: RefCell<Option<HashMap<usize, Vec<Collider>>>>, // the obstacles that rejected a move, per layer, only recorded by run_recording_blockers
+}
+
+const NO_PATH_FOUND: &str = "No path found";
+
+/// a search that found no path, with the obstacle colliders that rejected its moves if it ran out of frontier
+/// a search stopped by a limit has no blockers, it might have found a path with more time
+#[derive(Debug)]
+pub struct AStarFailure {
+    pub message: String,
+    pub blocking_colliders: Option<HashMap<usize, Vec<Collider>>>,
 }
 
 impl AStarModel {
@@ -74,7 +96,7 @@ impl AStarModel {
         width: f32,
         height: f32,
         center: FloatVec2,
-    ) -> Rc<Vec<Collider>> {
+    ) -> Arc<Vec<Collider>> {
         let left_border = BorderCollider {
             point_on_border: FloatVec2::new(center.x - width / 2.0, 0.0),
             normal: FloatVec2::new(-1.0, 0.0),
@@ -91,14 +113,14 @@ impl AStarModel {
             point_on_border: FloatVec2::new(0.0, center.y - height / 2.0),
             normal: FloatVec2::new(0.0, -1.0),
         };
-        Rc::new(vec![
+        Arc::new(vec![
             Collider::Border(left_border),
             Collider::Border(right_border),
             Collider::Border(top_border),
             Collider::Border(bottom_border),
         ])
     }
-    fn get_border_colliders(&self) -> Rc<Vec<Collider>> {
+    fn get_border_colliders(&self) -> Arc<Vec<Collider>> {
         if let Some(border_shapes) = self.border_colliders_cache.borrow().as_ref() {
             return border_shapes.clone();
         }
@@ -107,7 +129,7 @@ impl AStarModel {
         *self.border_colliders_cache.borrow_mut() = Some(border_colliders.clone());
         border_colliders
     }
-    fn get_border_shapes(&self) -> Rc<Vec<PrimShape>> {
+    fn get_border_shapes(&self) -> Arc<Vec<PrimShape>> {
         if let Some(border_shapes) = self.border_shapes_cache.borrow().as_ref() {
             return border_shapes.clone();
         }
@@ -143,7 +165,7 @@ impl AStarModel {
             start: bottom_left,
             end: bottom_right,
         });
-        let border_shapes: Rc<Vec<PrimShape>> = Rc::new(vec![
+        let border_shapes: Arc<Vec<PrimShape>> = Arc::new(vec![
             left_border_shape,
             right_border_shape,
             top_border_shape,
@@ -241,9 +263,11 @@ impl AStarModel {
         let obstacle_colliders = self.obstacle_colliders.get(&layer).unwrap();
         let obstacle_clearance_colliders = self.obstacle_clearance_colliders.get(&layer).unwrap();
         if obstacle_colliders.collides_with_set(trace_segment_clearance_colliders.iter()) {
+            self.record_blockers(layer, obstacle_colliders, &trace_segment_clearance_colliders);
             return true; // collision with an obstacle clearance shape
         }
         if obstacle_clearance_colliders.collides_with_set(trace_segment_colliders.iter()) {
+            self.record_blockers(layer, obstacle_clearance_colliders, &trace_segment_colliders);
             return true; // collision with an obstacle
         }
         if self.collides_with_border(trace_segment_colliders.iter()) {
@@ -251,7 +275,49 @@ impl AStarModel {
         }
         false // no collision
     }
-    fn check_collision_for_via(
+    /// remembers the obstacles that reject a move, if run_recording_blockers asked for them
+    fn record_blockers(&self, layer: usize, obstacles: &QuadTreeNode, colliders: &[Collider]) {
+        if let Some(blocking_colliders) = self.blocking_colliders.borrow_mut().as_mut() {
+            let found = blocking_colliders.entry(layer).or_default();
+            for collider in colliders {
+                obstacles.colliding_objects(collider, found);
+            }
+        }
+    }
+    pub(crate) fn check_collision_for_optimization(
+        &self,
+        start: FixedVec2,
+        end: FixedVec2,
+        width: f32,
+        clearance: f32,
+        layer: usize,
+    ) -> bool {
+        if self.check_collision_for_trace(start, end, width, clearance, layer) {
+            return true;
+        }
+        match &self.cost_map {
+            Some(cost_map) => cost_map.is_congested(start, end, layer),
+            None => false,
+        }
+    }
+    /// the arc counterpart of check_collision_for_optimization, for rounded corners
+    pub(crate) fn check_collision_for_arc(&self, arc: &TraceArc) -> bool {
+        let arc_colliders = arc.to_colliders();
+        let arc_clearance_colliders = arc.to_clearance_colliders();
+        let obstacle_colliders = self.obstacle_colliders.get(&arc.layer).unwrap();
+        let obstacle_clearance_colliders = self.obstacle_clearance_colliders.get(&arc.layer).unwrap();
+        if obstacle_colliders.collides_with_set(arc_clearance_colliders.iter()) {
+            return true; // collision with an obstacle clearance shape
+        }
+        if obstacle_clearance_colliders.collides_with_set(arc_colliders.iter()) {
+            return true; // collision with an obstacle
+        }
+        if self.collides_with_border(arc_colliders.iter()) {
+            return true; // collision with the border
+        }
+        false // no collision
+    }
+    pub(crate) fn check_collision_for_via_layer(
         &self,
         position: FixedVec2,
         via_diameter: f32,
@@ -271,9 +337,11 @@ impl AStarModel {
         let obstacle_colliders = self.obstacle_colliders.get(&layer).unwrap();
         let obstacle_clearance_colliders = self.obstacle_clearance_colliders.get(&layer).unwrap();
         if obstacle_clearance_colliders.collides_with(&collider) {
+            self.record_blockers(layer, obstacle_clearance_colliders, std::slice::from_ref(&collider));
             return true; // collision with an obstacle clearance shape
         }
         if obstacle_colliders.collides_with(&clearance_collider) {
+            self.record_blockers(layer, obstacle_colliders, std::slice::from_ref(&clearance_collider));
             return true; // collision with an obstacle
         }
         if self.collides_with_border(std::iter::once(&collider)) {
@@ -281,6 +349,12 @@ impl AStarModel {
         }
         false // no collision
     }
+    /// a via only occupies the layers it spans, so the other layers are not checked
+    fn check_collision_for_via(&self, position: FixedVec2, via_type: &ViaType) -> bool {
+        (via_type.min_layer..=via_type.max_layer).any(|layer| {
+            self.check_collision_for_via_layer(position, via_type.diameter, via_type.clearance, layer)
+        })
+    }
 
     fn is_grid_point(&self, position: &FixedVec2, astar_stride: FixedPoint) -> bool {
         position.x % astar_stride == FixedPoint::ZERO
@@ -945,7 +1019,7 @@ impl AStarModel {
             let renderables = astar_node.to_renderables(
                 self.trace_width,
                 self.trace_clearance,
-                self.via_diameter,
+                &self.via_types,
                 color,
             );
             render_model.trace_shape_renderables.extend(renderables);
@@ -976,7 +1050,7 @@ impl AStarModel {
         command_flag: CommandFlag,
         display_injection: &mut DisplayInjection,
     ) {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        if display_injection.is_stopped() {
             println!("A* search stopped by user");
             return;
         }
@@ -1104,6 +1178,9 @@ impl AStarModel {
         if display_injection.stop_requested.load(Ordering::Relaxed) {
             return Err("A* search stopped by user".to_string());
         }
+        if display_injection.budget.is_exhausted() {
+            return Err("A* search stopped by the solve budget".to_string());
+        }
 
         let astar_stride = {
             ASTAR_STRIDE.lock().unwrap().clone()
@@ -1131,6 +1208,7 @@ impl AStarModel {
                 actual_length: 0.0, // no length for the start node
                 estimated_cost: start_estimated_cost,
                 total_cost: start_estimated_cost,
+                via_type: None,
                 prev_node: None, // no previous node for the start node
             };
             frontier.push(BinaryHeapItem {
@@ -1159,11 +1237,11 @@ impl AStarModel {
                 let trace_path = current_node.to_trace_path(
                     self.trace_width,
                     self.trace_clearance,
-                    self.via_diameter,
+                    &self.via_types,
                 );
                 let check_collision_for_trace =
                     |start: FixedVec2, end: FixedVec2, width: f32, clearance: f32, layer: usize| {
-                        self.check_collision_for_trace(start, end, width, clearance, layer)
+                        self.check_collision_for_optimization(start, end, width, clearance, layer)
                     };
                
                 
@@ -1175,7 +1253,6 @@ impl AStarModel {
                     //  &check_collision_for_via,
                     self.trace_width,
                     self.trace_clearance,
-                    self.via_diameter,
                 );    
                 // println!("Finished one iteration of optimization");
                 self.display_final_trace(&trace_path, CommandFlag::AstarInOut, display_injection);                
@@ -1192,6 +1269,11 @@ impl AStarModel {
             }
             // don't consider visited nodes as trials
             trial_count += 1;
+            display_injection.budget.add_astar_expansions(1);
+            // the budget of the whole solve is checked every so often, the clock is too slow to read on every expansion
+            if trial_count % 256 == 0 && display_injection.budget.check() {
+                return Err("A* search stopped by the solve budget".to_string());
+            }
             if trial_count > ASTAR_MAX_EXPANSIONS.load(Ordering::Relaxed) {
                 // self.display_when_necessary(&frontier, CommandFlag::Auto, display_injection);
                 return Err("A* search exceeded maximum trials".to_string());
@@ -1202,7 +1284,7 @@ impl AStarModel {
             // new:
             // hoist the closure out of the directions loop for the aligned_with_end condition
             let mut try_push_node_to_frontier =
-                |direction: AStarNodeDirection, end_position: FixedVec2, end_layer: usize| -> Option<Rc<AstarNode>> {
+                |direction: AStarNodeDirection, end_position: FixedVec2, end_layer: usize, via_type: Option<usize>| -> Option<Rc<AstarNode>> {
                     assert!(
                         !matches!(direction, AStarNodeDirection::None),
                         "Direction should not be None"
@@ -1230,12 +1312,22 @@ impl AStarModel {
                     }
                     // let length: f64 = (direction.to_fixed_vec2().length() * length).to_num();
                     let length: f64 = (end_position - current_node.position).length().to_num();
-                    let via_cost = if let AStarNodeDirection::Vertical { .. } = direction {
-                        VIA_COST.load(Ordering::Relaxed) // vertical movement has a via cost
-                    } else {
-                        0.0 // no via cost for planar movements
+                    // vertical movement pays for the via type it uses, planar movement has no via cost
+                    let via_cost = match via_type {
+                        Some(via_type) => self.via_types[via_type].cost,
+                        None => 0.0,
+                    };
+                    let extra_cost = match (&self.cost_map, via_type) {
+                        (None, _) => 0.0,
+                        (Some(cost_map), Some(via_type)) => {
+                            let via_type = &self.via_types[via_type];
+                            cost_map.via_cost(end_position, via_type.min_layer, via_type.max_layer)
+                        }
+                        (Some(cost_map), None) => {
+                            cost_map.planar_cost(current_node.position, end_position, end_layer)
+                        }
                     };
-                    let actual_cost = current_node.actual_cost + length + via_cost;
+                    let actual_cost = current_node.actual_cost + length + via_cost + extra_cost;
                     let actual_length = current_node.actual_length + length;
                     let estimated_cost =
                         octile_distance_fixed(end_position, self.end);
@@ -1248,6 +1340,7 @@ impl AStarModel {
                         actual_length,
                         estimated_cost,
                         total_cost,
+                        via_type,
                         prev_node: Some(current_node.clone()), // link to the previous node
                     };
                     let new_node = Rc::new(new_node);
@@ -1298,38 +1391,40 @@ impl AStarModel {
                         AStarNodeDirection::Planar(end_direction),
                         self.end,
                         current_node.layer,
+                        None,
                     );
                     let end_node = end_node.unwrap();
 
                     // to do: directly success
-
-                    self.display_when_necessary(
-                        &frontier,
-                        CommandFlag::AstarInOut,
-                        display_injection,
-                    ); // display the initial state of the frontier
-
-                    // Reached the end node, construct the trace path
-                    let trace_path = end_node.to_trace_path(
-                        self.trace_width,
-                        self.trace_clearance,
-                        self.via_diameter,
-                    );
-                    let check_collision_for_trace =
-                        |start: FixedVec2, end: FixedVec2, width: f32, clearance: f32, layer: usize| {
-                            self.check_collision_for_trace(start, end, width, clearance, layer)
-                        };                    
-                    self.display_final_trace(&trace_path, CommandFlag::AstarInOut, display_injection);         
-                    let trace_path = optimize_path(
-                        &trace_path,
-                        &check_collision_for_trace,
-                        self.trace_width,
-                        self.trace_clearance,
-                        self.via_diameter,
-                    );    
-                    // println!("Finished one iteration of optimization");
-                    self.display_final_trace(&trace_path, CommandFlag::AstarInOut, display_injection);                
-                    return Ok(AStarResult { trace_path });
+                    // with a cost map the straight line may be congested, so the end node waits in the frontier
+                    if self.cost_map.is_none() {
+                        self.display_when_necessary(
+                            &frontier,
+                            CommandFlag::AstarInOut,
+                            display_injection,
+                        ); // display the initial state of the frontier
+
+                        // Reached the end node, construct the trace path
+                        let trace_path = end_node.to_trace_path(
+                            self.trace_width,
+                            self.trace_clearance,
+                            &self.via_types,
+                        );
+                        let check_collision_for_trace =
+                            |start: FixedVec2, end: FixedVec2, width: f32, clearance: f32, layer: usize| {
+                                self.check_collision_for_optimization(start, end, width, clearance, layer)
+                            };                    
+                        self.display_final_trace(&trace_path, CommandFlag::AstarInOut, display_injection);         
+                        let trace_path = optimize_path(
+                            &trace_path,
+                            &check_collision_for_trace,
+                            self.trace_width,
+                            self.trace_clearance,
+                        );    
+                        // println!("Finished one iteration of optimization");
+                        self.display_final_trace(&trace_path, CommandFlag::AstarInOut, display_injection);                
+                        return Ok(AStarResult { trace_path });
+                    }
 
 
 
@@ -1341,44 +1436,36 @@ impl AStarModel {
             }
 
             // this will call try_push_node_to_frontier multiple times
-            let mut try_place_vias = |position: FixedVec2,
-                                      via_diameter: f32,
-                                      clearance: f32,
-                                      layer: usize| {
-                if self.check_collision_for_via(position, via_diameter, clearance, layer) {
-                    return;
-                }
-                for lower_layer in (0..layer).rev() {
-                    if self.check_collision_for_via(position, via_diameter, clearance, lower_layer)
-                    {
-                        break;
-                    }
-                    try_push_node_to_frontier(
-                        AStarNodeDirection::Vertical { from_layer: layer },
-                        position,
-                        lower_layer,
-                    );
-                }
-                for upper_layer in (layer + 1)..self.num_layers {
-                    if self.check_collision_for_via(position, via_diameter, clearance, upper_layer)
-                    {
-                        break;
+            let mut try_place_vias = |position: FixedVec2, layer: usize| {
+                // collisions are checked once per via type, over all the layers it spans
+                let via_type_collisions: Vec<bool> = self
+                    .via_types
+                    .iter()
+                    .map(|via_type| {
+                        !via_type.connects(layer, layer) || self.check_collision_for_via(position, via_type)
+                    })
+                    .collect();
+                for other_layer in (0..self.num_layers).filter(|other_layer| *other_layer != layer) {
+                    // the catalog is sorted by cost, so the first via type that fits is the cheapest
+                    let via_type = self.via_types.iter().enumerate().position(|(index, via_type)| {
+                        via_type.connects(layer, other_layer) && !via_type_collisions[index]
+                    });
+                    if let Some(via_type) = via_type {
+                        try_push_node_to_frontier(
+                            AStarNodeDirection::Vertical { from_layer: layer },
+                            position,
+                            other_layer,
+                            Some(via_type),
+                        );
                     }
-                    try_push_node_to_frontier(
-                        AStarNodeDirection::Vertical { from_layer: layer },
-                        position,
-                        upper_layer,
-                    );
                 }
             };
             // new: try place a via if the current node is at a grid point
-            if self.is_grid_point(&current_node.position, astar_stride) {
-                try_place_vias(
-                    current_node.position,
-                    self.via_diameter,
-                    self.trace_clearance,
-                    current_node.layer,
-                );
+            // two vias in a row at the same position would stack, so a via never follows another one
+            if self.is_grid_point(&current_node.position, astar_stride)
+                && current_node.via_type.is_none()
+            {
+                try_place_vias(current_node.position, current_node.layer);
             }
 
             // process grid points or one-step-to-grid-points
@@ -1413,6 +1500,7 @@ impl AStarModel {
                     AStarNodeDirection::Planar(direction),
                     end_position,
                     current_node.layer,
+                    None,
                 );
                 if let None = self.is_aligned_with_end(current_node.position, current_node.layer) {
                     if let Some(intersection) = self.get_intersection_with_end_alignments(
@@ -1429,6 +1517,7 @@ impl AStarModel {
                             AStarNodeDirection::Planar(direction),
                             intersection,
                             current_node.layer,
+                            None,
                         );
                     }
                 }
@@ -1468,6 +1557,7 @@ impl AStarModel {
                     AStarNodeDirection::Planar(direction),
                     end_position,
                     current_node.layer,
+                    None,
                 );
                 if let None = self.is_aligned_with_end(current_node.position, current_node.layer) {
                     if let Some(intersection) = self.get_intersection_with_end_alignments(
@@ -1484,6 +1574,7 @@ impl AStarModel {
                             AStarNodeDirection::Planar(direction),
                             intersection,
                             current_node.layer,
+                            None,
                         );
                     }
                 }
@@ -1514,6 +1605,7 @@ impl AStarModel {
                             AStarNodeDirection::Planar(direction),
                             end_position,
                             current_node.layer,
+                            None,
                         );
                         found_point = true;
                         break;
@@ -1549,6 +1641,7 @@ impl AStarModel {
                             AStarNodeDirection::Planar(direction),
                             end_position,
                             current_node.layer,
+                            None,
                         );
                     } else {
                         // remove the tried direction
@@ -1577,6 +1670,7 @@ impl AStarModel {
                                     AStarNodeDirection::Planar(direction),
                                     end_position,
                                     current_node.layer,
+                                    None,
                                 );
                                 found_point = true;
                                 break; // only try one direction
@@ -1598,7 +1692,19 @@ impl AStarModel {
             ); // display the initial state of the frontier
         }
         // self.display_when_necessary(&frontier, CommandFlag::Auto, display_injection);
-        Err("No path found".to_string()) // no path found
+        Err(NO_PATH_FOUND.to_string()) // no path found
+    }
+
+    /// the same search as run, and if it runs out of frontier, the obstacles that rejected its moves
+    /// any set of obstacles that holds all of them leaves the search without a path as well
+    pub fn run_recording_blockers(&self, display_injection: &mut DisplayInjection) -> Result<AStarResult, AStarFailure> {
+        *self.blocking_colliders.borrow_mut() = Some(HashMap::new());
+        let result = self.run(display_injection);
+        let blocking_colliders = self.blocking_colliders.borrow_mut().take();
+        result.map_err(|message| AStarFailure {
+            blocking_colliders: blocking_colliders.filter(|_| message == NO_PATH_FOUND),
+            message,
+        })
     }
 }
 
@@ -1618,6 +1724,7 @@ pub struct AstarNode {
     pub actual_length: f64,
     pub estimated_cost: f64, // the estimated cost to reach the end node from this node
     pub total_cost: f64, // the total cost to reach this node from the start node, including the estimated cost to reach the end node
+    pub via_type: Option<usize>, // index into the via catalog, only set for vertical movements
     pub prev_node: Option<Rc<AstarNode>>, // the previous node in the path, used for backtracking
 }
 
@@ -1688,14 +1795,19 @@ impl AstarNode {
         self: Rc<Self>,
         width: f32,
         clearance: f32,
-        via_diameter: f32,
+        via_types: &[ViaType],
     ) -> TracePath {
         let mut current_node: Option<Rc<AstarNode>> = Some(self.clone());
         let mut next_node: Option<Rc<AstarNode>> = None;
         let mut anchors: Vec<TraceAnchor> = Vec::new(); // initializes with the end position
         let mut vias: Vec<Via> = Vec::new(); // initializes with the end position
         let mut pending_trace_anchor: Option<TraceAnchor> = None; // position, start, end
+        let mut via_nodes: Vec<(FixedVec2, usize)> = Vec::new(); // position and via type of every vertical movement
         while let Some(node) = &current_node {
+            if let AStarNodeDirection::Vertical { .. } = node.direction {
+                let via_type = node.via_type.expect("A vertical movement should have a via type");
+                via_nodes.push((node.position, via_type));
+            }
             if let Some(next_node) = next_node {
                 assert!(Self::is_direction_and_displacement_invariant(
                     next_node.clone(),
@@ -1733,6 +1845,8 @@ impl AstarNode {
             next_node, None
         ));
         anchors.reverse(); // reverse the anchors to get the correct order
+        via_nodes.reverse();
+        let mut via_nodes = via_nodes.into_iter();
         let mut segments: Vec<TraceSegment> = Vec::new();
         for i in 0..anchors.len() - 1 {
             let start_anchor = &anchors[i];
@@ -1755,12 +1869,19 @@ impl AstarNode {
             segments.push(segment);
             if start_anchor.start_layer != start_anchor.end_layer {
                 // if the start and end layers are different, we need to add a via
+                let (via_position, via_type) = via_nodes
+                    .next()
+                    .expect("Every layer change should come from a vertical movement");
+                assert_eq!(via_position, start_anchor.position);
+                let via_type = &via_types[via_type];
+                // the via occupies every layer it spans, not only the two layers it connects
                 let via = Via {
                     position: start_anchor.position,
-                    clearance,
-                    diameter: via_diameter,
-                    min_layer: usize::min(start_anchor.start_layer, start_anchor.end_layer),
-                    max_layer: usize::max(start_anchor.start_layer, start_anchor.end_layer),
+                    clearance: via_type.clearance,
+                    diameter: via_type.diameter,
+                    min_layer: via_type.min_layer,
+                    max_layer: via_type.max_layer,
+                    padstack: via_type.name.clone(),
                 };
                 vias.push(via);
             }
@@ -1774,6 +1895,7 @@ impl AstarNode {
             anchors,
             segments,
             vias,
+            arcs: Vec::new(),
             total_length: self.actual_length,
         }
     }
@@ -1781,7 +1903,7 @@ impl AstarNode {
         &self,
         width: f32,
         clearance: f32,
-        via_diameter: f32,
+        via_types: &[ViaType],
         color: [f32; 3],
     ) -> Vec<RenderableBatch> {
         // This function is used to convert the AstarNode to a TraceSegment
@@ -1831,8 +1953,11 @@ impl AstarNode {
                     RenderableBatch(clearance_renderables),
                 ]
             }
-            AStarNodeDirection::Vertical { from_layer } => {
+            AStarNodeDirection::Vertical { .. } => {
                 // draw a via
+                let via_diameter = self
+                    .via_type
+                    .map_or(width, |via_type| via_types[via_type].diameter);
                 let shape_renderable = ShapeRenderable {
                     shape: PrimShape::Circle(CircleShape {
                         position: self.position.to_float(),
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
 
 use shared::{collider::Collider, trace_path::TracePath};
 
@@ -9,9 +9,9 @@ use crate::quad_tree::QuadTreeNode;
 
 
 pub struct AStarCheck{
-    pub border_colliders: Rc<Vec<Collider>>,
-    pub obstacle_colliders: Rc<HashMap<usize, QuadTreeNode>>,
-    pub obstacle_clearance_colliders: Rc<HashMap<usize, QuadTreeNode>>,
+    pub border_colliders: Arc<Vec<Collider>>,
+    pub obstacle_colliders: Arc<HashMap<usize, QuadTreeNode>>,
+    pub obstacle_clearance_colliders: Arc<HashMap<usize, QuadTreeNode>>,
     pub solution_trace: TracePath,    
     pub num_layers: usize,
 }
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap};
+
+use shared::{
+    collider::Collider,
+    pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+    trace_path::{TraceAnchors, TracePath},
+};
+
+use crate::{display_injection::DisplayInjection, obstacles::build_pad_obstacles, partial_solution::astar_model_for_connection};
+
+/// a connection that could not be fixed, and the fixed traces that were in its way
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub connection_id: ConnectionID,
+    pub culprits: Vec<FixedTrace>,
+}
+
+impl Conflict {
+    /// merges the conflicts of the same connection, their culprits without duplicates
+    pub fn merge(conflicts: Vec<Conflict>) -> Vec<Conflict> {
+        let mut merged: Vec<Conflict> = Vec::new();
+        for conflict in conflicts {
+            let Some(existing) = merged.iter_mut().find(|existing| existing.connection_id == conflict.connection_id) else {
+                merged.push(conflict);
+                continue;
+            };
+            for culprit in conflict.culprits {
+                if !existing.culprits.iter().any(|other| is_same_trace(other, &culprit)) {
+                    existing.culprits.push(culprit);
+                }
+            }
+        }
+        merged
+    }
+}
+
+fn is_same_trace(fixed_trace1: &FixedTrace, fixed_trace2: &FixedTrace) -> bool {
+    fixed_trace1.connection_id == fixed_trace2.connection_id
+        && fixed_trace1.trace_path.anchors == fixed_trace2.trace_path.anchors
+}
+
+/// the fixed traces of other nets that the trace collides with
+pub fn blocking_traces(
+    trace_path: &TracePath,
+    net_name: &NetName,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> Vec<FixedTrace> {
+    let mut culprits: Vec<FixedTrace> = fixed_traces
+        .values()
+        .filter(|fixed_trace| fixed_trace.net_name != *net_name)
+        .filter(|fixed_trace| trace_path.collides_with(&fixed_trace.trace_path))
+        .cloned()
+        .collect();
+    culprits.sort_by_key(|fixed_trace| fixed_trace.connection_id);
+    culprits
+}
+
+/// the index of the node to resume from after a conflict, the deepest node on the stack that has not fixed every culprit yet,
+/// none when the culprits are fixed in every node, e.g. when they are prerouted
+pub fn backjump_index<'a>(
+    fixed_traces_per_node: impl DoubleEndedIterator<Item = &'a HashMap<ConnectionID, FixedTrace>> + ExactSizeIterator,
+    culprits: &[FixedTrace],
+) -> Option<usize> {
+    fixed_traces_per_node
+        .enumerate()
+        .rev()
+        .find(|(_, fixed_traces)| {
+            culprits.iter().any(|culprit| {
+                fixed_traces
+                    .get(&culprit.connection_id)
+                    .is_none_or(|fixed_trace| fixed_trace.trace_path.anchors != culprit.trace_path.anchors)
+            })
+        })
+        .map(|(index, _)| index)
+}
+
+/// the fixed traces of other nets that own one of the obstacle colliders a failed A* search recorded
+/// the colliders are the ones the obstacles were built from, so they compare equal to the trace's own
+pub fn traces_owning_colliders(
+    blocking_colliders: &HashMap<usize, Vec<Collider>>,
+    net_name: &NetName,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    num_layers: usize,
+) -> Vec<FixedTrace> {
+    let owns_blocker = |fixed_trace: &FixedTrace| {
+        let colliders = fixed_trace.trace_path.to_colliders(num_layers);
+        let clearance_colliders = fixed_trace.trace_path.to_clearance_colliders(num_layers);
+        blocking_colliders.iter().any(|(layer, blockers)| {
+            colliders[layer]
+                .iter()
+                .chain(clearance_colliders[layer].iter())
+                .any(|collider| blockers.contains(collider))
+        })
+    };
+    let mut culprits: Vec<FixedTrace> = fixed_traces
+        .values()
+        .filter(|fixed_trace| fixed_trace.net_name != *net_name)
+        .filter(|fixed_trace| owns_blocker(fixed_trace))
+        .cloned()
+        .collect();
+    culprits.sort_by_key(|fixed_trace| fixed_trace.connection_id);
+    culprits
+}
+
+/// the conflict of a connection that A* finds no path for among the fixed traces, with the traces that own what it ran into
+/// a colliding candidate proves nothing, the connection may still have other candidates and other paths,
+/// so this is the only kind of conflict the searches learn a nogood from
+/// none when A* finds a path, stops at a limit, or runs into nothing but pads
+pub fn exhausted_conflict(
+    problem: &PcbProblem,
+    connection_id: ConnectionID,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Option<Conflict> {
+    let connection = problem.nets.values().find_map(|net_info| net_info.connections.get(&connection_id))?;
+    let obstacles = build_pad_obstacles(problem, &[&connection.net_name], fixed_traces);
+    let failure = astar_model_for_connection(problem, connection, obstacles)
+        .run_recording_blockers(display_injection)
+        .err()?;
+    let culprits =
+        traces_owning_colliders(&failure.blocking_colliders?, &connection.net_name, fixed_traces, problem.num_layers);
+    (!culprits.is_empty()).then_some(Conflict { connection_id, culprits })
+}
+
+/// a connection that found no place while every trace of the set was fixed
+#[derive(Debug, Clone, PartialEq)]
+struct Nogood {
+    culprits: Vec<(ConnectionID, TraceAnchors)>, // sorted by connection
+    blocked_connection: ConnectionID,
+}
+
+/// learned sets of fixed traces that left a connection without a place,
+/// a branch is pruned when it is about to fix the last trace of a set again while the connection is still open
+/// a branch with only part of a set fixed, or with another trace for one of its connections, is not pruned
+#[derive(Debug, Clone, Default)]
+pub struct Nogoods {
+    nogoods: Vec<Nogood>,
+    by_culprit: HashMap<ConnectionID, BTreeMap<TraceAnchors, Vec<usize>>>, // indices into nogoods
+}
+
+impl Nogoods {
+    /// learns the whole culprit set of a conflict, a conflict without culprits was not caused by the fixed traces
+    pub fn learn(&mut self, conflict: &Conflict) {
+        if conflict.culprits.is_empty() {
+            return;
+        }
+        let mut culprits: Vec<(ConnectionID, TraceAnchors)> = conflict
+            .culprits
+            .iter()
+            .map(|culprit| (culprit.connection_id, culprit.trace_path.anchors.clone()))
+            .collect();
+        culprits.sort();
+        culprits.dedup();
+        let nogood = Nogood { culprits, blocked_connection: conflict.connection_id };
+        if self.nogoods.contains(&nogood) {
+            return;
+        }
+        println!(
+            "Learned nogood: the traces of connections {:?} leave connection {:?} without a place",
+            nogood.culprits.iter().map(|(connection_id, _)| connection_id.0).collect::<Vec<_>>(),
+            nogood.blocked_connection
+        );
+        for (connection_id, anchors) in nogood.culprits.iter() {
+            self.by_culprit
+                .entry(*connection_id)
+                .or_default()
+                .entry(anchors.clone())
+                .or_default()
+                .push(self.nogoods.len());
+        }
+        self.nogoods.push(nogood);
+    }
+
+    /// whether fixing the trace for the connection completes a learned set whose blocked connection is not fixed yet
+    pub fn forbids(
+        &self,
+        connection_id: ConnectionID,
+        trace_path: &TracePath,
+        fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    ) -> bool {
+        let Some(indices) = self
+            .by_culprit
+            .get(&connection_id)
+            .and_then(|trace_to_nogoods| trace_to_nogoods.get(&trace_path.anchors))
+        else {
+            return false;
+        };
+        indices.iter().map(|index| &self.nogoods[*index]).any(|nogood| {
+            !fixed_traces.contains_key(&nogood.blocked_connection)
+                && nogood.culprits.iter().all(|(culprit_id, anchors)| {
+                    *culprit_id == connection_id
+                        || fixed_traces
+                            .get(culprit_id)
+                            .is_some_and(|fixed_trace| fixed_trace.trace_path.anchors == *anchors)
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use shared::{
+        pcb_problem::{Connection, ConnectionID, FixedTrace, NetName},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::FloatVec2,
+    };
+
+    use crate::{
+        display_injection::DisplayInjection, obstacles::build_pad_obstacles,
+        partial_solution::astar_model_for_connection, test_pcb_problem::examples::example_problem,
+    };
+
+    use super::{backjump_index, exhausted_conflict, traces_owning_colliders, Conflict, Nogoods};
+
+    /// a closed square of another net's trace on one layer
+    fn square_trace(net_name: &NetName, connection_id: usize, center: FloatVec2, half_side: f32, layer: usize) -> FixedTrace {
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0)];
+        let anchors = corners
+            .iter()
+            .map(|(x, y)| TraceAnchor {
+                position: FloatVec2::new(center.x + x * half_side, center.y + y * half_side)
+                    .to_fixed()
+                    .to_nearest_even_even(),
+                start_layer: layer,
+                end_layer: layer,
+            })
+            .collect();
+        FixedTrace {
+            net_name: net_name.clone(),
+            connection_id: ConnectionID(connection_id),
+            trace_path: TracePath::from_anchors(TraceAnchors(anchors), 0.25, 0.2, vec![]),
+        }
+    }
+
+    #[test]
+    fn backjump_lands_on_the_node_that_fixes_the_last_culprit() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 2);
+        let mut net_infos: Vec<_> = problem.nets.values().collect();
+        net_infos.sort_by(|a, b| a.net_name.0.cmp(&b.net_name.0));
+        let net_info = net_infos[0];
+        let connection: &Connection = net_info
+            .connections
+            .values()
+            .max_by(|a, b| {
+                let distance = |connection: &Connection| {
+                    (net_info.pads[&connection.start_pad].position - net_info.pads[&connection.end_pad].position).length()
+                };
+                distance(a).total_cmp(&distance(b))
+            })
+            .unwrap();
+        let start = net_info.pads[&connection.start_pad].position;
+        assert!((start - net_info.pads[&connection.end_pad].position).length() > 6.0);
+
+        // two rings enclose the start pad on both layers, the far trace is in nobody's way
+        let other_net = NetName("other".to_string());
+        let front_ring = square_trace(&other_net, 1000, start, 3.0, 0);
+        let back_ring = square_trace(&other_net, 1001, start, 3.0, 1);
+        let far_trace = square_trace(
+            &other_net,
+            1002,
+            FloatVec2::new(problem.center.x + problem.width * 2.0, problem.center.y),
+            1.0,
+            0,
+        );
+        let mut stack: Vec<HashMap<ConnectionID, FixedTrace>> = vec![HashMap::new()];
+        for fixed_trace in [&front_ring, &back_ring, &far_trace] {
+            let mut fixed_traces = stack.last().unwrap().clone();
+            fixed_traces.insert(fixed_trace.connection_id, fixed_trace.clone());
+            stack.push(fixed_traces);
+        }
+
+        let fixed_traces = stack.last().unwrap();
+        let obstacles = build_pad_obstacles(&problem, &[&net_info.net_name], fixed_traces);
+        let astar_model = astar_model_for_connection(&problem, connection, obstacles);
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        let Err(failure) = astar_model.run_recording_blockers(&mut display_injection) else {
+            panic!("the rings leave no way out of the start pad");
+        };
+        let blocking_colliders = failure.blocking_colliders.expect("the search exhausted its frontier");
+
+        let culprits = traces_owning_colliders(&blocking_colliders, &net_info.net_name, fixed_traces, problem.num_layers);
+        let culprit_ids: Vec<ConnectionID> = culprits.iter().map(|culprit| culprit.connection_id).collect();
+        assert_eq!(culprit_ids, vec![ConnectionID(1000), ConnectionID(1001)]);
+        // the search resumes from the node that went on to fix the back ring, skipping the far trace
+        assert_eq!(backjump_index(stack.iter(), &culprits), Some(1));
+    }
+
+    #[test]
+    fn nogood_prunes_only_when_the_whole_culprit_set_is_fixed_again() {
+        let net_name = NetName("other".to_string());
+        let center = FloatVec2::new(0.0, 0.0);
+        let trace_a = square_trace(&net_name, 1, center, 1.0, 0);
+        let trace_b = square_trace(&net_name, 2, center, 2.0, 0);
+        let moved_b = square_trace(&net_name, 2, center, 4.0, 0);
+        let blocked = ConnectionID(3);
+        let mut nogoods = Nogoods::default();
+        nogoods.learn(&Conflict { connection_id: blocked, culprits: vec![trace_a.clone(), trace_b.clone()] });
+        let fixed = |traces: &[&FixedTrace]| -> HashMap<ConnectionID, FixedTrace> {
+            traces.iter().map(|fixed_trace| (fixed_trace.connection_id, (*fixed_trace).clone())).collect()
+        };
+
+        // the same branch again
+        assert!(nogoods.forbids(trace_b.connection_id, &trace_b.trace_path, &fixed(&[&trace_a])));
+        assert!(nogoods.forbids(trace_a.connection_id, &trace_a.trace_path, &fixed(&[&trace_b])));
+        // only part of the set
+        assert!(!nogoods.forbids(trace_a.connection_id, &trace_a.trace_path, &fixed(&[])));
+        // another trace for one of the culprits
+        assert!(!nogoods.forbids(moved_b.connection_id, &moved_b.trace_path, &fixed(&[&trace_a])));
+        assert!(!nogoods.forbids(trace_a.connection_id, &trace_a.trace_path, &fixed(&[&moved_b])));
+        // the blocked connection already found a place
+        let mut with_blocked = fixed(&[&trace_a]);
+        with_blocked.insert(blocked, FixedTrace { connection_id: blocked, ..moved_b.clone() });
+        assert!(!nogoods.forbids(trace_b.connection_id, &trace_b.trace_path, &with_blocked));
+    }
+
+    #[test]
+    fn a_colliding_candidate_does_not_keep_the_connection_from_a_later_solution() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 2);
+        let mut net_infos: Vec<_> = problem.nets.values().collect();
+        net_infos.sort_by(|a, b| a.net_name.0.cmp(&b.net_name.0));
+        let net_info = net_infos[0];
+        let connection = net_info.connections.values().min_by_key(|connection| connection.connection_id).unwrap();
+        let start = net_info.pads[&connection.start_pad].position;
+        let end = net_info.pads[&connection.end_pad].position;
+        // a small square of another net between the pads, the straight candidate runs into it but a path around it is free
+        let other_net = NetName("other".to_string());
+        let midpoint = FloatVec2::new((start.x + end.x) / 2.0, (start.y + end.y) / 2.0);
+        let square = square_trace(&other_net, 1000, midpoint, 0.3, 0);
+        let candidate_conflict = Conflict { connection_id: connection.connection_id, culprits: vec![square.clone()] };
+
+        // learned from the candidate, the nogood would forbid fixing the square while the connection is open
+        let mut unsound_nogoods = Nogoods::default();
+        unsound_nogoods.learn(&candidate_conflict);
+        assert!(unsound_nogoods.forbids(square.connection_id, &square.trace_path, &HashMap::new()));
+
+        // A* still finds a path around the square, so there is nothing to learn
+        let fixed_traces = HashMap::from([(square.connection_id, square.clone())]);
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        assert!(exhausted_conflict(&problem, connection.connection_id, &fixed_traces, &mut display_injection).is_none());
+        let obstacles = build_pad_obstacles(&problem, &[&net_info.net_name], &fixed_traces);
+        let path = astar_model_for_connection(&problem, connection, obstacles).run(&mut display_injection);
+        assert!(path.is_ok());
+
+        // rings around the start pad on both layers leave it no way out, that conflict is learned
+        let front_ring = square_trace(&other_net, 1001, start, 3.0, 0);
+        let back_ring = square_trace(&other_net, 1002, start, 3.0, 1);
+        let fixed_traces = HashMap::from([(front_ring.connection_id, front_ring), (back_ring.connection_id, back_ring)]);
+        let conflict = exhausted_conflict(&problem, connection.connection_id, &fixed_traces, &mut display_injection).unwrap();
+        let culprit_ids: Vec<ConnectionID> = conflict.culprits.iter().map(|culprit| culprit.connection_id).collect();
+        assert_eq!(culprit_ids, vec![ConnectionID(1001), ConnectionID(1002)]);
+    }
+}
@@ -1,21 +1,21 @@
 use std::{
     collections::{BinaryHeap, HashMap},
-    rc::Rc,
-    sync::{atomic::Ordering, Arc, Mutex},
+    sync::Arc,
 };
 
 use ordered_float::NotNan;
+use serde::{Deserialize, Serialize};
 use shared::{
     binary_heap_item::BinaryHeapItem,
     pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
     pcb_render_model::PcbRenderModel,
 };
 
-use crate::{bayesian_backtrack_algo::TraceCache, display_injection::{self, DisplayInjection}, proba_model::{ProbaModel, ProbaTrace, Traces}};
+use crate::{backjumping::{blocking_traces, Conflict, Nogoods}, bayesian_backtrack_algo::TraceCache, display_injection::{self, DisplayInjection}, proba_model::{ProbaModel, ProbaTrace, Traces}, shove::{shove_traces, ShoveObstacles}};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BacktrackNode {
-    pub remaining_trace_candidates: BinaryHeap<BinaryHeapItem<NotNan<f64>, Rc<ProbaTrace>>>, // The remaining trace candidates to be processed, sorted by their scores)>
+    pub remaining_trace_candidates: BinaryHeap<BinaryHeapItem<NotNan<f64>, Arc<ProbaTrace>>>, // The remaining trace candidates to be processed, sorted by their scores)>
     pub fixed_traces: HashMap<ConnectionID, FixedTrace>,
     pub fix_sequence: Vec<ConnectionID>, // The sequence of connections that have been fixed in this node
     pub prob_up_to_date: bool, // Whether the probabilistic model is up to date
@@ -25,7 +25,7 @@ impl BacktrackNode {
     fn from_proba_model(proba_model: &ProbaModel) -> Self {
         let mut fixed_traces: HashMap<ConnectionID, FixedTrace> = HashMap::new();
         let mut remaining_trace_candidates: BinaryHeap<
-            BinaryHeapItem<NotNan<f64>, Rc<ProbaTrace>>,
+            BinaryHeapItem<NotNan<f64>, Arc<ProbaTrace>>,
         > = BinaryHeap::new();
         for (connection_id, traces) in proba_model.connection_to_traces.iter() {
             match traces {
@@ -59,7 +59,7 @@ impl BacktrackNode {
         // Remove all trace candidates for this connection from the remaining candidates
         let mut remaining_trace_candidates_copy = self.remaining_trace_candidates.clone();
         let mut new_remaining_trace_candidates: BinaryHeap<
-            BinaryHeapItem<NotNan<f64>, Rc<ProbaTrace>>,
+            BinaryHeapItem<NotNan<f64>, Arc<ProbaTrace>>,
         > = BinaryHeap::new();
         for candidate in remaining_trace_candidates_copy.drain() {
             if candidate.value.connection_id != connection_id {
@@ -70,61 +70,71 @@ impl BacktrackNode {
         // Mark the probabilistic model as no longer up to date
         self.prob_up_to_date = false;
     }
-    /// If an attemp fails, return none; it will pop the priority queue in both scenarios
+    /// If an attemp fails, return the conflicts of the tried candidates; it will pop the priority queue in both scenarios
     /// assume there are still candidates in the priority queue
+    /// a colliding candidate is still accepted if the fixed traces in its way can be shoved aside,
+    /// a candidate that repeats a learned nogood is skipped
     pub fn try_fix_top_k_ranked_trace(
         &mut self,
+        problem: &PcbProblem,
+        prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+        shove_obstacles: &ShoveObstacles,
+        nogoods: &Nogoods,
         mut display_and_block: impl FnMut(&BacktrackNode),
         k: usize,
-    ) -> Option<Self> {
+    ) -> Result<Self, Vec<Conflict>> {
         // for self, peek from the priority queue
         // if succeed, remove all traces from the same connection, and generate a new node with the same priority queue and a fixed trace
-        // if fail, return error
-        let mut result_candidate: Option<BinaryHeapItem<NotNan<f64>, Rc<ProbaTrace>>> = None;
+        // if fail, return the fixed traces that blocked each tried candidate
+        let mut result_candidate: Option<BinaryHeapItem<NotNan<f64>, Arc<ProbaTrace>>> = None;
+        let mut shoved_traces: HashMap<ConnectionID, FixedTrace> = HashMap::new();
+        let mut conflicts: Vec<Conflict> = Vec::new();
         for i in 0..k {
             let top_ranked_candidate = self.remaining_trace_candidates.pop();
             let top_ranked_candidate = match top_ranked_candidate {
                 Some(candidate) => candidate,
                 None => {
                     println!("In try fix top k ranekd trace: No more trace candidates to fix");
-                    return None; // No more candidates to fix
+                    return Err(Conflict::merge(conflicts)); // No more candidates to fix
                 }
             };
             let top_ranked_trace_path = &top_ranked_candidate.value.trace_path;
             let top_ranked_trace_net = &top_ranked_candidate.value.net_name;
+            let connection_id = top_ranked_candidate.value.connection_id;
+            if nogoods.forbids(connection_id, top_ranked_trace_path, &self.fixed_traces) {
+                println!("Trial {}: Top ranked trace {} repeats a learned nogood, skipping it", i, top_ranked_trace_net.0);
+                continue;
+            }
             // check if the trace collides with any fixed trace
-            let filtered_fixed_traces: Vec<_> = self
-                .fixed_traces
-                .values()
-                .filter(|fixed_trace| fixed_trace.net_name != *top_ranked_trace_net)
-                .collect();
-            let mut collision_found = false;
-            for fixed_trace in filtered_fixed_traces {
-                if top_ranked_trace_path.collides_with(&fixed_trace.trace_path) {
-                    // If it collides, we cannot fix this trace
-                    println!("In try fix k top ranked trace:");
-                    println!(
-                        "Trial {}: Top ranked trace {} collides with a fixed trace {}, cannot fix it",
-                        i, top_ranked_candidate.value.net_name.0, fixed_trace.net_name.0
-                    );
-                    let mut display_node = self.clone();
-                    let connection_id = top_ranked_candidate.value.connection_id;
-                    // Create a new fixed trace
-                    let fixed_trace = FixedTrace {
-                        net_name: top_ranked_candidate.value.net_name.clone(),
-                        connection_id,
-                        trace_path: top_ranked_trace_path.clone(),
-                    };
-                    display_node.fix_trace(connection_id, fixed_trace);
-                    display_and_block(&display_node);
-                    collision_found = true;
-                    break;
-                }
+            let culprits = blocking_traces(top_ranked_trace_path, top_ranked_trace_net, &self.fixed_traces);
+            if culprits.is_empty() {
+                result_candidate = Some(top_ranked_candidate);
+                break;
             }
-            if !collision_found {
+            let proposed_trace = FixedTrace {
+                net_name: top_ranked_candidate.value.net_name.clone(),
+                connection_id,
+                trace_path: top_ranked_trace_path.clone(),
+            };
+            if let Some(moved) = shove_traces(problem, &self.fixed_traces, prerouted_traces, shove_obstacles, &proposed_trace) {
+                println!(
+                    "Trial {}: Top ranked trace {} shoved {} fixed traces aside",
+                    i, top_ranked_candidate.value.net_name.0, moved.len()
+                );
+                shoved_traces = moved;
                 result_candidate = Some(top_ranked_candidate);
                 break;
             }
+            // If it collides, we cannot fix this trace
+            println!("In try fix k top ranked trace:");
+            println!(
+                "Trial {}: Top ranked trace {} collides with {} fixed traces, the first of net {}, cannot fix it",
+                i, top_ranked_candidate.value.net_name.0, culprits.len(), culprits[0].net_name.0
+            );
+            let mut display_node = self.clone();
+            display_node.fix_trace(connection_id, proposed_trace);
+            display_and_block(&display_node);
+            conflicts.push(Conflict { connection_id, culprits });
         }
         if let Some(result_candidate) = result_candidate {
             // If it does not collide, we can fix this trace
@@ -137,10 +147,11 @@ impl BacktrackNode {
             };
             // delete all trace candidates for this connection in the new node
             let mut new_node = self.clone();
+            new_node.fixed_traces.extend(shoved_traces);
             new_node.fix_trace(connection_id, fixed_trace);
-            Some(new_node) // Return the new node with the fixed trace
+            Ok(new_node) // Return the new node with the fixed trace
         } else {
-            None // No more candidates to fix
+            Err(Conflict::merge(conflicts)) // No more candidates to fix
         }
     }
     pub fn from_fixed_traces(
@@ -150,7 +161,7 @@ impl BacktrackNode {
         trace_cache: &mut TraceCache,
         display_injection: &mut DisplayInjection
     ) -> Result<Self, String> {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        if display_injection.is_stopped() {
             println!("Stop requested, not creating BacktrackNode from fixed traces");
             return Err("Stop requested".to_string());
         }
@@ -165,7 +176,7 @@ impl BacktrackNode {
         trace_cache: &mut TraceCache,
         display_injection: &mut DisplayInjection,
     ) -> Result<(), String> {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        if display_injection.is_stopped() {
             println!("Stop requested, not updating ProbaModel");
             return Err("Stop requested".to_string());
         }
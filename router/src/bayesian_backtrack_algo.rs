@@ -5,32 +5,63 @@ use std::{
     time::Duration,
 };
 
+use serde::{Deserialize, Serialize};
 use shared::{
-    color_float3::ColorFloat3,
-    hyperparameters::{NUM_TOP_RANKED_TO_TRY, UPDATE_PROBA_SKIP_STRIDE},
-    pcb_problem::{ConnectionID, NetName, PcbProblem, PcbSolution},
-    pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable, UpdatePcbRenderModel},
-    prim_shape::PrimShape, trace_path::TracePath,
+    hyperparameters::{MAX_BAYESIAN_BACKJUMPS, NUM_TOP_RANKED_TO_TRY, UPDATE_PROBA_SKIP_STRIDE, USE_BACKJUMPING},
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem, PcbSolution},
+    trace_path::TracePath,
 };
 
 use crate::{
-    backtrack_node::BacktrackNode, block_or_sleep, command_flags::{CommandFlag, TARGET_COMMAND_LEVEL}, display_injection::{self, DisplayInjection}, naive_backtrack_algo::naive_backtrack
+    backjumping::{backjump_index, exhausted_conflict, Nogoods}, backtrack_node::BacktrackNode, block_or_sleep, checkpoint::{is_checkpoint_due, is_resuming_naive_backtrack, take_resumed_bayesian_backtrack, write_checkpoint, SearchState}, command_flags::CommandFlag, deterministic_rand::current_seed, display_injection::{display_when_necessary, DisplayInjection}, naive_backtrack_algo::naive_backtrack, partial_solution::{to_partial_solution, BestPartialState}, search_strategy::{search_frontier_from_settings, search_with_frontier, SearchContext, SearchOutcome}, shove::ShoveObstacles
 };
 
 
+/// the traces found for every connection so far, kept across runs so a reroute can reuse them
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct TraceCache{
     pub traces: HashMap<ConnectionID, Vec<TracePath>>,
 }
 
 
+fn node_to_solution(pcb_problem: &PcbProblem, node: &BacktrackNode) -> PcbSolution {
+    PcbSolution {
+        determined_traces: node.fixed_traces.clone(),
+        unrouted_connections: Vec::new(),
+        diff_pair_skews: HashMap::new(),
+        teardrops: Vec::new(),
+        zone_fills: Vec::new(),
+        seed: current_seed(),
+        scale_down_factor: pcb_problem.scale_down_factor,
+        pareto_front: Vec::new(),
+    }
+}
+
+/// the connections in the order the node fixed them, then the ones it left open
+fn naive_backtrack_heuristics(node: &BacktrackNode, connections: &[ConnectionID]) -> Vec<ConnectionID> {
+    let mut connections_set: HashSet<ConnectionID> = connections.iter().cloned().collect();
+    let mut heuristics: Vec<ConnectionID> = Vec::new();
+    assert!(node.fixed_traces.len() == node.fix_sequence.len(), "Fixed traces and fix sequence must have the same length");
+    for connection_id in node.fix_sequence.iter(){
+        heuristics.push(*connection_id);
+        connections_set.remove(connection_id);
+    }
+    for connection_id in connections_set.iter() {
+        heuristics.push(*connection_id);
+    }
+    assert!(heuristics.len() == connections.len(), "Heuristics must contain all connections");
+    heuristics
+}
+
 pub fn bayesian_backtrack(
     pcb_problem: &PcbProblem,
     trace_cache: &mut TraceCache,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
     display_injection: &mut DisplayInjection,
 ) -> Result<PcbSolution, String> {
-    if display_injection.stop_requested.load(Ordering::Relaxed) {
+    if display_injection.is_stopped() {
         println!("Stop requested, not running Bayesian backtrack");
-        return Err("Stop requested".to_string());
+        return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
     }
     let connections = pcb_problem.nets.iter()
         .flat_map(|(_, net_info)| net_info.connections.keys().cloned())
@@ -66,89 +97,87 @@ pub fn bayesian_backtrack(
     //     }
     // }
 
-    fn node_to_pcb_render_model(problem: &PcbProblem, node: &BacktrackNode) -> PcbRenderModel {
-        let mut trace_shape_renderables: Vec<RenderableBatch> = Vec::new();
-        let mut pad_shape_renderables: Vec<ShapeRenderable> = Vec::new();
-        let mut other_shape_renderables: Vec<ShapeRenderable> = Vec::new();
-        let mut net_name_to_color: HashMap<NetName, ColorFloat3> = HashMap::new();
-        for (_, net_info) in problem.nets.iter() {
-            net_name_to_color.insert(net_info.net_name.clone(), net_info.color);
-            for pad in net_info.pads.values(){
-                let pad_renderables = pad.to_renderables(net_info.color.to_float4(1.0));
-                let pad_clearance_renderables = pad.to_clearance_renderables(net_info.color.to_float4(0.5));
-                pad_shape_renderables.extend(pad_renderables);
-                pad_shape_renderables.extend(pad_clearance_renderables);
-            }
-        }
-        for fixed_trace in node.fixed_traces.values() {
-            let renderable_batches = fixed_trace
-                .trace_path
-                .to_renderables(net_name_to_color[&fixed_trace.net_name].to_float4(1.0));
-            trace_shape_renderables.extend(renderable_batches);
-        }
-        for line in &problem.obstacle_border_outlines {
-            other_shape_renderables.push(ShapeRenderable {
-                shape: PrimShape::Line(line.clone()),
-                color: [1.0, 0.0, 1.0, 1.0], // magenta color for borders
-            });
-        }
-        PcbRenderModel {
-            width: problem.width,
-            height: problem.height,
-            center: problem.center,
-            trace_shape_renderables,
-            pad_shape_renderables,
-            other_shape_renderables,
-        }
+    if is_resuming_naive_backtrack(display_injection) {
+        println!("Resuming the naive backtrack the Bayesian backtrack handed over to");
+        return naive_backtrack(pcb_problem, trace_cache, None, prerouted_traces, display_injection);
     }
-
-    fn display_when_necessary(
-        node: &BacktrackNode,
-        pcb_problem: &PcbProblem,
-        command_flag: CommandFlag,
-        display_injection: &mut DisplayInjection,
-        fall_through: bool, 
-    ) {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
-            println!("Stop requested, not displaying node");
-            return;
-        }
-        // println!("Displaying node in bayesian backtrack");
-        let target_command_level = TARGET_COMMAND_LEVEL.load(Ordering::Relaxed);
-        let task_command_level = command_flag.get_level();
-        if target_command_level <= task_command_level {
-            let render_model = node_to_pcb_render_model(pcb_problem, node);
-            while !(display_injection.can_submit_render_model)() {
-                // wait until we can submit the render model
-            }            
-            (display_injection.submit_render_model)(render_model);
-            if !fall_through{
-                (display_injection.block_until_signal)();        
+    // a failed or stopped search returns the node that got furthest, here or in the naive backtrack that takes over
+    // nogoods are learned while the search runs, a resumed search starts without them
+    let mut nogoods = Nogoods::default();
+    let shove_obstacles = ShoveObstacles::new(pcb_problem);
+    let mut best_partial_state = BestPartialState::new(prerouted_traces);
+    let mut heuristics: Option<Vec<ConnectionID>> = None;
+    if let Some((resumed_node_stack, best_partial_traces)) = take_resumed_bayesian_backtrack(display_injection) {
+        println!("Resuming the Bayesian backtrack with {} nodes on the stack", resumed_node_stack.len());
+        node_stack = resumed_node_stack;
+        best_partial_state.fixed_traces = best_partial_traces;
+    } else {
+        // prerouted traces start out fixed and are never backtracked
+        let mut prerouted_sequence: Vec<ConnectionID> = prerouted_traces.keys().cloned().collect();
+        prerouted_sequence.sort();
+        let first_node =
+            match BacktrackNode::from_fixed_traces(pcb_problem, prerouted_traces, prerouted_sequence, trace_cache, display_injection) {
+                Ok(first_node) => first_node,
+                Err(_) if display_injection.is_stopped() => {
+                    return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
+                }
+                Err(err) => return Err(err),
+            };
+        // assume the first node has trace candidates
+        if let Some(frontier) = search_frontier_from_settings() {
+            let display_node = |node: &BacktrackNode, display_injection: &mut DisplayInjection| {
+                display_when_necessary(&node.fixed_traces, pcb_problem, CommandFlag::ProbaModelResult, display_injection, false);
+            };
+            let context = SearchContext {
+                problem: pcb_problem,
+                trace_cache,
+                prerouted_traces,
+                shove_obstacles: &shove_obstacles,
+                nogoods: &mut nogoods,
+                best_partial_state: &mut best_partial_state,
+                display_injection,
+            };
+            let outcome = search_with_frontier(context, first_node, frontier, display_node)?;
+            match outcome {
+                SearchOutcome::Solved(node) => {
+                    println!("Found a solution!");
+                    display_when_necessary(&node.fixed_traces, pcb_problem, CommandFlag::Auto, display_injection, true);
+                    return Ok(node_to_solution(pcb_problem, &node));
+                }
+                SearchOutcome::Stopped => {
+                    return Ok(to_partial_solution(pcb_problem, best_partial_state.fixed_traces, display_injection));
+                }
+                SearchOutcome::Exhausted(deepest_node) => {
+                    println!("Failed to find a solution in the search, bringing the heuristics to naive backtrack");
+                    heuristics = Some(naive_backtrack_heuristics(&deepest_node, &connections));
+                }
             }
         } else {
-            if (display_injection.can_submit_render_model)() {
-                // If we can submit the render model, do it
-                let render_model = node_to_pcb_render_model(pcb_problem, node);
-                (display_injection.submit_render_model)(render_model);
-            }
+            node_stack.push(first_node);
         }
     }
-
-    let first_node =
-        BacktrackNode::from_fixed_traces(pcb_problem, &HashMap::new(), Vec::new(), trace_cache, display_injection)?;
-    // assume the first node has trace candidates
-    node_stack.push(first_node);
-
-    let mut heuristics: Option<Vec<ConnectionID>> = None;
+    let mut num_backjumps = 0;
 
     while node_stack.len() > 0 {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        display_injection.budget.check();
+        if is_checkpoint_due(display_injection) {
+            write_checkpoint(
+                prerouted_traces,
+                trace_cache,
+                SearchState::BayesianBacktrack {
+                    node_stack: node_stack.clone(),
+                    best_partial_traces: best_partial_state.fixed_traces.clone(),
+                },
+                display_injection,
+            );
+        }
+        if display_injection.is_stopped() {
             println!("Stop requested, exiting Bayesian backtrack");
-            return Err("Stop requested".to_string());
+            return Ok(to_partial_solution(pcb_problem, best_partial_state.fixed_traces, display_injection));
         }
         // print_current_stack(&node_stack);
         display_when_necessary(
-            node_stack.last().unwrap(),
+            &node_stack.last().unwrap().fixed_traces,
             pcb_problem,
             CommandFlag::ProbaModelResult,
             display_injection,
@@ -159,61 +188,104 @@ pub fn bayesian_backtrack(
             println!("Found a solution!");
             // println!("Number of samples taken: {}", shared::hyperparameters::SAMPLE_CNT.load(Ordering::SeqCst));
             // If the top node is a solution, we can return it
-            let fixed_traces = top_node.fixed_traces.clone();
-            let solution = PcbSolution {
-                determined_traces: fixed_traces,
-                scale_down_factor: pcb_problem.scale_down_factor,
-            };
+            let solution = node_to_solution(pcb_problem, top_node);
             // println!("Successfully found a solution with sample count {}", shared::hyperparameters::SAMPLE_CNT.load(Ordering::SeqCst));
             
 
-            display_when_necessary(top_node, pcb_problem, CommandFlag::Auto, display_injection, true);
+            display_when_necessary(&top_node.fixed_traces, pcb_problem, CommandFlag::Auto, display_injection, true);
             // heuristics = Some(top_node.fix_sequence.clone());
             // break; // break the loop to return the solution
             return Ok(solution);
         }
         let display_and_block_closure = |node: &BacktrackNode| {
-            display_when_necessary(node, pcb_problem, CommandFlag::ProbaModelResult, display_injection, false);
+            display_when_necessary(&node.fixed_traces, pcb_problem, CommandFlag::ProbaModelResult, display_injection, false);
         };
-        let new_node =
-            top_node.try_fix_top_k_ranked_trace(display_and_block_closure, NUM_TOP_RANKED_TO_TRY.load(Ordering::Relaxed));
-        if new_node.is_some(){
+        let new_node = top_node.try_fix_top_k_ranked_trace(
+            pcb_problem,
+            prerouted_traces,
+            &shove_obstacles,
+            &nogoods,
+            display_and_block_closure,
+            NUM_TOP_RANKED_TO_TRY.load(Ordering::Relaxed),
+        );
+        if let Ok(mut new_node) = new_node {
             println!(
                 "Successfully fixed the top ranked trace, pushing new node onto the stack"
             );
             // assert!(new_node.prob_up_to_date, "New node must be up to date");
-            let mut new_node = new_node.unwrap();
             if node_stack.len() % UPDATE_PROBA_SKIP_STRIDE.load(Ordering::Relaxed) == 0 {
                 let result = new_node.try_update_proba_model(pcb_problem, trace_cache, display_injection);
                 if let Err(err) = result {
+                    if display_injection.is_stopped() {
+                        println!("Stop requested while updating the probabilistic model, exiting Bayesian backtrack");
+                        // the new node has no model yet, the checkpoint goes on from its parent
+                        write_checkpoint(
+                            prerouted_traces,
+                            trace_cache,
+                            SearchState::BayesianBacktrack {
+                                node_stack: node_stack.clone(),
+                                best_partial_traces: best_partial_state.fixed_traces.clone(),
+                            },
+                            display_injection,
+                        );
+                        best_partial_state.offer(&new_node.fixed_traces);
+                        return Ok(to_partial_solution(pcb_problem, best_partial_state.fixed_traces, display_injection));
+                    }
                     println!("Failed to update the probabilistic model: {}", err);
                     panic!("Failed to update the probabilistic model");
                 }
             }
+            best_partial_state.offer(&new_node.fixed_traces);
+            display_injection.budget.add_backtrack_nodes(1);
             node_stack.push(new_node);
             continue; // Continue to the next iteration
         }else{
-            let mut connections_set: HashSet<ConnectionID> = connections.iter().cloned().collect();
-            let mut temp_heuristics: Vec<ConnectionID> = Vec::new();
-            assert!(top_node.fixed_traces.len() == top_node.fix_sequence.len(), "Fixed traces and fix sequence must have the same length");
-            for connection_id in top_node.fix_sequence.iter(){
-                temp_heuristics.push(*connection_id);
-                connections_set.remove(connection_id);
-            }
-            for connection_id in connections_set.iter() {
-                temp_heuristics.push(*connection_id);
+            let conflicts = new_node.unwrap_err();
+            // jump back to the node that fixed the traces in the way instead of giving up on this search
+            if USE_BACKJUMPING.load(Ordering::Relaxed)
+                && num_backjumps < MAX_BAYESIAN_BACKJUMPS.load(Ordering::Relaxed)
+                && !conflicts.is_empty()
+            {
+                // the tried candidates collided, a nogood is only learned if A* finds no path either
+                let fixed_traces = &node_stack.last().unwrap().fixed_traces;
+                for conflict in conflicts.iter() {
+                    if let Some(conflict) = exhausted_conflict(pcb_problem, conflict.connection_id, fixed_traces, display_injection) {
+                        nogoods.learn(&conflict);
+                    }
+                }
+                let culprits: Vec<FixedTrace> = conflicts.into_iter().flat_map(|conflict| conflict.culprits).collect();
+                if let Some(index) = backjump_index(node_stack.iter().map(|node| &node.fixed_traces), &culprits) {
+                    println!("Backjumping from node {} to node {}", node_stack.len() - 1, index);
+                    node_stack.truncate(index + 1);
+                    num_backjumps += 1;
+                    continue;
+                }
             }
-            assert!(temp_heuristics.len() == connections.len(), "Heuristics must contain all connections");
+            let top_node = node_stack.last().unwrap();
             println!("Failed to find a solution in Bayesian backtrack, bringing the heuristics to naive backtrack");
-            heuristics = Some(temp_heuristics);
+            heuristics = Some(naive_backtrack_heuristics(top_node, &connections));
             break;
         }       
     }
     // println!("Number of samples taken by Bayesian backtrack: {}", SAMPLE_CNT.load(Ordering::SeqCst));
     // SAMPLE_CNT.store(0, Ordering::SeqCst);
     assert!(heuristics.is_some(), "Heuristics must be set before calling naive backtrack");
-    let result = naive_backtrack(pcb_problem, trace_cache, heuristics, display_injection);
+    let result = naive_backtrack(pcb_problem, trace_cache, heuristics, prerouted_traces, display_injection);
     // println!("Number of samples taken by Naive backtrack: {}", SAMPLE_CNT.load(Ordering::SeqCst));
     // SAMPLE_CNT.store(0, Ordering::SeqCst);
-    result
+    match result {
+        // the naive backtrack did not get as far as this search did, the partial solution comes from here
+        Ok(solution)
+            if !solution.unrouted_connections.is_empty()
+                && best_partial_state.fixed_traces.len() > solution.determined_traces.len() =>
+        {
+            let bayesian_solution = to_partial_solution(pcb_problem, best_partial_state.fixed_traces, display_injection);
+            if bayesian_solution.unrouted_connections.len() < solution.unrouted_connections.len() {
+                Ok(bayesian_solution)
+            } else {
+                Ok(solution)
+            }
+        }
+        result => result,
+    }
 }
\ No newline at end of file
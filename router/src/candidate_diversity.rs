@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+
+use shared::{
+    trace_path::{TracePath, TraceSegment},
+    vec2::{FixedVec2, FloatVec2},
+};
+
+use crate::{
+    astar::AStarCostMap,
+    cell_grid::{CellGrid, GridCell},
+};
+
+/// steers A* away from the cells covered by the earlier candidates of a connection,
+/// so the next search returns an alternative instead of the same path again
+pub struct DiversityCostMap {
+    pub grid: CellGrid,
+    pub used_cells: HashSet<GridCell>,
+    pub penalty: f64, // extra cost per unit of length that runs through used cells
+}
+
+impl DiversityCostMap {
+    pub fn new(earlier_candidates: &[TracePath], cell_size: f32, penalty: f64) -> Self {
+        let grid = CellGrid { cell_size };
+        let used_cells = earlier_candidates
+            .iter()
+            .flat_map(|trace_path| grid.cells_of_trace(trace_path))
+            .collect();
+        DiversityCostMap { grid, used_cells, penalty }
+    }
+
+    fn used_fraction(&self, cells: &HashSet<GridCell>) -> f64 {
+        if cells.is_empty() {
+            return 0.0;
+        }
+        let num_used_cells = cells.iter().filter(|cell| self.used_cells.contains(cell)).count();
+        num_used_cells as f64 / cells.len() as f64
+    }
+}
+
+impl AStarCostMap for DiversityCostMap {
+    fn planar_cost(&self, start: FixedVec2, end: FixedVec2, layer: usize) -> f64 {
+        let cells = self.grid.cells_of_segment(start.to_float(), end.to_float(), 0.0, layer);
+        let length: f64 = (end - start).length().to_num();
+        self.penalty * length * self.used_fraction(&cells)
+    }
+    fn via_cost(&self, position: FixedVec2, min_layer: usize, max_layer: usize) -> f64 {
+        let cells = self.grid.cells_of_via(position.to_float(), 0.0, min_layer, max_layer);
+        self.penalty * self.grid.cell_size as f64 * self.used_fraction(&cells)
+    }
+    fn is_congested(&self, _start: FixedVec2, _end: FixedVec2, _layer: usize) -> bool {
+        false // overlapping an earlier candidate is allowed, just not preferred
+    }
+}
+
+/// the Hausdorff distance between the centerlines of two traces, sampled every step,
+/// segments on different layers never count as close
+pub fn hausdorff_distance(trace_path1: &TracePath, trace_path2: &TracePath, step: f32) -> f32 {
+    f32::max(
+        directed_hausdorff_distance(trace_path1, trace_path2, step),
+        directed_hausdorff_distance(trace_path2, trace_path1, step),
+    )
+}
+
+/// whether the trace is closer than min_distance to one of the earlier candidates
+pub fn is_near_duplicate(trace_path: &TracePath, earlier_candidates: &[TracePath], min_distance: f32, step: f32) -> bool {
+    earlier_candidates.iter().any(|earlier_candidate| {
+        trace_path.anchors == earlier_candidate.anchors
+            || hausdorff_distance(trace_path, earlier_candidate, step) < min_distance
+    })
+}
+
+fn directed_hausdorff_distance(from: &TracePath, to: &TracePath, step: f32) -> f32 {
+    let mut max_distance: f32 = 0.0;
+    for segment in from.segments.iter() {
+        let start = segment.start.to_float();
+        let end = segment.end.to_float();
+        let num_steps = ((end - start).length() / step).ceil().max(1.0) as usize;
+        for i in 0..=num_steps {
+            let t = i as f32 / num_steps as f32;
+            let point = FloatVec2::new(start.x + (end.x - start.x) * t, start.y + (end.y - start.y) * t);
+            let distance = to
+                .segments
+                .iter()
+                .filter(|other_segment| other_segment.layer == segment.layer)
+                .map(|other_segment| point_segment_distance(point, other_segment))
+                .fold(f32::INFINITY, f32::min);
+            max_distance = max_distance.max(distance);
+        }
+    }
+    max_distance
+}
+
+fn point_segment_distance(point: FloatVec2, segment: &TraceSegment) -> f32 {
+    let start = segment.start.to_float();
+    let direction = segment.end.to_float() - start;
+    let length2 = direction.magnitude2();
+    let t = if length2 > 0.0 {
+        ((point - start).dot(direction) / length2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let closest = FloatVec2::new(start.x + direction.x * t, start.y + direction.y * t);
+    (point - closest).length()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::{
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::{FixedVec2, FloatVec2},
+    };
+
+    use super::{hausdorff_distance, is_near_duplicate, DiversityCostMap};
+    use crate::astar::AStarCostMap;
+
+    fn point(x: f32, y: f32) -> FixedVec2 {
+        FloatVec2::new(x, y).to_fixed()
+    }
+
+    /// a trace of width and clearance 0.2 through the points on one layer
+    fn trace_through(points: &[(f32, f32)], layer: usize) -> TracePath {
+        let anchors = points
+            .iter()
+            .map(|&(x, y)| TraceAnchor { position: point(x, y), start_layer: layer, end_layer: layer })
+            .collect();
+        TracePath::from_anchors(TraceAnchors(anchors), 0.2, 0.2, Vec::new())
+    }
+
+    #[test]
+    fn the_hausdorff_distance_is_the_farthest_a_trace_strays_from_the_other() {
+        let straight = trace_through(&[(0.0, 0.0), (10.0, 0.0)], 0);
+        let parallel = trace_through(&[(0.0, 1.0), (10.0, 1.0)], 0);
+        // the top of the bump is 2 from the straight trace, and nothing is farther apart
+        let bump = trace_through(&[(0.0, 0.0), (4.0, 0.0), (6.0, 2.0), (8.0, 0.0), (10.0, 0.0)], 0);
+        let step = 0.1;
+        assert_eq!(hausdorff_distance(&straight, &straight, step), 0.0);
+        assert!((hausdorff_distance(&straight, &parallel, step) - 1.0).abs() < 1e-3);
+        assert!((hausdorff_distance(&straight, &bump, step) - 2.0).abs() < 1e-3);
+        assert_eq!(hausdorff_distance(&straight, &bump, step), hausdorff_distance(&bump, &straight, step));
+        let on_the_back = trace_through(&[(0.0, 0.0), (10.0, 0.0)], 1);
+        assert_eq!(hausdorff_distance(&straight, &on_the_back, step), f32::INFINITY);
+    }
+
+    #[test]
+    fn a_trace_near_an_earlier_candidate_is_a_near_duplicate() {
+        let earlier_candidates = vec![trace_through(&[(0.0, 0.0), (10.0, 0.0)], 0)];
+        let step = 0.1;
+        // the same anchors are a duplicate even when any distance is enough
+        assert!(is_near_duplicate(&earlier_candidates[0], &earlier_candidates, 0.0, step));
+        assert!(is_near_duplicate(&trace_through(&[(0.0, 0.2), (10.0, 0.2)], 0), &earlier_candidates, 0.5, step));
+        assert!(!is_near_duplicate(&trace_through(&[(0.0, 2.0), (10.0, 2.0)], 0), &earlier_candidates, 0.5, step));
+        assert!(!is_near_duplicate(&earlier_candidates[0], &[], 0.5, step));
+    }
+
+    #[test]
+    fn the_diversity_cost_map_charges_for_the_share_of_a_step_through_used_cells() {
+        let earlier_candidates = vec![trace_through(&[(0.0, 0.0), (10.0, 0.0)], 0)];
+        let penalty = 3.0;
+        let cost_map = DiversityCostMap::new(&earlier_candidates, 1.0, penalty);
+        // along the earlier candidate every cell is used, the step pays the penalty over its whole length
+        assert!((cost_map.planar_cost(point(2.0, 0.0), point(8.0, 0.0), 0) - penalty * 6.0).abs() < 1e-6);
+        // cells x 8 to 14, of which 8, 9 and 10 are used
+        let partly_used = cost_map.planar_cost(point(8.0, 0.0), point(14.0, 0.0), 0);
+        assert!((partly_used - penalty * 6.0 * 3.0 / 7.0).abs() < 1e-6);
+        assert_eq!(cost_map.planar_cost(point(2.0, 5.0), point(8.0, 5.0), 0), 0.0);
+        assert_eq!(cost_map.planar_cost(point(2.0, 0.0), point(8.0, 0.0), 1), 0.0);
+        // a via pays on the used layers it spans
+        assert!((cost_map.via_cost(point(5.0, 0.0), 0, 1) - penalty * 0.5).abs() < 1e-6);
+        assert_eq!(cost_map.via_cost(point(5.0, 5.0), 0, 1), 0.0);
+        assert!(!cost_map.is_congested(point(2.0, 0.0), point(8.0, 0.0), 0));
+    }
+}
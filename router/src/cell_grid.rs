@@ -0,0 +1,64 @@
+use std::collections::HashSet;
+
+use shared::{trace_path::TracePath, vec2::FloatVec2};
+
+/// a square cell of the board on one layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridCell {
+    pub x: i32,
+    pub y: i32,
+    pub layer: usize,
+}
+
+/// splits the board into square cells, so the space a trace takes up can be counted and compared
+pub struct CellGrid {
+    pub cell_size: f32,
+}
+
+impl CellGrid {
+    fn cells_of_box(&self, position: FloatVec2, radius: f32, layer: usize, cells: &mut HashSet<GridCell>) {
+        let x_min = ((position.x - radius) / self.cell_size).floor() as i32;
+        let x_max = ((position.x + radius) / self.cell_size).floor() as i32;
+        let y_min = ((position.y - radius) / self.cell_size).floor() as i32;
+        let y_max = ((position.y + radius) / self.cell_size).floor() as i32;
+        for x in x_min..=x_max {
+            for y in y_min..=y_max {
+                cells.insert(GridCell { x, y, layer });
+            }
+        }
+    }
+    /// cells swept by a segment of the given radius (half width plus half clearance)
+    pub fn cells_of_segment(&self, start: FloatVec2, end: FloatVec2, radius: f32, layer: usize) -> HashSet<GridCell> {
+        let mut cells = HashSet::new();
+        let length = (end - start).length();
+        let num_steps = (length / (self.cell_size / 2.0)).ceil().max(1.0) as usize;
+        for step in 0..=num_steps {
+            let t = step as f32 / num_steps as f32;
+            let position = FloatVec2::new(
+                start.x + (end.x - start.x) * t,
+                start.y + (end.y - start.y) * t,
+            );
+            self.cells_of_box(position, radius, layer, &mut cells);
+        }
+        cells
+    }
+    pub fn cells_of_via(&self, position: FloatVec2, radius: f32, min_layer: usize, max_layer: usize) -> HashSet<GridCell> {
+        let mut cells = HashSet::new();
+        for layer in min_layer..=max_layer {
+            self.cells_of_box(position, radius, layer, &mut cells);
+        }
+        cells
+    }
+    pub fn cells_of_trace(&self, trace_path: &TracePath) -> HashSet<GridCell> {
+        let mut cells = HashSet::new();
+        for segment in trace_path.segments.iter() {
+            let radius = segment.width / 2.0 + segment.clearance / 2.0;
+            cells.extend(self.cells_of_segment(segment.start.to_float(), segment.end.to_float(), radius, segment.layer));
+        }
+        for via in trace_path.vias.iter() {
+            let radius = via.diameter / 2.0 + via.clearance / 2.0;
+            cells.extend(self.cells_of_via(via.position.to_float(), radius, via.min_layer, via.max_layer));
+        }
+        cells
+    }
+}
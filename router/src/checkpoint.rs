@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+use shared::{
+    hyperparameters::CHECKPOINT_INTERVAL,
+    pad::PadName,
+    pcb_problem::{Connection, ConnectionID, FixedTrace, NetName, PcbProblem, PcbSolution},
+};
+
+use crate::{
+    backtrack_node::BacktrackNode, bayesian_backtrack_algo::TraceCache, deterministic_rand::current_seed,
+    display_injection::DisplayInjection, naive_backtrack_algo::NaiveBacktrackNode, pcb_problem_solve::SolverAlgorithm,
+};
+
+/// where a backtracking search was when the checkpoint was written
+#[derive(Serialize, Deserialize)]
+pub enum SearchState {
+    BayesianBacktrack {
+        node_stack: Vec<BacktrackNode>,
+        best_partial_traces: HashMap<ConnectionID, FixedTrace>,
+    },
+    // also the naive backtrack a Bayesian backtrack hands over to
+    NaiveBacktrack {
+        backtrack_stack: Vec<NaiveBacktrackNode>,
+        best_partial_traces: HashMap<ConnectionID, FixedTrace>,
+    },
+}
+
+/// a connection by its net and its two pads, sorted
+/// the parser numbers the connections in whatever order it meets them, so the ids of a board change from run to run
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConnectionKey {
+    pub net_name: NetName,
+    pub pads: (PadName, PadName),
+}
+
+impl ConnectionKey {
+    fn new(connection: &Connection) -> Self {
+        let pads = if connection.start_pad <= connection.end_pad {
+            (connection.start_pad.clone(), connection.end_pad.clone())
+        } else {
+            (connection.end_pad.clone(), connection.start_pad.clone())
+        };
+        ConnectionKey { net_name: connection.net_name.clone(), pads }
+    }
+}
+
+fn connection_keys(problem: &PcbProblem) -> HashMap<ConnectionID, ConnectionKey> {
+    problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.iter())
+        .map(|(connection_id, connection)| (*connection_id, ConnectionKey::new(connection)))
+        .collect()
+}
+
+/// everything a backtracking search needs to go on after the app was closed or crashed
+/// the random streams are all derived from the seed, so the seed is the whole random state
+#[derive(Serialize, Deserialize)]
+pub struct SolverCheckpoint {
+    pub algorithm: SolverAlgorithm,
+    pub seed: u64,
+    pub connections: HashMap<ConnectionID, ConnectionKey>, // the ids the search state uses, of the problem the solver routed
+    pub prerouted_traces: HashMap<ConnectionID, FixedTrace>, // fanout stubs, locked traces and differential pairs, which are not routed again
+    pub trace_cache: TraceCache,
+    pub search: SearchState,
+}
+
+impl SolverCheckpoint {
+    /// whether the checkpoint was written for this board, the connections are matched once the solve has fanned the board out
+    pub fn check_problem(&self, problem: &PcbProblem) -> Result<(), String> {
+        let net_names: HashSet<&NetName> = problem
+            .nets
+            .values()
+            .filter(|net_info| !net_info.connections.is_empty())
+            .map(|net_info| &net_info.net_name)
+            .collect();
+        let checkpoint_net_names: HashSet<&NetName> = self.connections.values().map(|key| &key.net_name).collect();
+        if net_names != checkpoint_net_names {
+            return Err("The checkpoint was written for another board".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn read_checkpoint(path: &Path) -> Result<SolverCheckpoint, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read checkpoint {}: {}", path.to_string_lossy(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse checkpoint {}: {}", path.to_string_lossy(), e))
+}
+
+/// the checkpoints of one solve, carried by its display injection so solves on other threads never write to its file
+pub struct Checkpointing {
+    path: PathBuf,
+    algorithm: SolverAlgorithm,
+    connections: HashMap<ConnectionID, ConnectionKey>, // of the problem the solver routes, set by match_checkpoint_connections
+    last_write: Instant,
+    resume: Option<SolverCheckpoint>, // taken apart by the solvers when they start
+}
+
+impl Checkpointing {
+    /// the backtracking solvers of the solve write checkpoints to the path, and pick up the search of the resumed checkpoint if there is one
+    pub(crate) fn new(path: &Path, algorithm: SolverAlgorithm, resume: Option<SolverCheckpoint>) -> Self {
+        Checkpointing {
+            path: path.to_path_buf(),
+            algorithm,
+            connections: HashMap::new(),
+            last_write: Instant::now(),
+            resume,
+        }
+    }
+}
+
+/// a problem with the connection ids of a resumed checkpoint
+pub(crate) struct RenumberedProblem {
+    pub problem: PcbProblem,
+    pub to_problem_id: HashMap<ConnectionID, ConnectionID>, // back to the ids of the problem it was made from
+}
+
+/// records the connections of the problem the solver is about to route, for the checkpoints
+/// a resumed search goes on with the ids of its checkpoint, so the problem is renumbered to them
+pub(crate) fn match_checkpoint_connections(
+    problem: &PcbProblem,
+    display_injection: &mut DisplayInjection,
+) -> Result<Option<RenumberedProblem>, String> {
+    let Some(checkpointing) = display_injection.checkpointing.as_mut() else {
+        return Ok(None);
+    };
+    let problem_connections = connection_keys(problem);
+    let Some(resume) = checkpointing.resume.as_ref() else {
+        checkpointing.connections = problem_connections;
+        return Ok(None);
+    };
+    let key_to_checkpoint_id: HashMap<&ConnectionKey, ConnectionID> =
+        resume.connections.iter().map(|(connection_id, key)| (key, *connection_id)).collect();
+    let mut to_checkpoint_id: HashMap<ConnectionID, ConnectionID> = HashMap::new();
+    for (connection_id, key) in problem_connections.iter() {
+        let Some(checkpoint_id) = key_to_checkpoint_id.get(key) else {
+            return Err(format!(
+                "The checkpoint has no connection of net {} between {} and {}",
+                key.net_name.0, key.pads.0 .0, key.pads.1 .0
+            ));
+        };
+        to_checkpoint_id.insert(*connection_id, *checkpoint_id);
+    }
+    if to_checkpoint_id.len() != resume.connections.len() || key_to_checkpoint_id.len() != resume.connections.len() {
+        return Err("The checkpoint was written for another board".to_string());
+    }
+    let mut nets = problem.nets.clone();
+    for net_info in nets.values_mut() {
+        net_info.connections = net_info
+            .connections
+            .values()
+            .map(|connection| {
+                let connection_id = to_checkpoint_id[&connection.connection_id];
+                (connection_id, Arc::new(Connection { connection_id, ..(**connection).clone() }))
+            })
+            .collect();
+    }
+    let renumbered_problem = PcbProblem {
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        num_layers: problem.num_layers,
+        obstacle_borders: problem.obstacle_borders.clone(),
+        obstacle_border_outlines: problem.obstacle_border_outlines.clone(),
+        obstacle_polygons: problem.obstacle_polygons.clone(),
+        nets,
+        diff_pairs: problem.diff_pairs.clone(),
+        length_constraints: problem.length_constraints.clone(),
+        zones: problem.zones.clone(),
+        scale_down_factor: problem.scale_down_factor,
+    };
+    checkpointing.connections = resume.connections.clone();
+    let to_problem_id = to_checkpoint_id.into_iter().map(|(problem_id, checkpoint_id)| (checkpoint_id, problem_id)).collect();
+    Ok(Some(RenumberedProblem { problem: renumbered_problem, to_problem_id }))
+}
+
+/// gives the solution of a renumbered problem the connection ids of the problem again
+pub(crate) fn renumber_solution(solution: &mut PcbSolution, to_problem_id: &HashMap<ConnectionID, ConnectionID>) {
+    solution.determined_traces = std::mem::take(&mut solution.determined_traces)
+        .into_values()
+        .map(|mut fixed_trace| {
+            fixed_trace.connection_id = to_problem_id[&fixed_trace.connection_id];
+            (fixed_trace.connection_id, fixed_trace)
+        })
+        .collect();
+    for unrouted_connection in solution.unrouted_connections.iter_mut() {
+        unrouted_connection.connection_id = to_problem_id[&unrouted_connection.connection_id];
+    }
+}
+
+/// the prerouted traces of the resumed checkpoint, the differential pairs are not routed again
+pub(crate) fn resumed_prerouted_traces(display_injection: &DisplayInjection) -> Option<HashMap<ConnectionID, FixedTrace>> {
+    let resume = display_injection.checkpointing.as_ref()?.resume.as_ref()?;
+    Some(resume.prerouted_traces.clone())
+}
+
+/// whether the resumed search is in the naive backtrack, so a Bayesian backtrack goes straight to it
+pub(crate) fn is_resuming_naive_backtrack(display_injection: &DisplayInjection) -> bool {
+    matches!(
+        display_injection.checkpointing.as_ref().and_then(|checkpointing| checkpointing.resume.as_ref()),
+        Some(SolverCheckpoint { search: SearchState::NaiveBacktrack { .. }, .. })
+    )
+}
+
+/// whether a checkpoint is being resumed and its search state was not taken yet
+pub(crate) fn is_resuming(display_injection: &DisplayInjection) -> bool {
+    display_injection.checkpointing.as_ref().is_some_and(|checkpointing| checkpointing.resume.is_some())
+}
+
+/// takes the search state of the resumed checkpoint if it belongs to a Bayesian backtrack
+pub(crate) fn take_resumed_bayesian_backtrack(
+    display_injection: &mut DisplayInjection,
+) -> Option<(Vec<BacktrackNode>, HashMap<ConnectionID, FixedTrace>)> {
+    let checkpointing = display_injection.checkpointing.as_mut()?;
+    let resume = checkpointing.resume.take()?;
+    match resume.search {
+        SearchState::BayesianBacktrack { node_stack, best_partial_traces } => Some((node_stack, best_partial_traces)),
+        search => {
+            checkpointing.resume = Some(SolverCheckpoint { search, ..resume });
+            None
+        }
+    }
+}
+
+/// takes the search state of the resumed checkpoint if it belongs to a naive backtrack
+pub(crate) fn take_resumed_naive_backtrack(
+    display_injection: &mut DisplayInjection,
+) -> Option<(Vec<NaiveBacktrackNode>, HashMap<ConnectionID, FixedTrace>)> {
+    let checkpointing = display_injection.checkpointing.as_mut()?;
+    let resume = checkpointing.resume.take()?;
+    match resume.search {
+        SearchState::NaiveBacktrack { backtrack_stack, best_partial_traces } => Some((backtrack_stack, best_partial_traces)),
+        search => {
+            checkpointing.resume = Some(SolverCheckpoint { search, ..resume });
+            None
+        }
+    }
+}
+
+/// a checkpoint is due every CHECKPOINT_INTERVAL seconds, and when the solve is about to stop
+pub(crate) fn is_checkpoint_due(display_injection: &DisplayInjection) -> bool {
+    let Some(checkpointing) = display_injection.checkpointing.as_ref() else {
+        return false;
+    };
+    if display_injection.is_stopped() {
+        return true;
+    }
+    let interval = CHECKPOINT_INTERVAL.load(Ordering::Relaxed);
+    interval > 0.0 && checkpointing.last_write.elapsed().as_secs_f64() >= interval
+}
+
+/// writes the search state next to the old checkpoint first, so a crash while writing keeps the old one
+/// a checkpoint that cannot be written is reported and the search goes on
+pub(crate) fn write_checkpoint(
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    trace_cache: &TraceCache,
+    search: SearchState,
+    display_injection: &mut DisplayInjection,
+) {
+    let Some(checkpointing) = display_injection.checkpointing.as_mut() else {
+        return;
+    };
+    let checkpoint = SolverCheckpoint {
+        algorithm: checkpointing.algorithm,
+        seed: current_seed(),
+        connections: checkpointing.connections.clone(),
+        prerouted_traces: prerouted_traces.clone(),
+        trace_cache: trace_cache.clone(),
+        search,
+    };
+    checkpointing.last_write = Instant::now();
+    let content = match serde_json::to_string(&checkpoint) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("Failed to serialize the checkpoint: {}", e);
+            return;
+        }
+    };
+    let temp_path = checkpointing.path.with_extension("tmp");
+    let result = fs::write(&temp_path, content).and_then(|_| fs::rename(&temp_path, &checkpointing.path));
+    match result {
+        Ok(()) => println!("Checkpoint written to {}", checkpointing.path.to_string_lossy()),
+        Err(e) => println!("Failed to write checkpoint {}: {}", checkpointing.path.to_string_lossy(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::AtomicBool,
+            Arc,
+        },
+    };
+
+    use super::{match_checkpoint_connections, read_checkpoint, Checkpointing, SearchState};
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        naive_backtrack_algo::naive_backtrack,
+        pcb_problem_solve::{resume_pcb_problem, SolverAlgorithm},
+        solve_budget::SolveBudget,
+        test_pcb_problem::examples::{example_problem, AutoCommandLevel},
+    };
+
+    #[test]
+    fn a_search_stopped_by_its_budget_is_resumed_from_its_checkpoint() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let checkpoint_path = std::env::temp_dir().join(format!("echo-{}.checkpoint.json", std::process::id()));
+        let mut display_injection = DisplayInjection {
+            budget: Arc::new(SolveBudget::with_limits(0.0, 10, 0)),
+            checkpointing: Some(Checkpointing::new(&checkpoint_path, SolverAlgorithm::NaiveBacktrack, None)),
+            ..DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true)
+        };
+        assert!(match_checkpoint_connections(&problem, &mut display_injection).unwrap().is_none());
+        let mut trace_cache = TraceCache::default();
+        for net_info in problem.nets.values() {
+            for connection_id in net_info.connections.keys() {
+                trace_cache.traces.insert(*connection_id, Vec::new());
+            }
+        }
+        let stopped_solution =
+            naive_backtrack(&problem, &mut trace_cache, None, &HashMap::new(), &mut display_injection).unwrap();
+        assert!(!stopped_solution.unrouted_connections.is_empty());
+
+        // the search is written when the budget stops it
+        let checkpoint = read_checkpoint(&checkpoint_path).unwrap();
+        checkpoint.check_problem(&problem).unwrap();
+        assert_eq!(checkpoint.algorithm, SolverAlgorithm::NaiveBacktrack);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        assert_eq!(checkpoint.connections.len(), num_connections);
+        let SearchState::NaiveBacktrack { backtrack_stack, .. } = &checkpoint.search else {
+            panic!("the checkpoint of a naive backtrack holds a Bayesian backtrack");
+        };
+        assert!(!backtrack_stack.is_empty());
+
+        // a resumed search without a budget finishes the board and removes its checkpoint
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        let solution = resume_pcb_problem(&problem, &checkpoint_path, &mut display_injection).unwrap();
+        assert!(solution.unrouted_connections.is_empty());
+        assert!(!checkpoint_path.exists());
+        assert_eq!(solution.determined_traces.len(), num_connections);
+        assert_eq!(solution.seed, checkpoint.seed);
+        assert!(display_injection.checkpointing.is_none());
+    }
+}
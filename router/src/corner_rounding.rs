@@ -0,0 +1,176 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, sync::atomic::Ordering};
+
+use shared::{
+    hyperparameters::ARC_CORNER_RADIUS,
+    pad::PadLayer,
+    pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+    trace_path::TraceArc,
+};
+
+use crate::{astar::AStarModel, obstacles::build_pad_obstacles, post_process::round_corners};
+
+/// rounds the sharp corners left on every trace that is not locked with the radii given for its net, returns the number of new arcs
+fn round_pass(
+    problem: &PcbProblem,
+    determined_traces: &mut HashMap<ConnectionID, FixedTrace>,
+    locked_connections: &HashSet<ConnectionID>,
+    radii_of_net: &dyn Fn(&NetName) -> Vec<f32>,
+) -> usize {
+    let mut connection_ids: Vec<ConnectionID> = determined_traces
+        .keys()
+        .filter(|connection_id| !locked_connections.contains(connection_id))
+        .cloned()
+        .collect();
+    connection_ids.sort();
+    // every other trace is an obstacle, including the rounded ones and the other connections of the same net,
+    // so only the trace being rounded is taken out and put back once it has its arcs
+    let mut obstacles = build_pad_obstacles(problem, &[], determined_traces);
+    let mut num_arcs = 0;
+    for connection_id in connection_ids {
+        let radii = radii_of_net(&determined_traces[&connection_id].net_name);
+        if radii.is_empty() {
+            continue;
+        }
+        let fixed_trace = &determined_traces[&connection_id];
+        let net_info = &problem.nets[&fixed_trace.net_name];
+        let trace_path = &fixed_trace.trace_path;
+        obstacles.remove_trace(trace_path, problem.num_layers);
+        let anchors = &trace_path.anchors.0;
+        let collision_model = AStarModel {
+            start: anchors[0].position,
+            end: anchors[anchors.len() - 1].position,
+            start_layers: PadLayer::All,
+            end_layers: PadLayer::All,
+            num_layers: problem.num_layers,
+            trace_width: net_info.trace_width,
+            trace_clearance: net_info.trace_clearance,
+            via_types: net_info.via_types.clone(),
+            width: problem.width,
+            height: problem.height,
+            center: problem.center,
+            obstacle_shapes: obstacles.obstacle_shapes.clone(),
+            obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes.clone(),
+            obstacle_colliders: obstacles.obstacle_colliders.clone(),
+            obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+            border_colliders_cache: RefCell::new(None),
+            border_shapes_cache: RefCell::new(None),
+            cost_map: None,
+            blocking_colliders: RefCell::new(None),
+        };
+        let check_collision_for_arc = |arc: &TraceArc| collision_model.check_collision_for_arc(arc);
+        let rounded_trace_path = round_corners(trace_path, &check_collision_for_arc, &radii);
+        num_arcs += rounded_trace_path.arcs.len() - trace_path.arcs.len();
+        drop(collision_model);
+        obstacles.add_trace(&rounded_trace_path, problem.num_layers);
+        determined_traces.get_mut(&connection_id).unwrap().trace_path = rounded_trace_path;
+    }
+    num_arcs
+}
+
+/// the post routing pass that rounds the corners of every trace with arcs of ARC_CORNER_RADIUS
+/// it runs before length tuning, which keeps the arcs and measures their lengths, the corners of the meanders stay sharp
+/// a corner on the outside of a parallel trace, like the outer half of a differential pair, collides as long as
+/// the inner corner is sharp, so the full radius is tried again until a pass adds no arc, before the smaller radii
+/// the locked traces of a reroute were rounded when they were first routed and are left as they are
+pub fn round_trace_corners(
+    problem: &PcbProblem,
+    determined_traces: &mut HashMap<ConnectionID, FixedTrace>,
+    locked_connections: &HashSet<ConnectionID>,
+) {
+    let radius = ARC_CORNER_RADIUS.load(Ordering::Relaxed) as f32;
+    let mut num_arcs = 0;
+    loop {
+        let num_new_arcs = round_pass(problem, determined_traces, locked_connections, &|_| vec![radius]);
+        if num_new_arcs == 0 {
+            break;
+        }
+        num_arcs += num_new_arcs;
+    }
+    num_arcs += round_pass(problem, determined_traces, locked_connections, &|net_name| {
+        let min_radius = problem.nets[net_name].trace_width / 2.0;
+        std::iter::successors(Some(radius / 2.0), |smaller| Some(smaller / 2.0))
+            .take_while(|smaller| *smaller >= min_radius)
+            .collect()
+    });
+    println!("Rounded {} corners with arcs", num_arcs);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use shared::{
+        pcb_problem::{ConnectionID, FixedTrace, LengthConstraint, NetName},
+        vec2::FixedVec2,
+    };
+
+    use super::round_pass;
+    use crate::{
+        length_tuning::tune_lengths,
+        pcb_problem_solve::SolverAlgorithm,
+        test_pcb_problem::examples::{example_problem, route_example},
+    };
+
+    fn num_arcs(determined_traces: &HashMap<ConnectionID, FixedTrace>) -> usize {
+        determined_traces.values().map(|fixed_trace| fixed_trace.trace_path.arcs.len()).sum()
+    }
+
+    /// the length of a net measured from its segments and arcs, not from the length the passes keep
+    fn measured_length(net_name: &NetName, determined_traces: &HashMap<ConnectionID, FixedTrace>) -> f64 {
+        determined_traces
+            .values()
+            .filter(|fixed_trace| fixed_trace.net_name == *net_name)
+            .map(|fixed_trace| fixed_trace.trace_path.calculate_total_length())
+            .sum()
+    }
+
+    /// the anchors run along the segments and through both ends of every arc
+    fn assert_anchors_follow_the_geometry(determined_traces: &HashMap<ConnectionID, FixedTrace>) {
+        for fixed_trace in determined_traces.values() {
+            let trace_path = &fixed_trace.trace_path;
+            let anchor_steps: Vec<(FixedVec2, FixedVec2)> =
+                trace_path.anchors.0.windows(2).map(|pair| (pair[0].position, pair[1].position)).collect();
+            for segment in trace_path.segments.iter() {
+                assert!(anchor_steps.contains(&(segment.start, segment.end)));
+            }
+            for arc in trace_path.arcs.iter() {
+                assert!(anchor_steps.contains(&(arc.start, arc.end)));
+            }
+        }
+    }
+
+    #[test]
+    fn tuning_rounded_traces_keeps_the_arcs_and_matches_the_rounded_lengths() {
+        let mut problem = example_problem(include_str!("../../examples/echo.dsn"), 6);
+        let mut determined_traces = route_example(&problem, SolverAlgorithm::NaiveBacktrack).determined_traces;
+        while round_pass(&problem, &mut determined_traces, &HashSet::new(), &|_| vec![1.0]) > 0 {}
+        let num_rounded_corners = num_arcs(&determined_traces);
+        assert!(num_rounded_corners > 0);
+        assert_anchors_follow_the_geometry(&determined_traces);
+
+        // the short nets would need more meanders than fit next to them
+        let mut net_names: Vec<NetName> = problem
+            .nets
+            .keys()
+            .filter(|net_name| measured_length(net_name, &determined_traces) > 10.0)
+            .cloned()
+            .collect();
+        net_names.sort();
+        assert!(net_names.len() >= 2);
+        let match_tolerance = 0.5;
+        problem.length_constraints.push(LengthConstraint {
+            name: "matched".to_string(),
+            net_names: net_names.clone(),
+            min_length: 0.0,
+            max_length: None,
+            match_tolerance: Some(match_tolerance),
+        });
+        tune_lengths(&problem, &mut determined_traces, &HashSet::new());
+
+        assert_eq!(num_rounded_corners, num_arcs(&determined_traces));
+        assert_anchors_follow_the_geometry(&determined_traces);
+        let lengths: Vec<f64> = net_names.iter().map(|net_name| measured_length(net_name, &determined_traces)).collect();
+        let longest = lengths.iter().cloned().fold(0.0, f64::max);
+        assert!(lengths.iter().all(|length| longest - length <= match_tolerance as f64 + 1e-6));
+    }
+}
@@ -1,10 +1,59 @@
-use std::sync::Mutex;
+use std::{cell::Cell, sync::atomic::Ordering};
 
-use lazy_static::lazy_static;
 use rand::{SeedableRng, rngs::StdRng};
+use shared::hyperparameters::RANDOM_SEED;
+
+thread_local! {
+    // the seed of the portfolio run on this thread, RANDOM_SEED is used outside of a portfolio
+    static RUN_SEED: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// the seed of the solve running on this thread
+pub fn current_seed() -> u64 {
+    RUN_SEED.with(|run_seed| run_seed.get()).unwrap_or(RANDOM_SEED.load(Ordering::Relaxed) as u64)
+}
+
+/// runs a solve with its own seed, without touching the seed of solves on other threads
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous_seed = RUN_SEED.with(|run_seed| run_seed.replace(Some(seed)));
+    let result = f();
+    RUN_SEED.with(|run_seed| run_seed.set(previous_seed));
+    result
+}
 
 pub fn create_deterministic_rng() -> StdRng {
-    let seed = 42; // Fixed seed for reproducibility
-    let rng = StdRng::seed_from_u64(seed);
-    rng
+    StdRng::seed_from_u64(current_seed())
+}
+
+// independent streams for work that runs in parallel, so the result does not depend on scheduling
+// the seed is passed in because the streams are drawn on worker threads that do not know the seed of the solve
+pub fn create_deterministic_rng_for_stream(seed: u64, stream: u64) -> StdRng {
+    StdRng::seed_from_u64(mix(mix(seed) ^ stream))
+}
+
+/// the splitmix64 finalizer, seeds that differ by one come out unrelated
+/// so the stream of one portfolio seed is not the shifted stream of the next seed
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn streams_of_consecutive_seeds_do_not_overlap() {
+        for seed in 0..16u64 {
+            for stream in 0..16u64 {
+                let mut shifted = create_deterministic_rng_for_stream(seed + 1, stream);
+                let mut next_stream = create_deterministic_rng_for_stream(seed, stream + 1);
+                assert_ne!(shifted.random::<u64>(), next_stream.random::<u64>());
+            }
+        }
+    }
 }
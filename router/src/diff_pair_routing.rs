@@ -0,0 +1,580 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::Arc,
+};
+
+use shared::{
+    pad::{Pad, PadLayer, PadShape},
+    pcb_problem::{Connection, ConnectionID, DiffPair, FixedTrace, NetInfo, NetName, PcbProblem},
+    trace_path::{Direction, TraceAnchor, TraceAnchors, TracePath},
+    vec2::{FixedPoint, FixedVec2, FloatVec2},
+};
+
+use crate::{
+    astar::AStarModel,
+    astar_check_struct::AStarCheck,
+    display_injection::DisplayInjection,
+    obstacles::{build_pad_obstacles, PadObstacles},
+};
+
+/// a pair whose nets are not a single two pad connection each, or whose pads don't share a layer, can't be coupled
+fn single_connection(net_info: &NetInfo) -> Option<&Arc<Connection>> {
+    if net_info.connections.len() == 1 {
+        net_info.connections.values().next()
+    } else {
+        None
+    }
+}
+
+/// the layers two pads have in common, None if they are on opposite sides
+fn common_pad_layer(pad1: &Pad, pad2: &Pad) -> Option<PadLayer> {
+    match (pad1.pad_layer, pad2.pad_layer) {
+        (PadLayer::All, other) | (other, PadLayer::All) => Some(other),
+        (PadLayer::Front, PadLayer::Front) => Some(PadLayer::Front),
+        (PadLayer::Back, PadLayer::Back) => Some(PadLayer::Back),
+        _ => None,
+    }
+}
+
+fn pad_position(pad: &Pad) -> FixedVec2 {
+    pad.position.to_fixed().to_nearest_even_even()
+}
+
+fn midpoint(position1: FixedVec2, position2: FixedVec2) -> FixedVec2 {
+    let position1 = position1.to_float();
+    let position2 = position2.to_float();
+    ((position1 + position2) / 2.0).to_fixed().to_nearest_even_even()
+}
+
+/// the radius of a circle around the pad center that covers the whole pad grown by the clearance,
+/// the clearance of a rectangular pad keeps its corners, so it is grown before the diagonal is taken
+fn pad_radius(pad: &Pad, clearance: f32) -> f32 {
+    match pad.shape {
+        PadShape::Circle { diameter } => diameter / 2.0 + clearance,
+        PadShape::Rectangle { width, height } | PadShape::RoundRect { width, height, .. } => {
+            FloatVec2::new(width + clearance * 2.0, height + clearance * 2.0).length() / 2.0
+        }
+    }
+}
+
+/// the point in front of a pad pair where the centerline starts, far enough out that
+/// a trace of the given half width and clearance starting there doesn't touch either pad
+/// it lies on the perpendicular of the pad axis, on the side facing the other end of the pair
+fn breakout_point(pad1: &Pad, pad2: &Pad, toward: FixedVec2, half_width: f32, clearance: f32) -> FixedVec2 {
+    let position1 = pad_position(pad1).to_float();
+    let position2 = pad_position(pad2).to_float();
+    let center = (position1 + position2) / 2.0;
+    let half_pitch = (position2 - position1).length() / 2.0;
+    let mut normal = (position2 - position1).perp().normalize();
+    if normal.dot(toward.to_float() - center) < 0.0 {
+        normal = FloatVec2::new(-normal.x, -normal.y);
+    }
+    let clearance = clearance.max(pad1.clearance).max(pad2.clearance);
+    let reach = f32::max(pad_radius(pad1, clearance), pad_radius(pad2, clearance)) + half_width;
+    // a small margin, so the rounding to the fixed point grid can't bring the start back into a pad
+    let distance = (reach * reach - half_pitch * half_pitch).max(0.0).sqrt() + 0.01;
+    FloatVec2::new(center.x + normal.x * distance, center.y + normal.y * distance)
+        .to_fixed()
+        .to_nearest_even_even()
+}
+
+fn distance(position1: FixedVec2, position2: FixedVec2) -> f64 {
+    (position2 - position1).to_float().length() as f64
+}
+
+fn direction_of(start: FixedVec2, end: FixedVec2) -> Option<(i64, i64)> {
+    let direction = Direction::from_points(start, end).ok()??;
+    let direction = direction.to_int_vec2();
+    Some((direction.x as i64, direction.y as i64))
+}
+
+fn to_bits(position: FixedVec2) -> (i64, i64) {
+    (position.x.to_bits() as i64, position.y.to_bits() as i64)
+}
+
+fn from_bits(x: i64, y: i64) -> FixedVec2 {
+    FixedVec2::new(FixedPoint::from_bits(x as i32), FixedPoint::from_bits(y as i32))
+}
+
+/// the smallest even number of fixed point bits that is at least the given length,
+/// even offsets keep every corner of the offset path on the fixed point grid
+fn even_bits_at_least(length: f64) -> i64 {
+    let bits = (length * (1i64 << FixedPoint::FRAC_NBITS) as f64).ceil() as i64;
+    bits + (bits & 1)
+}
+
+/// shifts an octilinear path sideways by the given distance, positive is to the left of the travel direction
+/// the corners are the intersections of the shifted segments, so every segment keeps its direction
+fn offset_path(centerline: &[FixedVec2], offset: f64) -> Option<Vec<FixedVec2>> {
+    let axis_offset = even_bits_at_least(offset.abs()) * offset.signum() as i64;
+    let diagonal_offset = even_bits_at_least(offset.abs() / std::f64::consts::SQRT_2) * offset.signum() as i64;
+    // a point on every shifted segment, together with its direction
+    let mut lines: Vec<((i64, i64), (i64, i64))> = Vec::new();
+    for window in centerline.windows(2) {
+        let (ux, uy) = direction_of(window[0], window[1])?;
+        let offset_bits = if ux != 0 && uy != 0 { diagonal_offset } else { axis_offset };
+        let (x, y) = to_bits(window[0]);
+        lines.push(((x - uy * offset_bits, y + ux * offset_bits), (ux, uy)));
+    }
+    let mut result: Vec<FixedVec2> = Vec::new();
+    let ((first_x, first_y), _) = lines[0];
+    result.push(from_bits(first_x, first_y));
+    for window in lines.windows(2) {
+        let ((x1, y1), (ux1, uy1)) = window[0];
+        let ((x2, y2), (ux2, uy2)) = window[1];
+        let det = ux1 * uy2 - uy1 * ux2;
+        if det == 0 {
+            // the segments are collinear, the shifted segments meet at the start of the second one
+            result.push(from_bits(x2, y2));
+            continue;
+        }
+        let numerator = (x2 - x1) * uy2 - (y2 - y1) * ux2;
+        if numerator % det != 0 {
+            return None;
+        }
+        let t = numerator / det;
+        result.push(from_bits(x1 + t * ux1, y1 + t * uy1));
+    }
+    let (last_x, last_y) = to_bits(*centerline.last().unwrap());
+    let ((_, _), (ux, uy)) = *lines.last().unwrap();
+    let offset_bits = if ux != 0 && uy != 0 { diagonal_offset } else { axis_offset };
+    result.push(from_bits(last_x - uy * offset_bits, last_y + ux * offset_bits));
+    // an inner corner that is too sharp for the offset flips a segment around
+    for (shifted, original) in result.windows(2).zip(centerline.windows(2)) {
+        if direction_of(shifted[0], shifted[1])? != direction_of(original[0], original[1])? {
+            return None;
+        }
+    }
+    Some(result)
+}
+
+/// a diagonal and a straight segment, so the fanout from a pad stays octilinear
+/// the diagonal comes first or last, whichever keeps the fanout clear of the other half
+pub(crate) fn fanout(start: FixedVec2, end: FixedVec2, diagonal_first: bool) -> Vec<FixedVec2> {
+    let (start_x, start_y) = to_bits(start);
+    let (end_x, end_y) = to_bits(end);
+    let (dx, dy) = (end_x - start_x, end_y - start_y);
+    let diagonal_length = i64::min(dx.abs(), dy.abs());
+    let bend = if diagonal_first {
+        from_bits(start_x + dx.signum() * diagonal_length, start_y + dy.signum() * diagonal_length)
+    } else {
+        from_bits(end_x - dx.signum() * diagonal_length, end_y - dy.signum() * diagonal_length)
+    };
+    vec![start, bend, end]
+}
+
+fn path_length(points: &[FixedVec2]) -> f64 {
+    points
+        .windows(2)
+        .map(|window| distance(window[0], window[1]))
+        .sum()
+}
+
+/// one half of a pair: its pads, its offset of the centerline and what it has to stay clear of
+struct PairHalf<'a> {
+    net_info: &'a NetInfo,
+    obstacles: &'a PadObstacles,
+    start_pad: FixedVec2,
+    coupled: &'a [FixedVec2],
+    end_pad: FixedVec2,
+}
+
+/// pad, fanout, coupled section, fanout, pad
+fn build_trace_path(
+    half: &PairHalf,
+    fanout_orders: (bool, bool),
+    layer: usize,
+    max_uncoupled_length: f64,
+) -> Option<TracePath> {
+    let PairHalf { net_info, start_pad, coupled, end_pad, .. } = *half;
+    let start_fanout = fanout(start_pad, coupled[0], fanout_orders.0);
+    let end_fanout = fanout(*coupled.last().unwrap(), end_pad, !fanout_orders.1);
+    if path_length(&start_fanout) > max_uncoupled_length || path_length(&end_fanout) > max_uncoupled_length {
+        return None;
+    }
+    let mut positions: Vec<FixedVec2> = start_fanout;
+    positions.extend(coupled.iter().skip(1));
+    positions.extend(end_fanout.into_iter().skip(1));
+    positions.dedup();
+    if positions.len() < 2 {
+        return None;
+    }
+    let anchors: Vec<TraceAnchor> = positions
+        .into_iter()
+        .map(|position| TraceAnchor {
+            position,
+            start_layer: layer,
+            end_layer: layer,
+        })
+        .collect();
+    Some(TracePath::from_anchors(
+        TraceAnchors(anchors),
+        net_info.trace_width,
+        net_info.trace_clearance,
+        Vec::new(),
+    ))
+}
+
+/// every way to fan out one half of the pair that stays clear of the obstacles of its net
+fn fanout_candidates(problem: &PcbProblem, half: &PairHalf, layer: usize, max_uncoupled_length: f64) -> Vec<TracePath> {
+    let fanout_orders = [(true, true), (false, true), (true, false), (false, false)];
+    fanout_orders
+        .into_iter()
+        .filter_map(|orders| build_trace_path(half, orders, layer, max_uncoupled_length))
+        .filter(|trace_path| check_against_obstacles(problem, half.obstacles, trace_path))
+        .collect()
+}
+
+pub(crate) fn check_against_obstacles(problem: &PcbProblem, obstacles: &PadObstacles, trace_path: &TracePath) -> bool {
+    let astar_check = AStarCheck {
+        border_colliders: AStarModel::calculate_border_colliders(problem.width, problem.height, problem.center),
+        obstacle_colliders: obstacles.obstacle_colliders.clone(),
+        obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+        solution_trace: trace_path.clone(),
+        num_layers: problem.num_layers,
+    };
+    astar_check.check()
+}
+
+/// routes the centerline of the pair as one wide trace, then splits it into the two halves
+/// returns None if the pair can't be coupled, it is then left to the regular solver
+fn route_diff_pair(
+    problem: &PcbProblem,
+    diff_pair: &DiffPair,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Option<[FixedTrace; 2]> {
+    let positive_net_info = problem.nets.get(&diff_pair.positive_net)?;
+    let negative_net_info = problem.nets.get(&diff_pair.negative_net)?;
+    let positive_connection = single_connection(positive_net_info)?;
+    let negative_connection = single_connection(negative_net_info)?;
+    let positive_start_pad = &positive_net_info.pads[&positive_connection.start_pad];
+    let positive_end_pad = &positive_net_info.pads[&positive_connection.end_pad];
+    let mut negative_start_pad = &negative_net_info.pads[&negative_connection.start_pad];
+    let mut negative_end_pad = &negative_net_info.pads[&negative_connection.end_pad];
+    // pair up the pads that are close to each other
+    let straight = distance(pad_position(positive_start_pad), pad_position(negative_start_pad))
+        + distance(pad_position(positive_end_pad), pad_position(negative_end_pad));
+    let crossed = distance(pad_position(positive_start_pad), pad_position(negative_end_pad))
+        + distance(pad_position(positive_end_pad), pad_position(negative_start_pad));
+    let negative_reversed = crossed < straight;
+    if negative_reversed {
+        std::mem::swap(&mut negative_start_pad, &mut negative_end_pad);
+    }
+    let start_layers = common_pad_layer(positive_start_pad, negative_start_pad)?;
+    let end_layers = common_pad_layer(positive_end_pad, negative_end_pad)?;
+
+    let trace_width = f32::max(positive_net_info.trace_width, negative_net_info.trace_width);
+    let trace_clearance = f32::max(positive_net_info.trace_clearance, negative_net_info.trace_clearance);
+    // the own pads of the pair are obstacles too, the centerline starts and ends in front of them
+    let obstacles = build_pad_obstacles(problem, &[], prerouted_traces);
+    let half_width = trace_width + diff_pair.gap / 2.0;
+    let start_midpoint = midpoint(pad_position(positive_start_pad), pad_position(negative_start_pad));
+    let end_midpoint = midpoint(pad_position(positive_end_pad), pad_position(negative_end_pad));
+    // the centerline stays on one layer, so both halves keep the same layer and length
+    let astar_model = AStarModel {
+        start: breakout_point(positive_start_pad, negative_start_pad, end_midpoint, half_width, trace_clearance),
+        end: breakout_point(positive_end_pad, negative_end_pad, start_midpoint, half_width, trace_clearance),
+        start_layers,
+        end_layers,
+        num_layers: problem.num_layers,
+        trace_width: trace_width * 2.0 + diff_pair.gap,
+        trace_clearance,
+        via_types: Vec::new(),
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        obstacle_shapes: obstacles.obstacle_shapes.clone(),
+        obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes.clone(),
+        obstacle_colliders: obstacles.obstacle_colliders.clone(),
+        obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+        border_colliders_cache: RefCell::new(None),
+        border_shapes_cache: RefCell::new(None),
+        cost_map: None,
+        blocking_colliders: RefCell::new(None),
+    };
+    let centerline = match astar_model.run(display_injection) {
+        Ok(result) => result.trace_path,
+        Err(e) => {
+            println!("Routing the centerline of pair {} failed: {}", diff_pair.positive_net.0, e);
+            return None;
+        }
+    };
+    let layer = centerline.anchors.0[0].end_layer;
+    let centerline: Vec<FixedVec2> = centerline.anchors.0.iter().map(|anchor| anchor.position).collect();
+    if centerline.len() < 2 {
+        return None;
+    }
+    // the positive half goes on the side its pads are on, at both ends
+    // the side is taken along the breakout, the first and last segments may already turn toward either pad
+    let side_of = |position: FixedVec2, from: FixedVec2, to: FixedVec2| {
+        let direction = (to - from).to_float();
+        let relative = (position - from).to_float();
+        (direction.x * relative.y - direction.y * relative.x).signum()
+    };
+    let start_side = side_of(pad_position(positive_start_pad), start_midpoint, centerline[0]);
+    let end_side = side_of(pad_position(positive_end_pad), centerline[centerline.len() - 1], end_midpoint);
+    if start_side != end_side {
+        println!("The halves of pair {} swap sides, not coupling it", diff_pair.positive_net.0);
+        return None;
+    }
+    let offset = (trace_width + diff_pair.gap) as f64 / 2.0;
+    let positive_coupled = offset_path(&centerline, offset * start_side as f64)?;
+    let negative_coupled = offset_path(&centerline, -offset * start_side as f64)?;
+    let max_uncoupled_length = diff_pair.max_uncoupled_length as f64;
+    // the fanouts and the corners are not covered by the centerline, so both halves are checked again
+    let positive_obstacles = build_pad_obstacles(problem, &[&diff_pair.positive_net], prerouted_traces);
+    let negative_obstacles = build_pad_obstacles(problem, &[&diff_pair.negative_net], prerouted_traces);
+    let positive_half = PairHalf {
+        net_info: positive_net_info,
+        obstacles: &positive_obstacles,
+        start_pad: pad_position(positive_start_pad),
+        coupled: &positive_coupled,
+        end_pad: pad_position(positive_end_pad),
+    };
+    let negative_half = PairHalf {
+        net_info: negative_net_info,
+        obstacles: &negative_obstacles,
+        start_pad: pad_position(negative_start_pad),
+        coupled: &negative_coupled,
+        end_pad: pad_position(negative_end_pad),
+    };
+    let positive_candidates = fanout_candidates(problem, &positive_half, layer, max_uncoupled_length);
+    let negative_candidates = fanout_candidates(problem, &negative_half, layer, max_uncoupled_length);
+    let Some((positive_trace_path, mut negative_trace_path)) = positive_candidates
+        .iter()
+        .flat_map(|positive| negative_candidates.iter().map(move |negative| (positive, negative)))
+        .find(|(positive, negative)| !positive.collides_with(negative))
+        .map(|(positive, negative)| (positive.clone(), negative.clone()))
+    else {
+        println!("The halves of pair {} collide after splitting, not coupling it", diff_pair.positive_net.0);
+        return None;
+    };
+    if negative_reversed {
+        // keep the trace in the direction of its connection
+        let mut anchors = negative_trace_path.anchors.0.clone();
+        anchors.reverse();
+        negative_trace_path = TracePath::from_anchors(
+            TraceAnchors(anchors),
+            negative_net_info.trace_width,
+            negative_net_info.trace_clearance,
+            Vec::new(),
+        );
+    }
+    Some([
+        FixedTrace {
+            net_name: diff_pair.positive_net.clone(),
+            connection_id: positive_connection.connection_id,
+            trace_path: positive_trace_path,
+        },
+        FixedTrace {
+            net_name: diff_pair.negative_net.clone(),
+            connection_id: negative_connection.connection_id,
+            trace_path: negative_trace_path,
+        },
+    ])
+}
+
+/// routes every differential pair as coupled traces before the solver runs,
+/// the solver keeps these traces fixed and routes around them
+pub fn route_diff_pairs(
+    problem: &PcbProblem,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Result<HashMap<ConnectionID, FixedTrace>, String> {
+    // the fanout stubs and the locked traces of a reroute are already fixed, the pairs route around them
+    let mut prerouted_traces: HashMap<ConnectionID, FixedTrace> = fixed_traces.clone();
+    for diff_pair in problem.diff_pairs.iter() {
+        if display_injection.is_stopped() {
+            println!("Stop requested, not routing differential pairs");
+            return Err("Stop requested".to_string());
+        }
+        let is_locked = fixed_traces.values().any(|fixed_trace| {
+            fixed_trace.net_name == diff_pair.positive_net || fixed_trace.net_name == diff_pair.negative_net
+        });
+        if is_locked {
+            continue;
+        }
+        match route_diff_pair(problem, diff_pair, &prerouted_traces, display_injection) {
+            Some(fixed_traces) => {
+                println!(
+                    "Routed differential pair {} / {} as coupled traces",
+                    diff_pair.positive_net.0, diff_pair.negative_net.0
+                );
+                for fixed_trace in fixed_traces {
+                    prerouted_traces.insert(fixed_trace.connection_id, fixed_trace);
+                }
+            }
+            None => {
+                println!(
+                    "Could not couple differential pair {} / {}, routing its nets independently",
+                    diff_pair.positive_net.0, diff_pair.negative_net.0
+                );
+            }
+        }
+    }
+    Ok(prerouted_traces)
+}
+
+/// the length difference between the two halves of every differential pair, keyed by the positive net
+pub fn calculate_diff_pair_skews(
+    problem: &PcbProblem,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> HashMap<NetName, f64> {
+    let net_length = |net_name: &NetName| -> f64 {
+        determined_traces
+            .values()
+            .filter(|fixed_trace| fixed_trace.net_name == *net_name)
+            .map(|fixed_trace| fixed_trace.trace_path.calculate_total_length())
+            .sum()
+    };
+    problem
+        .diff_pairs
+        .iter()
+        .map(|diff_pair| {
+            let skew = (net_length(&diff_pair.positive_net) - net_length(&diff_pair.negative_net)).abs();
+            (diff_pair.positive_net.clone(), skew)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use shared::{
+        pcb_problem::{ConnectionID, FixedTrace, NetName},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath, TraceSegment},
+        vec2::{FixedVec2, FloatVec2},
+    };
+
+    use super::{calculate_diff_pair_skews, direction_of, offset_path, route_diff_pairs};
+    use crate::{
+        display_injection::DisplayInjection,
+        test_pcb_problem::examples::{example_problem, AutoCommandLevel},
+    };
+
+    fn point(x: f32, y: f32) -> FixedVec2 {
+        FloatVec2::new(x, y).to_fixed()
+    }
+
+    /// the distance between the lines through two parallel segments, None if they are not parallel
+    /// or lie side by side over less than the given length
+    fn parallel_distance(segment1: &TraceSegment, segment2: &TraceSegment, min_overlap: f32) -> Option<f32> {
+        let direction1 = segment1.get_direction();
+        let direction2 = segment2.get_direction();
+        if direction1 != direction2 && direction1 != direction2.opposite() {
+            return None;
+        }
+        let start = segment1.start.to_float();
+        let direction = (segment1.end - segment1.start).to_float().normalize();
+        let along = |position: FixedVec2| direction.dot(position.to_float() - start);
+        let (along_start, along_end) = (along(segment2.start), along(segment2.end));
+        let overlap = f32::min(along(segment1.end), along_start.max(along_end)) - f32::max(0.0, along_start.min(along_end));
+        if overlap < min_overlap {
+            return None;
+        }
+        let relative = segment2.start.to_float() - start;
+        Some((direction.x * relative.y - direction.y * relative.x).abs())
+    }
+
+    fn fixed_trace(net_name: &str, connection_id: usize, points: &[FixedVec2]) -> FixedTrace {
+        let anchors = points
+            .iter()
+            .map(|&position| TraceAnchor { position, start_layer: 0, end_layer: 0 })
+            .collect();
+        FixedTrace {
+            net_name: NetName(net_name.to_string()),
+            connection_id: ConnectionID(connection_id),
+            trace_path: TracePath::from_anchors(TraceAnchors(anchors), 0.2, 0.2, vec![]),
+        }
+    }
+
+    #[test]
+    fn the_halves_of_a_pair_keep_the_gap_along_the_coupled_route() {
+        let problem = example_problem(include_str!("../../examples/diff_pair.dsn"), 2);
+        assert_eq!(problem.diff_pairs.len(), 1);
+        let diff_pair = &problem.diff_pairs[0];
+        assert_eq!(diff_pair.positive_net, NetName("USB_P".to_string()));
+        assert_eq!(diff_pair.negative_net, NetName("USB_N".to_string()));
+
+        let _auto_command_level = AutoCommandLevel::hold();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), false);
+        let routed = route_diff_pairs(&problem, &HashMap::new(), &mut display_injection).unwrap();
+        assert_eq!(routed.len(), 2);
+        let half = |net_name: &NetName| routed.values().find(|fixed_trace| fixed_trace.net_name == *net_name).unwrap();
+        let positive = half(&diff_pair.positive_net);
+        let negative = half(&diff_pair.negative_net);
+        assert!(!positive.trace_path.collides_with(&negative.trace_path));
+
+        // the clearance is the gap here, so the halves keep at least the gap everywhere,
+        // and where they run side by side over the coupled section their centers are one trace width and the gap apart
+        assert!(diff_pair.gap <= problem.nets[&diff_pair.positive_net].trace_clearance);
+        let pitch = problem.nets[&diff_pair.positive_net].trace_width + diff_pair.gap;
+        let mut coupled_length = 0.0;
+        for segment in positive.trace_path.segments.iter() {
+            for other in negative.trace_path.segments.iter() {
+                if let Some(distance) = parallel_distance(segment, other, 0.01) {
+                    assert!(distance > pitch - 1e-3, "the halves are {} apart, less than {}", distance, pitch);
+                    if distance < pitch + 1e-3 {
+                        coupled_length += segment.calculate_length();
+                    }
+                }
+            }
+        }
+        // the fanouts are short, most of the route is coupled
+        assert!(coupled_length > positive.trace_path.total_length / 2.0);
+    }
+
+    #[test]
+    fn an_offset_past_a_corner_too_sharp_for_it_is_refused() {
+        let gentle = [point(0.0, 0.0), point(4.0, 0.0), point(6.0, 2.0), point(10.0, 2.0)];
+        let offset = offset_path(&gentle, 0.5).unwrap();
+        assert_eq!(offset.len(), gentle.len());
+        for (shifted, original) in offset.windows(2).zip(gentle.windows(2)) {
+            assert_eq!(direction_of(shifted[0], shifted[1]), direction_of(original[0], original[1]));
+        }
+
+        // the inner side of a u turn narrower than twice the offset would run backwards
+        let u_turn = [point(0.0, 0.0), point(4.0, 0.0), point(4.0, 0.25), point(0.0, 0.25)];
+        assert!(offset_path(&u_turn, 0.5).is_none());
+        assert!(offset_path(&u_turn, -0.5).is_some());
+        // and so would the inner side of a corner that turns back by 135 degrees
+        let hairpin = [point(0.0, 0.0), point(4.0, 0.0), point(3.75, 0.25)];
+        assert!(offset_path(&hairpin, 0.5).is_none());
+    }
+
+    #[test]
+    fn the_skew_of_a_pair_is_the_length_difference_of_its_halves() {
+        let problem = example_problem(include_str!("../../examples/diff_pair.dsn"), 2);
+        let diff_pair = &problem.diff_pairs[0];
+        let _auto_command_level = AutoCommandLevel::hold();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), false);
+        let routed = route_diff_pairs(&problem, &HashMap::new(), &mut display_injection).unwrap();
+        assert_eq!(routed.len(), 2);
+        let segment_length = |net_name: &NetName| -> f64 {
+            routed
+                .values()
+                .filter(|fixed_trace| fixed_trace.net_name == *net_name)
+                .flat_map(|fixed_trace| fixed_trace.trace_path.segments.iter())
+                .map(|segment| segment.calculate_length())
+                .sum()
+        };
+        let expected = (segment_length(&diff_pair.positive_net) - segment_length(&diff_pair.negative_net)).abs();
+        let skews = calculate_diff_pair_skews(&problem, &routed);
+        assert!((skews[&diff_pair.positive_net] - expected).abs() < 1e-6);
+
+        // halves of known lengths, the positive one is a unit longer
+        let determined_traces = HashMap::from([
+            (ConnectionID(1), fixed_trace("USB_P", 1, &[point(0.0, 0.0), point(3.0, 0.0)])),
+            (ConnectionID(2), fixed_trace("USB_N", 2, &[point(0.0, 1.0), point(1.0, 1.0), point(1.0, 2.0)])),
+        ]);
+        let skews = calculate_diff_pair_skews(&problem, &determined_traces);
+        assert!((skews[&diff_pair.positive_net] - 1.0).abs() < 1e-6);
+    }
+}
+
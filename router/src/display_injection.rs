@@ -1,6 +1,22 @@
-use std::sync::{atomic::AtomicBool, Arc};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use shared::pcb_render_model::PcbRenderModel;
+use shared::{
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
+    pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable},
+    prim_shape::PrimShape,
+};
+
+use crate::{
+    checkpoint::Checkpointing,
+    command_flags::{CommandFlag, TARGET_COMMAND_LEVEL},
+    solve_budget::SolveBudget,
+};
 
 
 
@@ -9,4 +25,91 @@ pub struct DisplayInjection{
     pub can_submit_render_model: Box<dyn FnMut() -> bool + Send>, // will set the atomic bool to false automatically
     pub submit_render_model: Box<dyn FnMut(PcbRenderModel) + Send>,
     pub block_until_signal: Box<dyn FnMut() + Send>,
-}
\ No newline at end of file
+    pub budget: Arc<SolveBudget>, // a solve starts a new one, the threads of the solve share it
+    pub checkpointing: Option<Checkpointing>, // set by the solves with checkpoints, the workers on other threads never write them
+}
+
+impl DisplayInjection {
+    /// a display injection for solves and workers on other threads, which have no access to the display
+    /// with takes_render_models every render model is taken and dropped, a solve needs that or it waits forever at the steps that have to be displayed,
+    /// without it nothing is submitted, for workers that never wait for the display and need not build the render models
+    pub fn without_display(stop_requested: Arc<AtomicBool>, takes_render_models: bool) -> Self {
+        DisplayInjection {
+            stop_requested,
+            can_submit_render_model: Box::new(move || takes_render_models),
+            submit_render_model: Box::new(|_| {}),
+            block_until_signal: Box::new(|| {}),
+            budget: Arc::new(SolveBudget::start()),
+            checkpointing: None,
+        }
+    }
+
+    /// whether the user asked to stop or the budget of the solve ran out
+    pub fn is_stopped(&self) -> bool {
+        self.stop_requested.load(Ordering::Relaxed) || self.budget.is_exhausted()
+    }
+}
+
+/// the pads, the borders and the traces of a partial or complete routing, drawn in the colors of their nets
+pub fn fixed_traces_to_pcb_render_model(
+    problem: &PcbProblem,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> PcbRenderModel {
+    let mut trace_shape_renderables: Vec<RenderableBatch> = Vec::new();
+    let mut pad_shape_renderables: Vec<ShapeRenderable> = Vec::new();
+    let mut other_shape_renderables: Vec<ShapeRenderable> = Vec::new();
+    for net_info in problem.nets.values() {
+        for pad in net_info.pads.values() {
+            pad_shape_renderables.extend(pad.to_renderables(net_info.color.to_float4(1.0)));
+            pad_shape_renderables.extend(pad.to_clearance_renderables(net_info.color.to_float4(0.5)));
+        }
+    }
+    for fixed_trace in fixed_traces.values() {
+        let color = problem.nets[&fixed_trace.net_name].color.to_float4(1.0);
+        trace_shape_renderables.extend(fixed_trace.trace_path.to_renderables(color));
+    }
+    for line in &problem.obstacle_border_outlines {
+        other_shape_renderables.push(ShapeRenderable {
+            shape: PrimShape::Line(line.clone()),
+            color: [1.0, 0.0, 1.0, 1.0], // magenta color for borders
+        });
+    }
+    PcbRenderModel {
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        trace_shape_renderables,
+        pad_shape_renderables,
+        other_shape_renderables,
+    }
+}
+
+/// shows the traces and waits for the signal to go on if the step is at or above the target command level,
+/// otherwise shows them only if the display is free; with fall_through it never waits
+pub fn display_when_necessary(
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    problem: &PcbProblem,
+    command_flag: CommandFlag,
+    display_injection: &mut DisplayInjection,
+    fall_through: bool,
+) {
+    if display_injection.is_stopped() {
+        println!("Stop requested, not displaying the traces");
+        return;
+    }
+    let target_command_level = TARGET_COMMAND_LEVEL.load(Ordering::Relaxed);
+    let task_command_level = command_flag.get_level();
+    if target_command_level <= task_command_level {
+        let render_model = fixed_traces_to_pcb_render_model(problem, fixed_traces);
+        while !(display_injection.can_submit_render_model)() {
+            // wait until we can submit the render model
+        }
+        (display_injection.submit_render_model)(render_model);
+        if !fall_through {
+            (display_injection.block_until_signal)();
+        }
+    } else if (display_injection.can_submit_render_model)() {
+        let render_model = fixed_traces_to_pcb_render_model(problem, fixed_traces);
+        (display_injection.submit_render_model)(render_model);
+    }
+}
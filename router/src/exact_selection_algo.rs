@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc},
+};
+
+use shared::{
+    hyperparameters::{EXACT_SELECTION_EXTRA_ROUNDS, EXACT_SELECTION_MAX_NODES},
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem, PcbSolution},
+};
+
+use crate::{
+    bayesian_backtrack_algo::{bayesian_backtrack, TraceCache},
+    checkpoint::is_resuming,
+    deterministic_rand::current_seed,
+    display_injection::DisplayInjection,
+    maxsat::{Literal, MaxSatInstance, MaxSatResult},
+    partial_solution::to_partial_solution,
+    proba_model::{ProbaModel, ProbaTrace, Traces},
+};
+
+/// the candidates of a probabilistic model as weighted MaxSAT, one variable per candidate:
+/// exactly one candidate per connection, colliding candidates exclude each other,
+/// and a chosen candidate costs the negative log of its posterior, so the optimum is the most probable selection
+struct CandidateSelection {
+    instance: MaxSatInstance,
+    candidates: Vec<Arc<ProbaTrace>>, // indexed by variable
+}
+
+impl CandidateSelection {
+    fn encode(proba_model: &ProbaModel) -> Self {
+        let mut instance = MaxSatInstance::default();
+        let mut candidates: Vec<Arc<ProbaTrace>> = Vec::new();
+        let fixed_traces: Vec<&FixedTrace> = proba_model
+            .connection_to_traces
+            .values()
+            .filter_map(|traces| match traces {
+                Traces::Fixed(fixed_trace) => Some(fixed_trace),
+                Traces::Probabilistic(_) => None,
+            })
+            .collect();
+        let mut connection_ids: Vec<&ConnectionID> = proba_model.connection_to_traces.keys().collect();
+        connection_ids.sort();
+        for connection_id in connection_ids {
+            let Traces::Probabilistic(trace_map) = &proba_model.connection_to_traces[connection_id] else {
+                continue;
+            };
+            let mut proba_traces: Vec<&Arc<ProbaTrace>> = trace_map.values().collect();
+            proba_traces.sort_by_key(|proba_trace| proba_trace.proba_trace_id);
+            let mut variables: Vec<usize> = Vec::new();
+            for proba_trace in proba_traces {
+                let variable = instance.new_variable();
+                // a candidate in the way of a fixed trace of another net can not be chosen
+                if fixed_traces.iter().any(|fixed_trace| {
+                    fixed_trace.net_name != proba_trace.net_name
+                        && proba_trace.trace_path.collides_with(&fixed_trace.trace_path)
+                }) {
+                    instance.add_hard_clause(vec![Literal::negative(variable)]);
+                }
+                let posterior = proba_trace.get_posterior_with_fallback().clamp(1e-9, 1.0);
+                instance.add_soft_clause(-posterior.ln(), vec![Literal::negative(variable)]);
+                candidates.push(proba_trace.clone());
+                variables.push(variable);
+            }
+            // a connection without candidates leaves an empty clause, which makes the instance unsatisfiable
+            instance.add_exactly_one(&variables);
+        }
+        let variable_of: HashMap<_, usize> = candidates
+            .iter()
+            .enumerate()
+            .map(|(variable, proba_trace)| (proba_trace.proba_trace_id, variable))
+            .collect();
+        let mut collisions: Vec<(usize, usize)> = proba_model
+            .collision_adjacency
+            .iter()
+            .flat_map(|(proba_trace_id, colliding_ids)| {
+                colliding_ids
+                    .iter()
+                    .map(|colliding_id| (variable_of[proba_trace_id], variable_of[colliding_id]))
+            })
+            .filter(|(variable1, variable2)| variable1 < variable2)
+            .collect();
+        collisions.sort();
+        for (variable1, variable2) in collisions {
+            instance.add_hard_clause(vec![Literal::negative(variable1), Literal::negative(variable2)]);
+        }
+        CandidateSelection { instance, candidates }
+    }
+
+    fn to_fixed_traces(&self, assignment: &[bool]) -> HashMap<ConnectionID, FixedTrace> {
+        self.candidates
+            .iter()
+            .zip(assignment)
+            .filter(|(_, chosen)| **chosen)
+            .map(|(proba_trace, _)| {
+                let fixed_trace = FixedTrace {
+                    net_name: proba_trace.net_name.clone(),
+                    connection_id: proba_trace.connection_id,
+                    trace_path: proba_trace.trace_path.clone(),
+                };
+                (proba_trace.connection_id, fixed_trace)
+            })
+            .collect()
+    }
+}
+
+/// chooses one sampled candidate per connection with an exact MaxSAT solve instead of a backtracking search,
+/// samples more candidates while the solve proves that the ones it has can not all fit,
+/// and hands over to the Bayesian backtrack when the extra sampling rounds run out
+pub fn exact_selection(
+    pcb_problem: &PcbProblem,
+    trace_cache: &mut TraceCache,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    if display_injection.is_stopped() {
+        println!("Stop requested, not running exact selection");
+        return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
+    }
+    // only the searches it hands over to write checkpoints
+    if is_resuming(display_injection) {
+        println!("Resuming the search the exact selection handed over to");
+        return bayesian_backtrack(pcb_problem, trace_cache, prerouted_traces, display_injection);
+    }
+    let mut prerouted_sequence: Vec<ConnectionID> = prerouted_traces.keys().cloned().collect();
+    prerouted_sequence.sort();
+    let mut proba_model =
+        match ProbaModel::create_and_solve(pcb_problem, prerouted_traces, prerouted_sequence, trace_cache, display_injection) {
+            Ok(proba_model) => proba_model,
+            Err(_) if display_injection.is_stopped() => {
+                return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
+            }
+            Err(err) => return Err(err),
+        };
+    let max_nodes = EXACT_SELECTION_MAX_NODES.load(Ordering::Relaxed);
+    let extra_rounds = EXACT_SELECTION_EXTRA_ROUNDS.load(Ordering::Relaxed);
+    for round in 0..=extra_rounds {
+        display_injection.budget.check();
+        if display_injection.is_stopped() {
+            println!("Stop requested, exiting exact selection");
+            return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
+        }
+        let selection = CandidateSelection::encode(&proba_model);
+        println!(
+            "Exact selection over {} candidates with {} hard and {} soft clauses",
+            selection.candidates.len(),
+            selection.instance.hard_clauses.len(),
+            selection.instance.soft_clauses.len()
+        );
+        let assignment = match selection.instance.solve(max_nodes) {
+            MaxSatResult::Optimal { assignment, cost } => {
+                println!("Found the most probable selection, cost {:.4}", cost);
+                assignment
+            }
+            MaxSatResult::Feasible { assignment, cost } => {
+                println!("Node budget ran out, keeping the best selection found, cost {:.4}", cost);
+                assignment
+            }
+            MaxSatResult::Unsatisfiable => {
+                println!("The candidates can not be selected without a collision");
+                if round < extra_rounds {
+                    match proba_model.sample_more(pcb_problem, trace_cache, display_injection) {
+                        Ok(()) => continue,
+                        Err(_) if display_injection.is_stopped() => {
+                            return Ok(to_partial_solution(pcb_problem, prerouted_traces.clone(), display_injection));
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                break;
+            }
+            MaxSatResult::Unknown => {
+                println!("Node budget ran out before any selection was found");
+                break;
+            }
+        };
+        let mut determined_traces = prerouted_traces.clone();
+        determined_traces.extend(selection.to_fixed_traces(&assignment));
+        return Ok(PcbSolution {
+            determined_traces,
+            unrouted_connections: Vec::new(),
+            diff_pair_skews: HashMap::new(),
+            teardrops: Vec::new(),
+            zone_fills: Vec::new(),
+            seed: current_seed(),
+            scale_down_factor: pcb_problem.scale_down_factor,
+            pareto_front: Vec::new(),
+        });
+    }
+    println!("Exact selection found no selection, handing over to the Bayesian backtrack");
+    bayesian_backtrack(pcb_problem, trace_cache, prerouted_traces, display_injection)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::AtomicBool,
+            Arc,
+        },
+    };
+
+    use super::CandidateSelection;
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        maxsat::MaxSatResult,
+        pcb_problem_solve::SolverAlgorithm,
+        proba_model::ProbaModel,
+        test_pcb_problem::examples::{assert_no_collisions, example_problem, route_example, AutoCommandLevel},
+    };
+
+    #[test]
+    fn the_optimal_selection_takes_one_candidate_per_connection_without_collisions() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        let mut trace_cache = TraceCache::default();
+        for net_info in problem.nets.values() {
+            for connection_id in net_info.connections.keys() {
+                trace_cache.traces.insert(*connection_id, Vec::new());
+            }
+        }
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        let proba_model =
+            ProbaModel::create_and_solve(&problem, &HashMap::new(), Vec::new(), &mut trace_cache, &mut display_injection).unwrap();
+        let selection = CandidateSelection::encode(&proba_model);
+        let MaxSatResult::Optimal { assignment, .. } = selection.instance.solve(100_000) else {
+            panic!("the candidates of a small board are selected to optimality");
+        };
+        let fixed_traces = selection.to_fixed_traces(&assignment);
+        assert_eq!(fixed_traces.len(), num_connections);
+        assert_no_collisions(&fixed_traces);
+    }
+
+    #[test]
+    fn exact_selection_routes_a_small_board() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        let solution = route_example(&problem, SolverAlgorithm::ExactSelection);
+        assert!(solution.unrouted_connections.is_empty());
+        assert_eq!(solution.determined_traces.len(), num_connections);
+        assert_no_collisions(&solution.determined_traces);
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::Ordering, Arc},
+};
+
+use shared::{
+    hyperparameters::FANOUT_MAX_PITCH,
+    pad::{Pad, PadLayer, PadName, PadShape},
+    pcb_problem::{Connection, ConnectionID, FixedTrace, NetInfo, NetName, PcbProblem},
+    trace_path::{TraceAnchor, TraceAnchors, TracePath, Via},
+    vec2::{FixedVec2, FloatVec2},
+    via_type::{ViaKind, ViaType},
+};
+
+use crate::{
+    diff_pair_routing::{check_against_obstacles, fanout},
+    obstacles::build_pad_obstacles,
+};
+
+/// pads closer than the max pitch to each other form an array, smaller groups are ordinary footprints like two pad passives
+const FANOUT_MIN_ARRAY_PADS: usize = 6;
+
+/// fanout vias sit on a grid of 1/256 mm, so their position survives the round trip through the f32 pad position
+const FANOUT_GRID: f32 = 256.0;
+
+/// the dogbone of a pad, chosen by how its neighbors are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DogbonePattern {
+    Diagonal,                          // a grid like a BGA, the via goes in the middle of four pads
+    Straight { horizontal_row: bool }, // a row like the side of a QFN, the via goes out from the row
+}
+
+/// an SMD pad, every one of them counts towards the density of its array
+struct FanoutPad<'a> {
+    net_name: &'a NetName,
+    pad: &'a Pad,
+    layer: usize,
+}
+
+fn smd_layer(pad: &Pad, num_layers: usize) -> Option<usize> {
+    match pad.pad_layer {
+        PadLayer::Front => Some(0),
+        PadLayer::Back => Some(num_layers - 1),
+        PadLayer::All => None, // a through hole pad is already reachable from every layer
+    }
+}
+
+/// the radius of a circle around the pad center that covers the whole pad
+fn pad_radius(pad: &Pad) -> f32 {
+    match pad.shape {
+        PadShape::Circle { diameter } => diameter / 2.0,
+        PadShape::Rectangle { width, height } | PadShape::RoundRect { width, height, .. } => {
+            FloatVec2::new(width, height).length() / 2.0
+        }
+    }
+}
+
+fn snap_to_grid(position: FloatVec2) -> FloatVec2 {
+    FloatVec2::new(
+        (position.x * FANOUT_GRID).round() / FANOUT_GRID,
+        (position.y * FANOUT_GRID).round() / FANOUT_GRID,
+    )
+}
+
+fn distance(position1: FloatVec2, position2: FloatVec2) -> f32 {
+    (position2 - position1).length()
+}
+
+/// the cheapest via of the net that goes through the board, a fanout via must reach every layer
+fn through_via_type(net_info: &NetInfo, num_layers: usize) -> Option<&ViaType> {
+    net_info
+        .via_types
+        .iter()
+        .find(|via_type| via_type.kind(num_layers) == ViaKind::Through)
+}
+
+/// groups the candidate pads into arrays, two pads on the same layer closer than the max pitch are in the same array
+fn find_dense_arrays(candidates: &[FanoutPad], max_pitch: f32) -> Vec<Vec<usize>> {
+    let mut visited: Vec<bool> = vec![false; candidates.len()];
+    let mut arrays: Vec<Vec<usize>> = Vec::new();
+    for seed in 0..candidates.len() {
+        if visited[seed] {
+            continue;
+        }
+        visited[seed] = true;
+        let mut array: Vec<usize> = vec![seed];
+        let mut frontier: Vec<usize> = vec![seed];
+        while let Some(current) = frontier.pop() {
+            for other in 0..candidates.len() {
+                if visited[other] || candidates[other].layer != candidates[current].layer {
+                    continue;
+                }
+                if distance(candidates[current].pad.position, candidates[other].pad.position) <= max_pitch {
+                    visited[other] = true;
+                    array.push(other);
+                    frontier.push(other);
+                }
+            }
+        }
+        if array.len() >= FANOUT_MIN_ARRAY_PADS {
+            arrays.push(array);
+        }
+    }
+    arrays
+}
+
+/// the pitch of a pad is the distance to its nearest neighbor in the array,
+/// it is a grid pad if it has neighbors at that pitch along both axes
+fn pitch_and_pattern(candidates: &[FanoutPad], array: &[usize], index: usize) -> (f32, DogbonePattern) {
+    let position = candidates[index].pad.position;
+    let pitch = array
+        .iter()
+        .filter(|&&other| other != index)
+        .map(|&other| distance(position, candidates[other].pad.position))
+        .fold(f32::MAX, f32::min);
+    let neighbors: Vec<FloatVec2> = array
+        .iter()
+        .filter(|&&other| other != index)
+        .map(|&other| candidates[other].pad.position - position)
+        .filter(|offset| offset.length() <= pitch * 1.2)
+        .collect();
+    let has_horizontal = neighbors.iter().any(|offset| offset.x.abs() > offset.y.abs());
+    let has_vertical = neighbors.iter().any(|offset| offset.y.abs() > offset.x.abs());
+    let pattern = if has_horizontal && has_vertical {
+        DogbonePattern::Diagonal
+    } else {
+        DogbonePattern::Straight {
+            horizontal_row: has_horizontal,
+        }
+    };
+    (pitch, pattern)
+}
+
+/// where the via of a pad may go, best first
+/// a grid pad prefers the diagonal facing away from the array center,
+/// a row pad goes straight out from the row, and one via further out if the pitch is too fine for the vias to sit side by side
+fn via_candidates(
+    candidates: &[FanoutPad],
+    array: &[usize],
+    index: usize,
+    via_type: &ViaType,
+    clearance: f32,
+) -> Vec<FloatVec2> {
+    let pad = candidates[index].pad;
+    let centroid = array
+        .iter()
+        .fold(FloatVec2::new(0.0, 0.0), |sum, &other| sum + candidates[other].pad.position)
+        / array.len() as f32;
+    let outward = pad.position - centroid;
+    let sign_x = if outward.x < 0.0 { -1.0 } else { 1.0 };
+    let sign_y = if outward.y < 0.0 { -1.0 } else { 1.0 };
+    let (pitch, pattern) = pitch_and_pattern(candidates, array, index);
+    let offsets: Vec<FloatVec2> = match pattern {
+        DogbonePattern::Diagonal => {
+            let half_pitch = pitch / 2.0;
+            vec![
+                FloatVec2::new(sign_x * half_pitch, sign_y * half_pitch),
+                FloatVec2::new(-sign_x * half_pitch, sign_y * half_pitch),
+                FloatVec2::new(sign_x * half_pitch, -sign_y * half_pitch),
+                FloatVec2::new(-sign_x * half_pitch, -sign_y * half_pitch),
+            ]
+        }
+        DogbonePattern::Straight { horizontal_row } => {
+            // the row runs along the axis its neighbors are on, the via goes along the other one
+            let normal = if horizontal_row {
+                FloatVec2::new(0.0, sign_y)
+            } else {
+                FloatVec2::new(sign_x, 0.0)
+            };
+            let near = pad_radius(pad) + clearance + via_type.diameter / 2.0;
+            let mut distances = vec![near];
+            if pitch < via_type.diameter + via_type.clearance {
+                distances.push(near + via_type.diameter + via_type.clearance);
+            }
+            distances
+                .into_iter()
+                .map(|distance| FloatVec2::new(normal.x * distance, normal.y * distance))
+                .collect()
+        }
+    };
+    offsets
+        .into_iter()
+        .map(|offset| snap_to_grid(pad.position + offset))
+        .collect()
+}
+
+/// a short trace from the pad center to the via, on the layer of the pad
+fn stub_trace_path(
+    pad_position: FixedVec2,
+    via_position: FixedVec2,
+    layer: usize,
+    net_info: &NetInfo,
+    via_type: &ViaType,
+    num_layers: usize,
+) -> TracePath {
+    let mut positions = fanout(pad_position, via_position, true);
+    positions.dedup();
+    let anchors: Vec<TraceAnchor> = positions
+        .into_iter()
+        .map(|position| TraceAnchor {
+            position,
+            start_layer: layer,
+            end_layer: layer,
+        })
+        .collect();
+    let via = Via {
+        position: via_position,
+        diameter: via_type.diameter,
+        clearance: via_type.clearance,
+        min_layer: 0,
+        max_layer: num_layers - 1,
+        padstack: via_type.name.clone(),
+    };
+    TracePath::from_anchors(TraceAnchors(anchors), net_info.trace_width, net_info.trace_clearance, vec![via])
+}
+
+/// the pre-routing pass that escapes dense SMD and BGA footprints
+/// every pad of a dense array gets a short stub and a through via in a dogbone pattern,
+/// and its connections start or end at the via from then on
+/// returns the problem with the vias as pads, and the stubs as traces that are fixed before routing
+pub fn fanout_dense_pads(problem: &PcbProblem) -> (PcbProblem, HashMap<ConnectionID, FixedTrace>) {
+    let max_pitch = FANOUT_MAX_PITCH.load(Ordering::Relaxed) as f32;
+    fanout_dense_pads_with_pitch(problem, max_pitch)
+}
+
+/// fans out the arrays of pads closer than max_pitch to each other
+pub fn fanout_dense_pads_with_pitch(problem: &PcbProblem, max_pitch: f32) -> (PcbProblem, HashMap<ConnectionID, FixedTrace>) {
+    // the halves of a differential pair are coupled right from their pads
+    let paired_nets: HashSet<&NetName> = problem
+        .diff_pairs
+        .iter()
+        .flat_map(|diff_pair| [&diff_pair.positive_net, &diff_pair.negative_net])
+        .collect();
+    let mut net_names: Vec<&NetName> = problem.nets.keys().collect();
+    net_names.sort();
+    let mut candidates: Vec<FanoutPad> = Vec::new();
+    for net_name in net_names {
+        // every SMD pad counts towards the density, even one that is not connected
+        let net_info = &problem.nets[net_name];
+        let mut pad_names: Vec<&PadName> = net_info.pads.keys().collect();
+        pad_names.sort();
+        for pad_name in pad_names {
+            let pad = &net_info.pads[pad_name];
+            if let Some(layer) = smd_layer(pad, problem.num_layers) {
+                candidates.push(FanoutPad { net_name, pad, layer });
+            }
+        }
+    }
+    let arrays = find_dense_arrays(&candidates, max_pitch);
+
+    // a stub takes its id from the place of its pad among the SMD pads, not from the stubs placed before it,
+    // so the ids stay the same from run to run even if another pad is fanned out or skipped
+    let first_stub_connection_id = problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.keys())
+        .map(|connection_id| connection_id.0 + 1)
+        .max()
+        .unwrap_or(0);
+    let mut nets: HashMap<NetName, NetInfo> = problem.nets.clone();
+    let mut stub_traces: HashMap<ConnectionID, FixedTrace> = HashMap::new();
+    let mut num_fanned_out = 0;
+    let mut num_skipped = 0;
+    // the pads of every net and the stubs placed so far, the net of the pad being fanned out is taken out for its check
+    let mut obstacles = build_pad_obstacles(problem, &[], &HashMap::new());
+    for array in arrays.iter() {
+        for &index in array.iter() {
+            let FanoutPad { net_name, pad, layer } = &candidates[index];
+            let net_info = &problem.nets[*net_name];
+            let is_connected = net_info
+                .connections
+                .values()
+                .any(|connection| connection.start_pad == pad.name || connection.end_pad == pad.name);
+            if !is_connected || paired_nets.contains(net_name) {
+                continue;
+            }
+            let Some(via_type) = through_via_type(net_info, problem.num_layers) else {
+                num_skipped += 1;
+                continue;
+            };
+            // the stubs placed so far are obstacles, so neighboring vias keep their clearance
+            let own_stubs: Vec<&TracePath> = stub_traces
+                .values()
+                .filter(|stub_trace| stub_trace.net_name == **net_name)
+                .map(|stub_trace| &stub_trace.trace_path)
+                .collect();
+            obstacles.remove_pads(net_info, problem.num_layers);
+            for own_stub in own_stubs.iter() {
+                obstacles.remove_trace(own_stub, problem.num_layers);
+            }
+            let clearance = f32::max(f32::max(via_type.clearance, pad.clearance), net_info.trace_clearance);
+            let pad_position = pad.position.to_fixed().to_nearest_even_even();
+            let stub = via_candidates(&candidates, array, index, via_type, clearance)
+                .into_iter()
+                .map(|via_position| {
+                    let via_position = via_position.to_fixed();
+                    stub_trace_path(pad_position, via_position, *layer, net_info, via_type, problem.num_layers)
+                })
+                .find(|trace_path| check_against_obstacles(problem, &obstacles, trace_path));
+            obstacles.add_pads(net_info, problem.num_layers);
+            for own_stub in own_stubs {
+                obstacles.add_trace(own_stub, problem.num_layers);
+            }
+            let Some(stub) = stub else {
+                num_skipped += 1;
+                continue;
+            };
+            // the via becomes a pad of the net, and the connections of the pad move to it
+            let via_position = stub.vias[0].position;
+            let fanout_pad_name = PadName(format!("{}-fanout", pad.name.0));
+            let fanout_pad = Pad {
+                name: fanout_pad_name.clone(),
+                position: via_position.to_float(),
+                shape: PadShape::Circle {
+                    diameter: via_type.diameter,
+                },
+                rotation: cgmath::Deg(0.0),
+                clearance: via_type.clearance,
+                pad_layer: PadLayer::All,
+            };
+            let new_net_info = nets.get_mut(*net_name).unwrap();
+            new_net_info.pads.insert(fanout_pad_name.clone(), fanout_pad);
+            for connection in new_net_info.connections.values_mut() {
+                let mut new_connection = (**connection).clone();
+                if new_connection.start_pad == pad.name {
+                    new_connection.start_pad = fanout_pad_name.clone();
+                }
+                if new_connection.end_pad == pad.name {
+                    new_connection.end_pad = fanout_pad_name.clone();
+                }
+                *connection = Arc::new(new_connection);
+            }
+            let connection_id = ConnectionID(first_stub_connection_id + index);
+            new_net_info.connections.insert(
+                connection_id,
+                Arc::new(Connection {
+                    net_name: (*net_name).clone(),
+                    connection_id,
+                    start_pad: pad.name.clone(),
+                    end_pad: fanout_pad_name,
+                }),
+            );
+            obstacles.add_trace(&stub, problem.num_layers);
+            stub_traces.insert(
+                connection_id,
+                FixedTrace {
+                    net_name: (*net_name).clone(),
+                    connection_id,
+                    trace_path: stub,
+                },
+            );
+            num_fanned_out += 1;
+        }
+    }
+    println!(
+        "Fanned out {} pads in {} dense arrays, {} pads had no room for a via",
+        num_fanned_out,
+        arrays.len(),
+        num_skipped
+    );
+    let fanned_out_problem = PcbProblem {
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        num_layers: problem.num_layers,
+        obstacle_borders: problem.obstacle_borders.clone(),
+        obstacle_border_outlines: problem.obstacle_border_outlines.clone(),
+        obstacle_polygons: problem.obstacle_polygons.clone(),
+        nets,
+        diff_pairs: problem.diff_pairs.clone(),
+        length_constraints: problem.length_constraints.clone(),
+        zones: problem.zones.clone(),
+        scale_down_factor: problem.scale_down_factor,
+    };
+    (fanned_out_problem, stub_traces)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use parser::parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end};
+    use shared::{
+        pad::PadName,
+        pcb_problem::{ConnectionID, PcbProblem},
+    };
+
+    use super::fanout_dense_pads_with_pitch;
+    use crate::obstacles::build_pad_obstacles;
+
+    // the headers of digistump are a dense array at a 0.1 inch pitch
+    const HEADER_PITCH: f32 = 2.6;
+
+    /// the pad each stub starts from, by the id of its connection
+    fn stub_pads(problem: &PcbProblem) -> HashMap<ConnectionID, PadName> {
+        let (fanned_out_problem, stub_traces) = fanout_dense_pads_with_pitch(problem, HEADER_PITCH);
+        stub_traces
+            .values()
+            .map(|stub_trace| {
+                let connection = &fanned_out_problem.nets[&stub_trace.net_name].connections[&stub_trace.connection_id];
+                (stub_trace.connection_id, connection.start_pad.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skipping_a_pad_keeps_the_ids_of_the_other_stubs() {
+        let dsn_struct = parse_start_to_dsn_struct(include_str!("../../examples/digistump.dsn").to_string()).unwrap();
+        let mut problem = parse_struct_to_end(&dsn_struct).unwrap();
+        let stubs = stub_pads(&problem);
+        assert!(stubs.len() > 1);
+        assert_eq!(stubs, stub_pads(&problem));
+
+        // a pad without connections is not fanned out
+        let max_connection_id = problem.nets.values().flat_map(|net_info| net_info.connections.keys()).max().cloned();
+        let (skipped_id, skipped_pad) = stubs
+            .iter()
+            .filter(|(_, pad_name)| {
+                problem.nets.values().flat_map(|net_info| net_info.connections.values()).all(|connection| {
+                    Some(connection.connection_id) != max_connection_id
+                        || (connection.start_pad != **pad_name && connection.end_pad != **pad_name)
+                })
+            })
+            .min()
+            .map(|(connection_id, pad_name)| (*connection_id, pad_name.clone()))
+            .unwrap();
+        for net_info in problem.nets.values_mut() {
+            net_info
+                .connections
+                .retain(|_, connection| connection.start_pad != skipped_pad && connection.end_pad != skipped_pad);
+        }
+        let mut expected_stubs = stubs.clone();
+        expected_stubs.remove(&skipped_id);
+        assert_eq!(stub_pads(&problem), expected_stubs);
+    }
+
+    #[test]
+    fn the_connections_of_a_fanned_out_pad_start_at_its_via_clear_of_the_other_nets() {
+        let dsn_struct = parse_start_to_dsn_struct(include_str!("../../examples/digistump.dsn").to_string()).unwrap();
+        let problem = parse_struct_to_end(&dsn_struct).unwrap();
+        let (fanned_out_problem, stub_traces) = fanout_dense_pads_with_pitch(&problem, HEADER_PITCH);
+        assert!(!stub_traces.is_empty());
+        for stub_trace in stub_traces.values() {
+            let net_info = &fanned_out_problem.nets[&stub_trace.net_name];
+            let stub_connection = &net_info.connections[&stub_trace.connection_id];
+            let pad = &net_info.pads[&stub_connection.start_pad];
+            let via_pad = &net_info.pads[&stub_connection.end_pad];
+            assert_eq!(stub_trace.trace_path.vias.len(), 1);
+            assert_eq!(stub_trace.trace_path.vias[0].position.to_float(), via_pad.position);
+            assert!((stub_trace.trace_path.segments[0].start.to_float() - pad.position).length() < 0.01);
+            // the other connections of the pad now start or end at its via
+            assert!(net_info
+                .connections
+                .values()
+                .filter(|connection| connection.connection_id != stub_trace.connection_id)
+                .all(|connection| connection.start_pad != pad.name && connection.end_pad != pad.name));
+
+            let obstacles = build_pad_obstacles(&fanned_out_problem, &[&stub_trace.net_name], &stub_traces);
+            let colliders = stub_trace.trace_path.to_colliders(problem.num_layers);
+            let clearance_colliders = stub_trace.trace_path.to_clearance_colliders(problem.num_layers);
+            for layer in 0..problem.num_layers {
+                assert!(!obstacles.obstacle_clearance_colliders[&layer].collides_with_set(colliders[&layer].iter()));
+                assert!(!obstacles.obstacle_colliders[&layer].collides_with_set(clearance_colliders[&layer].iter()));
+            }
+        }
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{atomic::Ordering, Arc},
+};
+
+use rand::Rng;
+use shared::{
+    hyperparameters::{
+        FIXED_POINT_ITERATIONS, HALF_PROBABILITY_OPPORTUNITY_COST, INFERENCE_ENGINE, INFERENCE_MAX_ITERATIONS, INFERENCE_TOLERANCE,
+    },
+    pcb_problem::ConnectionID,
+};
+
+use crate::{
+    deterministic_rand::{create_deterministic_rng_for_stream, current_seed},
+    proba_model::{ProbaModel, ProbaTrace, ProbaTraceID},
+};
+
+// all engines work on the same model of the candidates, so their posteriors can be compared:
+// every probabilistic trace is used or not, a used trace weighs its prior times its score,
+// and two colliding traces that are both used cost exp(-k), halved every HALF_PROBABILITY_OPPORTUNITY_COST
+// the posterior of a trace is the probability that it is used
+
+/// how an inference run went
+#[derive(Debug, Clone)]
+pub struct InferenceDiagnostics {
+    pub engine: &'static str,
+    pub iterations: usize,
+    pub residual: f64, // the largest change of a posterior or message in the last iteration, or between the two halves of the Gibbs samples
+    pub converged: bool, // the residual fell below the tolerance
+}
+
+impl fmt::Display for InferenceDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inference: {} iterations, residual {:.2e}, {}",
+            self.engine,
+            self.iterations,
+            self.residual,
+            if self.converged { "converged" } else { "not converged" }
+        )
+    }
+}
+
+/// updates the posteriors of the probabilistic traces of a model from their scores and the collision adjacency
+pub trait InferenceEngine {
+    fn name(&self) -> &'static str;
+
+    /// on_iteration is called after every iteration, to display the model
+    fn infer(&self, proba_model: &ProbaModel, on_iteration: &mut dyn FnMut(&ProbaModel)) -> InferenceDiagnostics;
+}
+
+/// which inference engine the Bayesian backtrack uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InferenceEngineKind {
+    FixedPoint,
+    BeliefPropagation,
+    Gibbs,
+    MeanField,
+}
+
+impl InferenceEngineKind {
+    pub fn from_setting(value: usize) -> Self {
+        match value {
+            1 => InferenceEngineKind::BeliefPropagation,
+            2 => InferenceEngineKind::Gibbs,
+            3 => InferenceEngineKind::MeanField,
+            _ => InferenceEngineKind::FixedPoint,
+        }
+    }
+
+    pub fn to_engine(self) -> Box<dyn InferenceEngine> {
+        let max_iterations = INFERENCE_MAX_ITERATIONS.load(Ordering::Relaxed).max(1);
+        let tolerance = INFERENCE_TOLERANCE.load(Ordering::Relaxed);
+        match self {
+            InferenceEngineKind::FixedPoint => {
+                Box::new(FixedPointInference { num_iterations: FIXED_POINT_ITERATIONS.load(Ordering::Relaxed) })
+            }
+            InferenceEngineKind::BeliefPropagation => {
+                Box::new(BeliefPropagationInference { max_iterations, tolerance, damping: 0.5 })
+            }
+            InferenceEngineKind::Gibbs => Box::new(GibbsInference { num_sweeps: max_iterations, tolerance }),
+            InferenceEngineKind::MeanField => Box::new(MeanFieldInference { max_iterations, tolerance, damping: 0.5 }),
+        }
+    }
+}
+
+/// the engine selected by INFERENCE_ENGINE
+pub fn inference_engine_from_settings() -> Box<dyn InferenceEngine> {
+    InferenceEngineKind::from_setting(INFERENCE_ENGINE.load(Ordering::Relaxed)).to_engine()
+}
+
+/// the cost k of a collision between two used traces
+pub(crate) fn collision_penalty() -> f64 {
+    f64::ln(2.0) / HALF_PROBABILITY_OPPORTUNITY_COST.load(Ordering::Relaxed)
+}
+
+/// the prior times the score of a trace, kept away from 0 and 1 so its log odds are finite
+fn unary_weight(proba_trace: &ProbaTrace) -> f64 {
+    (proba_trace.get_normalized_prior() * proba_trace.score).clamp(1e-9, 1.0 - 1e-9)
+}
+
+fn logit(p: f64) -> f64 {
+    (p / (1.0 - p)).ln()
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// the probabilistic traces in id order, so every engine visits them in the same order every run
+fn sorted_proba_traces(proba_model: &ProbaModel) -> BTreeMap<ProbaTraceID, Arc<ProbaTrace>> {
+    proba_model.proba_traces().into_iter().collect()
+}
+
+fn set_posteriors(proba_traces: &BTreeMap<ProbaTraceID, Arc<ProbaTrace>>, posteriors: &HashMap<ProbaTraceID, f64>) {
+    for (proba_trace_id, proba_trace) in proba_traces.iter() {
+        let posterior = posteriors[proba_trace_id];
+        assert!((0.0..=1.0).contains(&posterior), "Posterior must be between 0 and 1, got: {}", posterior);
+        *proba_trace.posterior.lock().unwrap() = Some(posterior);
+    }
+}
+
+/// the update the Bayesian backtrack always used, a fixed number of synchronous steps of
+/// posterior = prior * score * exp(-k * sum of the posteriors of the colliding traces)
+pub struct FixedPointInference {
+    pub num_iterations: usize,
+}
+
+impl InferenceEngine for FixedPointInference {
+    fn name(&self) -> &'static str {
+        "Fixed point"
+    }
+
+    fn infer(&self, proba_model: &ProbaModel, on_iteration: &mut dyn FnMut(&ProbaModel)) -> InferenceDiagnostics {
+        let mut residual = 0.0;
+        for _ in 0..self.num_iterations {
+            residual = proba_model.update_posterior();
+            on_iteration(proba_model);
+        }
+        InferenceDiagnostics {
+            engine: self.name(),
+            iterations: self.num_iterations,
+            residual,
+            converged: residual < INFERENCE_TOLERANCE.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// mean-field iterations on the used or not variables of the traces, damped, until the posteriors settle
+pub struct MeanFieldInference {
+    pub max_iterations: usize,
+    pub tolerance: f64,
+    pub damping: f64, // the share of the old posterior kept in every step
+}
+
+impl InferenceEngine for MeanFieldInference {
+    fn name(&self) -> &'static str {
+        "Mean field"
+    }
+
+    fn infer(&self, proba_model: &ProbaModel, on_iteration: &mut dyn FnMut(&ProbaModel)) -> InferenceDiagnostics {
+        let proba_traces = sorted_proba_traces(proba_model);
+        let k = collision_penalty();
+        let mut posteriors: HashMap<ProbaTraceID, f64> =
+            proba_traces.iter().map(|(proba_trace_id, proba_trace)| (*proba_trace_id, unary_weight(proba_trace))).collect();
+        let mut iterations = 0;
+        let mut residual = f64::INFINITY;
+        while iterations < self.max_iterations && residual >= self.tolerance {
+            residual = 0.0;
+            let mut new_posteriors: HashMap<ProbaTraceID, f64> = HashMap::new();
+            for (proba_trace_id, proba_trace) in proba_traces.iter() {
+                let expected_collisions: f64 =
+                    proba_model.collision_adjacency[proba_trace_id].iter().map(|adjacent_id| posteriors[adjacent_id]).sum();
+                let target = sigmoid(logit(unary_weight(proba_trace)) - k * expected_collisions);
+                let old = posteriors[proba_trace_id];
+                let new = self.damping * old + (1.0 - self.damping) * target;
+                residual = f64::max(residual, (new - old).abs());
+                new_posteriors.insert(*proba_trace_id, new);
+            }
+            posteriors = new_posteriors;
+            iterations += 1;
+            set_posteriors(&proba_traces, &posteriors);
+            on_iteration(proba_model);
+        }
+        InferenceDiagnostics { engine: self.name(), iterations, residual, converged: residual < self.tolerance }
+    }
+}
+
+/// sum-product loopy belief propagation on the collision graph, with the messages as log odds ratios and damped
+pub struct BeliefPropagationInference {
+    pub max_iterations: usize,
+    pub tolerance: f64,
+    pub damping: f64, // the share of the old message kept in every step
+}
+
+impl InferenceEngine for BeliefPropagationInference {
+    fn name(&self) -> &'static str {
+        "Loopy belief propagation"
+    }
+
+    fn infer(&self, proba_model: &ProbaModel, on_iteration: &mut dyn FnMut(&ProbaModel)) -> InferenceDiagnostics {
+        let proba_traces = sorted_proba_traces(proba_model);
+        let k = collision_penalty();
+        let unary_log_odds: HashMap<ProbaTraceID, f64> = proba_traces
+            .iter()
+            .map(|(proba_trace_id, proba_trace)| (*proba_trace_id, logit(unary_weight(proba_trace))))
+            .collect();
+        // (from, to) to the log of message(used) / message(not used)
+        let mut messages: HashMap<(ProbaTraceID, ProbaTraceID), f64> = HashMap::new();
+        for (proba_trace_id, adjacent_ids) in proba_model.collision_adjacency.iter() {
+            for adjacent_id in adjacent_ids.iter() {
+                messages.insert((*proba_trace_id, *adjacent_id), 0.0);
+            }
+        }
+        let beliefs = |messages: &HashMap<(ProbaTraceID, ProbaTraceID), f64>| -> HashMap<ProbaTraceID, f64> {
+            proba_traces
+                .keys()
+                .map(|proba_trace_id| {
+                    let incoming: f64 = proba_model.collision_adjacency[proba_trace_id]
+                        .iter()
+                        .map(|adjacent_id| messages[&(*adjacent_id, *proba_trace_id)])
+                        .sum();
+                    (*proba_trace_id, unary_log_odds[proba_trace_id] + incoming)
+                })
+                .collect()
+        };
+        let mut iterations = 0;
+        let mut residual = f64::INFINITY;
+        while iterations < self.max_iterations && residual >= self.tolerance {
+            residual = 0.0;
+            let log_odds = beliefs(&messages);
+            let mut new_messages: HashMap<(ProbaTraceID, ProbaTraceID), f64> = HashMap::new();
+            for (&(from, to), &old) in messages.iter() {
+                // the odds of the sender without what the receiver told it
+                let sender_odds = (log_odds[&from] - messages[&(to, from)]).exp();
+                let target = ((1.0 + sender_odds * (-k).exp()) / (1.0 + sender_odds)).ln();
+                let new = self.damping * old + (1.0 - self.damping) * target;
+                residual = f64::max(residual, (new - old).abs());
+                new_messages.insert((from, to), new);
+            }
+            messages = new_messages;
+            iterations += 1;
+            let posteriors = beliefs(&messages).into_iter().map(|(proba_trace_id, log_odds)| (proba_trace_id, sigmoid(log_odds))).collect();
+            set_posteriors(&proba_traces, &posteriors);
+            on_iteration(proba_model);
+        }
+        InferenceDiagnostics { engine: self.name(), iterations, residual, converged: residual < self.tolerance }
+    }
+}
+
+/// Gibbs sampling of consistent assignments, at most one trace per connection and no two colliding traces
+/// a connection can also be left without a trace, which weighs as much as its average trace after one collision
+/// the posterior of a trace is how often it is used after the burn-in, smoothed by its weight so it never is 0
+pub struct GibbsInference {
+    pub num_sweeps: usize,
+    pub tolerance: f64,
+}
+
+// far from the streams of the sampling threads, which are numbered by net
+const GIBBS_STREAM: u64 = 1 << 32;
+
+impl InferenceEngine for GibbsInference {
+    fn name(&self) -> &'static str {
+        "Gibbs sampling"
+    }
+
+    fn infer(&self, proba_model: &ProbaModel, on_iteration: &mut dyn FnMut(&ProbaModel)) -> InferenceDiagnostics {
+        let proba_traces = sorted_proba_traces(proba_model);
+        let k = collision_penalty();
+        let mut connection_to_traces: BTreeMap<ConnectionID, Vec<ProbaTraceID>> = BTreeMap::new();
+        for (proba_trace_id, proba_trace) in proba_traces.iter() {
+            connection_to_traces.entry(proba_trace.connection_id).or_default().push(*proba_trace_id);
+        }
+        let weights: HashMap<ProbaTraceID, f64> =
+            proba_traces.iter().map(|(proba_trace_id, proba_trace)| (*proba_trace_id, unary_weight(proba_trace))).collect();
+        let mut rng = create_deterministic_rng_for_stream(current_seed(), GIBBS_STREAM + proba_model.fix_sequence.len() as u64);
+        // the trace each connection uses, starting from none
+        let mut assignment: HashMap<ConnectionID, Option<ProbaTraceID>> =
+            connection_to_traces.keys().map(|connection_id| (*connection_id, None)).collect();
+        let burn_in = self.num_sweeps / 5;
+        let num_samples = self.num_sweeps - burn_in;
+        // use counts in the first and the second half of the samples
+        let mut counts: [HashMap<ProbaTraceID, usize>; 2] = [HashMap::new(), HashMap::new()];
+        for sweep in 0..self.num_sweeps {
+            for (connection_id, trace_ids) in connection_to_traces.iter() {
+                let used_elsewhere = |proba_trace_id: &ProbaTraceID| {
+                    proba_model.collision_adjacency[proba_trace_id].iter().any(|adjacent_id| {
+                        let adjacent_connection = proba_traces[adjacent_id].connection_id;
+                        adjacent_connection != *connection_id && assignment[&adjacent_connection] == Some(*adjacent_id)
+                    })
+                };
+                let mut choices: Vec<(Option<ProbaTraceID>, f64)> = trace_ids
+                    .iter()
+                    .filter(|proba_trace_id| !used_elsewhere(proba_trace_id))
+                    .map(|proba_trace_id| (Some(*proba_trace_id), weights[proba_trace_id]))
+                    .collect();
+                let average_weight = trace_ids.iter().map(|proba_trace_id| weights[proba_trace_id]).sum::<f64>() / trace_ids.len() as f64;
+                choices.push((None, average_weight * (-k).exp()));
+                let total: f64 = choices.iter().map(|(_, weight)| weight).sum();
+                let mut draw = rng.random::<f64>() * total;
+                let mut chosen = None;
+                for (choice, weight) in choices.iter() {
+                    chosen = *choice;
+                    if draw < *weight {
+                        break;
+                    }
+                    draw -= weight;
+                }
+                assignment.insert(*connection_id, chosen);
+            }
+            if sweep >= burn_in {
+                let half = if (sweep - burn_in) * 2 < num_samples { 0 } else { 1 };
+                for proba_trace_id in assignment.values().flatten() {
+                    *counts[half].entry(*proba_trace_id).or_default() += 1;
+                }
+            }
+            if (sweep + 1) % 10 == 0 {
+                on_iteration(proba_model);
+            }
+        }
+        let half_sizes = [num_samples.div_ceil(2), num_samples / 2];
+        let frequency = |half: usize, proba_trace_id: &ProbaTraceID| {
+            let count = counts[half].get(proba_trace_id).copied().unwrap_or(0) as f64;
+            (count + weights[proba_trace_id]) / (half_sizes[half] as f64 + 1.0)
+        };
+        let mut residual: f64 = 0.0;
+        let mut posteriors: HashMap<ProbaTraceID, f64> = HashMap::new();
+        for proba_trace_id in proba_traces.keys() {
+            residual = residual.max((frequency(0, proba_trace_id) - frequency(1, proba_trace_id)).abs());
+            let count = counts.iter().map(|half_counts| half_counts.get(proba_trace_id).copied().unwrap_or(0)).sum::<usize>() as f64;
+            posteriors.insert(*proba_trace_id, (count + weights[proba_trace_id]) / (num_samples as f64 + 1.0));
+        }
+        set_posteriors(&proba_traces, &posteriors);
+        on_iteration(proba_model);
+        // the halves never agree better than their sampling noise
+        let noise = 2.0 / (half_sizes[1].max(1) as f64).sqrt();
+        InferenceDiagnostics {
+            engine: self.name(),
+            iterations: self.num_sweeps,
+            residual,
+            converged: residual < self.tolerance.max(noise),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        num::NonZeroUsize,
+        sync::{Arc, Mutex},
+    };
+
+    use shared::{
+        pcb_problem::{ConnectionID, NetName},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::FloatVec2,
+    };
+
+    use super::{
+        BeliefPropagationInference, FixedPointInference, GibbsInference, InferenceEngine, MeanFieldInference,
+    };
+    use crate::{
+        deterministic_rand::with_seed,
+        proba_model::{ProbaModel, ProbaTrace, ProbaTraceID, Traces},
+    };
+
+    /// a model of the given traces, as (connection, prior, score), and the collisions between them
+    /// the trace ids are the indices into the traces, the geometry plays no part in inference
+    fn collision_graph(traces: &[(usize, f64, f64)], collisions: &[(usize, usize)]) -> ProbaModel {
+        let mut connection_to_traces: HashMap<ConnectionID, Traces> = HashMap::new();
+        for (index, &(connection, prior, score)) in traces.iter().enumerate() {
+            let anchors = [0.0, 1.0]
+                .into_iter()
+                .map(|x| TraceAnchor {
+                    position: FloatVec2::new(x, connection as f32).to_fixed().to_nearest_even_even(),
+                    start_layer: 0,
+                    end_layer: 0,
+                })
+                .collect();
+            let proba_trace = ProbaTrace {
+                net_name: NetName(format!("net{}", connection)),
+                connection_id: ConnectionID(connection),
+                proba_trace_id: ProbaTraceID(index),
+                trace_path: TracePath::from_anchors(TraceAnchors(anchors), 0.2, 0.2, vec![]),
+                score,
+                iteration: NonZeroUsize::new(1).unwrap(),
+                prior,
+                posterior: Mutex::new(None),
+                temp_posterior: Mutex::new(None),
+            };
+            let entry = connection_to_traces
+                .entry(ConnectionID(connection))
+                .or_insert_with(|| Traces::Probabilistic(HashMap::new()));
+            if let Traces::Probabilistic(trace_map) = entry {
+                trace_map.insert(ProbaTraceID(index), Arc::new(proba_trace));
+            }
+        }
+        let mut collision_adjacency: HashMap<ProbaTraceID, HashSet<ProbaTraceID>> =
+            (0..traces.len()).map(|index| (ProbaTraceID(index), HashSet::new())).collect();
+        for &(index1, index2) in collisions {
+            collision_adjacency.get_mut(&ProbaTraceID(index1)).unwrap().insert(ProbaTraceID(index2));
+            collision_adjacency.get_mut(&ProbaTraceID(index2)).unwrap().insert(ProbaTraceID(index1));
+        }
+        ProbaModel {
+            trace_id_generator: Box::new((traces.len()..).map(ProbaTraceID)),
+            connection_to_traces,
+            fix_sequence: Vec::new(),
+            collision_adjacency,
+            next_iteration: NonZeroUsize::new(2).unwrap(),
+            inference_diagnostics: Vec::new(),
+        }
+    }
+
+    fn posteriors(proba_model: &ProbaModel) -> Vec<f64> {
+        let mut proba_traces: Vec<(ProbaTraceID, Arc<ProbaTrace>)> = proba_model.proba_traces().into_iter().collect();
+        proba_traces.sort_by_key(|(proba_trace_id, _)| *proba_trace_id);
+        proba_traces.iter().map(|(_, proba_trace)| proba_trace.get_posterior_with_fallback()).collect()
+    }
+
+    // three connections with two candidates each, their first candidates collide in a triangle
+    const LOOPY_TRACES: [(usize, f64, f64); 6] =
+        [(0, 0.5, 0.9), (0, 0.5, 0.4), (1, 0.5, 0.8), (1, 0.5, 0.5), (2, 0.5, 0.7), (2, 0.5, 0.6)];
+    const LOOPY_COLLISIONS: [(usize, usize); 4] = [(0, 2), (2, 4), (4, 0), (1, 3)];
+
+    #[test]
+    fn every_engine_converges_to_posteriors_a_connection_can_hold() {
+        let engines: Vec<Box<dyn InferenceEngine>> = vec![
+            Box::new(FixedPointInference { num_iterations: 50 }),
+            Box::new(BeliefPropagationInference { max_iterations: 200, tolerance: 1e-6, damping: 0.5 }),
+            Box::new(GibbsInference { num_sweeps: 4000, tolerance: 1e-4 }),
+            Box::new(MeanFieldInference { max_iterations: 200, tolerance: 1e-6, damping: 0.5 }),
+        ];
+        for engine in engines {
+            let proba_model = collision_graph(&LOOPY_TRACES, &LOOPY_COLLISIONS);
+            let mut num_calls = 0;
+            let diagnostics = engine.infer(&proba_model, &mut |_| num_calls += 1);
+            assert!(diagnostics.converged, "{}", diagnostics);
+            assert!(num_calls > 0);
+            let posteriors = posteriors(&proba_model);
+            assert!(posteriors.iter().all(|posterior| (0.0..=1.0).contains(posterior)), "{}: {:?}", engine.name(), posteriors);
+            // a connection uses at most one of its candidates, their posteriors never sum past 1
+            for connection in 0..3 {
+                let sum: f64 = LOOPY_TRACES
+                    .iter()
+                    .zip(posteriors.iter())
+                    .filter(|((trace_connection, _, _), _)| *trace_connection == connection)
+                    .map(|(_, posterior)| posterior)
+                    .sum();
+                assert!(sum <= 1.0 + 1e-9, "{}: the candidates of connection {} sum to {}", engine.name(), connection, sum);
+            }
+            // a collision only lowers a posterior below the prior times the score, Gibbs counts how often a trace is used instead
+            if engine.name() != "Gibbs sampling" {
+                for ((_, prior, score), posterior) in LOOPY_TRACES.iter().zip(posteriors.iter()) {
+                    assert!(*posterior <= prior * score + 1e-6, "{}: {} above {}", engine.name(), posterior, prior * score);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn on_a_tree_belief_propagation_and_mean_field_agree_with_the_fixed_point() {
+        // a star of collisions around the first candidate of connection 0, belief propagation finds the exact marginals
+        // on a tree, the fixed point update leaves out how the neighbours of the hub depend on each other,
+        // which for candidates this unlikely moves a posterior by a few percent
+        let traces = [(0, 0.5, 0.04), (0, 0.5, 0.032), (1, 0.5, 0.048), (1, 0.5, 0.04), (2, 0.5, 0.024), (2, 0.5, 0.04)];
+        let collisions = [(0, 2), (0, 4), (0, 3), (1, 5)];
+        let fixed_point = collision_graph(&traces, &collisions);
+        FixedPointInference { num_iterations: 100 }.infer(&fixed_point, &mut |_| {});
+        let fixed_point = posteriors(&fixed_point);
+        let engines: Vec<Box<dyn InferenceEngine>> = vec![
+            Box::new(BeliefPropagationInference { max_iterations: 200, tolerance: 1e-9, damping: 0.5 }),
+            Box::new(MeanFieldInference { max_iterations: 200, tolerance: 1e-9, damping: 0.5 }),
+        ];
+        for engine in engines {
+            let proba_model = collision_graph(&traces, &collisions);
+            assert!(engine.infer(&proba_model, &mut |_| {}).converged);
+            for (posterior, expected) in posteriors(&proba_model).iter().zip(fixed_point.iter()) {
+                assert!((posterior - expected).abs() < expected * 0.05, "{}: {} instead of {}", engine.name(), posterior, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn gibbs_sampling_is_deterministic_under_a_fixed_seed() {
+        let sample = |seed: u64| {
+            let proba_model = collision_graph(&LOOPY_TRACES, &LOOPY_COLLISIONS);
+            with_seed(seed, || GibbsInference { num_sweeps: 200, tolerance: 1e-4 }.infer(&proba_model, &mut |_| {}));
+            posteriors(&proba_model)
+        };
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+    }
+}
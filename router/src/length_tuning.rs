@@ -0,0 +1,185 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    sync::atomic::Ordering,
+};
+
+use shared::{
+    hyperparameters::MEANDER_MAX_AMPLITUDE,
+    pad::PadLayer,
+    pcb_problem::{ConnectionID, FixedTrace, LengthConstraint, NetName, PcbProblem},
+    vec2::FixedVec2,
+};
+
+use crate::{astar::AStarModel, obstacles::build_pad_obstacles, post_process::insert_meanders};
+
+/// the total routed length of a net, over all of its connections
+fn net_length(net_name: &NetName, determined_traces: &HashMap<ConnectionID, FixedTrace>) -> f64 {
+    determined_traces
+        .values()
+        .filter(|fixed_trace| fixed_trace.net_name == *net_name)
+        .map(|fixed_trace| fixed_trace.trace_path.total_length)
+        .sum()
+}
+
+/// the length every net of the constraint should reach, the longest net of a matched group sets the target of the others
+fn target_lengths(
+    constraint: &LengthConstraint,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> Vec<(NetName, f64)> {
+    let lengths: Vec<f64> = constraint
+        .net_names
+        .iter()
+        .map(|net_name| net_length(net_name, determined_traces))
+        .collect();
+    let longest = lengths.iter().cloned().fold(0.0, f64::max);
+    let mut target = constraint.min_length as f64;
+    if let Some(match_tolerance) = constraint.match_tolerance {
+        target = f64::max(target, longest - match_tolerance as f64);
+    }
+    if let Some(max_length) = constraint.max_length {
+        target = f64::min(target, max_length as f64);
+    }
+    constraint
+        .net_names
+        .iter()
+        .cloned()
+        .zip(lengths)
+        .filter(|(_, length)| *length < target)
+        .map(|(net_name, length)| (net_name, target - length))
+        .collect()
+}
+
+/// lengthens the traces of one net with meanders, the longest connection first, locked traces are never lengthened
+fn tune_net(
+    problem: &PcbProblem,
+    net_name: &NetName,
+    needed_length: f64,
+    determined_traces: &mut HashMap<ConnectionID, FixedTrace>,
+    locked_connections: &HashSet<ConnectionID>,
+) {
+    let net_info = &problem.nets[net_name];
+    let max_amplitude = MEANDER_MAX_AMPLITUDE.load(Ordering::Relaxed) as f32;
+    let mut connection_ids: Vec<ConnectionID> = determined_traces
+        .values()
+        .filter(|fixed_trace| fixed_trace.net_name == *net_name)
+        .map(|fixed_trace| fixed_trace.connection_id)
+        .filter(|connection_id| !locked_connections.contains(connection_id))
+        .collect();
+    connection_ids.sort_by(|a, b| {
+        let length_a = determined_traces[a].trace_path.total_length;
+        let length_b = determined_traces[b].trace_path.total_length;
+        length_b.partial_cmp(&length_a).unwrap().then(a.cmp(b))
+    });
+    let mut remaining_length = needed_length;
+    // every other trace is an obstacle, including the other connections of the same net,
+    // so only the trace being tuned is taken out and put back once it has its meanders
+    let mut obstacles = build_pad_obstacles(problem, &[], determined_traces);
+    for connection_id in connection_ids {
+        if remaining_length <= 0.0 {
+            break;
+        }
+        let trace_path = &determined_traces[&connection_id].trace_path;
+        obstacles.remove_trace(trace_path, problem.num_layers);
+        let anchors = &trace_path.anchors.0;
+        let collision_model = AStarModel {
+            start: anchors[0].position,
+            end: anchors[anchors.len() - 1].position,
+            start_layers: PadLayer::All,
+            end_layers: PadLayer::All,
+            num_layers: problem.num_layers,
+            trace_width: net_info.trace_width,
+            trace_clearance: net_info.trace_clearance,
+            via_types: net_info.via_types.clone(),
+            width: problem.width,
+            height: problem.height,
+            center: problem.center,
+            obstacle_shapes: obstacles.obstacle_shapes.clone(),
+            obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes.clone(),
+            obstacle_colliders: obstacles.obstacle_colliders.clone(),
+            obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+            border_colliders_cache: RefCell::new(None),
+            border_shapes_cache: RefCell::new(None),
+            cost_map: None,
+            blocking_colliders: RefCell::new(None),
+        };
+        let check_collision_for_trace =
+            |start: FixedVec2, end: FixedVec2, width: f32, clearance: f32, layer: usize| {
+                collision_model.check_collision_for_optimization(start, end, width, clearance, layer)
+            };
+        let tuned_trace_path = insert_meanders(
+            trace_path,
+            &check_collision_for_trace,
+            net_info.trace_width,
+            net_info.trace_clearance,
+            max_amplitude,
+            remaining_length,
+        );
+        remaining_length -= tuned_trace_path.total_length - trace_path.total_length;
+        drop(collision_model);
+        obstacles.add_trace(&tuned_trace_path, problem.num_layers);
+        determined_traces.get_mut(&connection_id).unwrap().trace_path = tuned_trace_path;
+    }
+    if remaining_length > 0.0 {
+        println!(
+            "Net {} is still {:.3} mm short after tuning, there is no free space for more meanders",
+            net_name.0, remaining_length
+        );
+    }
+}
+
+/// the nets of a constraint that are outside its rule, with their lengths
+fn violations(
+    constraint: &LengthConstraint,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> Vec<(NetName, f64)> {
+    let lengths: Vec<(NetName, f64)> = constraint
+        .net_names
+        .iter()
+        .map(|net_name| (net_name.clone(), net_length(net_name, determined_traces)))
+        .collect();
+    let longest = lengths.iter().map(|(_, length)| *length).fold(0.0, f64::max);
+    lengths
+        .into_iter()
+        .filter(|(_, length)| {
+            let too_short = *length < constraint.min_length as f64;
+            let too_long = constraint
+                .max_length
+                .is_some_and(|max_length| *length > max_length as f64);
+            let unmatched = constraint
+                .match_tolerance
+                .is_some_and(|match_tolerance| longest - *length > match_tolerance as f64);
+            too_short || too_long || unmatched
+        })
+        .collect()
+}
+
+/// the post routing pass that meets the length constraints, it only ever adds length
+/// the halves of a differential pair are left alone, a meander in one half would break the coupling
+/// the locked traces of a reroute count towards the length of their net but keep their shape
+pub fn tune_lengths(
+    problem: &PcbProblem,
+    determined_traces: &mut HashMap<ConnectionID, FixedTrace>,
+    locked_connections: &HashSet<ConnectionID>,
+) {
+    let paired_nets: HashSet<&NetName> = problem
+        .diff_pairs
+        .iter()
+        .flat_map(|diff_pair| [&diff_pair.positive_net, &diff_pair.negative_net])
+        .collect();
+    for constraint in problem.length_constraints.iter() {
+        for (net_name, needed_length) in target_lengths(constraint, determined_traces) {
+            if paired_nets.contains(&net_name) {
+                println!("Not tuning net {}, it is part of a differential pair", net_name.0);
+                continue;
+            }
+            tune_net(problem, &net_name, needed_length, determined_traces, locked_connections);
+        }
+        for (net_name, length) in violations(constraint, determined_traces) {
+            println!(
+                "Net {} of length group {} is {:.3} mm long, outside the rule",
+                net_name.0, constraint.name, length
+            );
+        }
+    }
+}
@@ -11,4 +11,27 @@ pub mod astar_check_struct;
 pub mod naive_backtrack_algo;
 pub mod bayesian_backtrack_algo;
 pub mod deterministic_rand;
-pub mod display_injection;
\ No newline at end of file
+pub mod display_injection;
+pub mod obstacles;
+pub mod negotiated_congestion_algo;
+pub mod diff_pair_routing;
+pub mod length_tuning;
+pub mod teardrops;
+pub mod fanout;
+pub mod zone_fill;
+pub mod corner_rounding;
+pub mod shove;
+pub mod partial_solution;
+pub mod solve_budget;
+pub mod portfolio;
+pub mod inference;
+pub mod checkpoint;
+pub mod candidate_diversity;
+pub mod maxsat;
+pub mod exact_selection_algo;
+pub mod backjumping;
+pub mod search_strategy;
+pub mod mcts_order_algo;
+pub mod annealing;
+pub mod pareto_front;
+pub mod cell_grid;
@@ -0,0 +1,422 @@
+/// a variable or its negation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Literal {
+    pub variable: usize,
+    pub negated: bool,
+}
+
+impl Literal {
+    pub fn positive(variable: usize) -> Self {
+        Literal { variable, negated: false }
+    }
+    pub fn negative(variable: usize) -> Self {
+        Literal { variable, negated: true }
+    }
+    fn holds_for(&self, value: bool) -> bool {
+        value != self.negated
+    }
+}
+
+/// weighted partial MaxSAT, every hard clause must hold and the summed weight of the violated soft clauses is minimized
+#[derive(Debug, Clone, Default)]
+pub struct MaxSatInstance {
+    pub num_variables: usize,
+    pub hard_clauses: Vec<Vec<Literal>>,
+    pub soft_clauses: Vec<(f64, Vec<Literal>)>, // weights must not be negative
+}
+
+#[derive(Debug, Clone)]
+pub enum MaxSatResult {
+    Optimal { assignment: Vec<bool>, cost: f64 },
+    Feasible { assignment: Vec<bool>, cost: f64 }, // the node budget ran out before the assignment was proven optimal
+    Unsatisfiable,                                 // the hard clauses can not all hold
+    Unknown,                                       // the node budget ran out before any assignment was found
+}
+
+impl MaxSatInstance {
+    pub fn new_variable(&mut self) -> usize {
+        self.num_variables += 1;
+        self.num_variables - 1
+    }
+
+    pub fn add_hard_clause(&mut self, clause: Vec<Literal>) {
+        self.hard_clauses.push(clause);
+    }
+
+    pub fn add_soft_clause(&mut self, weight: f64, clause: Vec<Literal>) {
+        assert!(weight >= 0.0, "Soft clause weight must not be negative, got: {}", weight);
+        self.soft_clauses.push((weight, clause));
+    }
+
+    /// exactly one of the variables is true, as one at least one clause and pairwise at most one clauses
+    pub fn add_exactly_one(&mut self, variables: &[usize]) {
+        self.add_hard_clause(variables.iter().map(|variable| Literal::positive(*variable)).collect());
+        for (i, variable1) in variables.iter().enumerate() {
+            for variable2 in variables[i + 1..].iter() {
+                self.add_hard_clause(vec![Literal::negative(*variable1), Literal::negative(*variable2)]);
+            }
+        }
+    }
+
+    /// branch and bound over the variables with unit propagation of the hard clauses, gives up after max_nodes branches
+    pub fn solve(&self, max_nodes: usize) -> MaxSatResult {
+        let mut solver = Solver::new(self, max_nodes);
+        if solver.propagate_initial_units() {
+            solver.branch();
+        }
+        match (solver.best, solver.budget_exhausted) {
+            (Some((assignment, cost)), false) => MaxSatResult::Optimal { assignment, cost },
+            (Some((assignment, cost)), true) => MaxSatResult::Feasible { assignment, cost },
+            (None, false) => MaxSatResult::Unsatisfiable,
+            (None, true) => MaxSatResult::Unknown,
+        }
+    }
+}
+
+struct Solver<'a> {
+    instance: &'a MaxSatInstance,
+    values: Vec<Option<bool>>,
+    trail: Vec<usize>,                      // assigned variables in order, undone on backtracking
+    hard_occurrences: Vec<Vec<usize>>,      // hard clauses each variable appears in
+    unit_costs: Vec<[f64; 2]>,              // weight of the unit soft clauses a variable violates when false and when true
+    best: Option<(Vec<bool>, f64)>,
+    num_nodes: usize,
+    max_nodes: usize,
+    budget_exhausted: bool,
+}
+
+impl<'a> Solver<'a> {
+    fn new(instance: &'a MaxSatInstance, max_nodes: usize) -> Self {
+        let mut hard_occurrences = vec![Vec::new(); instance.num_variables];
+        for (clause_index, clause) in instance.hard_clauses.iter().enumerate() {
+            for literal in clause.iter() {
+                hard_occurrences[literal.variable].push(clause_index);
+            }
+        }
+        let mut unit_costs = vec![[0.0; 2]; instance.num_variables];
+        for (weight, clause) in instance.soft_clauses.iter() {
+            if let [literal] = clause.as_slice() {
+                // the clause is violated by the value that falsifies the literal
+                unit_costs[literal.variable][literal.negated as usize] += weight;
+            }
+        }
+        Solver {
+            instance,
+            values: vec![None; instance.num_variables],
+            trail: Vec::new(),
+            hard_occurrences,
+            unit_costs,
+            best: None,
+            num_nodes: 0,
+            max_nodes,
+            budget_exhausted: false,
+        }
+    }
+
+    fn assign(&mut self, variable: usize, value: bool) {
+        self.values[variable] = Some(value);
+        self.trail.push(variable);
+    }
+
+    fn undo_to(&mut self, trail_length: usize) {
+        while self.trail.len() > trail_length {
+            let variable = self.trail.pop().unwrap();
+            self.values[variable] = None;
+        }
+    }
+
+    /// whether the clause holds, and its unassigned literals
+    fn clause_state(&self, clause: &[Literal]) -> (bool, Vec<Literal>) {
+        let mut unassigned = Vec::new();
+        for literal in clause.iter() {
+            match self.values[literal.variable] {
+                Some(value) if literal.holds_for(value) => return (true, Vec::new()),
+                Some(_) => {}
+                None => unassigned.push(*literal),
+            }
+        }
+        (false, unassigned)
+    }
+
+    /// assigns the literals that are forced by the hard clauses, false on a conflict
+    fn propagate(&mut self, mut queue: Vec<usize>) -> bool {
+        while let Some(variable) = queue.pop() {
+            for occurrence in 0..self.hard_occurrences[variable].len() {
+                let clause_index = self.hard_occurrences[variable][occurrence];
+                let (satisfied, unassigned) = self.clause_state(&self.instance.hard_clauses[clause_index]);
+                if satisfied {
+                    continue;
+                }
+                match unassigned.as_slice() {
+                    [] => return false,
+                    [literal] => {
+                        let literal = *literal;
+                        self.assign(literal.variable, !literal.negated);
+                        queue.push(literal.variable);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    fn propagate_initial_units(&mut self) -> bool {
+        for clause in self.instance.hard_clauses.iter() {
+            match clause.as_slice() {
+                [] => return false,
+                [literal] => match self.values[literal.variable] {
+                    Some(value) if !literal.holds_for(value) => return false,
+                    Some(_) => {}
+                    None => {
+                        self.assign(literal.variable, !literal.negated);
+                        if !self.propagate(vec![literal.variable]) {
+                            return false;
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// the weight of the soft clauses the partial assignment already violates
+    fn violated_cost(&self) -> f64 {
+        self.instance
+            .soft_clauses
+            .iter()
+            .filter(|(_, clause)| {
+                clause
+                    .iter()
+                    .all(|literal| matches!(self.values[literal.variable], Some(value) if !literal.holds_for(value)))
+            })
+            .map(|(weight, _)| weight)
+            .sum()
+    }
+
+    fn literal_cost(&self, literal: &Literal) -> f64 {
+        self.unit_costs[literal.variable][!literal.negated as usize]
+    }
+
+    /// every open hard clause costs at least its cheapest literal, summed over clauses that share no unassigned variable
+    fn remaining_cost_bound(&self, open_clauses: &[Vec<Literal>]) -> f64 {
+        let mut counted_variables = vec![false; self.instance.num_variables];
+        let mut bound = 0.0;
+        for unassigned in open_clauses.iter() {
+            if unassigned.iter().any(|literal| counted_variables[literal.variable]) {
+                continue;
+            }
+            let cheapest = unassigned.iter().map(|literal| self.literal_cost(literal)).fold(f64::INFINITY, f64::min);
+            bound += cheapest;
+            for literal in unassigned.iter() {
+                counted_variables[literal.variable] = true;
+            }
+        }
+        bound
+    }
+
+    /// the literal to branch on at the current node, None when the node is pruned by the bound or is a complete assignment
+    fn branch_literal(&mut self) -> Option<Literal> {
+        // the open clauses of the node are dropped before its children are visited, the search holds only its decisions
+        let open_clauses: Vec<Vec<Literal>> = self
+            .instance
+            .hard_clauses
+            .iter()
+            .map(|clause| self.clause_state(clause))
+            .filter(|(satisfied, _)| !satisfied)
+            .map(|(_, unassigned)| unassigned)
+            .collect();
+        let cost = self.violated_cost();
+        if let Some((_, best_cost)) = &self.best
+            && cost + self.remaining_cost_bound(&open_clauses) >= *best_cost
+        {
+            return None;
+        }
+        // the open clause with the fewest choices is branched on, its cheapest literal first
+        match open_clauses.iter().min_by_key(|unassigned| unassigned.len()) {
+            Some(unassigned) => Some(
+                *unassigned
+                    .iter()
+                    .min_by(|literal1, literal2| self.literal_cost(literal1).total_cmp(&self.literal_cost(literal2)))
+                    .expect("Open clauses have unassigned literals"),
+            ),
+            None => match self.values.iter().position(|value| value.is_none()) {
+                // the hard clauses hold, the soft clauses decide the rest
+                Some(variable) => {
+                    let cheaper_value = self.unit_costs[variable][1] < self.unit_costs[variable][0];
+                    Some(Literal { variable, negated: !cheaper_value })
+                }
+                None => {
+                    let assignment = self.values.iter().map(|value| value.unwrap()).collect();
+                    self.best = Some((assignment, cost));
+                    None
+                }
+            },
+        }
+    }
+
+    /// assigns the literal the value and propagates it, false on a conflict
+    fn decide(&mut self, literal: Literal, literal_value: bool) -> bool {
+        self.assign(literal.variable, literal_value != literal.negated);
+        self.propagate(vec![literal.variable])
+    }
+
+    /// depth first over the decisions with an explicit stack, so the depth is not bounded by the call stack
+    /// every decision tries its literal true and then false
+    fn branch(&mut self) {
+        let mut decisions: Vec<Decision> = Vec::new();
+        let mut is_new_node = true;
+        loop {
+            if is_new_node {
+                if self.num_nodes >= self.max_nodes {
+                    self.budget_exhausted = true;
+                    return;
+                }
+                self.num_nodes += 1;
+                if let Some(literal) = self.branch_literal() {
+                    decisions.push(Decision { trail_length: self.trail.len(), literal, tried_false: false });
+                    is_new_node = self.decide(literal, true);
+                    continue;
+                }
+            }
+            // the node is done, the nearest decision with a value left goes on with it
+            loop {
+                let Some(decision) = decisions.last_mut() else {
+                    return;
+                };
+                self.undo_to(decision.trail_length);
+                if !decision.tried_false {
+                    decision.tried_false = true;
+                    let literal = decision.literal;
+                    is_new_node = self.decide(literal, false);
+                    break;
+                }
+                decisions.pop();
+            }
+        }
+    }
+}
+
+/// a literal the search branched on, with the trail length to undo to before its next value
+struct Decision {
+    trail_length: usize,
+    literal: Literal,
+    tried_false: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{Literal, MaxSatInstance, MaxSatResult};
+
+    /// the cheapest assignment that keeps every hard clause, by trying them all
+    fn brute_force_optimum(instance: &MaxSatInstance) -> Option<f64> {
+        let holds = |clause: &[Literal], assignment: &[bool]| {
+            clause.iter().any(|literal| literal.holds_for(assignment[literal.variable]))
+        };
+        (0..1usize << instance.num_variables)
+            .map(|bits| (0..instance.num_variables).map(|variable| bits & (1 << variable) != 0).collect::<Vec<bool>>())
+            .filter(|assignment| instance.hard_clauses.iter().all(|clause| holds(clause, assignment)))
+            .map(|assignment| {
+                instance
+                    .soft_clauses
+                    .iter()
+                    .filter(|(_, clause)| !holds(clause, &assignment))
+                    .map(|(weight, _)| weight)
+                    .sum::<f64>()
+            })
+            .min_by(f64::total_cmp)
+    }
+
+    /// two connections with a choice of candidates each, where the cheapest candidate of the first collides with the cheap one of the second
+    fn two_connections() -> MaxSatInstance {
+        let mut instance = MaxSatInstance::default();
+        let first: Vec<usize> = (0..3).map(|_| instance.new_variable()).collect();
+        let second: Vec<usize> = (0..2).map(|_| instance.new_variable()).collect();
+        instance.add_exactly_one(&first);
+        instance.add_exactly_one(&second);
+        instance.add_hard_clause(vec![Literal::negative(first[1]), Literal::negative(second[0])]);
+        for (variable, weight) in first.iter().chain(second.iter()).zip([5.0, 2.0, 4.0, 1.0, 10.0]) {
+            instance.add_soft_clause(weight, vec![Literal::negative(*variable)]);
+        }
+        instance
+    }
+
+    #[test]
+    fn the_optimum_avoids_the_conflict() {
+        let MaxSatResult::Optimal { assignment, cost } = two_connections().solve(1000) else {
+            panic!("a small satisfiable instance is solved to optimality");
+        };
+        assert_eq!(assignment, vec![false, false, true, true, false]);
+        assert_eq!(cost, 5.0);
+    }
+
+    #[test]
+    fn exactly_one_with_every_pair_in_conflict_is_unsatisfiable() {
+        let mut instance = MaxSatInstance::default();
+        let first = [instance.new_variable(), instance.new_variable()];
+        let second = [instance.new_variable(), instance.new_variable()];
+        instance.add_exactly_one(&first);
+        instance.add_exactly_one(&second);
+        for variable1 in first {
+            for variable2 in second {
+                instance.add_hard_clause(vec![Literal::negative(variable1), Literal::negative(variable2)]);
+            }
+        }
+        assert!(matches!(instance.solve(1000), MaxSatResult::Unsatisfiable));
+    }
+
+    #[test]
+    fn a_small_node_budget_gives_up_without_a_proof() {
+        let instance = two_connections();
+        assert!(matches!(instance.solve(1), MaxSatResult::Unknown));
+        let mut max_nodes = 1;
+        loop {
+            match instance.solve(max_nodes) {
+                MaxSatResult::Optimal { cost, .. } => {
+                    assert_eq!(cost, 5.0);
+                    break;
+                }
+                MaxSatResult::Feasible { cost, .. } => assert!(cost >= 5.0),
+                MaxSatResult::Unknown => {}
+                MaxSatResult::Unsatisfiable => panic!("a budget that runs out proves nothing"),
+            }
+            max_nodes += 1;
+            assert!(max_nodes < 1000, "the instance is solved well within the budget");
+        }
+    }
+
+    #[test]
+    fn the_bound_never_prunes_the_optimum() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..200 {
+            let mut instance = MaxSatInstance::default();
+            let groups: Vec<Vec<usize>> = (0..3)
+                .map(|_| (0..rng.random_range(1..4)).map(|_| instance.new_variable()).collect())
+                .collect();
+            for group in groups.iter() {
+                instance.add_exactly_one(group);
+            }
+            for _ in 0..rng.random_range(0..6) {
+                let variable1 = rng.random_range(0..instance.num_variables);
+                let variable2 = rng.random_range(0..instance.num_variables);
+                instance.add_hard_clause(vec![Literal::negative(variable1), Literal::negative(variable2)]);
+            }
+            for variable in 0..instance.num_variables {
+                instance.add_soft_clause(rng.random_range(0..10) as f64, vec![Literal::negative(variable)]);
+            }
+            for _ in 0..rng.random_range(0..4) {
+                let literal1 = Literal { variable: rng.random_range(0..instance.num_variables), negated: rng.random() };
+                let literal2 = Literal { variable: rng.random_range(0..instance.num_variables), negated: rng.random() };
+                instance.add_soft_clause(rng.random_range(0..10) as f64, vec![literal1, literal2]);
+            }
+            match (instance.solve(100_000), brute_force_optimum(&instance)) {
+                (MaxSatResult::Optimal { cost, .. }, Some(optimum)) => assert_eq!(cost, optimum, "{:?}", instance),
+                (MaxSatResult::Unsatisfiable, None) => {}
+                (result, optimum) => panic!("{:?} for an optimum of {:?} of {:?}", result, optimum, instance),
+            }
+        }
+    }
+}
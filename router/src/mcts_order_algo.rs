@@ -0,0 +1,328 @@
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+use rand::{rngs::StdRng, Rng};
+use shared::{
+    hyperparameters::{MCTS_EXPLORATION, MCTS_ITERATIONS, MCTS_ROLLOUT_RANDOMNESS},
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem, PcbSolution},
+    trace_path::TracePath,
+};
+
+use crate::{
+    backjumping::blocking_traces,
+    bayesian_backtrack_algo::TraceCache,
+    command_flags::CommandFlag,
+    deterministic_rand::{create_deterministic_rng, current_seed},
+    display_injection::{display_when_necessary, DisplayInjection},
+    obstacles::build_pad_obstacles,
+    partial_solution::{astar_model_for_connection, to_partial_solution},
+};
+
+/// a node of the search tree, its order is the connections on the path from the root
+struct MctsNode {
+    connection: Option<ConnectionID>, // routed right after the order of the parent, none at the root
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried_connections: Vec<ConnectionID>, // not expanded into a child yet, in the base order
+    num_visits: usize,
+    total_reward: f64,
+}
+
+/// the traces of a rollout, the connections that could not be routed in its order are missing
+struct Rollout {
+    fixed_traces: HashMap<ConnectionID, FixedTrace>,
+    total_length: f64,
+}
+
+impl Rollout {
+    /// more routed connections, then less length
+    fn is_better_than(&self, other: &Rollout) -> bool {
+        match self.fixed_traces.len().cmp(&other.fixed_traces.len()) {
+            std::cmp::Ordering::Equal => self.total_length < other.total_length,
+            ordering => ordering == std::cmp::Ordering::Greater,
+        }
+    }
+}
+
+/// the connections by the straight-line length between their pads, shortest first
+fn base_order(problem: &PcbProblem, prerouted_traces: &HashMap<ConnectionID, FixedTrace>) -> Vec<ConnectionID> {
+    let mut connections: Vec<(f32, ConnectionID)> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| {
+            net_info.connections.values().map(move |connection| {
+                let start = net_info.pads[&connection.start_pad].position;
+                let end = net_info.pads[&connection.end_pad].position;
+                ((end - start).length(), connection.connection_id)
+            })
+        })
+        .filter(|(_, connection_id)| !prerouted_traces.contains_key(connection_id))
+        .collect();
+    connections.sort_by(|(length1, id1), (length2, id2)| length1.total_cmp(length2).then(id1.cmp(id2)));
+    connections.into_iter().map(|(_, connection_id)| connection_id).collect()
+}
+
+/// routes the connections one after another around the traces before them,
+/// a cached trace that fits is taken before A* is run, and a connection A* fails on is left out
+fn route_in_order(
+    problem: &PcbProblem,
+    order: &[ConnectionID],
+    trace_cache: &mut TraceCache,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Rollout {
+    let connections: HashMap<ConnectionID, _> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.iter())
+        .map(|(connection_id, connection)| (*connection_id, connection.clone()))
+        .collect();
+    let mut fixed_traces = prerouted_traces.clone();
+    let mut total_length = 0.0;
+    for connection_id in order {
+        if display_injection.is_stopped() {
+            break;
+        }
+        let connection = &connections[connection_id];
+        let cached_traces = trace_cache.traces.entry(*connection_id).or_default();
+        let cached_trace: Option<TracePath> = cached_traces
+            .iter()
+            .find(|cached_trace| blocking_traces(cached_trace, &connection.net_name, &fixed_traces).is_empty())
+            .cloned();
+        let trace_path = match cached_trace {
+            Some(trace_path) => trace_path,
+            None => {
+                let obstacles = build_pad_obstacles(problem, &[&connection.net_name], &fixed_traces);
+                match astar_model_for_connection(problem, connection, obstacles).run(display_injection) {
+                    Ok(result) => {
+                        cached_traces.push(result.trace_path.clone());
+                        result.trace_path
+                    }
+                    Err(_) => continue,
+                }
+            }
+        };
+        total_length += trace_path.total_length;
+        fixed_traces.insert(
+            *connection_id,
+            FixedTrace { net_name: connection.net_name.clone(), connection_id: *connection_id, trace_path },
+        );
+    }
+    Rollout { fixed_traces, total_length }
+}
+
+/// the child with the best upper confidence bound
+fn select_child(nodes: &[MctsNode], node_index: usize, exploration: f64) -> usize {
+    let node = &nodes[node_index];
+    let log_visits = (node.num_visits.max(1) as f64).ln();
+    *node
+        .children
+        .iter()
+        .max_by(|child1, child2| {
+            let uct = |child: usize| {
+                let child = &nodes[child];
+                child.total_reward / child.num_visits as f64 + exploration * (log_visits / child.num_visits as f64).sqrt()
+            };
+            uct(**child1).total_cmp(&uct(**child2))
+        })
+        .expect("A fully expanded node has children")
+}
+
+/// the order of a node, from the root down
+fn order_of(nodes: &[MctsNode], mut node_index: usize) -> Vec<ConnectionID> {
+    let mut order: Vec<ConnectionID> = Vec::new();
+    while let Some(connection) = nodes[node_index].connection {
+        order.push(connection);
+        node_index = nodes[node_index].parent.unwrap();
+    }
+    order.reverse();
+    order
+}
+
+/// the rest of the order after the prefix, mostly in the base order with a random connection picked now and then
+fn complete_order(prefix: &[ConnectionID], base_order: &[ConnectionID], randomness: f64, rng: &mut StdRng) -> Vec<ConnectionID> {
+    let mut remaining: Vec<ConnectionID> = base_order.iter().filter(|id| !prefix.contains(id)).cloned().collect();
+    let mut order = prefix.to_vec();
+    while !remaining.is_empty() {
+        let index = if rng.random::<f64>() < randomness { rng.random_range(0..remaining.len()) } else { 0 };
+        order.push(remaining.remove(index));
+    }
+    order
+}
+
+/// Monte Carlo tree search over the order in which the connections are routed, a tree node is the start of an order
+/// and its rollouts route the whole board in an order that starts with it, UCT steers toward orders that route more connections;
+/// the best rollout becomes the solution, a partial one if no rollout routed every connection
+pub fn mcts_order(
+    problem: &PcbProblem,
+    trace_cache: &mut TraceCache,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    if display_injection.is_stopped() {
+        println!("Stop requested, not running the Monte Carlo tree search");
+        return Ok(to_partial_solution(problem, prerouted_traces.clone(), display_injection));
+    }
+    let base_order = base_order(problem, prerouted_traces);
+    let num_connections = base_order.len();
+    let exploration = MCTS_EXPLORATION.load(Ordering::Relaxed);
+    let randomness = MCTS_ROLLOUT_RANDOMNESS.load(Ordering::Relaxed);
+    let num_iterations = MCTS_ITERATIONS.load(Ordering::Relaxed).max(1);
+    let mut rng = create_deterministic_rng();
+    let mut nodes: Vec<MctsNode> = vec![MctsNode {
+        connection: None,
+        parent: None,
+        children: Vec::new(),
+        untried_connections: base_order.clone(),
+        num_visits: 0,
+        total_reward: 0.0,
+    }];
+    let mut best_rollout: Option<Rollout> = None;
+    for iteration in 0..num_iterations {
+        display_injection.budget.check();
+        if display_injection.is_stopped() {
+            println!("Stop requested, exiting the Monte Carlo tree search");
+            break;
+        }
+        // selection
+        let mut node_index = 0;
+        while nodes[node_index].untried_connections.is_empty() && !nodes[node_index].children.is_empty() {
+            node_index = select_child(&nodes, node_index, exploration);
+        }
+        // expansion
+        if !nodes[node_index].untried_connections.is_empty() {
+            let connection = nodes[node_index].untried_connections.remove(0);
+            let mut prefix = order_of(&nodes, node_index);
+            prefix.push(connection);
+            let untried_connections = base_order.iter().filter(|id| !prefix.contains(id)).cloned().collect();
+            nodes.push(MctsNode {
+                connection: Some(connection),
+                parent: Some(node_index),
+                children: Vec::new(),
+                untried_connections,
+                num_visits: 0,
+                total_reward: 0.0,
+            });
+            let child_index = nodes.len() - 1;
+            nodes[node_index].children.push(child_index);
+            node_index = child_index;
+        }
+        // rollout
+        let order = complete_order(&order_of(&nodes, node_index), &base_order, randomness, &mut rng);
+        let rollout = route_in_order(problem, &order, trace_cache, prerouted_traces, display_injection);
+        let num_routed = rollout.fixed_traces.len() - prerouted_traces.len();
+        let reward = if num_connections == 0 { 1.0 } else { num_routed as f64 / num_connections as f64 };
+        // backpropagation
+        let mut backpropagated = Some(node_index);
+        while let Some(index) = backpropagated {
+            nodes[index].num_visits += 1;
+            nodes[index].total_reward += reward;
+            backpropagated = nodes[index].parent;
+        }
+        if best_rollout.as_ref().is_none_or(|best_rollout| rollout.is_better_than(best_rollout)) {
+            println!(
+                "Iteration {}: rollout routed {} of {} connections, length {:.2}",
+                iteration, num_routed, num_connections, rollout.total_length
+            );
+            display_when_necessary(&rollout.fixed_traces, problem, CommandFlag::ProbaModelResult, display_injection, true);
+            best_rollout = Some(rollout);
+        }
+        if num_routed == num_connections {
+            println!("Rollout routed every connection after {} iterations", iteration + 1);
+            break;
+        }
+    }
+    let fixed_traces = match best_rollout {
+        Some(best_rollout) => best_rollout.fixed_traces,
+        None => prerouted_traces.clone(),
+    };
+    if fixed_traces.len() - prerouted_traces.len() < num_connections {
+        println!("No rollout routed every connection, returning the best one");
+        return Ok(to_partial_solution(problem, fixed_traces, display_injection));
+    }
+    display_when_necessary(&fixed_traces, problem, CommandFlag::Auto, display_injection, true);
+    Ok(PcbSolution {
+        determined_traces: fixed_traces,
+        unrouted_connections: Vec::new(),
+        diff_pair_skews: HashMap::new(),
+        teardrops: Vec::new(),
+        zone_fills: Vec::new(),
+        seed: current_seed(),
+        scale_down_factor: problem.scale_down_factor,
+        pareto_front: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use shared::pcb_problem::ConnectionID;
+
+    use super::{base_order, complete_order, route_in_order};
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        deterministic_rand::{create_deterministic_rng, with_seed},
+        display_injection::DisplayInjection,
+        pcb_problem_solve::SolverAlgorithm,
+        test_pcb_problem::examples::{assert_no_collisions, example_problem, route_example, AutoCommandLevel},
+    };
+
+    #[test]
+    fn a_completed_order_keeps_its_prefix_and_routes_every_connection_once() {
+        let base_order: Vec<ConnectionID> = (0..8).map(ConnectionID).collect();
+        let prefix = [ConnectionID(5), ConnectionID(2)];
+        let mut rng = with_seed(3, create_deterministic_rng);
+        let order = complete_order(&prefix, &base_order, 0.5, &mut rng);
+        assert_eq!(order[..2], prefix);
+        let mut sorted_order = order.clone();
+        sorted_order.sort();
+        assert_eq!(sorted_order, base_order);
+        // without randomness the rest is in the base order
+        assert_eq!(complete_order(&prefix, &base_order, 0.0, &mut rng)[2..], [0, 1, 3, 4, 6, 7].map(ConnectionID));
+    }
+
+    #[test]
+    fn the_search_routes_a_small_board_the_same_way_for_a_seed() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        let lengths_with_seed = |seed: u64| {
+            let solution = with_seed(seed, || route_example(&problem, SolverAlgorithm::MonteCarloOrder));
+            assert!(solution.unrouted_connections.is_empty());
+            assert_eq!(solution.determined_traces.len(), num_connections);
+            assert_no_collisions(&solution.determined_traces);
+            solution
+                .determined_traces
+                .iter()
+                .map(|(connection_id, fixed_trace)| (*connection_id, fixed_trace.trace_path.total_length))
+                .collect::<HashMap<ConnectionID, f64>>()
+        };
+        assert_eq!(lengths_with_seed(7), lengths_with_seed(7));
+    }
+
+    #[test]
+    fn a_later_rollout_routes_what_the_first_one_could_not() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        // the board has no usable via, the shorter net runs straight across it between pads at its edges,
+        // which cuts off one pad of the longer diagonal net from the other unless the diagonal net goes first
+        let problem = example_problem(include_str!("../../examples/order_trap.dsn"), 2);
+        let prerouted_traces = HashMap::new();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        let first_rollout = route_in_order(
+            &problem,
+            &base_order(&problem, &prerouted_traces),
+            &mut TraceCache::default(),
+            &prerouted_traces,
+            &mut display_injection,
+        );
+        assert_eq!(first_rollout.fixed_traces.len(), 1);
+        assert!(first_rollout.fixed_traces.values().all(|fixed_trace| fixed_trace.net_name.0 == "ACROSS"));
+
+        let solution = route_example(&problem, SolverAlgorithm::MonteCarloOrder);
+        assert!(solution.unrouted_connections.is_empty());
+        assert_eq!(solution.determined_traces.len(), 2);
+        assert_no_collisions(&solution.determined_traces);
+    }
+}
@@ -1,12 +1,14 @@
-use std::{cell::RefCell, cmp::Reverse, collections::{BinaryHeap, HashMap, VecDeque}, hash::Hash, rc::Rc, sync::{atomic::Ordering, Arc, Mutex}, thread, time::Duration};
+use std::{cell::RefCell, collections::{BinaryHeap, HashMap, VecDeque}, hash::Hash, sync::{atomic::Ordering, Arc, Mutex}, thread, time::Duration};
 
 use ordered_float::NotNan;
-use shared::{binary_heap_item::BinaryHeapItem, collider::Collider, color_float3::ColorFloat3, hyperparameters::NUM_NAIVE_PATH_FINDING_CALLS, pad::{Pad, PadName}, pcb_problem::{Connection, ConnectionID, FixedTrace, NetInfo, NetName, PcbProblem, PcbSolution}, pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable}, prim_shape::PrimShape, trace_path::{self, TracePath}};
+use serde::{Deserialize, Serialize};
+use shared::{binary_heap_item::BinaryHeapItem, collider::Collider, hyperparameters::{NUM_NAIVE_PATH_FINDING_CALLS, USE_BACKJUMPING}, pad::{Pad, PadName}, pcb_problem::{Connection, ConnectionID, FixedTrace, NetInfo, NetName, PcbProblem, PcbSolution}, prim_shape::PrimShape, trace_path::{self, TracePath}, trace_scorer::{trace_scorer, ScoringContext}};
 
-use crate::{astar::{self, AStarModel}, astar_check_struct::AStarCheck, bayesian_backtrack_algo::TraceCache, command_flags::{CommandFlag, TARGET_COMMAND_LEVEL}, display_injection::{self, DisplayInjection}, quad_tree::QuadTreeNode};
+use crate::{astar::{self, AStarModel}, astar_check_struct::AStarCheck, backjumping::{backjump_index, traces_owning_colliders, Conflict, Nogoods}, bayesian_backtrack_algo::TraceCache, checkpoint::{is_checkpoint_due, take_resumed_naive_backtrack, write_checkpoint, SearchState}, command_flags::CommandFlag, deterministic_rand::current_seed, display_injection::{display_when_necessary, DisplayInjection}, partial_solution::{to_partial_solution, BestPartialState}, quad_tree::QuadTreeNode, shove::{shove_traces, ShoveObstacles}};
 
 
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NaiveBacktrackNode{
     pub current_connection: Option<ConnectionID>,
     pub alternative_connections: VecDeque<ConnectionID>,
@@ -43,85 +45,18 @@ impl NaiveBacktrackNode{
     }
 }
 
-fn node_to_pcb_render_model(problem: &PcbProblem, node: &NaiveBacktrackNode) -> PcbRenderModel {
-    let mut trace_shape_renderables: Vec<RenderableBatch> = Vec::new();
-    let mut pad_shape_renderables: Vec<ShapeRenderable> = Vec::new();
-    let mut other_shape_renderables: Vec<ShapeRenderable> = Vec::new();
-    let mut net_name_to_color: HashMap<NetName, ColorFloat3> = HashMap::new();
-    for (_, net_info) in problem.nets.iter() {
-        net_name_to_color.insert(net_info.net_name.clone(), net_info.color);
-        for pad in net_info.pads.values(){
-            let pad_renderables = pad.to_renderables(net_info.color.to_float4(1.0));
-            let pad_clearance_renderables = pad.to_clearance_renderables(net_info.color.to_float4(0.5));
-            pad_shape_renderables.extend(pad_renderables);
-            pad_shape_renderables.extend(pad_clearance_renderables);
-        }
-    }
-    for fixed_trace in node.fixed_connections.values() {
-        let trace_path = &fixed_trace.trace_path;
-        let renderable_batches = trace_path
-            .to_renderables(net_name_to_color[&fixed_trace.net_name].to_float4(1.0));
-        trace_shape_renderables.extend(renderable_batches);
-    }
-    for line in &problem.obstacle_border_outlines {
-        other_shape_renderables.push(ShapeRenderable {
-            shape: PrimShape::Line(line.clone()),
-            color: [1.0, 0.0, 1.0, 1.0], // magenta color for borders
-        });
-    }
-    PcbRenderModel {
-        width: problem.width,
-        height: problem.height,
-        center: problem.center,
-        trace_shape_renderables,
-        pad_shape_renderables,
-        other_shape_renderables,
-    }
-}
-
-fn display_when_necessary(
-    node: &NaiveBacktrackNode,
-    pcb_problem: &PcbProblem,
-    command_flag: CommandFlag,
-    display_injection: &mut DisplayInjection,
-    fall_through: bool,
-) {
-    if display_injection.stop_requested.load(Ordering::Relaxed) {
-        println!("Stop requested, not displaying node");
-        return;
-    }
-    // println!("In naive backtrack's display_when_necessary");
-    println!("Displaying node in naive backtrack");
-    let target_command_level = TARGET_COMMAND_LEVEL.load(Ordering::Relaxed);
-    let task_command_level = command_flag.get_level();
-    if target_command_level <= task_command_level {
-        let render_model = node_to_pcb_render_model(pcb_problem, node);
-        while !(display_injection.can_submit_render_model)() {
-            // wait until we can submit the render model
-        }
-        (display_injection.submit_render_model)(render_model);
-        if !fall_through {
-            (display_injection.block_until_signal)();
-        }
-        // println!("Successfully submitted render model");
-    } else {
-        if (display_injection.can_submit_render_model)() {
-            // If we can submit the render model, do it
-            let render_model = node_to_pcb_render_model(pcb_problem, node);
-            (display_injection.submit_render_model)(render_model);
-        }
-    }
-}
 pub fn naive_backtrack(problem: &PcbProblem, 
     trace_cache: &mut TraceCache,
     heuristics: Option<Vec<ConnectionID>>,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
     display_injection: &mut DisplayInjection,
 ) -> Result<PcbSolution, String> {
-    if display_injection.stop_requested.load(Ordering::Relaxed) {
+    if display_injection.is_stopped() {
         println!("Stop requested, not running naive backtrack");
-        return Err("Stop requested".to_string());
+        return Ok(to_partial_solution(problem, prerouted_traces.clone(), display_injection));
     }
     // println!("Inside naive backtrack");
+    let shove_obstacles = ShoveObstacles::new(problem);
     // prepare the obstacles for the first A* run    
     let border_colliders = AStarModel::calculate_border_colliders(problem.width, problem.height, problem.center);
         
@@ -131,6 +66,8 @@ pub fn naive_backtrack(problem: &PcbProblem,
         let quad_tree_y_min = problem.center.y as f32 - quad_tree_side_length / 2.0;
         let quad_tree_y_max = problem.center.y as f32 + quad_tree_side_length / 2.0;
 
+    // a resumed search already has its stack, and with it the order of the connections
+    let resumed_search = take_resumed_naive_backtrack(display_injection);
     // let dummy_top_node = NaiveBacktrackNode{
     //     current_connection: None,
     //     alternative_connections: VecDeque::new(),
@@ -140,8 +77,12 @@ pub fn naive_backtrack(problem: &PcbProblem,
     // display_when_necessary(&dummy_top_node, problem, CommandFlag::ProbaModelResult, display_injection, false);
     let ordered_connection_vec = if let Some(heuristics) = heuristics {
         heuristics
+    } else if resumed_search.is_some() {
+        Vec::new()
     } else {        
-        let mut connection_to_length: HashMap<ConnectionID, NotNan<f32>> = HashMap::new();
+        // the connections whose traces score best around the pads alone are routed first
+        let trace_scorer = trace_scorer();
+        let mut connection_to_score: HashMap<ConnectionID, NotNan<f64>> = HashMap::new();
         for (net_name, net_info) in problem.nets.iter() {
             // obstacles are pads
             let mut obstacle_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem.num_layers)
@@ -203,12 +144,15 @@ pub fn naive_backtrack(problem: &PcbProblem,
                     }
                 }
             }
-            let obstacle_shapes = Rc::new(obstacle_shapes);
-            let obstacle_clearance_shapes = Rc::new(obstacle_clearance_shapes);
-            let obstacle_colliders = Rc::new(obstacle_colliders);
-            let obstacle_clearance_colliders = Rc::new(obstacle_clearance_colliders);
+            let obstacle_shapes = Arc::new(obstacle_shapes);
+            let obstacle_clearance_shapes = Arc::new(obstacle_clearance_shapes);
+            let obstacle_colliders = Arc::new(obstacle_colliders);
+            let obstacle_clearance_colliders = Arc::new(obstacle_clearance_colliders);
             
             for connection in net_info.connections.values() {
+                if prerouted_traces.contains_key(&connection.connection_id) {
+                    continue; // prerouted traces are not part of the search
+                }
                 let mut trace_path: Option<TracePath> = None;
                 let current_connection_trace_cache = trace_cache.traces.get_mut(&connection.connection_id).unwrap();
                 for cache_trace_path in current_connection_trace_cache.iter() {
@@ -245,7 +189,7 @@ pub fn naive_backtrack(problem: &PcbProblem,
                         num_layers: problem.num_layers,
                         trace_width: net_info.trace_width,
                         trace_clearance: net_info.trace_clearance,
-                        via_diameter: net_info.via_diameter,
+                        via_types: net_info.via_types.clone(),
                         width: problem.width,
                         height: problem.height,
                         center: problem.center,
@@ -255,6 +199,8 @@ pub fn naive_backtrack(problem: &PcbProblem,
                         obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
                         border_colliders_cache: RefCell::new(None),
                         border_shapes_cache: RefCell::new(None),
+                        cost_map: None,
+                        blocking_colliders: RefCell::new(None),
                     };
                     NUM_NAIVE_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
                     let result = astar_model.run(display_injection);
@@ -268,23 +214,49 @@ pub fn naive_backtrack(problem: &PcbProblem,
                     current_connection_trace_cache.push(result.trace_path.clone());
                     result.trace_path
                 };
-                connection_to_length.insert(connection.connection_id, NotNan::new(trace_path.total_length as f32).unwrap());
+                let score = trace_scorer.score(&trace_path, &ScoringContext { problem, net_name });
+                connection_to_score.insert(connection.connection_id, NotNan::new(score).unwrap());
             }
         }
-        let mut connection_heap: BinaryHeap<BinaryHeapItem<Reverse<NotNan<f32>>, ConnectionID>> = BinaryHeap::new();
-        for (connection_id, length) in connection_to_length.iter() {
-            connection_heap.push(BinaryHeapItem::new(Reverse(*length), *connection_id));
+        let mut connection_heap: BinaryHeap<BinaryHeapItem<NotNan<f64>, ConnectionID>> = BinaryHeap::new();
+        for (connection_id, score) in connection_to_score.iter() {
+            connection_heap.push(BinaryHeapItem::new(*score, *connection_id));
         }
-        let ordered_connection_vec: Vec<ConnectionID> = connection_heap.drain().map(|item| item.value).collect();
+        let ordered_connection_vec: Vec<ConnectionID> = std::iter::from_fn(|| connection_heap.pop()).map(|item| item.value).collect();
         ordered_connection_vec
     };
-    // SAMPLE_CNT.store(0, Ordering::Relaxed);
-    let mut backtrack_stack: Vec<NaiveBacktrackNode> = Vec::new();
-
-    let root_node = NaiveBacktrackNode::new_empty(&ordered_connection_vec);
-    backtrack_stack.push(root_node);
+    let ordered_connection_vec: Vec<ConnectionID> = ordered_connection_vec
+        .into_iter()
+        .filter(|connection_id| !prerouted_traces.contains_key(connection_id))
+        .collect();
+    // a failed or stopped search returns the node that got furthest
+    let (mut backtrack_stack, mut best_partial_state) = match resumed_search {
+        Some((backtrack_stack, best_partial_traces)) => {
+            println!("Resuming the naive backtrack with {} nodes on the stack", backtrack_stack.len());
+            (backtrack_stack, BestPartialState { fixed_traces: best_partial_traces })
+        }
+        None => {
+            if ordered_connection_vec.is_empty() {
+                println!("All connections are prerouted, nothing to search");
+                return Ok(PcbSolution {
+                    determined_traces: prerouted_traces.clone(),
+                    unrouted_connections: Vec::new(),
+                    diff_pair_skews: HashMap::new(),
+                    teardrops: Vec::new(),
+                    zone_fills: Vec::new(),
+                    seed: current_seed(),
+                    scale_down_factor: problem.scale_down_factor,
+                    pareto_front: Vec::new(),
+                });
+            }
+            // SAMPLE_CNT.store(0, Ordering::Relaxed);
+            let mut root_node = NaiveBacktrackNode::new_empty(&ordered_connection_vec);
+            root_node.fixed_connections = prerouted_traces.clone(); // they stay fixed in every node
+            (vec![root_node], BestPartialState::new(prerouted_traces))
+        }
+    };
 
-    let connections: HashMap<ConnectionID, Rc<Connection>> = problem.nets.values()
+    let connections: HashMap<ConnectionID, Arc<Connection>> = problem.nets.values()
         .flat_map(|net_info| net_info.connections.iter())
         .map(|(id, connection)| (*id, connection.clone()))
         .collect();
@@ -300,6 +272,9 @@ pub fn naive_backtrack(problem: &PcbProblem,
         })
         .collect();
 
+    // learned while the search runs, a resumed search starts without them
+    let mut nogoods = Nogoods::default();
+
     // dfs
     // fn print_top_node(top_node: &NaiveBacktrackNode) {
     //     print!("Top node: fixed_connections: ");
@@ -315,9 +290,21 @@ pub fn naive_backtrack(problem: &PcbProblem,
     // }
 
     while !backtrack_stack.is_empty() {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        display_injection.budget.check();
+        if is_checkpoint_due(display_injection) {
+            write_checkpoint(
+                prerouted_traces,
+                trace_cache,
+                SearchState::NaiveBacktrack {
+                    backtrack_stack: backtrack_stack.clone(),
+                    best_partial_traces: best_partial_state.fixed_traces.clone(),
+                },
+                display_injection,
+            );
+        }
+        if display_injection.is_stopped() {
             println!("Stop requested, exiting naive backtrack");
-            return Err("Stop requested".to_string());
+            return Ok(to_partial_solution(problem, best_partial_state.fixed_traces, display_injection));
         }
         // Get the top node from the stack
         
@@ -325,11 +312,11 @@ pub fn naive_backtrack(problem: &PcbProblem,
         // print_top_node(top_node);
         assert!(top_node.current_connection.is_none());
 
-        display_when_necessary(&top_node, &problem, CommandFlag::ProbaModelResult, display_injection, false);
+        display_when_necessary(&top_node.fixed_connections, &problem, CommandFlag::ProbaModelResult, display_injection, false);
         if top_node.alternative_connections.is_empty() {
             if !top_node.failed_connections.is_empty() {
-                println!("No more alternative connections but have failed connections, fail to solve");
-                return Err("Failed to solve PCB problem: No more alternative connections but have failed connections".to_string());
+                println!("No more alternative connections but have failed connections, returning the best partial solution");
+                return Ok(to_partial_solution(problem, best_partial_state.fixed_traces, display_injection));
             }
             // is solution
             let fixed_connections = top_node.fixed_connections.clone();
@@ -341,10 +328,16 @@ pub fn naive_backtrack(problem: &PcbProblem,
                 .collect();
             let pcb_solution = PcbSolution{
                 determined_traces: fixed_traces,
+                unrouted_connections: Vec::new(),
+                diff_pair_skews: HashMap::new(),
+                teardrops: Vec::new(),
+                zone_fills: Vec::new(),
+                seed: current_seed(),
                 scale_down_factor: problem.scale_down_factor,
+                pareto_front: Vec::new(),
             };
             println!("Successfully solved PCB problem using naive backtrack");
-            display_when_necessary(&top_node, &problem, CommandFlag::Auto, display_injection, true);
+            display_when_necessary(&top_node.fixed_connections, &problem, CommandFlag::Auto, display_injection, true);
             return Ok(pcb_solution);
         }
         // select the next connection
@@ -434,10 +427,10 @@ pub fn naive_backtrack(problem: &PcbProblem,
                 }
             }            
         }
-        let obstacle_shapes = Rc::new(obstacle_shapes);
-        let obstacle_clearance_shapes = Rc::new(obstacle_clearance_shapes);
-        let obstacle_colliders = Rc::new(obstacle_colliders);
-        let obstacle_clearance_colliders = Rc::new(obstacle_clearance_colliders);
+        let obstacle_shapes = Arc::new(obstacle_shapes);
+        let obstacle_clearance_shapes = Arc::new(obstacle_clearance_shapes);
+        let obstacle_colliders = Arc::new(obstacle_colliders);
+        let obstacle_clearance_colliders = Arc::new(obstacle_clearance_colliders);
 
 
         // check cache first
@@ -451,7 +444,7 @@ pub fn naive_backtrack(problem: &PcbProblem,
                 solution_trace: cache_trace_path.clone(),
                 num_layers: problem.num_layers,
             };
-            if astar_check.check() {
+            if astar_check.check() && !nogoods.forbids(current_connection, cache_trace_path, &top_node.fixed_connections) {
                 // println!("Cache Hit!");                            
                 trace_path = Some(cache_trace_path.clone());
                 break; // we found a trace that satisfies the constraints, no need to generate a new one
@@ -478,7 +471,7 @@ pub fn naive_backtrack(problem: &PcbProblem,
                 num_layers: problem.num_layers,
                 trace_width: net_info.trace_width,
                 trace_clearance: net_info.trace_clearance,
-                via_diameter: net_info.via_diameter,
+                via_types: net_info.via_types.clone(),
                 width: problem.width,
                 height: problem.height,
                 center: problem.center,
@@ -488,18 +481,82 @@ pub fn naive_backtrack(problem: &PcbProblem,
                 obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
                 border_colliders_cache: RefCell::new(None),
                 border_shapes_cache: RefCell::new(None),
+                cost_map: None,
+                blocking_colliders: RefCell::new(None),
             };
             NUM_NAIVE_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
-            let result = astar_model.run(display_injection);
+            let result = astar_model.run_recording_blockers(display_injection);
             let result = match result {
                 Ok(result) => result,
-                Err(e) => {
-                    println!("Cannot find a path for connection {:?}, popping node", connection.connection_id);
+                Err(failure) => {
+                    // before giving up, see if a cached trace fits once the fixed traces in its way are shoved aside
+                    let shoved = current_connection_trace_cache.iter().find_map(|cache_trace_path| {
+                        let proposed_trace = FixedTrace {
+                            net_name: connection.net_name.clone(),
+                            connection_id: connection.connection_id,
+                            trace_path: cache_trace_path.clone(),
+                        };
+                        shove_traces(problem, &top_node.fixed_connections, prerouted_traces, &shove_obstacles, &proposed_trace)
+                            .map(|moved| (proposed_trace, moved))
+                    });
+                    if let Some((proposed_trace, moved)) = shoved {
+                        println!("Shoved {} traces aside for connection {:?}", moved.len(), connection.connection_id);
+                        let mut new_node = top_node.push_node(current_connection, proposed_trace);
+                        new_node.fixed_connections.extend(moved);
+                        best_partial_state.offer(&new_node.fixed_connections);
+                        display_injection.budget.add_backtrack_nodes(1);
+                        backtrack_stack.push(new_node);
+                        continue;
+                    }
+                    // the fixed traces that own an obstacle A* ran into, only known when A* ran out of moves rather than into a limit
+                    let conflict = failure
+                        .blocking_colliders
+                        .map(|blocking_colliders| Conflict {
+                            connection_id: current_connection,
+                            culprits: traces_owning_colliders(
+                                &blocking_colliders,
+                                &connection.net_name,
+                                &top_node.fixed_connections,
+                                problem.num_layers,
+                            ),
+                        })
+                        .filter(|conflict| !conflict.culprits.is_empty());
+                    if USE_BACKJUMPING.load(Ordering::Relaxed)
+                        && let Some(conflict) = conflict
+                    {
+                        nogoods.learn(&conflict);
+                        if let Some(index) = backjump_index(
+                            backtrack_stack.iter().map(|node| &node.fixed_connections),
+                            &conflict.culprits,
+                        ) {
+                            println!(
+                                "Cannot find a path for connection {:?}, backjumping from node {} to node {}",
+                                current_connection,
+                                backtrack_stack.len() - 1,
+                                index
+                            );
+                            backtrack_stack.truncate(index + 1);
+                            // the failed connection is routed before the culprits this time
+                            let node = backtrack_stack.last_mut().unwrap();
+                            if let Some(position) = node.alternative_connections.iter().position(|id| *id == current_connection) {
+                                node.alternative_connections.remove(position);
+                                node.alternative_connections.push_front(current_connection);
+                            }
+                            continue;
+                        }
+                    }
+                    println!("Cannot find a path for connection {:?}: {}, popping node", connection.connection_id, failure.message);
                     backtrack_stack.pop();
                     continue;
                 }
             };
             current_connection_trace_cache.push(result.trace_path.clone());
+            if nogoods.forbids(current_connection, &result.trace_path, &top_node.fixed_connections) {
+                println!("The path for connection {:?} repeats a learned nogood, pruning the branch", current_connection);
+                top_node.current_connection = None;
+                top_node.failed_connections.push(current_connection);
+                continue;
+            }
             result.trace_path
         };
         let fixed_trace = FixedTrace{
@@ -508,7 +565,10 @@ pub fn naive_backtrack(problem: &PcbProblem,
             trace_path,
         };
         let new_node = top_node.push_node(current_connection, fixed_trace);
+        best_partial_state.offer(&new_node.fixed_connections);
+        display_injection.budget.add_backtrack_nodes(1);
         backtrack_stack.push(new_node);  
     }
-    Err("No solution found".to_string())
+    println!("No solution found, returning the best partial solution");
+    Ok(to_partial_solution(problem, best_partial_state.fixed_traces, display_injection))
 }
\ No newline at end of file
@@ -0,0 +1,411 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    rc::Rc,
+    sync::{Arc, atomic::Ordering},
+};
+
+use shared::{
+    collider::Collider,
+    hyperparameters::{
+        ASTAR_STRIDE, HISTORY_CONGESTION_INCREMENT, NEGOTIATED_CONGESTION_MAX_ITERATIONS,
+        NUM_NEGOTIATED_PATH_FINDING_CALLS, PRESENT_CONGESTION_GROWTH,
+        PRESENT_CONGESTION_INITIAL_FACTOR,
+    },
+    pad::Pad,
+    pcb_problem::{Connection, ConnectionID, FixedTrace, NetInfo, NetName, PcbProblem, PcbSolution, UnroutedReason},
+    trace_path::TracePath,
+    vec2::FixedVec2,
+};
+
+use crate::{
+    astar::{AStarCostMap, AStarModel},
+    bayesian_backtrack_algo::TraceCache,
+    cell_grid::{CellGrid, GridCell},
+    command_flags::CommandFlag,
+    deterministic_rand::current_seed,
+    display_injection::{display_when_necessary, DisplayInjection},
+    obstacles::{build_pad_obstacles, PadObstacles},
+    partial_solution::{failure_reason, to_partial_solution, to_partial_solution_with_reasons, BestPartialState},
+};
+
+/// congestion bookkeeping shared between the solver and the cost maps handed to A*
+pub struct CongestionState {
+    pub grid: CellGrid,
+    pub history: HashMap<GridCell, f64>, // accumulated cost of cells that stayed overused
+    pub occupancy: HashMap<GridCell, HashMap<NetName, usize>>, // how many traces of each net cover the cell
+}
+
+impl CongestionState {
+    pub fn new(cell_size: f32) -> Self {
+        CongestionState {
+            grid: CellGrid { cell_size },
+            history: HashMap::new(),
+            occupancy: HashMap::new(),
+        }
+    }
+    pub fn add_trace(&mut self, net_name: &NetName, trace_path: &TracePath) {
+        for cell in self.grid.cells_of_trace(trace_path) {
+            *self.occupancy.entry(cell).or_default().entry(net_name.clone()).or_insert(0) += 1;
+        }
+    }
+    pub fn remove_trace(&mut self, net_name: &NetName, trace_path: &TracePath) {
+        for cell in self.grid.cells_of_trace(trace_path) {
+            let nets = self.occupancy.get_mut(&cell).expect("Removing a trace from an unoccupied cell");
+            let count = nets.get_mut(net_name).expect("Removing a trace of a net that does not occupy the cell");
+            *count -= 1;
+            if *count == 0 {
+                nets.remove(net_name);
+            }
+            if nets.is_empty() {
+                self.occupancy.remove(&cell);
+            }
+        }
+    }
+    /// number of nets other than net_name that occupy the cell
+    pub fn present_congestion(&self, cell: &GridCell, net_name: &NetName) -> usize {
+        match self.occupancy.get(cell) {
+            Some(nets) => nets.keys().filter(|other| *other != net_name).count(),
+            None => 0,
+        }
+    }
+    /// bumps the history cost of every cell shared by more than one net, returns the number of such cells
+    pub fn update_history(&mut self, increment: f64) -> usize {
+        let mut num_overused_cells = 0;
+        for (cell, nets) in self.occupancy.iter() {
+            if nets.len() > 1 {
+                num_overused_cells += 1;
+                *self.history.entry(*cell).or_insert(0.0) += increment * (nets.len() - 1) as f64;
+            }
+        }
+        num_overused_cells
+    }
+    fn cells_cost(&self, cells: &HashSet<GridCell>, net_name: &NetName, present_factor: f64) -> f64 {
+        let mut cost = 0.0;
+        for cell in cells {
+            let history = self.history.get(cell).cloned().unwrap_or(0.0);
+            let present = self.present_congestion(cell, net_name) as f64;
+            // PathFinder: (base + history) * present, minus the base that A* already pays as length
+            cost += self.grid.cell_size as f64 * ((1.0 + history) * (1.0 + present_factor * present) - 1.0);
+        }
+        cost
+    }
+}
+
+/// the cost map of one connection for one round of negotiation
+pub struct CongestionCostMap {
+    pub state: Rc<RefCell<CongestionState>>,
+    pub net_name: NetName,
+    pub present_factor: f64,
+    pub trace_radius: f32, // half trace width plus half clearance
+    pub via_radius: f32,   // half via diameter plus half clearance
+}
+
+impl AStarCostMap for CongestionCostMap {
+    fn planar_cost(&self, start: FixedVec2, end: FixedVec2, layer: usize) -> f64 {
+        let state = self.state.borrow();
+        let cells = state.grid.cells_of_segment(start.to_float(), end.to_float(), self.trace_radius, layer);
+        state.cells_cost(&cells, &self.net_name, self.present_factor)
+    }
+    fn via_cost(&self, position: FixedVec2, min_layer: usize, max_layer: usize) -> f64 {
+        let state = self.state.borrow();
+        let cells = state.grid.cells_of_via(position.to_float(), self.via_radius, min_layer, max_layer);
+        state.cells_cost(&cells, &self.net_name, self.present_factor)
+    }
+    fn is_congested(&self, start: FixedVec2, end: FixedVec2, layer: usize) -> bool {
+        let state = self.state.borrow();
+        let cells = state.grid.cells_of_segment(start.to_float(), end.to_float(), self.trace_radius, layer);
+        cells
+            .iter()
+            .any(|cell| state.present_congestion(cell, &self.net_name) > 0)
+    }
+}
+
+fn collider_sets_collide(colliders1: &[Collider], colliders2: &[Collider]) -> bool {
+    colliders1
+        .iter()
+        .any(|collider1| colliders2.iter().any(|collider2| collider1.collides_with(collider2)))
+}
+
+/// unlike TracePath::collides_with, this also takes vias into account
+pub fn trace_paths_conflict(trace_path1: &TracePath, trace_path2: &TracePath, num_layers: usize) -> bool {
+    if trace_path1.collides_with(trace_path2) {
+        return true;
+    }
+    if trace_path1.vias.is_empty() && trace_path2.vias.is_empty() {
+        return false;
+    }
+    let colliders1 = trace_path1.to_colliders(num_layers);
+    let clearance_colliders1 = trace_path1.to_clearance_colliders(num_layers);
+    let colliders2 = trace_path2.to_colliders(num_layers);
+    let clearance_colliders2 = trace_path2.to_clearance_colliders(num_layers);
+    for layer in 0..num_layers {
+        if collider_sets_collide(&colliders1[&layer], &clearance_colliders2[&layer])
+            || collider_sets_collide(&clearance_colliders1[&layer], &colliders2[&layer])
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// connections whose traces overlap a trace of another net
+fn find_conflicting_connections(
+    routes: &HashMap<ConnectionID, FixedTrace>,
+    num_layers: usize,
+) -> BTreeSet<ConnectionID> {
+    let mut conflicting_connections: BTreeSet<ConnectionID> = BTreeSet::new();
+    let mut connection_ids: Vec<ConnectionID> = routes.keys().cloned().collect();
+    connection_ids.sort();
+    for (i, connection_id1) in connection_ids.iter().enumerate() {
+        let fixed_trace1 = &routes[connection_id1];
+        for connection_id2 in connection_ids.iter().skip(i + 1) {
+            let fixed_trace2 = &routes[connection_id2];
+            if fixed_trace1.net_name == fixed_trace2.net_name {
+                continue;
+            }
+            if trace_paths_conflict(&fixed_trace1.trace_path, &fixed_trace2.trace_path, num_layers) {
+                conflicting_connections.insert(*connection_id1);
+                conflicting_connections.insert(*connection_id2);
+            }
+        }
+    }
+    conflicting_connections
+}
+
+/// the routes of an iteration without their conflicts, the conflicting connections are added back one by one
+/// as long as they collide with nothing that is kept
+fn conflict_free_routes(
+    routes: &HashMap<ConnectionID, FixedTrace>,
+    conflicting_connections: &BTreeSet<ConnectionID>,
+    num_layers: usize,
+) -> HashMap<ConnectionID, FixedTrace> {
+    let mut kept_routes: HashMap<ConnectionID, FixedTrace> = routes
+        .iter()
+        .filter(|(connection_id, _)| !conflicting_connections.contains(connection_id))
+        .map(|(connection_id, fixed_trace)| (*connection_id, fixed_trace.clone()))
+        .collect();
+    for connection_id in conflicting_connections.iter() {
+        let fixed_trace = &routes[connection_id];
+        let conflicts = kept_routes.values().any(|kept_trace| {
+            kept_trace.net_name != fixed_trace.net_name
+                && trace_paths_conflict(&kept_trace.trace_path, &fixed_trace.trace_path, num_layers)
+        });
+        if !conflicts {
+            kept_routes.insert(*connection_id, fixed_trace.clone());
+        }
+    }
+    kept_routes
+}
+
+/// PathFinder-style negotiated congestion:
+/// every connection is routed with only pads as hard obstacles, so traces of different nets may overlap.
+/// Overlapped cells get more expensive every round, and conflicting connections are ripped up and rerouted
+/// until no overlaps remain.
+pub fn negotiated_congestion(
+    problem: &PcbProblem,
+    trace_cache: &mut TraceCache,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    if display_injection.is_stopped() {
+        println!("Stop requested, not running negotiated congestion");
+        return Ok(to_partial_solution(problem, prerouted_traces.clone(), display_injection));
+    }
+    let connections: HashMap<ConnectionID, Arc<Connection>> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.iter())
+        .map(|(id, connection)| (*id, connection.clone()))
+        .collect();
+    let connection_to_net_info: HashMap<ConnectionID, &NetInfo> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| {
+            net_info
+                .connections
+                .keys()
+                .map(move |connection_id| (*connection_id, net_info))
+        })
+        .collect();
+    // prerouted traces are never ripped up
+    let mut ordered_connections: Vec<ConnectionID> = connections
+        .keys()
+        .filter(|connection_id| !prerouted_traces.contains_key(connection_id))
+        .cloned()
+        .collect();
+    ordered_connections.sort();
+
+    let pad_obstacles: HashMap<NetName, PadObstacles> = problem
+        .nets
+        .keys()
+        .map(|net_name| (net_name.clone(), build_pad_obstacles(problem, &[net_name], prerouted_traces)))
+        .collect();
+
+    let cell_size: f32 = ASTAR_STRIDE.lock().unwrap().to_num();
+    let state = Rc::new(RefCell::new(CongestionState::new(cell_size)));
+    let mut routes: HashMap<ConnectionID, FixedTrace> = prerouted_traces.clone();
+    // a run that does not converge returns the most complete conflict free subset of an iteration
+    let mut best_partial_state = BestPartialState::new(prerouted_traces);
+    let mut present_factor = PRESENT_CONGESTION_INITIAL_FACTOR.load(Ordering::Relaxed);
+    let history_increment = HISTORY_CONGESTION_INCREMENT.load(Ordering::Relaxed);
+    let present_growth = PRESENT_CONGESTION_GROWTH.load(Ordering::Relaxed);
+    let max_iterations = NEGOTIATED_CONGESTION_MAX_ITERATIONS.load(Ordering::Relaxed);
+
+    let mut connections_to_route: BTreeSet<ConnectionID> = ordered_connections.iter().cloned().collect();
+    // connections that have never been routed, with the reason of their last failure
+    let mut unrouted_reasons: HashMap<ConnectionID, UnroutedReason> = HashMap::new();
+    for iteration in 0..max_iterations {
+        for connection_id in connections_to_route.iter() {
+            display_injection.budget.check();
+            if display_injection.is_stopped() {
+                println!("Stop requested, exiting negotiated congestion");
+                return Ok(to_partial_solution(problem, best_partial_state.fixed_traces, display_injection));
+            }
+            let connection = &connections[connection_id];
+            let net_info = connection_to_net_info[connection_id];
+            // rip up
+            let old_route = routes.remove(connection_id);
+            if let Some(old_route) = &old_route {
+                state.borrow_mut().remove_trace(&connection.net_name, &old_route.trace_path);
+            }
+            let start_pad: &Pad = net_info.pads.get(&connection.start_pad).unwrap();
+            let end_pad: &Pad = net_info.pads.get(&connection.end_pad).unwrap();
+            let obstacles = &pad_obstacles[&connection.net_name];
+            let cost_map = CongestionCostMap {
+                state: state.clone(),
+                net_name: connection.net_name.clone(),
+                present_factor,
+                trace_radius: net_info.trace_width / 2.0 + net_info.trace_clearance / 2.0,
+                // the widest via in the catalog, so the congestion estimate never misses a cell
+                via_radius: net_info
+                    .via_types
+                    .iter()
+                    .map(|via_type| via_type.diameter)
+                    .fold(net_info.trace_width, f32::max)
+                    / 2.0
+                    + net_info.trace_clearance / 2.0,
+            };
+            let astar_model = AStarModel {
+                start: start_pad.position.to_fixed().to_nearest_even_even(),
+                end: end_pad.position.to_fixed().to_nearest_even_even(),
+                start_layers: start_pad.pad_layer,
+                end_layers: end_pad.pad_layer,
+                num_layers: problem.num_layers,
+                trace_width: net_info.trace_width,
+                trace_clearance: net_info.trace_clearance,
+                via_types: net_info.via_types.clone(),
+                width: problem.width,
+                height: problem.height,
+                center: problem.center,
+                obstacle_shapes: obstacles.obstacle_shapes.clone(),
+                obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes.clone(),
+                obstacle_colliders: obstacles.obstacle_colliders.clone(),
+                obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+                border_colliders_cache: RefCell::new(None),
+                border_shapes_cache: RefCell::new(None),
+                cost_map: Some(Rc::new(cost_map)),
+                blocking_colliders: RefCell::new(None),
+            };
+            NUM_NEGOTIATED_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
+            let trace_path = match astar_model.run(display_injection) {
+                Ok(result) => result.trace_path,
+                Err(e) => match old_route {
+                    // keep the previous route and let the next round try again
+                    Some(old_route) => {
+                        println!("Rerouting connection {:?} failed: {}, keeping the old route", connection_id, e);
+                        old_route.trace_path
+                    }
+                    // leave it out and try again in the next round, the other connections keep going
+                    None => {
+                        println!("Routing connection {:?} failed: {}, retrying it in the next round", connection_id, e);
+                        unrouted_reasons.insert(*connection_id, failure_reason(&e, display_injection));
+                        continue;
+                    }
+                },
+            };
+            unrouted_reasons.remove(connection_id);
+            state.borrow_mut().add_trace(&connection.net_name, &trace_path);
+            let connection_trace_cache = trace_cache.traces.get_mut(connection_id).unwrap();
+            connection_trace_cache.push(trace_path.clone());
+            routes.insert(
+                *connection_id,
+                FixedTrace {
+                    net_name: connection.net_name.clone(),
+                    connection_id: *connection_id,
+                    trace_path,
+                },
+            );
+        }
+        display_when_necessary(&routes, problem, CommandFlag::ProbaModelResult, display_injection, false);
+
+        let mut conflicting_connections = find_conflicting_connections(&routes, problem.num_layers);
+        conflicting_connections.retain(|connection_id| !prerouted_traces.contains_key(connection_id));
+        best_partial_state.offer(&conflict_free_routes(&routes, &conflicting_connections, problem.num_layers));
+        let num_overused_cells = state.borrow_mut().update_history(history_increment);
+        println!(
+            "Negotiated congestion iteration {}: {} conflicting connections, {} unrouted connections, {} overused cells",
+            iteration,
+            conflicting_connections.len(),
+            unrouted_reasons.len(),
+            num_overused_cells
+        );
+        // nothing conflicts and every connection tried this round failed again, more rounds would not change that
+        if conflicting_connections.is_empty()
+            && !unrouted_reasons.is_empty()
+            && connections_to_route.iter().all(|connection_id| unrouted_reasons.contains_key(connection_id))
+        {
+            println!("Negotiated congestion cannot route {} connections, returning the partial solution", unrouted_reasons.len());
+            return Ok(to_partial_solution_with_reasons(problem, best_partial_state.fixed_traces, &unrouted_reasons, display_injection));
+        }
+        if conflicting_connections.is_empty() && unrouted_reasons.is_empty() {
+            println!("Successfully solved PCB problem using negotiated congestion");
+            display_when_necessary(&routes, problem, CommandFlag::Auto, display_injection, true);
+            return Ok(PcbSolution {
+                determined_traces: routes,
+                unrouted_connections: Vec::new(),
+                diff_pair_skews: HashMap::new(),
+                teardrops: Vec::new(),
+                zone_fills: Vec::new(),
+                seed: current_seed(),
+                scale_down_factor: problem.scale_down_factor,
+                pareto_front: Vec::new(),
+            });
+        }
+        connections_to_route = conflicting_connections;
+        connections_to_route.extend(unrouted_reasons.keys().cloned());
+        present_factor *= present_growth;
+    }
+    println!(
+        "Negotiated congestion did not converge within {} iterations, returning the best partial solution",
+        max_iterations
+    );
+    Ok(to_partial_solution_with_reasons(problem, best_partial_state.fixed_traces, &unrouted_reasons, display_injection))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::pcb_problem::FixedTrace;
+
+    use super::trace_paths_conflict;
+    use crate::{
+        pcb_problem_solve::SolverAlgorithm,
+        test_pcb_problem::examples::{example_problem, route_example},
+    };
+
+    #[test]
+    fn negotiation_ends_with_no_overlaps_between_nets() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 4);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        let solution = route_example(&problem, SolverAlgorithm::NegotiatedCongestion);
+        assert!(solution.unrouted_connections.is_empty());
+        assert_eq!(solution.determined_traces.len(), num_connections);
+        // the vias count as well, unlike in a plain collision check
+        let fixed_traces: Vec<&FixedTrace> = solution.determined_traces.values().collect();
+        for (index, fixed_trace) in fixed_traces.iter().enumerate() {
+            for other in fixed_traces[index + 1..].iter().filter(|other| other.net_name != fixed_trace.net_name) {
+                assert!(!trace_paths_conflict(&fixed_trace.trace_path, &other.trace_path, problem.num_layers));
+            }
+        }
+    }
+}
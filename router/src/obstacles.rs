@@ -0,0 +1,183 @@
+use std::{collections::HashMap, sync::Arc};
+
+use shared::{
+    collider::Collider,
+    pcb_problem::{ConnectionID, FixedTrace, NetInfo, NetName, PcbProblem},
+    prim_shape::PrimShape,
+    trace_path::TracePath,
+};
+
+use crate::quad_tree::QuadTreeNode;
+
+/// the shapes and quad trees of the hard obstacles on each layer, shared with the A* models built from them
+/// the passes that move one trace at a time build the obstacles once and then add and remove single pieces,
+/// a change copies the quad trees only while an A* model built from them is still alive
+pub(crate) struct PadObstacles {
+    pub obstacle_shapes: Arc<HashMap<usize, Vec<PrimShape>>>,
+    pub obstacle_clearance_shapes: Arc<HashMap<usize, Vec<PrimShape>>>,
+    pub obstacle_colliders: Arc<HashMap<usize, QuadTreeNode>>,
+    pub obstacle_clearance_colliders: Arc<HashMap<usize, QuadTreeNode>>,
+}
+
+impl PadObstacles {
+    fn add_shapes(&mut self, layer: usize, shapes: Vec<PrimShape>, clearance_shapes: Vec<PrimShape>) {
+        Arc::make_mut(&mut self.obstacle_colliders)
+            .get_mut(&layer)
+            .unwrap()
+            .extend(shapes.iter().map(Collider::from_prim_shape));
+        Arc::make_mut(&mut self.obstacle_clearance_colliders)
+            .get_mut(&layer)
+            .unwrap()
+            .extend(clearance_shapes.iter().map(Collider::from_prim_shape));
+        Arc::make_mut(&mut self.obstacle_shapes).get_mut(&layer).unwrap().extend(shapes);
+        Arc::make_mut(&mut self.obstacle_clearance_shapes).get_mut(&layer).unwrap().extend(clearance_shapes);
+    }
+
+    fn remove_shapes(&mut self, layer: usize, shapes: &[PrimShape], clearance_shapes: &[PrimShape]) {
+        let obstacle_colliders = Arc::make_mut(&mut self.obstacle_colliders).get_mut(&layer).unwrap();
+        for shape in shapes {
+            obstacle_colliders.remove(&Collider::from_prim_shape(shape));
+        }
+        let obstacle_clearance_colliders = Arc::make_mut(&mut self.obstacle_clearance_colliders).get_mut(&layer).unwrap();
+        for clearance_shape in clearance_shapes {
+            obstacle_clearance_colliders.remove(&Collider::from_prim_shape(clearance_shape));
+        }
+        let remove_from = |layer_shapes: &mut Vec<PrimShape>, shapes: &[PrimShape]| {
+            for shape in shapes {
+                if let Some(index) = layer_shapes.iter().position(|layer_shape| layer_shape == shape) {
+                    layer_shapes.swap_remove(index);
+                }
+            }
+        };
+        remove_from(Arc::make_mut(&mut self.obstacle_shapes).get_mut(&layer).unwrap(), shapes);
+        remove_from(Arc::make_mut(&mut self.obstacle_clearance_shapes).get_mut(&layer).unwrap(), clearance_shapes);
+    }
+
+    pub fn add_pads(&mut self, net_info: &NetInfo, num_layers: usize) {
+        for pad in net_info.pads.values() {
+            for layer in pad.pad_layer.get_iter(num_layers) {
+                self.add_shapes(layer, pad.to_shapes(), pad.to_clearance_shapes());
+            }
+        }
+    }
+
+    pub fn remove_pads(&mut self, net_info: &NetInfo, num_layers: usize) {
+        for pad in net_info.pads.values() {
+            for layer in pad.pad_layer.get_iter(num_layers) {
+                self.remove_shapes(layer, &pad.to_shapes(), &pad.to_clearance_shapes());
+            }
+        }
+    }
+
+    pub fn add_trace(&mut self, trace_path: &TracePath, num_layers: usize) {
+        let mut clearance_shapes = trace_path.to_clearance_shapes(num_layers);
+        for (layer, shapes) in trace_path.to_shapes(num_layers) {
+            self.add_shapes(layer, shapes, clearance_shapes.remove(&layer).unwrap_or_default());
+        }
+        for (layer, clearance_shapes) in clearance_shapes {
+            self.add_shapes(layer, Vec::new(), clearance_shapes);
+        }
+    }
+
+    pub fn remove_trace(&mut self, trace_path: &TracePath, num_layers: usize) {
+        let mut clearance_shapes = trace_path.to_clearance_shapes(num_layers);
+        for (layer, shapes) in trace_path.to_shapes(num_layers) {
+            self.remove_shapes(layer, &shapes, &clearance_shapes.remove(&layer).unwrap_or_default());
+        }
+        for (layer, clearance_shapes) in clearance_shapes {
+            self.remove_shapes(layer, &[], &clearance_shapes);
+        }
+    }
+}
+
+/// the pads and prerouted traces of every net but the excluded ones, as hard obstacles on each layer
+pub(crate) fn build_pad_obstacles(
+    problem: &PcbProblem,
+    excluded_net_names: &[&NetName],
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> PadObstacles {
+    let quad_tree_side_length = f32::max(problem.width, problem.height);
+    let quad_tree_x_min = problem.center.x - quad_tree_side_length / 2.0;
+    let quad_tree_x_max = problem.center.x + quad_tree_side_length / 2.0;
+    let quad_tree_y_min = problem.center.y - quad_tree_side_length / 2.0;
+    let quad_tree_y_max = problem.center.y + quad_tree_side_length / 2.0;
+    let new_quad_tree = || {
+        QuadTreeNode::new(quad_tree_x_min, quad_tree_x_max, quad_tree_y_min, quad_tree_y_max, 0)
+    };
+    let mut obstacles = PadObstacles {
+        obstacle_shapes: Arc::new((0..problem.num_layers).map(|layer| (layer, Vec::new())).collect()),
+        obstacle_clearance_shapes: Arc::new((0..problem.num_layers).map(|layer| (layer, Vec::new())).collect()),
+        obstacle_colliders: Arc::new((0..problem.num_layers).map(|layer| (layer, new_quad_tree())).collect()),
+        obstacle_clearance_colliders: Arc::new((0..problem.num_layers).map(|layer| (layer, new_quad_tree())).collect()),
+    };
+    for (_, net_info) in problem
+        .nets
+        .iter()
+        .filter(|(other_net_name, _)| !excluded_net_names.contains(other_net_name))
+    {
+        obstacles.add_pads(net_info, problem.num_layers);
+    }
+    for fixed_trace in prerouted_traces
+        .values()
+        .filter(|fixed_trace| !excluded_net_names.contains(&&fixed_trace.net_name))
+    {
+        obstacles.add_trace(&fixed_trace.trace_path, problem.num_layers);
+    }
+    obstacles
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use parser::parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end};
+    use shared::{
+        collider::Collider,
+        pcb_problem::PcbProblem,
+        prim_shape::{CircleShape, PrimShape},
+        vec2::FloatVec2,
+    };
+
+    use super::{build_pad_obstacles, PadObstacles};
+
+    /// whether a small probe collides with the obstacles, on a grid over the board and on every layer
+    fn probe_collisions(problem: &PcbProblem, obstacles: &PadObstacles) -> Vec<bool> {
+        let mut collisions = Vec::new();
+        for layer in 0..problem.num_layers {
+            for i in 0..40 {
+                for j in 0..40 {
+                    let position = FloatVec2::new(
+                        problem.center.x - problem.width / 2.0 + problem.width * (i as f32 + 0.5) / 40.0,
+                        problem.center.y - problem.height / 2.0 + problem.height * (j as f32 + 0.5) / 40.0,
+                    );
+                    let probe = Collider::from_prim_shape(&PrimShape::Circle(CircleShape { position, diameter: 0.2 }));
+                    collisions.push(obstacles.obstacle_colliders[&layer].collides_with(&probe));
+                    collisions.push(obstacles.obstacle_clearance_colliders[&layer].collides_with(&probe));
+                }
+            }
+        }
+        collisions
+    }
+
+    #[test]
+    fn removing_pads_after_adding_them_restores_collisions() {
+        let dsn_struct = parse_start_to_dsn_struct(include_str!("../../examples/ping.dsn").to_string()).unwrap();
+        let problem = parse_struct_to_end(&dsn_struct).unwrap();
+        let net_info = problem.nets.values().max_by_key(|net_info| net_info.pads.len()).unwrap();
+        let mut obstacles = build_pad_obstacles(&problem, &[&net_info.net_name], &HashMap::new());
+        let collisions_before = probe_collisions(&problem, &obstacles);
+        let num_shapes_before: usize = obstacles.obstacle_shapes.values().map(Vec::len).sum();
+
+        obstacles.add_pads(net_info, problem.num_layers);
+        let collisions_with_pads = probe_collisions(&problem, &obstacles);
+        assert_ne!(collisions_before, collisions_with_pads);
+        assert_eq!(
+            collisions_with_pads,
+            probe_collisions(&problem, &build_pad_obstacles(&problem, &[], &HashMap::new()))
+        );
+
+        obstacles.remove_pads(net_info, problem.num_layers);
+        assert_eq!(collisions_before, probe_collisions(&problem, &obstacles));
+        assert_eq!(num_shapes_before, obstacles.obstacle_shapes.values().map(Vec::len).sum::<usize>());
+    }
+}
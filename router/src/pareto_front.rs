@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use shared::{
+    pareto::SolutionObjectives,
+    pcb_problem::{ConnectionID, FixedTrace, ParetoSolution, PcbProblem},
+};
+
+/// the complete routings of one run that no other routing of the run beats on every objective
+#[derive(Default)]
+pub struct ParetoFront {
+    routings: Vec<(SolutionObjectives, HashMap<ConnectionID, FixedTrace>)>,
+}
+
+impl ParetoFront {
+    /// adds a complete routing unless a routing of the front dominates or equals it, and drops the ones it dominates
+    pub fn offer(&mut self, problem: &PcbProblem, determined_traces: &HashMap<ConnectionID, FixedTrace>) {
+        let objectives = SolutionObjectives::from_traces(problem, determined_traces);
+        if self.routings.iter().any(|(other, _)| *other == objectives || other.dominates(&objectives)) {
+            return;
+        }
+        self.routings.retain(|(other, _)| !objectives.dominates(other));
+        self.routings.push((objectives, determined_traces.clone()));
+    }
+
+    /// the routings in the order they joined the front
+    pub fn into_routings(self) -> Vec<HashMap<ConnectionID, FixedTrace>> {
+        self.routings.into_iter().map(|(_, determined_traces)| determined_traces).collect()
+    }
+}
+
+/// the solutions that no other solution dominates, of equal ones the first is kept
+pub(crate) fn non_dominated(solutions: Vec<ParetoSolution>) -> Vec<ParetoSolution> {
+    let objectives: Vec<SolutionObjectives> = solutions.iter().map(|pareto_solution| pareto_solution.objectives).collect();
+    solutions
+        .into_iter()
+        .enumerate()
+        .filter(|(index, pareto_solution)| {
+            !objectives.iter().enumerate().any(|(other_index, other)| {
+                other.dominates(&pareto_solution.objectives) || (other_index < *index && *other == pareto_solution.objectives)
+            })
+        })
+        .map(|(_, pareto_solution)| pareto_solution)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use shared::{
+        pareto::SolutionObjectives,
+        pcb_problem::{ConnectionID, FixedTrace, ParetoSolution, PcbProblem, PcbSolution},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath, Via},
+        vec2::FloatVec2,
+    };
+
+    use super::{non_dominated, ParetoFront};
+    use crate::test_pcb_problem::examples::example_problem;
+
+    fn pareto_solution(seed: u64, total_length: f64, num_vias: usize, min_clearance_slack: f64) -> ParetoSolution {
+        ParetoSolution {
+            objectives: SolutionObjectives { total_length, num_vias, num_bends: 0, min_clearance_slack },
+            solution: PcbSolution {
+                determined_traces: HashMap::new(),
+                unrouted_connections: Vec::new(),
+                diff_pair_skews: HashMap::new(),
+                teardrops: Vec::new(),
+                zone_fills: Vec::new(),
+                seed,
+                scale_down_factor: 1.0,
+                pareto_front: Vec::new(),
+            },
+        }
+    }
+
+    /// the one connection of the board routed through the given points, with a via wherever the layer changes
+    fn complete_routing(problem: &PcbProblem, waypoints: &[(FloatVec2, usize)]) -> HashMap<ConnectionID, FixedTrace> {
+        let net_info = problem.nets.values().next().unwrap();
+        let connection = net_info.connections.values().next().unwrap();
+        let via_type = &net_info.via_types[0];
+        let mut anchors: Vec<TraceAnchor> = Vec::new();
+        let mut vias: Vec<Via> = Vec::new();
+        let mut points = vec![(net_info.pads[&connection.start_pad].position, 0)];
+        points.extend_from_slice(waypoints);
+        points.push((net_info.pads[&connection.end_pad].position, 0));
+        for (index, &(position, layer)) in points.iter().enumerate() {
+            let position = position.to_fixed().to_nearest_even_even();
+            let previous_layer = if index == 0 { layer } else { points[index - 1].1 };
+            if previous_layer != layer {
+                vias.push(Via {
+                    position,
+                    diameter: via_type.diameter,
+                    clearance: via_type.clearance,
+                    min_layer: previous_layer.min(layer),
+                    max_layer: previous_layer.max(layer),
+                    padstack: via_type.name.clone(),
+                });
+            }
+            anchors.push(TraceAnchor { position, start_layer: previous_layer, end_layer: layer });
+        }
+        let trace_path =
+            TracePath::from_anchors(TraceAnchors(anchors), net_info.trace_width, net_info.trace_clearance, vias);
+        let fixed_trace = FixedTrace { net_name: net_info.net_name.clone(), connection_id: connection.connection_id, trace_path };
+        HashMap::from([(connection.connection_id, fixed_trace)])
+    }
+
+    #[test]
+    fn the_front_keeps_only_routings_nothing_dominates() {
+        // one net, so the clearance slack is the same for every routing
+        let problem = example_problem(include_str!("../../examples/diff_pair.dsn"), 1);
+        let net_info = problem.nets.values().next().unwrap();
+        let connection = net_info.connections.values().next().unwrap();
+        let start = net_info.pads[&connection.start_pad].position;
+        let end = net_info.pads[&connection.end_pad].position;
+        // straight across on the front layer, and a longer detour over the back layer
+        let short = complete_routing(&problem, &[]);
+        let detour = complete_routing(
+            &problem,
+            &[
+                (FloatVec2::new(start.x, start.y - 2.0), 0),
+                (FloatVec2::new(start.x, start.y - 2.0), 1),
+                (FloatVec2::new(end.x, end.y - 2.0), 1),
+                (FloatVec2::new(end.x, end.y - 2.0), 0),
+            ],
+        );
+        let short_objectives = SolutionObjectives::from_traces(&problem, &short);
+        let detour_objectives = SolutionObjectives::from_traces(&problem, &detour);
+        assert!(short_objectives.total_length < detour_objectives.total_length);
+        assert!(short_objectives.num_vias < detour_objectives.num_vias);
+        assert!(short_objectives.dominates(&detour_objectives));
+
+        let mut front = ParetoFront::default();
+        front.offer(&problem, &detour);
+        front.offer(&problem, &detour);
+        assert_eq!(front.routings.len(), 1);
+        front.offer(&problem, &short);
+        front.offer(&problem, &detour);
+        let routings = front.into_routings();
+        assert_eq!(routings.len(), 1);
+        assert_eq!(routings[0][&connection.connection_id].trace_path.vias.len(), 0);
+    }
+
+    #[test]
+    fn dominated_and_repeated_solutions_are_dropped() {
+        let solutions = vec![
+            pareto_solution(0, 10.0, 2, 0.1),
+            pareto_solution(1, 12.0, 2, 0.1), // longer than the first
+            pareto_solution(2, 8.0, 3, 0.1),  // shorter with more vias
+            pareto_solution(3, 10.0, 2, 0.1), // the same as the first
+            pareto_solution(4, 12.0, 2, 0.3), // longer with more slack
+        ];
+        let seeds: Vec<u64> = non_dominated(solutions).iter().map(|pareto_solution| pareto_solution.solution.seed).collect();
+        assert_eq!(seeds, vec![0, 2, 4]);
+    }
+}
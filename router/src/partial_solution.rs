@@ -0,0 +1,172 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use shared::pcb_problem::{
+    Connection, ConnectionID, FixedTrace, NetName, PcbProblem, PcbSolution, UnroutedConnection, UnroutedReason,
+};
+
+use crate::{
+    astar::AStarModel,
+    deterministic_rand::current_seed,
+    display_injection::DisplayInjection,
+    obstacles::{build_pad_obstacles, PadObstacles},
+};
+
+/// the most complete state a solver reached without any collision, so a run that fails still has routes to return
+pub struct BestPartialState {
+    pub fixed_traces: HashMap<ConnectionID, FixedTrace>,
+}
+
+impl BestPartialState {
+    pub fn new(prerouted_traces: &HashMap<ConnectionID, FixedTrace>) -> Self {
+        BestPartialState {
+            fixed_traces: prerouted_traces.clone(),
+        }
+    }
+    /// keeps the traces if there are more of them than in the best state so far
+    pub fn offer(&mut self, fixed_traces: &HashMap<ConnectionID, FixedTrace>) {
+        if fixed_traces.len() > self.fixed_traces.len() {
+            self.fixed_traces = fixed_traces.clone();
+        }
+    }
+}
+
+pub(crate) fn astar_model_for_connection(problem: &PcbProblem, connection: &Connection, obstacles: PadObstacles) -> AStarModel {
+    let net_info = &problem.nets[&connection.net_name];
+    let start_pad = &net_info.pads[&connection.start_pad];
+    let end_pad = &net_info.pads[&connection.end_pad];
+    AStarModel {
+        start: start_pad.position.to_fixed().to_nearest_even_even(),
+        end: end_pad.position.to_fixed().to_nearest_even_even(),
+        start_layers: start_pad.pad_layer,
+        end_layers: end_pad.pad_layer,
+        num_layers: problem.num_layers,
+        trace_width: net_info.trace_width,
+        trace_clearance: net_info.trace_clearance,
+        via_types: net_info.via_types.clone(),
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        obstacle_shapes: obstacles.obstacle_shapes,
+        obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes,
+        obstacle_colliders: obstacles.obstacle_colliders,
+        obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders,
+        border_colliders_cache: RefCell::new(None),
+        border_shapes_cache: RefCell::new(None),
+        cost_map: None,
+        blocking_colliders: RefCell::new(None),
+    }
+}
+
+/// a stop comes from the user unless the budget of the solve ran out
+fn stop_reason(display_injection: &DisplayInjection) -> UnroutedReason {
+    if display_injection.budget.is_exhausted() {
+        UnroutedReason::BudgetExhausted
+    } else {
+        UnroutedReason::Stopped
+    }
+}
+
+/// why a connection cannot be routed around the kept traces, from a run that only has the pads in its way
+fn diagnose_unrouted(
+    problem: &PcbProblem,
+    connection: &Connection,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> UnroutedReason {
+    let obstacles = build_pad_obstacles(problem, &[&connection.net_name], &HashMap::new());
+    match astar_model_for_connection(problem, connection, obstacles).run(display_injection) {
+        Ok(result) => {
+            let blocking_net: Option<&NetName> = fixed_traces
+                .values()
+                .filter(|fixed_trace| fixed_trace.net_name != connection.net_name)
+                .filter(|fixed_trace| fixed_trace.trace_path.collides_with(&result.trace_path))
+                .map(|fixed_trace| &fixed_trace.net_name)
+                .min();
+            match blocking_net {
+                Some(net_name) => UnroutedReason::BlockedByNet(net_name.clone()),
+                None => UnroutedReason::ExpansionsExhausted,
+            }
+        }
+        Err(e) => failure_reason(&e, display_injection),
+    }
+}
+
+/// why an A* run with only the pads in its way failed
+pub(crate) fn failure_reason(error: &str, display_injection: &DisplayInjection) -> UnroutedReason {
+    if display_injection.is_stopped() {
+        stop_reason(display_injection)
+    } else if error.contains("maximum trials") {
+        UnroutedReason::ExpansionsExhausted
+    } else {
+        UnroutedReason::NoPath
+    }
+}
+
+/// turns the traces a failed or stopped run kept into a solution that lists what is missing
+/// every missing connection gets one last A* run around the kept traces, and the ones that still fail get a reason
+pub fn to_partial_solution(
+    problem: &PcbProblem,
+    fixed_traces: HashMap<ConnectionID, FixedTrace>,
+    display_injection: &mut DisplayInjection,
+) -> PcbSolution {
+    to_partial_solution_with_reasons(problem, fixed_traces, &HashMap::new(), display_injection)
+}
+
+/// like to_partial_solution, but the connections a solver already knows the reason for are not tried again
+pub fn to_partial_solution_with_reasons(
+    problem: &PcbProblem,
+    fixed_traces: HashMap<ConnectionID, FixedTrace>,
+    known_reasons: &HashMap<ConnectionID, UnroutedReason>,
+    display_injection: &mut DisplayInjection,
+) -> PcbSolution {
+    let mut fixed_traces = fixed_traces;
+    let mut missing_connections: Vec<&Connection> = problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.values())
+        .filter(|connection| !fixed_traces.contains_key(&connection.connection_id))
+        .map(|connection| connection.as_ref())
+        .collect();
+    missing_connections.sort_by_key(|connection| connection.connection_id);
+    let mut unrouted_connections: Vec<UnroutedConnection> = Vec::new();
+    for connection in missing_connections {
+        let reason = if display_injection.is_stopped() {
+            stop_reason(display_injection)
+        } else if let Some(reason) = known_reasons.get(&connection.connection_id) {
+            reason.clone()
+        } else {
+            let obstacles = build_pad_obstacles(problem, &[&connection.net_name], &fixed_traces);
+            if let Ok(result) = astar_model_for_connection(problem, connection, obstacles).run(display_injection) {
+                fixed_traces.insert(
+                    connection.connection_id,
+                    FixedTrace {
+                        net_name: connection.net_name.clone(),
+                        connection_id: connection.connection_id,
+                        trace_path: result.trace_path,
+                    },
+                );
+                continue;
+            }
+            diagnose_unrouted(problem, connection, &fixed_traces, display_injection)
+        };
+        println!(
+            "Connection {:?} of net {} is left unrouted: {:?}",
+            connection.connection_id, connection.net_name.0, reason
+        );
+        unrouted_connections.push(UnroutedConnection {
+            connection_id: connection.connection_id,
+            net_name: connection.net_name.clone(),
+            reason,
+        });
+    }
+    PcbSolution {
+        determined_traces: fixed_traces,
+        unrouted_connections,
+        diff_pair_skews: HashMap::new(),
+        teardrops: Vec::new(),
+        zone_fills: Vec::new(),
+        seed: current_seed(),
+        scale_down_factor: problem.scale_down_factor,
+        pareto_front: Vec::new(),
+    }
+}
@@ -1,34 +1,321 @@
-use std::sync::{atomic::Ordering, Arc, Mutex};
+use std::{collections::{HashMap, HashSet}, fs, path::Path, sync::{atomic::Ordering, Arc}};
 
-use shared::{pcb_problem::{ConnectionID, PcbProblem, PcbSolution}, pcb_render_model::PcbRenderModel};
+use serde::{Deserialize, Serialize};
+use shared::{hyperparameters::{ARC_CORNER_RADIUS, PORTFOLIO_NUM_SEEDS, USE_ANNEALING, USE_FANOUT, USE_PARETO_FRONT, USE_TEARDROPS}, pareto::SolutionObjectives, pcb_problem::{ConnectionID, FixedTrace, NetName, ParetoSolution, PcbProblem, PcbSolution}, pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable}, prim_shape::PrimShape};
 
-use crate::{bayesian_backtrack_algo::{bayesian_backtrack, TraceCache}, display_injection::{self, DisplayInjection}, naive_backtrack_algo::naive_backtrack};
+use crate::{annealing::anneal_solution, bayesian_backtrack_algo::{bayesian_backtrack, TraceCache}, checkpoint::{match_checkpoint_connections, read_checkpoint, renumber_solution, resumed_prerouted_traces, Checkpointing}, corner_rounding::round_trace_corners, deterministic_rand::with_seed, diff_pair_routing::{calculate_diff_pair_skews, route_diff_pairs}, display_injection::{self, DisplayInjection}, exact_selection_algo::exact_selection, fanout::fanout_dense_pads, length_tuning::tune_lengths, mcts_order_algo::mcts_order, naive_backtrack_algo::naive_backtrack, negotiated_congestion_algo::negotiated_congestion, pareto_front::{non_dominated, ParetoFront}, portfolio::run_seed_portfolio, solve_budget::SolveBudget, teardrops::generate_teardrops, zone_fill::fill_zones};
 
 
 
-/// this either calls naive backtrack or bayesian backtrack
+/// the finished board, after length tuning and with the teardrops and zone fills, which the solvers never display
+fn solution_to_pcb_render_model(problem: &PcbProblem, solution: &PcbSolution) -> PcbRenderModel {
+    let mut trace_shape_renderables: Vec<RenderableBatch> = Vec::new();
+    let mut pad_shape_renderables: Vec<ShapeRenderable> = Vec::new();
+    let mut other_shape_renderables: Vec<ShapeRenderable> = Vec::new();
+    for net_info in problem.nets.values() {
+        for pad in net_info.pads.values() {
+            pad_shape_renderables.extend(pad.to_renderables(net_info.color.to_float4(1.0)));
+            pad_shape_renderables.extend(pad.to_clearance_renderables(net_info.color.to_float4(0.5)));
+        }
+    }
+    for fixed_trace in solution.determined_traces.values() {
+        let color = problem.nets[&fixed_trace.net_name].color.to_float4(1.0);
+        trace_shape_renderables.extend(fixed_trace.trace_path.to_renderables(color));
+    }
+    for teardrop in solution.teardrops.iter() {
+        trace_shape_renderables.push(teardrop.to_renderables(1.0));
+    }
+    for zone_fill in solution.zone_fills.iter() {
+        trace_shape_renderables.push(zone_fill.to_renderables(1.0));
+    }
+    for line in &problem.obstacle_border_outlines {
+        other_shape_renderables.push(ShapeRenderable {
+            shape: PrimShape::Line(line.clone()),
+            color: [1.0, 0.0, 1.0, 1.0], // magenta color for borders
+        });
+    }
+    PcbRenderModel {
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        trace_shape_renderables,
+        pad_shape_renderables,
+        other_shape_renderables,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SolverAlgorithm {
+    NaiveBacktrack,
+    BayesianBacktrack,
+    NegotiatedCongestion,
+    ExactSelection,
+    MonteCarloOrder,
+}
+
+impl SolverAlgorithm {
+    /// 0 backtracks, with Bayesian inference if use_bayesian, 1 negotiated congestion, 2 exact selection, 3 Monte Carlo order
+    pub fn from_setting(value: usize, use_bayesian: bool) -> Self {
+        match value {
+            1 => SolverAlgorithm::NegotiatedCongestion,
+            2 => SolverAlgorithm::ExactSelection,
+            3 => SolverAlgorithm::MonteCarloOrder,
+            _ if use_bayesian => SolverAlgorithm::BayesianBacktrack,
+            _ => SolverAlgorithm::NaiveBacktrack,
+        }
+    }
+}
+
+/// which traces of an earlier solution a reroute keeps, by connection or by whole net
+/// a connection is rerouted if it or its net is marked for rerouting, or if the earlier solution has no trace for it,
+/// unless it or its net is locked; every other trace is kept as it is
+#[derive(Debug, Clone, Default)]
+pub struct RerouteSelection {
+    pub locked_connections: HashSet<ConnectionID>,
+    pub locked_nets: HashSet<NetName>,
+    pub reroute_connections: HashSet<ConnectionID>,
+    pub reroute_nets: HashSet<NetName>,
+}
+
+/// the traces of the earlier solution that stay fixed while the rest of the problem is rerouted
+fn select_locked_traces(
+    problem: &PcbProblem,
+    previous_solution: &PcbSolution,
+    selection: &RerouteSelection,
+) -> Result<HashMap<ConnectionID, FixedTrace>, String> {
+    if let Some(connection_id) = selection.locked_connections.intersection(&selection.reroute_connections).next() {
+        return Err(format!("Connection {:?} is both locked and marked for rerouting", connection_id));
+    }
+    if let Some(net_name) = selection.locked_nets.intersection(&selection.reroute_nets).next() {
+        return Err(format!("Net {} is both locked and marked for rerouting", net_name.0));
+    }
+    let mut locked_traces: HashMap<ConnectionID, FixedTrace> = HashMap::new();
+    for (net_name, net_info) in problem.nets.iter() {
+        for connection_id in net_info.connections.keys() {
+            let is_locked = selection.locked_connections.contains(connection_id) || selection.locked_nets.contains(net_name);
+            let is_rerouted = selection.reroute_connections.contains(connection_id) || selection.reroute_nets.contains(net_name);
+            match previous_solution.determined_traces.get(connection_id) {
+                Some(fixed_trace) if is_locked || !is_rerouted => {
+                    locked_traces.insert(*connection_id, fixed_trace.clone());
+                }
+                None if is_locked => {
+                    return Err(format!("Connection {:?} is locked but has no trace in the previous solution", connection_id));
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(locked_traces)
+}
+
+/// this fans out the dense footprints and routes the differential pairs first, then calls naive backtrack, bayesian backtrack or negotiated congestion,
+/// anneals a fully routed board if USE_ANNEALING is set, and finally rounds the corners, tunes the nets with length constraints, adds the teardrops and fills the zones
+/// a run that fails or is stopped still returns the most complete state it reached, with the unrouted connections listed
+/// with PORTFOLIO_NUM_SEEDS above 1 the routing is run once per seed and the best solution is kept
+/// with USE_PARETO_FRONT set the solution also carries every complete solution of the runs and the annealing that no other beats on every objective
 pub fn solve_pcb_problem(
     pcb_problem: &PcbProblem,
-    bayesian: bool,
+    algorithm: SolverAlgorithm,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    solve_pcb_problem_with_cache(pcb_problem, algorithm, &mut TraceCache::default(), display_injection)
+}
+
+/// the same as solve_pcb_problem, with the traces the solver finds left in the cache for a later reroute
+pub fn solve_pcb_problem_with_cache(
+    pcb_problem: &PcbProblem,
+    algorithm: SolverAlgorithm,
+    trace_cache: &mut TraceCache,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    solve_with_locked_traces(pcb_problem, algorithm, None, trace_cache, display_injection)
+}
+
+/// the same as solve_pcb_problem, with the state of a backtracking search written to the checkpoint file every CHECKPOINT_INTERVAL seconds and when it stops
+/// the checkpoint is removed once every connection is routed, negotiated congestion writes no checkpoints
+pub fn solve_pcb_problem_with_checkpoints(
+    pcb_problem: &PcbProblem,
+    algorithm: SolverAlgorithm,
+    checkpoint_path: &Path,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    display_injection.checkpointing = Some(Checkpointing::new(checkpoint_path, algorithm, None));
+    let result = solve_pcb_problem(pcb_problem, algorithm, display_injection);
+    finish_checkpointing(checkpoint_path, &result, display_injection);
+    result
+}
+
+/// goes on with the search of a checkpoint, with the solver, the seed and the trace cache it was written with,
+/// and keeps writing checkpoints to the same file
+pub fn resume_pcb_problem(
+    pcb_problem: &PcbProblem,
+    checkpoint_path: &Path,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    let mut checkpoint = read_checkpoint(checkpoint_path)?;
+    checkpoint.check_problem(pcb_problem)?;
+    let algorithm = checkpoint.algorithm;
+    let seed = checkpoint.seed;
+    let mut trace_cache = std::mem::take(&mut checkpoint.trace_cache);
+    println!("Resuming the {:?} search of seed {} from {}", algorithm, seed, checkpoint_path.to_string_lossy());
+    display_injection.checkpointing = Some(Checkpointing::new(checkpoint_path, algorithm, Some(checkpoint)));
+    let result = with_seed(seed, || solve_pcb_problem_with_cache(pcb_problem, algorithm, &mut trace_cache, display_injection));
+    finish_checkpointing(checkpoint_path, &result, display_injection);
+    result
+}
+
+fn finish_checkpointing(checkpoint_path: &Path, result: &Result<PcbSolution, String>, display_injection: &mut DisplayInjection) {
+    display_injection.checkpointing = None;
+    if let Ok(solution) = result
+        && solution.unrouted_connections.is_empty()
+        && fs::remove_file(checkpoint_path).is_ok()
+    {
+        println!("Removed the checkpoint of the finished search");
+    }
+}
+
+/// keeps the locked traces of an earlier solution and routes only the rest of the problem again
+/// the locked traces are fixed from the start, they are never backtracked or shoved, and the post routing passes leave them as they are
+/// the trace cache of the earlier run is reused, except for the traces that now collide with a locked trace
+pub fn reroute_pcb_problem(
+    pcb_problem: &PcbProblem,
+    previous_solution: &PcbSolution,
+    selection: &RerouteSelection,
+    algorithm: SolverAlgorithm,
+    trace_cache: &mut TraceCache,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    solve_with_locked_traces(pcb_problem, algorithm, Some((previous_solution, selection)), trace_cache, display_injection)
+}
+
+fn solve_with_locked_traces(
+    pcb_problem: &PcbProblem,
+    algorithm: SolverAlgorithm,
+    reroute: Option<(&PcbSolution, &RerouteSelection)>,
+    trace_cache: &mut TraceCache,
     display_injection: &mut DisplayInjection,
 ) -> Result<PcbSolution, String> {
+    display_injection.budget = Arc::new(SolveBudget::start());
+    // the fanout vias are pads of the fanned out problem, everything after this routes that problem
+    let fanned_out_problem: PcbProblem;
+    let mut fanout_traces: HashMap<ConnectionID, FixedTrace> = HashMap::new();
+    let pcb_problem = if USE_FANOUT.load(Ordering::Relaxed) {
+        (fanned_out_problem, fanout_traces) = fanout_dense_pads(pcb_problem);
+        &fanned_out_problem
+    } else {
+        pcb_problem
+    };
     let connections: Vec<ConnectionID> = pcb_problem.nets.iter().flat_map(|(_, net_info)| net_info.connections.keys().cloned()).collect::<Vec<_>>();
-    let mut trace_cache = TraceCache{
-        traces: connections.iter().map(|&connection_id| (connection_id, Vec::new())).collect(),
+    let locked_traces = match reroute {
+        Some((previous_solution, selection)) => select_locked_traces(pcb_problem, previous_solution, selection)?,
+        None => HashMap::new(),
     };
+    if reroute.is_some() {
+        println!("Rerouting {} of {} connections", connections.len() - locked_traces.len(), connections.len());
+    }
+    for (net_name, net_info) in pcb_problem.nets.iter() {
+        for connection_id in net_info.connections.keys() {
+            let cached_traces = trace_cache.traces.entry(*connection_id).or_default();
+            if locked_traces.contains_key(connection_id) {
+                continue;
+            }
+            // a cached trace through a locked trace of another net can never be used again
+            cached_traces.retain(|trace_path| {
+                !locked_traces
+                    .values()
+                    .filter(|locked_trace| locked_trace.net_name != *net_name)
+                    .any(|locked_trace| trace_path.collides_with(&locked_trace.trace_path))
+            });
+        }
+    }
+    let is_checkpointing = display_injection.checkpointing.is_some();
+    if PORTFOLIO_NUM_SEEDS.load(Ordering::Relaxed) > 1 && is_checkpointing {
+        println!("A search with checkpoints runs a single seed, the portfolio is skipped");
+    }
+    if PORTFOLIO_NUM_SEEDS.load(Ordering::Relaxed) <= 1 || is_checkpointing {
+        return route_problem(pcb_problem, algorithm, &connections, &fanout_traces, &locked_traces, trace_cache, display_injection);
+    }
+    let solution = run_seed_portfolio(trace_cache, display_injection, |run_trace_cache, run_display_injection| {
+        route_problem(pcb_problem, algorithm, &connections, &fanout_traces, &locked_traces, run_trace_cache, run_display_injection)
+    })?;
+    // the display shows the last run of the portfolio, or nothing if the runs were on other threads, so the kept solution is shown again
+    if (display_injection.can_submit_render_model)() {
+        (display_injection.submit_render_model)(solution_to_pcb_render_model(pcb_problem, &solution));
+    }
+    Ok(solution)
+}
 
-    let result = if bayesian {
-        // Call the Bayesian backtrack function
-        bayesian_backtrack(pcb_problem, &mut trace_cache, display_injection)
-    } else {
-        // Call the naive backtrack function
-        naive_backtrack(pcb_problem, &mut trace_cache, None, display_injection)
+/// rounds the corners, tunes the nets with length constraints, adds the teardrops, fills the zones and measures the skews of the differential pairs
+/// the corners are rounded first, so the tuner measures the lengths of the arcs and the matched lengths are the final ones
+fn apply_post_routing_passes(pcb_problem: &PcbProblem, solution: &mut PcbSolution, locked_connections: &HashSet<ConnectionID>) {
+    if ARC_CORNER_RADIUS.load(Ordering::Relaxed) > 0.0 {
+        round_trace_corners(pcb_problem, &mut solution.determined_traces, locked_connections);
+    }
+    tune_lengths(pcb_problem, &mut solution.determined_traces, locked_connections);
+    if USE_TEARDROPS.load(Ordering::Relaxed) {
+        solution.teardrops = generate_teardrops(pcb_problem, &solution.determined_traces);
+        println!("Added {} teardrops", solution.teardrops.len());
+    }
+    solution.zone_fills = fill_zones(pcb_problem, &solution.determined_traces, &solution.teardrops);
+    solution.diff_pair_skews = calculate_diff_pair_skews(pcb_problem, &solution.determined_traces);
+}
+
+/// routes the fanned out problem around the fanout stubs and the locked traces with one seed, then runs the post routing passes
+fn route_problem(
+    pcb_problem: &PcbProblem,
+    algorithm: SolverAlgorithm,
+    connections: &[ConnectionID],
+    fanout_traces: &HashMap<ConnectionID, FixedTrace>,
+    locked_traces: &HashMap<ConnectionID, FixedTrace>,
+    trace_cache: &mut TraceCache,
+    display_injection: &mut DisplayInjection,
+) -> Result<PcbSolution, String> {
+    // a resumed search routes the board with the connection ids of its checkpoint, and its solution gets the ids of the board back
+    let renumbered = match_checkpoint_connections(pcb_problem, display_injection)?;
+    let (pcb_problem, to_problem_id) = match &renumbered {
+        Some(renumbered) => (&renumbered.problem, Some(&renumbered.to_problem_id)),
+        None => (pcb_problem, None),
+    };
+    let locked_connections: HashSet<ConnectionID> = locked_traces.keys().cloned().collect();
+    let mut fanout_traces = fanout_traces.clone();
+    fanout_traces.extend(locked_traces.clone());
+    // a resumed search keeps the differential pairs it was written with
+    let prerouted_traces = match resumed_prerouted_traces(display_injection) {
+        Some(prerouted_traces) => prerouted_traces,
+        None => route_diff_pairs(pcb_problem, &fanout_traces, display_injection)?,
+    };
+
+    let result = match algorithm {
+        SolverAlgorithm::BayesianBacktrack => {
+            bayesian_backtrack(pcb_problem, trace_cache, &prerouted_traces, display_injection)
+        }
+        SolverAlgorithm::NaiveBacktrack => {
+            naive_backtrack(pcb_problem, trace_cache, None, &prerouted_traces, display_injection)
+        }
+        SolverAlgorithm::NegotiatedCongestion => {
+            negotiated_congestion(pcb_problem, trace_cache, &prerouted_traces, display_injection)
+        }
+        SolverAlgorithm::ExactSelection => {
+            exact_selection(pcb_problem, trace_cache, &prerouted_traces, display_injection)
+        }
+        SolverAlgorithm::MonteCarloOrder => {
+            mcts_order(pcb_problem, trace_cache, &prerouted_traces, display_injection)
+        }
     };
     match result{
-        Ok(solution) => {
-            println!("PCB problem solved successfully");
+        Ok(mut solution) => {
+            if solution.unrouted_connections.is_empty() {
+                println!("PCB problem solved successfully");
+            } else {
+                // the partial routes still get the post routing passes, so they can be exported as they are
+                println!(
+                    "Partial solution: routed {} of {} connections",
+                    solution.determined_traces.len(),
+                    connections.len()
+                );
+            }
             // println!("Sample Count: {}", SAMPLE_CNT.load(Ordering::SeqCst));
-            if solution.determined_traces.len() < connections.len() {
+            if solution.determined_traces.len() + solution.unrouted_connections.len() < connections.len() {
                 let err_msg = format!(
                     "Not all connections were solved. Expected: {}, Found: {}",
                     connections.len(),
@@ -37,6 +324,59 @@ pub fn solve_pcb_problem(
                 println!("{}", err_msg);
                 return Err(err_msg);
             }
+
+            // every complete routing the run finds is a candidate of the front, the solver result and the accepted annealing moves
+            let mut pareto_front = USE_PARETO_FRONT.load(Ordering::Relaxed).then(ParetoFront::default);
+            if let Some(pareto_front) = pareto_front.as_mut()
+                && solution.unrouted_connections.is_empty()
+            {
+                pareto_front.offer(pcb_problem, &solution.determined_traces);
+            }
+            // the fanout stubs, the differential pairs and the locked traces are prerouted and stay as they are
+            if USE_ANNEALING.load(Ordering::Relaxed) && solution.unrouted_connections.is_empty() {
+                anneal_solution(
+                    pcb_problem,
+                    &mut solution.determined_traces,
+                    &prerouted_traces,
+                    trace_cache,
+                    pareto_front.as_mut(),
+                    display_injection,
+                );
+            }
+            apply_post_routing_passes(pcb_problem, &mut solution, &locked_connections);
+            if (display_injection.can_submit_render_model)() {
+                (display_injection.submit_render_model)(solution_to_pcb_render_model(pcb_problem, &solution));
+            }
+            if let Some(pareto_front) = pareto_front {
+                let pareto_solutions: Vec<ParetoSolution> = pareto_front
+                    .into_routings()
+                    .into_iter()
+                    .map(|determined_traces| {
+                        let mut pareto_solution = PcbSolution {
+                            determined_traces,
+                            unrouted_connections: Vec::new(),
+                            diff_pair_skews: HashMap::new(),
+                            teardrops: Vec::new(),
+                            zone_fills: Vec::new(),
+                            seed: solution.seed,
+                            scale_down_factor: solution.scale_down_factor,
+                            pareto_front: Vec::new(),
+                        };
+                        apply_post_routing_passes(pcb_problem, &mut pareto_solution, &locked_connections);
+                        let objectives = SolutionObjectives::from_traces(pcb_problem, &pareto_solution.determined_traces);
+                        ParetoSolution { objectives, solution: pareto_solution }
+                    })
+                    .collect();
+                // the post routing passes change the objectives, so the front is filtered again
+                solution.pareto_front = non_dominated(pareto_solutions);
+                println!("The Pareto front holds {} solutions", solution.pareto_front.len());
+            }
+            if let Some(to_problem_id) = to_problem_id {
+                renumber_solution(&mut solution, to_problem_id);
+                for pareto_solution in solution.pareto_front.iter_mut() {
+                    renumber_solution(&mut pareto_solution.solution, to_problem_id);
+                }
+            }
             Ok(solution)
         }
         Err(e) => {
@@ -46,3 +386,54 @@ pub fn solve_pcb_problem(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Arc};
+
+    use shared::pcb_problem::NetName;
+
+    use super::{reroute_pcb_problem, RerouteSelection, SolverAlgorithm};
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        test_pcb_problem::examples::{example_problem, route_example},
+    };
+
+    #[test]
+    fn rerouting_keeps_the_locked_traces_as_they_were() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let solution = route_example(&problem, SolverAlgorithm::NaiveBacktrack);
+        assert!(solution.unrouted_connections.is_empty());
+        let mut net_names: Vec<&NetName> = problem.nets.keys().collect();
+        net_names.sort();
+        // the first net is locked as a whole, the first connection of the second one on its own
+        let locked_net = net_names[0].clone();
+        let locked_connection = *problem.nets[net_names[1]].connections.keys().min().unwrap();
+        let selection = RerouteSelection {
+            locked_nets: [locked_net.clone()].into(),
+            locked_connections: [locked_connection].into(),
+            reroute_nets: net_names[1..].iter().map(|net_name| (*net_name).clone()).collect(),
+            ..Default::default()
+        };
+        let rerouted = reroute_pcb_problem(
+            &problem,
+            &solution,
+            &selection,
+            SolverAlgorithm::NaiveBacktrack,
+            &mut TraceCache::default(),
+            &mut DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true),
+        )
+        .unwrap();
+
+        assert!(rerouted.unrouted_connections.is_empty());
+        let locked_ids = problem.nets[&locked_net].connections.keys().chain([&locked_connection]);
+        for connection_id in locked_ids {
+            assert_eq!(
+                rerouted.determined_traces[connection_id].trace_path.anchors,
+                solution.determined_traces[connection_id].trace_path.anchors
+            );
+        }
+        assert_eq!(rerouted.determined_traces.len(), solution.determined_traces.len());
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use shared::{
+    hyperparameters::{PORTFOLIO_METRIC, PORTFOLIO_NUM_SEEDS, PORTFOLIO_THREADS, RANDOM_SEED},
+    pcb_problem::{ParetoSolution, PcbSolution},
+};
+
+use crate::{
+    bayesian_backtrack_algo::TraceCache, deterministic_rand::with_seed, display_injection::DisplayInjection,
+    pareto_front::non_dominated,
+};
+
+/// which solution of a portfolio is kept, a solution with more connections routed is always better
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortfolioMetric {
+    Completion, // the earliest seed among the most complete solutions
+    Length,     // the shortest total trace length among the most complete solutions
+    Vias,       // the fewest vias among the most complete solutions, then the shortest
+}
+
+impl PortfolioMetric {
+    pub fn from_setting(value: usize) -> Self {
+        match value {
+            1 => PortfolioMetric::Length,
+            2 => PortfolioMetric::Vias,
+            _ => PortfolioMetric::Completion,
+        }
+    }
+
+    fn is_better(&self, solution: &PcbSolution, best: &PcbSolution) -> bool {
+        if solution.unrouted_connections.len() != best.unrouted_connections.len() {
+            return solution.unrouted_connections.len() < best.unrouted_connections.len();
+        }
+        match self {
+            PortfolioMetric::Completion => false,
+            PortfolioMetric::Length => total_length(solution) < total_length(best),
+            PortfolioMetric::Vias => {
+                (num_vias(solution), total_length(solution)) < (num_vias(best), total_length(best))
+            }
+        }
+    }
+}
+
+fn total_length(solution: &PcbSolution) -> f64 {
+    solution.determined_traces.values().map(|fixed_trace| fixed_trace.trace_path.total_length).sum()
+}
+
+fn num_vias(solution: &PcbSolution) -> usize {
+    solution.determined_traces.values().map(|fixed_trace| fixed_trace.trace_path.vias.len()).sum()
+}
+
+struct PortfolioRun {
+    seed: u64,
+    result: Result<PcbSolution, String>,
+    trace_cache: TraceCache,
+}
+
+fn print_run(run: &PortfolioRun) {
+    match &run.result {
+        Ok(solution) => println!(
+            "Seed {}: routed {} connections, {} unrouted, length {:.2}, {} vias",
+            run.seed,
+            solution.determined_traces.len(),
+            solution.unrouted_connections.len(),
+            total_length(solution),
+            num_vias(solution)
+        ),
+        Err(e) => println!("Seed {}: failed, {}", run.seed, e),
+    }
+}
+
+/// runs the solve with PORTFOLIO_NUM_SEEDS seeds from RANDOM_SEED on, on up to PORTFOLIO_THREADS threads,
+/// and keeps the best solution by PORTFOLIO_METRIC
+/// the runs share the budget of the solve, no new run starts once it is exhausted or a stop is requested
+/// every run starts from its own copy of the trace cache, and the cache of the kept run is handed back
+/// the Pareto fronts of the runs are merged into the front of the kept solution
+pub(crate) fn run_seed_portfolio<F>(
+    trace_cache: &mut TraceCache,
+    display_injection: &mut DisplayInjection,
+    solve: F,
+) -> Result<PcbSolution, String>
+where
+    F: Fn(&mut TraceCache, &mut DisplayInjection) -> Result<PcbSolution, String> + Sync,
+{
+    let num_seeds = PORTFOLIO_NUM_SEEDS.load(Ordering::Relaxed).max(1);
+    let first_seed = RANDOM_SEED.load(Ordering::Relaxed) as u64;
+    let seeds: Vec<u64> = (0..num_seeds as u64).map(|index| first_seed.wrapping_add(index)).collect();
+    let num_threads = PORTFOLIO_THREADS.load(Ordering::Relaxed);
+    let metric = PortfolioMetric::from_setting(PORTFOLIO_METRIC.load(Ordering::Relaxed));
+    run_seed_portfolio_with(trace_cache, display_injection, &seeds, num_threads, metric, solve)
+}
+
+/// runs the solve once per seed, in seed order on one thread or in any order on up to num_threads threads,
+/// and keeps the best solution by the metric, the earlier seed on a tie
+fn run_seed_portfolio_with<F>(
+    trace_cache: &mut TraceCache,
+    display_injection: &mut DisplayInjection,
+    seeds: &[u64],
+    num_threads: usize,
+    metric: PortfolioMetric,
+    solve: F,
+) -> Result<PcbSolution, String>
+where
+    F: Fn(&mut TraceCache, &mut DisplayInjection) -> Result<PcbSolution, String> + Sync,
+{
+    let num_seeds = seeds.len();
+    let num_threads = num_threads.clamp(1, num_seeds.max(1));
+    println!("Running a portfolio of {} seeds on {} threads, keeping the best by {:?}", num_seeds, num_threads, metric);
+
+    let mut runs: Vec<PortfolioRun> = Vec::new();
+    if num_threads == 1 {
+        for &seed in seeds.iter() {
+            if !runs.is_empty() && (display_injection.budget.check() || display_injection.is_stopped()) {
+                break;
+            }
+            let mut run_trace_cache = trace_cache.clone();
+            let result = with_seed(seed, || solve(&mut run_trace_cache, display_injection));
+            let run = PortfolioRun { seed, result, trace_cache: run_trace_cache };
+            print_run(&run);
+            runs.push(run);
+        }
+    } else {
+        // the runs on other threads cannot use the display, their render models are dropped and they only share the stop flag and the budget
+        let next_index = AtomicUsize::new(0);
+        let finished_runs: Mutex<Vec<(usize, PortfolioRun)>> = Mutex::new(Vec::new());
+        let stop_requested = display_injection.stop_requested.clone();
+        let budget = display_injection.budget.clone();
+        let shared_trace_cache: &TraceCache = trace_cache;
+        thread::scope(|scope| {
+            for _ in 0..num_threads {
+                scope.spawn(|| {
+                    let mut run_display_injection = DisplayInjection {
+                        budget: budget.clone(),
+                        ..DisplayInjection::without_display(stop_requested.clone(), true)
+                    };
+                    loop {
+                        let index = next_index.fetch_add(1, Ordering::Relaxed);
+                        if index >= seeds.len() {
+                            break;
+                        }
+                        if index > 0 && (run_display_injection.budget.check() || run_display_injection.is_stopped()) {
+                            break;
+                        }
+                        let seed = seeds[index];
+                        let mut run_trace_cache = shared_trace_cache.clone();
+                        let result = with_seed(seed, || solve(&mut run_trace_cache, &mut run_display_injection));
+                        let run = PortfolioRun { seed, result, trace_cache: run_trace_cache };
+                        print_run(&run);
+                        finished_runs.lock().unwrap().push((index, run));
+                    }
+                });
+            }
+        });
+        let mut finished_runs = finished_runs.into_inner().unwrap();
+        // in seed order, so ties go to the earlier seed however the threads were scheduled
+        finished_runs.sort_by_key(|(index, _)| *index);
+        runs = finished_runs.into_iter().map(|(_, run)| run).collect();
+    }
+
+    // the kept solution carries the front of every run, not only its own
+    let pareto_solutions: Vec<ParetoSolution> = runs
+        .iter_mut()
+        .filter_map(|run| run.result.as_mut().ok())
+        .flat_map(|solution| std::mem::take(&mut solution.pareto_front))
+        .collect();
+    let mut best_run: Option<PortfolioRun> = None;
+    let mut first_error: Option<String> = None;
+    for run in runs {
+        match &run.result {
+            Ok(solution) => {
+                let is_better = match &best_run {
+                    Some(PortfolioRun { result: Ok(best_solution), .. }) => metric.is_better(solution, best_solution),
+                    _ => true,
+                };
+                if is_better {
+                    best_run = Some(run);
+                }
+            }
+            Err(e) => {
+                first_error.get_or_insert_with(|| e.clone());
+            }
+        }
+    }
+    match best_run {
+        Some(run) => {
+            println!("Portfolio keeps the solution of seed {}", run.seed);
+            *trace_cache = run.trace_cache;
+            run.result.map(|mut solution| {
+                solution.pareto_front = non_dominated(pareto_solutions);
+                solution
+            })
+        }
+        None => Err(first_error.unwrap_or_else(|| "No portfolio run finished".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use shared::{
+        pcb_problem::{ConnectionID, FixedTrace, NetName, PcbSolution, UnroutedConnection, UnroutedReason},
+        trace_path::{TraceAnchors, TracePath, Via},
+        vec2::{FixedPoint, FixedVec2},
+    };
+
+    use super::{run_seed_portfolio_with, PortfolioMetric};
+    use crate::{
+        bayesian_backtrack_algo::TraceCache, deterministic_rand::current_seed, display_injection::DisplayInjection,
+    };
+
+    /// what the run of a seed finds: the number of unrouted connections, the total length and the number of vias
+    type RunOutcome = (usize, f64, usize);
+
+    fn solution(seed: u64, (num_unrouted, total_length, num_vias): RunOutcome) -> PcbSolution {
+        let via = Via {
+            position: FixedVec2::new(FixedPoint::ZERO, FixedPoint::ZERO),
+            diameter: 0.6,
+            clearance: 0.2,
+            min_layer: 0,
+            max_layer: 1,
+            padstack: "Via[0-1]_600:300_um".to_string(),
+        };
+        let trace_path = TracePath {
+            anchors: TraceAnchors(Vec::new()),
+            segments: Vec::new(),
+            vias: vec![via; num_vias],
+            arcs: Vec::new(),
+            total_length,
+        };
+        let fixed_trace = FixedTrace { net_name: NetName("N".to_string()), connection_id: ConnectionID(0), trace_path };
+        let unrouted_connections = (1..=num_unrouted)
+            .map(|index| UnroutedConnection {
+                connection_id: ConnectionID(index),
+                net_name: NetName("N".to_string()),
+                reason: UnroutedReason::NoPath,
+            })
+            .collect();
+        PcbSolution {
+            determined_traces: HashMap::from([(ConnectionID(0), fixed_trace)]),
+            unrouted_connections,
+            diff_pair_skews: HashMap::new(),
+            teardrops: Vec::new(),
+            zone_fills: Vec::new(),
+            seed,
+            scale_down_factor: 1.0,
+            pareto_front: Vec::new(),
+        }
+    }
+
+    /// the seed of the solution the portfolio keeps when the seeds 0, 1, ... find the given outcomes
+    fn kept_seed(outcomes: &[RunOutcome], num_threads: usize, metric: PortfolioMetric) -> u64 {
+        let seeds: Vec<u64> = (0..outcomes.len() as u64).collect();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), false);
+        let mut trace_cache = TraceCache { traces: HashMap::new() };
+        run_seed_portfolio_with(&mut trace_cache, &mut display_injection, &seeds, num_threads, metric, |_, _| {
+            let seed = current_seed();
+            Ok(solution(seed, outcomes[seed as usize]))
+        })
+        .unwrap()
+        .seed
+    }
+
+    #[test]
+    fn each_metric_keeps_its_best_solution() {
+        let outcomes = [
+            (1, 5.0, 0),  // the shortest and without vias, but one connection is unrouted
+            (0, 30.0, 4), // complete
+            (0, 20.0, 3), // complete and the shortest of the complete ones
+            (0, 25.0, 1), // complete with the fewest vias
+        ];
+        for num_threads in [1, 3] {
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Completion), 1);
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Length), 2);
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Vias), 3);
+        }
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_seed() {
+        let outcomes = [
+            (1, 10.0, 0), // one connection is unrouted
+            (0, 20.0, 2),
+            (0, 20.0, 2), // the same as the seed before it
+            (0, 30.0, 2), // as many vias, but longer
+        ];
+        for num_threads in [1, 4] {
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Completion), 1);
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Length), 1);
+            assert_eq!(kept_seed(&outcomes, num_threads, PortfolioMetric::Vias), 1);
+        }
+    }
+}
@@ -1,17 +1,9 @@
-use fixed::traits::Fixed;
 // use crate::block_or_sleep::{block_or_sleep, block_thread};
 use shared::{
-    binary_heap_item::BinaryHeapItem,
-    hyperparameters::{ASTAR_STRIDE},
-    pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable, UpdatePcbRenderModel},
-    prim_shape::{CircleShape, PrimShape, RectangleShape},
-    trace_path::{Direction, TraceAnchor, TraceAnchors, TracePath, TraceSegment, Via},
+    trace_path::{Direction, TraceAnchor, TraceAnchors, TraceArc, TracePath, TraceSegment},
     vec2::{FixedPoint, FixedVec2, FloatVec2, IntVec2},
 };
 
-use std::ops::Range;
-use std::path::Path;
-
 
 fn is_convex(dir1: Direction, dir2: Direction, dir3: Direction) -> bool {
     let angle1 = (dir1.to_degree_angle() - dir3.to_degree_angle()).abs();
@@ -558,7 +550,6 @@ pub fn optimize_path(
     // check_collision_for_via: &dyn Fn(FixedVec2, f32, f32, usize, usize) -> bool, // min, max
     trace_width: f32,
     trace_clearance: f32,
-    via_diameter: f32,
 ) -> TracePath {    
     let path = &trace_path.anchors.0;
     let mut optimized = path.clone();    
@@ -664,6 +655,293 @@ pub fn optimize_path(
         // return (TracePath::from_anchors(TraceAnchors(optimized), trace_width, trace_clearance, via_diameter), true);
     }
     let result_trace_anchors = TraceAnchors(optimized);
-    let result_trace_path = TracePath::from_anchors(result_trace_anchors, trace_width, trace_clearance, via_diameter);
+    let result_trace_path = TracePath::from_anchors(result_trace_anchors, trace_width, trace_clearance, trace_path.vias.clone());
     result_trace_path
 }
+
+/// the offset of a point along a direction from a start, none if the point is not on that line
+fn offset_along(start: FixedVec2, direction: Direction, point: FixedVec2) -> Option<FixedPoint> {
+    let offset = FixedPoint::max((point.x - start.x).abs(), (point.y - start.y).abs());
+    (start + direction.to_fixed_vec2(offset) == point).then_some(offset)
+}
+
+/// whether a point lies on a straight segment, its ends included
+fn is_on_segment(segment: &TraceSegment, point: FixedVec2) -> bool {
+    let Ok(Some(direction)) = Direction::from_points(segment.start, segment.end) else {
+        return segment.start == point;
+    };
+    let length = FixedPoint::max((segment.end.x - segment.start.x).abs(), (segment.end.y - segment.start.y).abs());
+    offset_along(segment.start, direction, point).is_some_and(|offset| offset <= length)
+}
+
+/// inserts rectangular meanders into the segments of a trace until it is at least the needed length longer
+/// every new segment is checked with the same collision function optimize_path uses, and against the rest of the trace
+/// the rounded corners of the trace are kept, meanders only go into the straight part between the arcs
+pub fn insert_meanders(
+    trace_path: &TracePath,
+    check_collision_for_trace: &dyn Fn(FixedVec2, FixedVec2, f32, f32, usize) -> bool,
+    trace_width: f32,
+    trace_clearance: f32,
+    max_amplitude: f32,
+    needed_length: f64,
+) -> TracePath {
+    let anchors = &trace_path.anchors.0;
+    // center to center distance between two neighbouring legs of a meander
+    let spacing = (trace_width + trace_clearance) as f64;
+    let mut remaining_length = needed_length;
+    let mut tuned: Vec<TraceAnchor> = vec![anchors[0].clone()];
+    // between the ends of an arc the anchors take a straight step the trace does not take
+    let is_arc_chord = |start: FixedVec2, end: FixedVec2, layer: usize| {
+        trace_path.arcs.iter().any(|arc| arc.layer == layer && arc.start == start && arc.end == end)
+    };
+    for window in anchors.windows(2) {
+        let start = window[0].position;
+        let end = window[1].position;
+        let layer = window[0].end_layer;
+        if remaining_length > 0.0 && start != end && !is_arc_chord(start, end, layer) {
+            let direction = Direction::from_points(start, end).unwrap().unwrap();
+            // one unit of offset moves this far along a diagonal
+            let unit_length = if direction.is_diagonal() { std::f64::consts::SQRT_2 } else { 1.0 };
+            // offsets are rounded up to an even number of bits, so every corner stays on the grid
+            let to_offset = |length: f64| -> FixedPoint {
+                let bits = (length / unit_length * (1i64 << FixedPoint::FRAC_NBITS) as f64).ceil() as i32;
+                FixedPoint::from_bits(bits + (bits & 1))
+            };
+            let pitch = to_offset(spacing);
+            let segment_offset = FixedPoint::max((end.x - start.x).abs(), (end.y - start.y).abs());
+            let on_window = |point: FixedVec2| offset_along(start, direction, point).filter(|offset| *offset <= segment_offset);
+            // the arcs of the corners at both ends take the first and the last part of the segment
+            let mut trim_start = FixedPoint::ZERO;
+            let mut trim_end = FixedPoint::ZERO;
+            for arc in trace_path.arcs.iter().filter(|arc| arc.layer == layer) {
+                match (on_window(arc.start), on_window(arc.end)) {
+                    (None, Some(end_offset)) => trim_start = FixedPoint::max(trim_start, end_offset),
+                    (Some(start_offset), None) => trim_end = FixedPoint::max(trim_end, segment_offset - start_offset),
+                    _ => {}
+                }
+            }
+            let check_collision_with_self = |leg_start: FixedVec2, leg_end: FixedVec2, width: f32, clearance: f32, layer: usize| -> bool {
+                if check_collision_for_trace(leg_start, leg_end, width, clearance, layer) {
+                    return true;
+                }
+                let leg = TraceSegment {
+                    start: leg_start,
+                    end: leg_end,
+                    width,
+                    clearance,
+                    layer,
+                };
+                // the segment the meander replaces is left out, the legs start on it
+                let collides_with_segments = trace_path.segments.iter().any(|segment| {
+                    let replaced = segment.layer == layer && on_window(segment.start).is_some() && on_window(segment.end).is_some();
+                    !replaced && segment.collides_with(&leg)
+                });
+                let collides_with_arcs = trace_path.arcs.iter().any(|arc| {
+                    let (arc_colliders, arc_clearance_colliders) = (arc.to_colliders(), arc.to_clearance_colliders());
+                    leg.to_colliders().iter().any(|collider| {
+                        arc_clearance_colliders.iter().any(|arc_clearance_collider| collider.collides_with(arc_clearance_collider))
+                    }) || leg.to_clearance_colliders().iter().any(|collider| {
+                        arc_colliders.iter().any(|arc_collider| collider.collides_with(arc_collider))
+                    })
+                });
+                let collides_with_vias = trace_path.vias.iter().any(|via| {
+                    let via_collider = via.to_collider();
+                    let via_clearance_collider = via.to_clearance_collider();
+                    leg.to_colliders().iter().any(|collider| collider.collides_with(&via_clearance_collider))
+                        || leg.to_clearance_colliders().iter().any(|collider| collider.collides_with(&via_collider))
+                });
+                collides_with_segments || collides_with_arcs || collides_with_vias
+            };
+            let mut offset = trim_start + pitch;
+            while remaining_length > 0.0 && offset + pitch * 2 <= segment_offset - trim_end {
+                let base = start + direction.to_fixed_vec2(offset);
+                let max_amplitude_here = to_offset(f64::min(max_amplitude as f64, remaining_length / 2.0));
+                let mut placed = false;
+                for side in [direction.left_90_dir(), direction.right_90_dir()] {
+                    let legs = |amplitude: FixedPoint| -> Vec<(FixedVec2, FixedVec2)> {
+                        let up = base + side.to_fixed_vec2(amplitude);
+                        let across = up + direction.to_fixed_vec2(pitch);
+                        let down = base + direction.to_fixed_vec2(pitch);
+                        vec![(base, up), (up, across), (across, down)]
+                    };
+                    let amplitude = binary_approach_to_obstacles(
+                        &legs,
+                        FixedPoint::ZERO,
+                        max_amplitude_here + FixedPoint::DELTA,
+                        &check_collision_with_self,
+                        trace_width,
+                        trace_clearance,
+                        layer,
+                    );
+                    let amplitude = FixedPoint::from_bits(amplitude.to_bits() & !1);
+                    // a meander lower than the spacing of its legs is not worth the corners, unless it is the last one
+                    if amplitude == FixedPoint::ZERO || amplitude < FixedPoint::min(pitch, max_amplitude_here) {
+                        continue;
+                    }
+                    for (leg_start, _) in legs(amplitude) {
+                        tuned.push(TraceAnchor {
+                            position: leg_start,
+                            start_layer: layer,
+                            end_layer: layer,
+                        });
+                    }
+                    tuned.push(TraceAnchor {
+                        position: base + direction.to_fixed_vec2(pitch),
+                        start_layer: layer,
+                        end_layer: layer,
+                    });
+                    remaining_length -= 2.0 * amplitude.to_num::<f64>() * unit_length;
+                    offset += pitch * 2;
+                    placed = true;
+                    break;
+                }
+                if !placed {
+                    offset += pitch;
+                }
+            }
+        }
+        tuned.push(window[1].clone());
+    }
+    let mut tuned_trace_path = TracePath::from_anchors(TraceAnchors(tuned), trace_width, trace_clearance, trace_path.vias.clone());
+    if trace_path.arcs.is_empty() {
+        return tuned_trace_path;
+    }
+    tuned_trace_path.segments.retain(|segment| !is_arc_chord(segment.start, segment.end, segment.layer));
+    // the segments at corners rounded before the anchors followed the arcs are trimmed back to them,
+    // the meanders stayed clear of them
+    for arc in trace_path.arcs.iter() {
+        if let Some(segment) = tuned_trace_path
+            .segments
+            .iter_mut()
+            .find(|segment| segment.layer == arc.layer && segment.end != arc.start && is_on_segment(segment, arc.start))
+        {
+            segment.end = arc.start;
+        }
+        if let Some(segment) = tuned_trace_path
+            .segments
+            .iter_mut()
+            .find(|segment| segment.layer == arc.layer && segment.start != arc.end && is_on_segment(segment, arc.end))
+        {
+            segment.start = arc.end;
+        }
+    }
+    tuned_trace_path.segments.retain(|segment| segment.start != segment.end);
+    tuned_trace_path.arcs = trace_path.arcs.clone();
+    tuned_trace_path.total_length = tuned_trace_path.calculate_total_length();
+    tuned_trace_path
+}
+
+/// replaces the sharp corners of a trace with tangent arcs, trying the radii in order until one is free of collisions
+/// a corner is skipped if it sits on a via or changes layers, and a radius is capped to fit the segments on both sides
+/// the corners that are already rounded are kept, the anchor of a new one is replaced by the ends of its arc
+pub fn round_corners(
+    trace_path: &TracePath,
+    check_collision_for_arc: &dyn Fn(&TraceArc) -> bool,
+    radii: &[f32],
+) -> TracePath {
+    let mut segments = trace_path.segments.clone();
+    let mut arcs = trace_path.arcs.clone();
+    let mut rounded_corners: Vec<(FixedVec2, usize, FixedVec2, FixedVec2)> = Vec::new(); // corner, layer, arc start, arc end
+    // offsets are rounded down to an even number of bits, so the trimmed segments stay on the grid
+    let to_offset = |length: f32, direction: Direction| -> FixedPoint {
+        let unit_length = if direction.is_diagonal() { std::f32::consts::SQRT_2 } else { 1.0 };
+        let bits = (length / unit_length * (1i64 << FixedPoint::FRAC_NBITS) as f32).floor() as i32;
+        FixedPoint::from_bits(bits & !1)
+    };
+    for index in 0..segments.len().saturating_sub(1) {
+        let (before, after) = (&segments[index], &segments[index + 1]);
+        let corner = before.end;
+        if before.layer != after.layer
+            || corner != after.start
+            || trace_path.vias.iter().any(|via| via.position == corner)
+        {
+            continue;
+        }
+        let (Ok(Some(direction_before)), Ok(Some(direction_after))) = (
+            Direction::from_points(before.start, before.end),
+            Direction::from_points(after.start, after.end),
+        ) else {
+            continue;
+        };
+        let incoming = (before.end.to_float() - before.start.to_float()).normalize();
+        let outgoing = (after.end.to_float() - after.start.to_float()).normalize();
+        let cross = incoming.x * outgoing.y - incoming.y * outgoing.x;
+        let turn = cross.abs().atan2(incoming.dot(outgoing));
+        // straight joints have nothing to round, sharp turns would need more than a quarter circle
+        if !(1e-3..=std::f32::consts::FRAC_PI_2 + 1e-3).contains(&turn) {
+            continue;
+        }
+        let tan_half_turn = (turn / 2.0).tan();
+        // each corner may use up to half of both of its segments, so the neighbouring corner keeps the other half
+        let max_tangent_length = f32::min(
+            trace_path.segments[index].calculate_length() as f32,
+            trace_path.segments[index + 1].calculate_length() as f32,
+        ) / 2.0;
+        let counterclockwise = cross > 0.0;
+        let inward = if counterclockwise { incoming.perp() } else { FloatVec2::new(incoming.y, -incoming.x) };
+        let mut tried_radii: Vec<f32> = Vec::new();
+        for &radius in radii {
+            let radius = f32::min(radius, max_tangent_length / tan_half_turn);
+            if radius < before.width / 2.0 || tried_radii.contains(&radius) {
+                continue;
+            }
+            tried_radii.push(radius);
+            let tangent_length = radius * tan_half_turn;
+            let offset_before = to_offset(tangent_length, direction_before);
+            let offset_after = to_offset(tangent_length, direction_after);
+            if offset_before == FixedPoint::ZERO || offset_after == FixedPoint::ZERO {
+                continue;
+            }
+            let start = corner - direction_before.to_fixed_vec2(offset_before);
+            let end = corner + direction_after.to_fixed_vec2(offset_after);
+            // the radius follows the rounded tangent length, the end is off the circle by a few bits at most
+            let arc_radius = (corner.to_float() - start.to_float()).length() / tan_half_turn;
+            let start_float = start.to_float();
+            let arc = TraceArc {
+                start,
+                end,
+                center: FloatVec2::new(start_float.x + inward.x * arc_radius, start_float.y + inward.y * arc_radius),
+                radius: arc_radius,
+                counterclockwise,
+                width: before.width,
+                clearance: before.clearance,
+                layer: before.layer,
+            };
+            if check_collision_for_arc(&arc) {
+                continue;
+            }
+            segments[index].end = start;
+            segments[index + 1].start = end;
+            rounded_corners.push((corner, arc.layer, start, end));
+            arcs.push(arc);
+            break;
+        }
+    }
+    segments.retain(|segment| segment.start != segment.end);
+    // the anchors run through the ends of the arcs, an arc stands in for the straight step between them
+    let mut anchors: Vec<TraceAnchor> = Vec::new();
+    for anchor in trace_path.anchors.0.iter() {
+        let rounded_corner = rounded_corners.iter().find(|(corner, layer, _, _)| {
+            *corner == anchor.position && anchor.start_layer == *layer && anchor.end_layer == *layer
+        });
+        let positions = match rounded_corner {
+            Some((_, _, start, end)) => vec![*start, *end],
+            None => vec![anchor.position],
+        };
+        for position in positions {
+            // a segment used up by the arcs at both of its ends leaves the end of one arc on the start of the next
+            if anchors.last().is_none_or(|last| last.position != position || last.end_layer != anchor.start_layer) {
+                anchors.push(TraceAnchor { position, ..anchor.clone() });
+            }
+        }
+    }
+    let mut rounded = TracePath {
+        anchors: TraceAnchors(anchors),
+        segments,
+        vias: trace_path.vias.clone(),
+        arcs,
+        total_length: 0.0,
+    };
+    rounded.total_length = rounded.calculate_total_length();
+    rounded
+}
@@ -1,53 +1,53 @@
 use std::{
     cell::RefCell,
-    collections::{BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     num::NonZeroUsize,
     rc::Rc,
     sync::{Arc, Mutex, atomic::Ordering},
 };
 
 use rand::distr::{Distribution, weighted::WeightedIndex};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use shared::{
     collider::Collider,
     hyperparameters::{
-        FIRST_ITERATION_NUM_TRACES, FIRST_ITERATION_PROBABILITY, HALF_PROBABILITY_OPPORTUNITY_COST, MAX_GENERATION_ATTEMPTS, NUM_BAYESIAN_PATH_FINDING_CALLS, SAMPLE_ITERATIONS, SECOND_ITERATION_NUM_TRACES, SECOND_ITERATION_PROBABILITY
+        ASTAR_STRIDE, DIVERSITY_ALTERNATIVES, DIVERSITY_MIN_DISTANCE, DIVERSITY_PENALTY, HALF_PROBABILITY_OPPORTUNITY_COST, MAX_GENERATION_ATTEMPTS, NUM_BAYESIAN_PATH_FINDING_CALLS, SAMPLING_SCHEDULE, SAMPLING_THREADS, SCHEDULE_STABLE_TOLERANCE, SamplingRound
     },
     pcb_problem::{Connection, ConnectionID, FixedTrace, NetName, PcbProblem},
     pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable},
     prim_shape::PrimShape,
     trace_path::{TraceAnchors, TracePath},
+    trace_scorer::{trace_scorer, ScoringContext},
 };
 
 use crate::{
-    astar::AStarModel, astar_check_struct::AStarCheck, bayesian_backtrack_algo::TraceCache, command_flags::{CommandFlag, TARGET_COMMAND_LEVEL}, deterministic_rand::create_deterministic_rng, display_injection::{self, DisplayInjection}, quad_tree::{self, QuadTreeNode}
+    astar::{AStarCostMap, AStarModel}, astar_check_struct::AStarCheck, candidate_diversity::{is_near_duplicate, DiversityCostMap}, bayesian_backtrack_algo::TraceCache, command_flags::{CommandFlag, TARGET_COMMAND_LEVEL}, deterministic_rand::{create_deterministic_rng_for_stream, current_seed}, display_injection::{self, DisplayInjection}, inference::{inference_engine_from_settings, InferenceDiagnostics}, quad_tree::{self, QuadTreeNode}
 };
 
-#[derive(Copy, Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
+#[derive(Copy, Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ProbaTraceID(pub usize);
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ProbaTrace {
     pub net_name: NetName,                    // The net that the trace belongs to
     pub connection_id: ConnectionID,          // The connection that the trace belongs to
     pub proba_trace_id: ProbaTraceID,         // Unique identifier for the trace
     pub trace_path: TracePath,                // The path of the trace
+    pub score: f64,                           // from the trace scorer, in (0, 1], fixed once the trace is sampled
     pub iteration: NonZeroUsize, // The iteration that the trace belongs to, starting from 1
-    pub posterior: RefCell<Option<f64>>, // to be accessed in the next iteration
-    pub temp_posterior: RefCell<Option<f64>>, // serve as a buffer for simultaneous updates
+    pub prior: f64,                           // normalized prior of the sampling round of the trace
+    pub posterior: Mutex<Option<f64>>, // to be accessed in the next iteration
+    pub temp_posterior: Mutex<Option<f64>>, // serve as a buffer for simultaneous updates
 }
 
 impl ProbaTrace {
-    fn get_normalized_prior(&self) -> f64 {
-        assert!(self.iteration == NonZeroUsize::new(1).unwrap() || self.iteration == NonZeroUsize::new(2).unwrap());
-        if self.iteration == NonZeroUsize::new(1).unwrap() {
-            FIRST_ITERATION_PROBABILITY.load(Ordering::Relaxed)
-        } else {
-            SECOND_ITERATION_PROBABILITY.load(Ordering::Relaxed)
-        }
+    pub(crate) fn get_normalized_prior(&self) -> f64 {
+        self.prior
     }
 
     pub fn get_posterior_with_fallback(&self) -> f64 {
-        let posterior = self.posterior.borrow();
+        let posterior = self.posterior.lock().unwrap();
         if let Some(posterior) = posterior.as_ref() {
             *posterior
         } else {
@@ -59,7 +59,7 @@ impl ProbaTrace {
 #[derive(Debug, Clone)]
 pub enum Traces {
     Fixed(FixedTrace), // A trace that is fixed and does not change
-    Probabilistic(HashMap<ProbaTraceID, Rc<ProbaTrace>>), // A trace that is probabilistic and can change
+    Probabilistic(HashMap<ProbaTraceID, Arc<ProbaTrace>>), // A trace that is probabilistic and can change
 }
 
 // a connection can have either a determined trace or multiple probabilistic traces
@@ -70,9 +70,33 @@ pub struct ProbaModel {
     // pub visited_traces: BTreeSet<TraceAnchors>,
     pub collision_adjacency: HashMap<ProbaTraceID, HashSet<ProbaTraceID>>, // TraceID to set of colliding TraceIDs
     pub next_iteration: NonZeroUsize, // The next iteration to be processed, starting from 1
+    pub inference_diagnostics: Vec<InferenceDiagnostics>, // one per sampling iteration
+}
+
+/// the pool the nets are sampled on with the number of threads it was built for
+static SAMPLING_THREAD_POOL: Mutex<Option<(usize, Arc<rayon::ThreadPool>)>> = Mutex::new(None);
+
+/// the sampling pool, built on first use and again only when SAMPLING_THREADS changes
+fn sampling_thread_pool() -> Arc<rayon::ThreadPool> {
+    let num_threads = SAMPLING_THREADS.load(Ordering::Relaxed);
+    let mut sampling_thread_pool = SAMPLING_THREAD_POOL.lock().unwrap();
+    match sampling_thread_pool.as_ref() {
+        Some((pool_num_threads, thread_pool)) if *pool_num_threads == num_threads => thread_pool.clone(),
+        _ => {
+            let thread_pool = Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_threads)
+                    .build()
+                    .expect("Failed to build the sampling thread pool"),
+            );
+            *sampling_thread_pool = Some((num_threads, thread_pool.clone()));
+            thread_pool
+        }
+    }
 }
 
 impl ProbaModel {
+    /// samples and infers with the schedule and the tolerance of the settings
     pub fn create_and_solve(
         problem: &PcbProblem,
         fixed_traces: &HashMap<ConnectionID, FixedTrace>,
@@ -80,7 +104,30 @@ impl ProbaModel {
         trace_cache: &mut TraceCache,
         display_injection: &mut DisplayInjection,
     ) -> Result<Self, String> {
-        if display_injection.stop_requested.load(Ordering::Relaxed) {
+        let sampling_schedule = SAMPLING_SCHEDULE.lock().unwrap().clone();
+        let stable_tolerance = SCHEDULE_STABLE_TOLERANCE.load(Ordering::Relaxed);
+        Self::create_and_solve_with_schedule(
+            problem,
+            fixed_traces,
+            fix_sequence,
+            trace_cache,
+            display_injection,
+            &sampling_schedule,
+            stable_tolerance,
+        )
+    }
+
+    /// runs the rounds of the schedule in order, stopping early once no posterior changed more than the tolerance
+    pub fn create_and_solve_with_schedule(
+        problem: &PcbProblem,
+        fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+        fix_sequence: Vec<ConnectionID>,
+        trace_cache: &mut TraceCache,
+        display_injection: &mut DisplayInjection,
+        sampling_schedule: &[SamplingRound],
+        stable_tolerance: f64,
+    ) -> Result<Self, String> {
+        if display_injection.is_stopped() {
             println!("Stop requested, not creating ProbaModel");
             return Err("Stop requested".to_string());
         }
@@ -105,10 +152,11 @@ impl ProbaModel {
             fix_sequence,
             collision_adjacency: HashMap::new(),
             next_iteration: NonZeroUsize::new(1).expect("Next iteration must be non-zero"),
+            inference_diagnostics: Vec::new(),
         };
         // display and block
         let display_when_necessary = |proba_model: &ProbaModel, command_flag: CommandFlag, display_injection: &mut DisplayInjection| {
-            if display_injection.stop_requested.load(Ordering::Relaxed) {
+            if display_injection.is_stopped() {
                 println!("Stop requested, not displaying ProbaModel");
                 return;
             }
@@ -135,34 +183,81 @@ impl ProbaModel {
         display_when_necessary(&proba_model, CommandFlag::UpdatePosteriorResult, display_injection);
         println!("After displaying proba model");
         // sample and then update posterior
-        // to do: specify iteration number
-        for j in 0..SAMPLE_ITERATIONS.load(Ordering::Relaxed) {
-            println!("Sampling new traces for iteration {}", j + 1);
-            proba_model.sample_new_traces(problem,  trace_cache, display_injection);
+        let inference_engine = inference_engine_from_settings();
+        for (j, sampling_round) in sampling_schedule.iter().enumerate() {
+            println!("Sampling new traces for iteration {} of {}", j + 1, sampling_schedule.len());
+            let previous_posteriors: HashMap<ProbaTraceID, f64> = proba_model
+                .proba_traces()
+                .iter()
+                .map(|(proba_trace_id, proba_trace)| (*proba_trace_id, proba_trace.get_posterior_with_fallback()))
+                .collect();
+            proba_model.sample_new_traces(problem, sampling_round, trace_cache, display_injection);
             println!("Done sampling new traces");
             display_when_necessary(&proba_model, CommandFlag::UpdatePosteriorResult, display_injection);
 
-            for i in 0..10 {
-                // println!("Updating posterior for the {}th time", i + 1);
-                proba_model.update_posterior();
-                display_when_necessary(&proba_model, CommandFlag::AstarInOut, display_injection);
-            }
+            let diagnostics = inference_engine.infer(&proba_model, &mut |proba_model| {
+                display_when_necessary(proba_model, CommandFlag::AstarInOut, display_injection);
+            });
+            println!("{}", diagnostics);
+            proba_model.inference_diagnostics.push(diagnostics);
             println!("Updated posterior");
             display_when_necessary(&proba_model, CommandFlag::UpdatePosteriorResult, display_injection);
+            // the traces of the first round have no posterior to compare with
+            if j > 0 && j + 1 < sampling_schedule.len() {
+                let posterior_change = proba_model.max_posterior_change(&previous_posteriors);
+                if posterior_change < stable_tolerance {
+                    println!(
+                        "Posteriors stable after iteration {} (largest change {:.6}), skipping the remaining {} iterations",
+                        j + 1,
+                        posterior_change,
+                        sampling_schedule.len() - j - 1
+                    );
+                    break;
+                }
+            }
+        }
+        // the samplers give up when a stop is requested, a model sampled halfway must not be searched or checkpointed
+        if display_injection.is_stopped() {
+            println!("Stop requested while sampling, discarding the ProbaModel");
+            return Err("Stop requested".to_string());
         }
         Ok(proba_model)
     }
 
+    /// samples one more round like the last round of the schedule and infers the posteriors again
+    pub fn sample_more(
+        &mut self,
+        problem: &PcbProblem,
+        trace_cache: &mut TraceCache,
+        display_injection: &mut DisplayInjection,
+    ) -> Result<(), String> {
+        let sampling_round = *SAMPLING_SCHEDULE
+            .lock()
+            .unwrap()
+            .last()
+            .expect("Sampling schedule must have a round");
+        println!("Sampling new traces for extra iteration {}", self.next_iteration);
+        self.sample_new_traces(problem, &sampling_round, trace_cache, display_injection);
+        if display_injection.is_stopped() {
+            println!("Stop requested while sampling, discarding the extra iteration");
+            return Err("Stop requested".to_string());
+        }
+        let diagnostics = inference_engine_from_settings().infer(self, &mut |_| {});
+        println!("{}", diagnostics);
+        self.inference_diagnostics.push(diagnostics);
+        Ok(())
+    }
+
     fn sample_new_traces(
         &mut self,
         problem: &PcbProblem,
+        sampling_round: &SamplingRound,
         trace_cache: &mut TraceCache,
         display_injection: &mut DisplayInjection,
     ) {
-        let mut rng = create_deterministic_rng();
-        let mut new_proba_traces: Vec<Rc<ProbaTrace>> = Vec::new();
+        let mut new_proba_traces: Vec<Arc<ProbaTrace>> = Vec::new();
         // connection_id to connection
-        let mut connections: HashMap<ConnectionID, Rc<Connection>> = HashMap::new();
+        let mut connections: HashMap<ConnectionID, Arc<Connection>> = HashMap::new();
         // connection_id to net_id
         let mut connection_to_net: HashMap<ConnectionID, NetName> = HashMap::new();
         for (net_name, net_info) in problem.nets.iter() {
@@ -173,7 +268,7 @@ impl ProbaModel {
         }
 
         // proba_trace_id to proba_trace
-        let mut proba_traces: HashMap<ProbaTraceID, Rc<ProbaTrace>> = HashMap::new();
+        let mut proba_traces: HashMap<ProbaTraceID, Arc<ProbaTrace>> = HashMap::new();
         // visited TraceAnchors
         let mut visited_traces: BTreeSet<TraceAnchors> = BTreeSet::new();
         // if a new trace is not in the following container, it will be added to the proba traces
@@ -217,8 +312,11 @@ impl ProbaModel {
             if let Traces::Probabilistic(trace_ids) = traces {
                 let mut sum_posterior: f64 = 0.0;
                 let num_traces = trace_ids.len();
-                for (_, proba_trace) in trace_ids.iter() {
-                    let posterior = proba_trace.get_posterior_with_fallback();
+                // sum in id order, so the floating point result is the same every run
+                let mut sorted_trace_ids: Vec<&ProbaTraceID> = trace_ids.keys().collect();
+                sorted_trace_ids.sort();
+                for proba_trace_id in sorted_trace_ids {
+                    let posterior = trace_ids[proba_trace_id].get_posterior_with_fallback();
                     sum_posterior += posterior;
                 }
                 // let remaining_probability = *NEXT_ITERATION_TO_REMAINING_PROBABILITY
@@ -249,434 +347,83 @@ impl ProbaModel {
         }
 
         let border_colliders = AStarModel::calculate_border_colliders(problem.width, problem.height, problem.center);
-        
-        // the outer loop for generating the dijkstra model
-        for (net_name, net_info) in problem.nets.iter() {
-            // collect connections that are not in this net
-            let obstacle_connections: HashSet<ConnectionID> = problem
-                .nets
-                .iter()
-                .filter(|(other_net_id, _)| **other_net_id != *net_name)
-                .flat_map(|(_, net_info)| net_info.connections.keys())
-                .cloned()
-                .collect();
-            let mut obstacle_pad_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem
-                .num_layers)
-                .map(|layer| (layer, Vec::new()))
-                .collect();
-            let mut obstacle_pad_clearance_shapes: HashMap<usize, Vec<PrimShape>> = (0
-                ..problem.num_layers)
-                .map(|layer| (layer, Vec::new()))
-                .collect();
-            for (_, net_info) in problem
-                .nets
-                .iter()
-                .filter(|(other_net_id, _)| **other_net_id != *net_name)
-            {
-                for pad in net_info.pads.values(){
-                    let pad_layers = pad.pad_layer.get_iter(problem.num_layers);
-                    for layer in pad_layers{
-                        obstacle_pad_shapes.get_mut(&layer).unwrap().extend(pad.to_shapes());
-                        obstacle_pad_clearance_shapes.get_mut(&layer).unwrap().extend(pad.to_clearance_shapes());
-                    }
-                }
-            }
-            // initialize the number of generated traces for each connection
-            let mut num_generated_traces: HashMap<ConnectionID, usize> = self
-                .connection_to_traces
-                .keys()
-                .map(|connection_id| (*connection_id, 0))
-                .collect();
-            // initialize the number of generation attempts
-            let mut num_generation_attempts: usize = 0;
-            // the inner loop for generating traces for each connection in the net
-            assert!(self.next_iteration == NonZeroUsize::new(1).unwrap() || self.next_iteration == NonZeroUsize::new(2).unwrap());
-            let max_num_traces = if self.next_iteration == NonZeroUsize::new(1).unwrap(){
-                FIRST_ITERATION_NUM_TRACES.load(Ordering::Relaxed)
-            }else{
-                SECOND_ITERATION_NUM_TRACES.load(Ordering::Relaxed)
-            };
+        let max_num_traces = sampling_round.num_traces;
 
-            // in this while loop, we will generate obstacles fosuitable for the whole net            
-            
-            // let mut connection_to_visited_traces: HashMap<ConnectionID, Vec<TracePath>> =
-            //     HashMap::new();
-            while num_generation_attempts < MAX_GENERATION_ATTEMPTS.load(Ordering::Relaxed)
-                && num_generated_traces
-                    .values()
-                    .any(|&count| count < max_num_traces)
-            {
-                let mut failed_connections: HashSet<ConnectionID> = HashSet::new();
-                // println!("Generation attempt: {}", num_generation_attempts + 1);
-                num_generation_attempts += 1;
-                let mut sampled_obstacle_traces: HashMap<ConnectionID, Option<ProbaTraceID>> =
-                    HashMap::new();
-                // randomly generate a trace for each pad pair of other nets (in a rare case the trace will not be generated)
-                for obstacle_connection_id in obstacle_connections.iter() {
-                    // sample a trace from this connection
-                    let traces = self
-                        .connection_to_traces
-                        .get(obstacle_connection_id)
-                        .expect(
-                            format!(
-                                "ConnectionID {:?} not found in connection_to_traces",
-                                obstacle_connection_id
-                            )
-                            .as_str(),
-                        );
-                    let trace_ids = if let Traces::Probabilistic(trace_ids) = traces {
-                        trace_ids.keys().cloned().collect::<Vec<ProbaTraceID>>()
-                    } else {
-                        continue; // Skip fixed traces
-                    };
-                    let mut sum_normalized_posterior: f64 = 0.0;
-                    let mut normalized_posteriors: Vec<f64> = Vec::new();
-                    for trace_id in trace_ids.iter() {
-                        let normalized_posterior =
-                            *temp_normalized_posteriors.get(trace_id).expect(
-                                format!("No normalized posterior for trace ID {:?}", trace_id)
-                                    .as_str(),
-                            );
-                        sum_normalized_posterior += normalized_posterior;
-                        normalized_posteriors.push(normalized_posterior);
-                    }
-                    // assert!(
-                    //     sum_normalized_posterior < 1.0,
-                    //     "Sum of normalized posteriors must be less than 1.0, got: {}",
-                    //     sum_normalized_posterior
-                    // );
-                    let num_trace_candidates = normalized_posteriors.len();
-                    let remaining_probability = 1.0 - sum_normalized_posterior;
-                    normalized_posteriors.push(remaining_probability);
-                    let dist = WeightedIndex::new(normalized_posteriors).unwrap();
-                    let index = dist.sample(&mut rng);
-                    let chosen_proba_trace_id: Option<ProbaTraceID> =
-                        if index < num_trace_candidates {
-                            Some(trace_ids[index])
-                        } else {
-                            None
+        // each net is sampled with its own random stream against an immutable snapshot of the model,
+        // so the sampled traces do not depend on the number of threads
+        let mut net_names: Vec<&NetName> = problem.nets.keys().collect();
+        net_names.sort();
+        let sampling_context = SamplingContext {
+            problem,
+            connection_to_traces: &self.connection_to_traces,
+            proba_traces: &proba_traces,
+            temp_normalized_posteriors: &temp_normalized_posteriors,
+            this_round_visited_traces: &this_round_visited_traces,
+            trace_cache,
+            border_colliders,
+            max_num_traces,
+            seed: current_seed(),
+        };
+        // stepping through A* needs the display, so it stays on this thread
+        let step_through_astar =
+            TARGET_COMMAND_LEVEL.load(Ordering::Relaxed) <= CommandFlag::AstarInOut.get_level();
+        let net_results: Vec<Vec<(ConnectionID, TracePath)>> = if step_through_astar {
+            net_names
+                .iter()
+                .enumerate()
+                .map(|(net_index, net_name)| {
+                    sampling_context.sample_net(net_index, net_name, display_injection)
+                })
+                .collect()
+        } else {
+            let thread_pool = sampling_thread_pool();
+            let stop_requested = display_injection.stop_requested.clone();
+            let budget = display_injection.budget.clone();
+            thread_pool.install(|| {
+                net_names
+                    .par_iter()
+                    .enumerate()
+                    .map(|(net_index, net_name)| {
+                        let mut headless_display_injection = DisplayInjection {
+                            budget: budget.clone(),
+                            ..DisplayInjection::without_display(stop_requested.clone(), false)
                         };
-                    sampled_obstacle_traces.insert(*obstacle_connection_id, chosen_proba_trace_id);
-                }
-                let mut obstacle_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem.num_layers)
-                    .map(|layer| (layer, Vec::new()))
-                    .collect();
-                let mut obstacle_clearance_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem
-                    .num_layers)
-                    .map(|layer| (layer, Vec::new()))
-                    .collect();
-                let quad_tree_side_length = f32::max(problem.width as f32, problem.height as f32);
-                let quad_tree_x_min = problem.center.x as f32 - quad_tree_side_length / 2.0;
-                let quad_tree_x_max = problem.center.x as f32 + quad_tree_side_length / 2.0;
-                let quad_tree_y_min = problem.center.y as f32 - quad_tree_side_length / 2.0;
-                let quad_tree_y_max = problem.center.y as f32 + quad_tree_side_length / 2.0;
-                let mut obstacle_colliders: HashMap<usize, QuadTreeNode> = (0..problem.num_layers)
-                    .map(|layer| {
-                        (
-                            layer,
-                            QuadTreeNode::new(
-                                quad_tree_x_min,
-                                quad_tree_x_max,
-                                quad_tree_y_min,
-                                quad_tree_y_max,
-                                0,
-                            ),
-                        )
-                    })
-                    .collect();
-                let mut obstacle_clearance_colliders: HashMap<usize, QuadTreeNode> = (0..problem
-                    .num_layers)
-                    .map(|layer| {
-                        (
-                            layer,
-                            QuadTreeNode::new(
-                                quad_tree_x_min,
-                                quad_tree_x_max,
-                                quad_tree_y_min,
-                                quad_tree_y_max,
-                                0,
-                            ),
-                        )
-                    })
-                    .collect();
-                for i in (0..problem.num_layers).into_iter() {
-                    // add pad shapes and clearance shapes
-                    let obstacle_shapes = obstacle_shapes.get_mut(&i).unwrap();
-                    let obstacle_clearance_shapes = obstacle_clearance_shapes.get_mut(&i).unwrap();
-                    let obstacle_colliders = obstacle_colliders.get_mut(&i).unwrap();
-                    let obstacle_clearance_colliders =
-                        obstacle_clearance_colliders.get_mut(&i).unwrap();
-                    for shape in obstacle_pad_shapes[&i].iter() {
-                        obstacle_shapes.push(shape.clone());
-                        let collider = Collider::from_prim_shape(shape);
-                        obstacle_colliders.insert(collider);
-                    }
-                    for clearance_shape in
-                        obstacle_pad_clearance_shapes[&i].iter()
-                    {
-                        obstacle_clearance_shapes.push(clearance_shape.clone());
-                        let clearance_collider = Collider::from_prim_shape(clearance_shape);
-                        obstacle_clearance_colliders.insert(clearance_collider);
-                    }
-                }
-                // add fixed traces to the obstacle shapes
-                for obstacle_connection_id in obstacle_connections.iter() {
-                    let traces = self
-                        .connection_to_traces
-                        .get(obstacle_connection_id)
-                        .expect(
-                            format!(
-                                "ConnectionID {:?} not found in connection_to_traces",
-                                obstacle_connection_id
-                            )
-                            .as_str(),
-                        );
-                    let fixed_trace = if let Traces::Fixed(fixed_trace) = traces {
-                        fixed_trace
-                    } else {
-                        continue; // Skip probabilistic traces
-                    };
-                    let trace_path = &fixed_trace.trace_path;
-                    let trace_shapes = trace_path.to_shapes(problem.num_layers);
-                    let trace_clearance_shapes = trace_path.to_clearance_shapes(problem.num_layers);
-                    let trace_colliders = trace_path.to_colliders(problem.num_layers);
-                    let trace_clearance_colliders = trace_path.to_clearance_colliders(problem.num_layers);
-                    for layer in (0..problem.num_layers).into_iter() {
-                        let shapes = trace_shapes.get(&layer).unwrap();
-                        let clearance_shapes = trace_clearance_shapes.get(&layer).unwrap();
-                        let colliders = trace_colliders.get(&layer).unwrap();
-                        let clearance_colliders = trace_clearance_colliders.get(&layer).unwrap();
-                        obstacle_shapes.get_mut(&layer).unwrap().extend(shapes.iter().cloned());
-                        obstacle_clearance_shapes
-                            .get_mut(&layer)
-                            .unwrap()
-                            .extend(clearance_shapes.iter().cloned());
-                        obstacle_colliders.get_mut(&layer).unwrap().extend(colliders.iter().cloned());
-                        obstacle_clearance_colliders
-                            .get_mut(&layer)
-                            .unwrap()
-                            .extend(clearance_colliders.iter().cloned());
-                    }                   
-                }
-                // add all sampled traces to the obstacle shapes
-                for (_, proba_trace_id) in sampled_obstacle_traces.iter() {
-                    let proba_trace_id = if let Some(proba_trace_id) = proba_trace_id {
-                        *proba_trace_id
-                    } else {
-                        continue; // Skip if no trace was sampled
-                    };
-                    let proba_trace = proba_traces.get(&proba_trace_id).expect(
-                        format!(
-                            "ProbaTraceID {:?} not found in proba_traces",
-                            proba_trace_id
-                        )
-                        .as_str(),
-                    );
-                    let trace_segments = &proba_trace.trace_path.segments;
-                    for segment in trace_segments.iter() {
-                        let layer = segment.layer;
-                        let shapes = segment.to_shapes();
-                        let clearance_shapes = segment.to_clearance_shapes();
-                        let obstacle_shapes = obstacle_shapes.get_mut(&layer).unwrap();
-                        let obstacle_clearance_shapes =
-                            obstacle_clearance_shapes.get_mut(&layer).unwrap();
-                        let obstacle_colliders = obstacle_colliders.get_mut(&layer).unwrap();
-                        let obstacle_clearance_colliders =
-                            obstacle_clearance_colliders.get_mut(&layer).unwrap();
-                        for shape in shapes.iter() {
-                            obstacle_shapes.push(shape.clone());
-                            let collider = Collider::from_prim_shape(shape);
-                            obstacle_colliders.insert(collider);
-                        }
-                        for clearance_shape in clearance_shapes.iter() {
-                            obstacle_clearance_shapes.push(clearance_shape.clone());
-                            let clearance_collider = Collider::from_prim_shape(clearance_shape);
-                            obstacle_clearance_colliders.insert(clearance_collider);
-                        }
-                    }
-                }                
-                let obstacle_shapes = Rc::new(obstacle_shapes);
-                let obstacle_clearance_shapes = Rc::new(obstacle_clearance_shapes);
-                let obstacle_colliders: Rc<HashMap<usize, QuadTreeNode>> =
-                    Rc::new(obstacle_colliders);
-                let obstacle_clearance_colliders: Rc<HashMap<usize, QuadTreeNode>> =
-                    Rc::new(obstacle_clearance_colliders);
-                // to do: reuse the obstacle shapes and obstacle clearance shapes
-
-                let connections = &problem
-                    .nets
-                    .get(net_name)
-                    .expect(format!("NetID {:?} not found in nets", net_name).as_str())
-                    .connections;
-                // only consider connections with probabilistic traces
-                let connections: Vec<(ConnectionID, Rc<Connection>)> = connections
-                    .iter()
-                    .filter(|(connection_id, _)| {
-                        let traces = self.connection_to_traces.get(connection_id).expect(
-                            format!(
-                                "ConnectionID {:?} not found in connection_to_traces",
-                                connection_id
-                            )
-                            .as_str(),
-                        );
-                        if let Traces::Probabilistic(_) = traces {
-                            true // Only consider connections with probabilistic traces
-                        } else {
-                            false // Skip fixed traces
-                        }
+                        sampling_context.sample_net(net_index, net_name, &mut headless_display_injection)
                     })
-                    .map(|(connection_id, connection)| (*connection_id, connection.clone()))
-                    .collect();
-
-                for (connection_id, connection) in connections.iter() {
-                    if failed_connections.contains(connection_id) {
-                        println!("ConnectionID {:?} already failed, skipping", connection_id);
-                        continue; // Skip this connection if it already failed
-                    }
-                    let connection_num_generated_traces =
-                        *num_generated_traces.get(connection_id).expect(
-                            format!(
-                                "ConnectionID {:?} not found in num_generated_traces",
-                                connection_id
-                            )
-                            .as_str(),
-                        );
-                    if connection_num_generated_traces >= max_num_traces {
-                        // println!(
-                        //     "ConnectionID {:?} already has enough traces, skipping",
-                        //     connection_id
-                        // );
-                        continue; // Skip this connection if it already has enough traces
-                    }
-                    // first check if any of the traces in connection_to_visited_traces satisfy the constraints
-                    // let current_connection_visited_traces = connection_to_visited_traces
-                    //     .entry(*connection_id)
-                    //     .or_insert_with(Vec::new);
-                    let current_connection_visited_traces = this_round_visited_traces
-                        .entry(*connection_id)
-                        .or_insert_with(Vec::new);
-                    // first check if this round's visited traces contain a trace that satisfies the constraints
-                    let mut found_satisfying_trace = false;
-                    for (i, trace_path) in current_connection_visited_traces.iter().enumerate() {
-                        let astar_check = AStarCheck {
-                            border_colliders: border_colliders.clone(),
-                            obstacle_colliders: obstacle_colliders.clone(),
-                            obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
-                            solution_trace: trace_path.clone(),
-                            num_layers: problem.num_layers,
-                        };
-                        if astar_check.check() {
-                            found_satisfying_trace = true; // the trace satisfies the constraints
-                            // println!("OK: Stored trace path {} satisfies the constraints", i);
-                            break; // we found a trace that satisfies the constraints, no need to generate a new one
-                        } else {
-                            // println!("Err: Stored trace path {} does not satisfy the constraints", i);
-                        }
-                    }
-                    if found_satisfying_trace {
-                        // println!(
-                        //     "Found a satisfying trace for ConnectionID {:?}, skipping A*",
-                        //     connection_id
-                        // );
-                        continue; // Skip this connection if a satisfying trace is found
-                    }
-                    
-                    // if not, check if the trace cache contains a trace that satisfies the constraints
-                    // if not, we will generate a new trace
-                    // in both cases, the trace will be added to proba traces
-                    let mut cached_trace: Option<TracePath> = None;
-                    let current_connection_trace_cache = trace_cache.traces.get_mut(connection_id).unwrap();
-                    for trace_path in current_connection_trace_cache.iter() {                        
-                        let astar_check = AStarCheck{
-                            border_colliders: border_colliders.clone(),
-                            obstacle_colliders: obstacle_colliders.clone(),
-                            obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
-                            solution_trace: trace_path.clone(),
-                            num_layers: problem.num_layers,
-                        };
-                        if astar_check.check() {
-                            // println!("Cache Hit!");                            
-                            cached_trace = Some(trace_path.clone());
-                            
-                            break; // we found a trace that satisfies the constraints, no need to generate a new one
-                        }else{
-                           //  println!("Cache Miss!");
-                        }
-                    }
-                    let trace_path = if let Some(generated_trace) = cached_trace {
-                        // we found a trace that satisfies the constraints, use it
-                        generated_trace
-                    } else {
-                        // prepare for the a star model
-                        let start_pad = net_info.pads.get(&connection.start_pad).unwrap();
-                        let end_pad = net_info.pads.get(&connection.end_pad).unwrap();
-                        let start = start_pad.position.to_fixed().to_nearest_even_even();
-                        let end = end_pad.position.to_fixed().to_nearest_even_even();
-                        let start_layers = start_pad.pad_layer;
-                        let end_layers = end_pad.pad_layer;
-                        let astar_model = AStarModel {
-                            width: problem.width,
-                            height: problem.height,
-                            center: problem.center,
-                            obstacle_shapes: obstacle_shapes.clone(),
-                            obstacle_clearance_shapes: obstacle_clearance_shapes.clone(),
-                            obstacle_colliders: obstacle_colliders.clone(),
-                            obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
-                            start,
-                            end,
-                            start_layers,
-                            end_layers,
-                            num_layers: problem.num_layers,
-                            trace_width: net_info.trace_width,
-                            trace_clearance: net_info.trace_clearance,
-                            via_diameter: net_info.via_diameter,
-                            border_colliders_cache: RefCell::new(None), // Cache for border points, initialized to None
-                            border_shapes_cache: RefCell::new(None), // Cache for border shapes, initialized to None
-                        };
-                        // run A* algorithm to find a path
-                        NUM_BAYESIAN_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
-                        let astar_result = astar_model.run(display_injection);
-                        let astar_result = match astar_result {
-                            Ok(result) => result,
-                            Err(err) => {
-                                println!("A* algorithm failed: {}", err);
-                                failed_connections.insert(*connection_id);
-                                continue; // Skip this connection if A* fails
-                            }
-                        };
-                        let trace_path = astar_result.trace_path;
-                        trace_path
-                    };
-
-                    // assert!(!visited_traces.contains(&trace_path.anchors), "Trace path is supposed to be a new one generated by A*");       
+                    .collect()
+            })
+        };
 
-                    visited_traces.insert(trace_path.anchors.clone());
-                    current_connection_visited_traces.push(trace_path.clone());
-                    current_connection_trace_cache.push(trace_path.clone());
-
-                    let proba_trace_id = self
-                        .trace_id_generator
-                        .next()
-                        .expect("TraceID generator exhausted");
-                    let proba_trace = ProbaTrace {
-                        net_name: net_name.clone(),
-                        connection_id: *connection_id,
-                        proba_trace_id,
-                        trace_path,
-                        iteration: self.next_iteration,
-                        posterior: RefCell::new(None), // Initialize with None, will be updated later
-                        temp_posterior: RefCell::new(None), // Temporary posterior for simultaneous updates
-                    };
-                    new_proba_traces.push(Rc::new(proba_trace));
-                    let num = num_generated_traces.get_mut(connection_id).expect(
-                        format!(
-                            "ConnectionID {:?} not found in num_generated_traces",
-                            connection_id
-                        )
-                        .as_str(),
-                    );
-                    *num += 1;
+        // merge in net order so that trace ids are assigned deterministically
+        let trace_scorer = trace_scorer();
+        for (net_name, net_result) in net_names.iter().zip(net_results) {
+            for (connection_id, trace_path) in net_result {
+                // the same anchors sampled again would only split the posterior of the first copy
+                if !visited_traces.insert(trace_path.anchors.clone()) {
+                    continue;
                 }
+                trace_cache
+                    .traces
+                    .get_mut(&connection_id)
+                    .unwrap()
+                    .push(trace_path.clone());
+                let proba_trace_id = self
+                    .trace_id_generator
+                    .next()
+                    .expect("TraceID generator exhausted");
+                let score = trace_scorer.score(&trace_path, &ScoringContext { problem, net_name });
+                let proba_trace = ProbaTrace {
+                    net_name: (*net_name).clone(),
+                    connection_id,
+                    proba_trace_id,
+                    trace_path,
+                    score,
+                    iteration: self.next_iteration,
+                    prior: sampling_round.prior,
+                    posterior: Mutex::new(None), // Initialize with None, will be updated later
+                    temp_posterior: Mutex::new(None), // Temporary posterior for simultaneous updates
+                };
+                new_proba_traces.push(Arc::new(proba_trace));
             }
         }
 
@@ -710,7 +457,7 @@ impl ProbaModel {
         }
 
         // update proba_traces to include the new traces
-        let mut proba_traces: HashMap<ProbaTraceID, Rc<ProbaTrace>> = HashMap::new();
+        let mut proba_traces: HashMap<ProbaTraceID, Arc<ProbaTrace>> = HashMap::new();
         for traces in self.connection_to_traces.values() {
             if let Traces::Probabilistic(trace_map) = traces {
                 for (proba_trace_id, proba_trace) in trace_map.iter() {
@@ -875,9 +622,21 @@ impl ProbaModel {
         }
     }
 
-    pub fn update_posterior(&mut self) {
-        let proba_traces: HashMap<ProbaTraceID, Rc<ProbaTrace>> = self
-            .connection_to_traces
+    /// the traces of the connections that are not fixed
+    /// the largest change of a posterior since the given ones, traces without one are skipped
+    fn max_posterior_change(&self, previous_posteriors: &HashMap<ProbaTraceID, f64>) -> f64 {
+        self.proba_traces()
+            .iter()
+            .filter_map(|(proba_trace_id, proba_trace)| {
+                previous_posteriors
+                    .get(proba_trace_id)
+                    .map(|previous_posterior| (proba_trace.get_posterior_with_fallback() - previous_posterior).abs())
+            })
+            .fold(0.0, f64::max)
+    }
+
+    pub fn proba_traces(&self) -> HashMap<ProbaTraceID, Arc<ProbaTrace>> {
+        self.connection_to_traces
             .values()
             .filter_map(|traces| {
                 if let Traces::Probabilistic(trace_map) = traces {
@@ -887,7 +646,12 @@ impl ProbaModel {
                 }
             })
             .flatten()
-            .collect();
+            .collect()
+    }
+
+    /// one synchronous fixed point step of the posteriors, returns the largest change of a posterior
+    pub fn update_posterior(&self) -> f64 {
+        let proba_traces = self.proba_traces();
         // Update the posterior probabilities for all traces in the model
         for (proba_trace_id, proba_trace) in proba_traces.iter() {
             let adjacent_traces = self.collision_adjacency.get(proba_trace_id).expect(
@@ -914,11 +678,7 @@ impl ProbaModel {
             let current_posterior = proba_trace.get_posterior_with_fallback();
             // let opportunity_cost = target_posterior / current_posterior;
 
-            let score = proba_trace.trace_path.get_score();
-            assert!(score >= 0.0 && score <= 1.0,
-                "Score must be between 0 and 1, got: {}",
-                score
-            );
+            let score = proba_trace.score;
             // let score_weight = *SCORE_WEIGHT.lock().unwrap();
             // let opportunity_cost_weight = *OPPORTUNITY_COST_WEIGHT.lock().unwrap();
             let k = f64::ln(2.0) / HALF_PROBABILITY_OPPORTUNITY_COST.load(Ordering::Relaxed);
@@ -956,17 +716,626 @@ impl ProbaModel {
                 "New posterior must be between 0 and 1, got: {}",
                 new_posterior
             );
-            let mut temp_posterior = proba_trace.temp_posterior.borrow_mut();
+            let mut temp_posterior = proba_trace.temp_posterior.lock().unwrap();
             *temp_posterior = Some(new_posterior);
         }
         // move temp_posterior to posterior
+        let mut max_change: f64 = 0.0;
         for (_, proba_trace) in proba_traces.iter() {
-            let mut posterior = proba_trace.posterior.borrow_mut();
-            let mut temp_posterior = proba_trace.temp_posterior.borrow_mut();
+            let old_posterior = proba_trace.get_posterior_with_fallback();
+            let mut posterior = proba_trace.posterior.lock().unwrap();
+            let mut temp_posterior = proba_trace.temp_posterior.lock().unwrap();
             let temp_posterior_val = temp_posterior.unwrap();
+            max_change = max_change.max((temp_posterior_val - old_posterior).abs());
             *posterior = Some(temp_posterior_val);
             // reset temp_posterior
             *temp_posterior = None;
         }
+        max_change
+    }
+}
+
+// read-only view of the model shared by the sampling threads
+struct SamplingContext<'a> {
+    problem: &'a PcbProblem,
+    connection_to_traces: &'a HashMap<ConnectionID, Traces>,
+    proba_traces: &'a HashMap<ProbaTraceID, Arc<ProbaTrace>>,
+    temp_normalized_posteriors: &'a HashMap<ProbaTraceID, f64>,
+    this_round_visited_traces: &'a HashMap<ConnectionID, Vec<TracePath>>,
+    trace_cache: &'a TraceCache,
+    border_colliders: Arc<Vec<Collider>>,
+    max_num_traces: usize,
+    seed: u64, // the seed of the solve, the sampling threads cannot read it from their own thread
+}
+
+impl SamplingContext<'_> {
+    /// generates new traces for the connections of one net, in the order they were generated
+    fn sample_net(
+        &self,
+        net_index: usize,
+        net_name: &NetName,
+        display_injection: &mut DisplayInjection,
+    ) -> Vec<(ConnectionID, TracePath)> {
+        let problem = self.problem;
+        let net_info = problem
+            .nets
+            .get(net_name)
+            .expect(format!("NetID {:?} not found in nets", net_name).as_str());
+        let mut rng = create_deterministic_rng_for_stream(self.seed, net_index as u64);
+        // collect connections that are not in this net, sorted so that the random stream is consumed in a fixed order
+        let obstacle_connections: BTreeSet<ConnectionID> = problem
+            .nets
+            .iter()
+            .filter(|(other_net_id, _)| **other_net_id != *net_name)
+            .flat_map(|(_, net_info)| net_info.connections.keys())
+            .cloned()
+            .collect();
+        let mut obstacle_pad_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem
+            .num_layers)
+            .map(|layer| (layer, Vec::new()))
+            .collect();
+        let mut obstacle_pad_clearance_shapes: HashMap<usize, Vec<PrimShape>> = (0
+            ..problem.num_layers)
+            .map(|layer| (layer, Vec::new()))
+            .collect();
+        for (_, net_info) in problem
+            .nets
+            .iter()
+            .filter(|(other_net_id, _)| **other_net_id != *net_name)
+        {
+            for pad in net_info.pads.values(){
+                let pad_layers = pad.pad_layer.get_iter(problem.num_layers);
+                for layer in pad_layers{
+                    obstacle_pad_shapes.get_mut(&layer).unwrap().extend(pad.to_shapes());
+                    obstacle_pad_clearance_shapes.get_mut(&layer).unwrap().extend(pad.to_clearance_shapes());
+                }
+            }
+        }
+        // only consider connections with probabilistic traces
+        let mut connections: Vec<(ConnectionID, Arc<Connection>)> = net_info
+            .connections
+            .iter()
+            .filter(|(connection_id, _)| {
+                let traces = self.connection_to_traces.get(connection_id).expect(
+                    format!(
+                        "ConnectionID {:?} not found in connection_to_traces",
+                        connection_id
+                    )
+                    .as_str(),
+                );
+                matches!(traces, Traces::Probabilistic(_))
+            })
+            .map(|(connection_id, connection)| (*connection_id, connection.clone()))
+            .collect();
+        connections.sort_by_key(|(connection_id, _)| *connection_id);
+        // this net's share of the visited traces, extended locally as new traces are found
+        let mut this_round_visited_traces: HashMap<ConnectionID, Vec<TracePath>> = connections
+            .iter()
+            .map(|(connection_id, _)| {
+                let visited_traces = self
+                    .this_round_visited_traces
+                    .get(connection_id)
+                    .cloned()
+                    .unwrap_or_default();
+                (*connection_id, visited_traces)
+            })
+            .collect();
+        // initialize the number of generated traces for each connection
+        let mut num_generated_traces: HashMap<ConnectionID, usize> = connections
+            .iter()
+            .map(|(connection_id, _)| (*connection_id, 0))
+            .collect();
+        let mut new_traces: Vec<(ConnectionID, TracePath)> = Vec::new();
+        let cell_size: f32 = ASTAR_STRIDE.lock().unwrap().to_num();
+        let diversity_penalty = DIVERSITY_PENALTY.load(Ordering::Relaxed);
+        let diversity_alternatives = DIVERSITY_ALTERNATIVES.load(Ordering::Relaxed);
+        let diversity_min_distance = DIVERSITY_MIN_DISTANCE.load(Ordering::Relaxed) as f32;
+        // initialize the number of generation attempts
+        let mut num_generation_attempts: usize = 0;
+
+        // in this while loop, we will generate obstacles fosuitable for the whole net
+        while num_generation_attempts < MAX_GENERATION_ATTEMPTS.load(Ordering::Relaxed)
+            && num_generated_traces
+                .values()
+                .any(|&count| count < self.max_num_traces)
+        {
+            let mut failed_connections: HashSet<ConnectionID> = HashSet::new();
+            num_generation_attempts += 1;
+            let mut sampled_obstacle_traces: BTreeMap<ConnectionID, Option<ProbaTraceID>> =
+                BTreeMap::new();
+            // randomly generate a trace for each pad pair of other nets (in a rare case the trace will not be generated)
+            for obstacle_connection_id in obstacle_connections.iter() {
+                // sample a trace from this connection
+                let traces = self
+                    .connection_to_traces
+                    .get(obstacle_connection_id)
+                    .expect(
+                        format!(
+                            "ConnectionID {:?} not found in connection_to_traces",
+                            obstacle_connection_id
+                        )
+                        .as_str(),
+                    );
+                let mut trace_ids = if let Traces::Probabilistic(trace_ids) = traces {
+                    trace_ids.keys().cloned().collect::<Vec<ProbaTraceID>>()
+                } else {
+                    continue; // Skip fixed traces
+                };
+                trace_ids.sort();
+                let mut sum_normalized_posterior: f64 = 0.0;
+                let mut normalized_posteriors: Vec<f64> = Vec::new();
+                for trace_id in trace_ids.iter() {
+                    let normalized_posterior =
+                        *self.temp_normalized_posteriors.get(trace_id).expect(
+                            format!("No normalized posterior for trace ID {:?}", trace_id)
+                                .as_str(),
+                        );
+                    sum_normalized_posterior += normalized_posterior;
+                    normalized_posteriors.push(normalized_posterior);
+                }
+                let num_trace_candidates = normalized_posteriors.len();
+                let remaining_probability = 1.0 - sum_normalized_posterior;
+                normalized_posteriors.push(remaining_probability);
+                let dist = WeightedIndex::new(normalized_posteriors).unwrap();
+                let index = dist.sample(&mut rng);
+                let chosen_proba_trace_id: Option<ProbaTraceID> =
+                    if index < num_trace_candidates {
+                        Some(trace_ids[index])
+                    } else {
+                        None
+                    };
+                sampled_obstacle_traces.insert(*obstacle_connection_id, chosen_proba_trace_id);
+            }
+            let mut obstacle_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem.num_layers)
+                .map(|layer| (layer, Vec::new()))
+                .collect();
+            let mut obstacle_clearance_shapes: HashMap<usize, Vec<PrimShape>> = (0..problem
+                .num_layers)
+                .map(|layer| (layer, Vec::new()))
+                .collect();
+            let quad_tree_side_length = f32::max(problem.width as f32, problem.height as f32);
+            let quad_tree_x_min = problem.center.x as f32 - quad_tree_side_length / 2.0;
+            let quad_tree_x_max = problem.center.x as f32 + quad_tree_side_length / 2.0;
+            let quad_tree_y_min = problem.center.y as f32 - quad_tree_side_length / 2.0;
+            let quad_tree_y_max = problem.center.y as f32 + quad_tree_side_length / 2.0;
+            let mut obstacle_colliders: HashMap<usize, QuadTreeNode> = (0..problem.num_layers)
+                .map(|layer| {
+                    (
+                        layer,
+                        QuadTreeNode::new(
+                            quad_tree_x_min,
+                            quad_tree_x_max,
+                            quad_tree_y_min,
+                            quad_tree_y_max,
+                            0,
+                        ),
+                    )
+                })
+                .collect();
+            let mut obstacle_clearance_colliders: HashMap<usize, QuadTreeNode> = (0..problem
+                .num_layers)
+                .map(|layer| {
+                    (
+                        layer,
+                        QuadTreeNode::new(
+                            quad_tree_x_min,
+                            quad_tree_x_max,
+                            quad_tree_y_min,
+                            quad_tree_y_max,
+                            0,
+                        ),
+                    )
+                })
+                .collect();
+            for i in 0..problem.num_layers {
+                // add pad shapes and clearance shapes
+                let obstacle_shapes = obstacle_shapes.get_mut(&i).unwrap();
+                let obstacle_clearance_shapes = obstacle_clearance_shapes.get_mut(&i).unwrap();
+                let obstacle_colliders = obstacle_colliders.get_mut(&i).unwrap();
+                let obstacle_clearance_colliders =
+                    obstacle_clearance_colliders.get_mut(&i).unwrap();
+                for shape in obstacle_pad_shapes[&i].iter() {
+                    obstacle_shapes.push(shape.clone());
+                    let collider = Collider::from_prim_shape(shape);
+                    obstacle_colliders.insert(collider);
+                }
+                for clearance_shape in
+                    obstacle_pad_clearance_shapes[&i].iter()
+                {
+                    obstacle_clearance_shapes.push(clearance_shape.clone());
+                    let clearance_collider = Collider::from_prim_shape(clearance_shape);
+                    obstacle_clearance_colliders.insert(clearance_collider);
+                }
+            }
+            // add fixed traces to the obstacle shapes
+            for obstacle_connection_id in obstacle_connections.iter() {
+                let traces = self
+                    .connection_to_traces
+                    .get(obstacle_connection_id)
+                    .expect(
+                        format!(
+                            "ConnectionID {:?} not found in connection_to_traces",
+                            obstacle_connection_id
+                        )
+                        .as_str(),
+                    );
+                let fixed_trace = if let Traces::Fixed(fixed_trace) = traces {
+                    fixed_trace
+                } else {
+                    continue; // Skip probabilistic traces
+                };
+                let trace_path = &fixed_trace.trace_path;
+                let trace_shapes = trace_path.to_shapes(problem.num_layers);
+                let trace_clearance_shapes = trace_path.to_clearance_shapes(problem.num_layers);
+                let trace_colliders = trace_path.to_colliders(problem.num_layers);
+                let trace_clearance_colliders = trace_path.to_clearance_colliders(problem.num_layers);
+                for layer in 0..problem.num_layers {
+                    let shapes = trace_shapes.get(&layer).unwrap();
+                    let clearance_shapes = trace_clearance_shapes.get(&layer).unwrap();
+                    let colliders = trace_colliders.get(&layer).unwrap();
+                    let clearance_colliders = trace_clearance_colliders.get(&layer).unwrap();
+                    obstacle_shapes.get_mut(&layer).unwrap().extend(shapes.iter().cloned());
+                    obstacle_clearance_shapes
+                        .get_mut(&layer)
+                        .unwrap()
+                        .extend(clearance_shapes.iter().cloned());
+                    obstacle_colliders.get_mut(&layer).unwrap().extend(colliders.iter().cloned());
+                    obstacle_clearance_colliders
+                        .get_mut(&layer)
+                        .unwrap()
+                        .extend(clearance_colliders.iter().cloned());
+                }
+            }
+            // add all sampled traces to the obstacle shapes
+            for (_, proba_trace_id) in sampled_obstacle_traces.iter() {
+                let proba_trace_id = if let Some(proba_trace_id) = proba_trace_id {
+                    *proba_trace_id
+                } else {
+                    continue; // Skip if no trace was sampled
+                };
+                let proba_trace = self.proba_traces.get(&proba_trace_id).expect(
+                    format!(
+                        "ProbaTraceID {:?} not found in proba_traces",
+                        proba_trace_id
+                    )
+                    .as_str(),
+                );
+                let trace_segments = &proba_trace.trace_path.segments;
+                for segment in trace_segments.iter() {
+                    let layer = segment.layer;
+                    let shapes = segment.to_shapes();
+                    let clearance_shapes = segment.to_clearance_shapes();
+                    let obstacle_shapes = obstacle_shapes.get_mut(&layer).unwrap();
+                    let obstacle_clearance_shapes =
+                        obstacle_clearance_shapes.get_mut(&layer).unwrap();
+                    let obstacle_colliders = obstacle_colliders.get_mut(&layer).unwrap();
+                    let obstacle_clearance_colliders =
+                        obstacle_clearance_colliders.get_mut(&layer).unwrap();
+                    for shape in shapes.iter() {
+                        obstacle_shapes.push(shape.clone());
+                        let collider = Collider::from_prim_shape(shape);
+                        obstacle_colliders.insert(collider);
+                    }
+                    for clearance_shape in clearance_shapes.iter() {
+                        obstacle_clearance_shapes.push(clearance_shape.clone());
+                        let clearance_collider = Collider::from_prim_shape(clearance_shape);
+                        obstacle_clearance_colliders.insert(clearance_collider);
+                    }
+                }
+            }
+            let obstacle_shapes = Arc::new(obstacle_shapes);
+            let obstacle_clearance_shapes = Arc::new(obstacle_clearance_shapes);
+            let obstacle_colliders: Arc<HashMap<usize, QuadTreeNode>> =
+                Arc::new(obstacle_colliders);
+            let obstacle_clearance_colliders: Arc<HashMap<usize, QuadTreeNode>> =
+                Arc::new(obstacle_clearance_colliders);
+
+            for (connection_id, connection) in connections.iter() {
+                if failed_connections.contains(connection_id) {
+                    println!("ConnectionID {:?} already failed, skipping", connection_id);
+                    continue; // Skip this connection if it already failed
+                }
+                let connection_num_generated_traces =
+                    *num_generated_traces.get(connection_id).expect(
+                        format!(
+                            "ConnectionID {:?} not found in num_generated_traces",
+                            connection_id
+                        )
+                        .as_str(),
+                    );
+                if connection_num_generated_traces >= self.max_num_traces {
+                    continue; // Skip this connection if it already has enough traces
+                }
+                let current_connection_visited_traces = this_round_visited_traces
+                    .get_mut(connection_id)
+                    .unwrap();
+                // first check if this round's visited traces contain a trace that satisfies the constraints
+                let mut found_satisfying_trace = false;
+                for trace_path in current_connection_visited_traces.iter() {
+                    let astar_check = AStarCheck {
+                        border_colliders: self.border_colliders.clone(),
+                        obstacle_colliders: obstacle_colliders.clone(),
+                        obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
+                        solution_trace: trace_path.clone(),
+                        num_layers: problem.num_layers,
+                    };
+                    if astar_check.check() {
+                        found_satisfying_trace = true; // the trace satisfies the constraints
+                        break; // we found a trace that satisfies the constraints, no need to generate a new one
+                    }
+                }
+                if found_satisfying_trace {
+                    continue; // Skip this connection if a satisfying trace is found
+                }
+
+                // if not, check if the trace cache contains a trace that satisfies the constraints
+                // if not, we will generate a new trace
+                // in both cases, the trace will be added to proba traces
+                let mut cached_trace: Option<TracePath> = None;
+                let current_connection_trace_cache = self.trace_cache.traces.get(connection_id).unwrap();
+                for trace_path in current_connection_trace_cache.iter() {
+                    let astar_check = AStarCheck{
+                        border_colliders: self.border_colliders.clone(),
+                        obstacle_colliders: obstacle_colliders.clone(),
+                        obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
+                        solution_trace: trace_path.clone(),
+                        num_layers: problem.num_layers,
+                    };
+                    if astar_check.check() {
+                        cached_trace = Some(trace_path.clone());
+                        break; // we found a trace that satisfies the constraints, no need to generate a new one
+                    }
+                }
+                // prepare for the a star model
+                let start_pad = net_info.pads.get(&connection.start_pad).unwrap();
+                let end_pad = net_info.pads.get(&connection.end_pad).unwrap();
+                let start = start_pad.position.to_fixed().to_nearest_even_even();
+                let end = end_pad.position.to_fixed().to_nearest_even_even();
+                let start_layers = start_pad.pad_layer;
+                let end_layers = end_pad.pad_layer;
+                // the earlier candidates of the connection are penalized, so the search looks for an alternative
+                let build_astar_model = |earlier_candidates: &[TracePath]| {
+                    let cost_map: Option<Rc<dyn AStarCostMap>> = if diversity_penalty > 0.0 && !earlier_candidates.is_empty() {
+                        Some(Rc::new(DiversityCostMap::new(earlier_candidates, cell_size, diversity_penalty)))
+                    } else {
+                        None
+                    };
+                    AStarModel {
+                        width: problem.width,
+                        height: problem.height,
+                        center: problem.center,
+                        obstacle_shapes: obstacle_shapes.clone(),
+                        obstacle_clearance_shapes: obstacle_clearance_shapes.clone(),
+                        obstacle_colliders: obstacle_colliders.clone(),
+                        obstacle_clearance_colliders: obstacle_clearance_colliders.clone(),
+                        start,
+                        end,
+                        start_layers,
+                        end_layers,
+                        num_layers: problem.num_layers,
+                        trace_width: net_info.trace_width,
+                        trace_clearance: net_info.trace_clearance,
+                        via_types: net_info.via_types.clone(),
+                        border_colliders_cache: RefCell::new(None), // Cache for border points, initialized to None
+                        border_shapes_cache: RefCell::new(None), // Cache for border shapes, initialized to None
+                        cost_map,
+                        blocking_colliders: RefCell::new(None),
+                    }
+                };
+                let trace_path = if let Some(generated_trace) = cached_trace {
+                    // we found a trace that satisfies the constraints, use it
+                    generated_trace
+                } else {
+                    let astar_model = build_astar_model(current_connection_visited_traces);
+                    // run A* algorithm to find a path
+                    NUM_BAYESIAN_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
+                    let astar_result = astar_model.run(display_injection);
+                    match astar_result {
+                        Ok(result) => result.trace_path,
+                        Err(err) => {
+                            println!("A* algorithm failed: {}", err);
+                            failed_connections.insert(*connection_id);
+                            continue; // Skip this connection if A* fails
+                        }
+                    }
+                };
+
+                current_connection_visited_traces.push(trace_path.clone());
+                new_traces.push((*connection_id, trace_path));
+                let num = num_generated_traces.get_mut(connection_id).expect(
+                    format!(
+                        "ConnectionID {:?} not found in num_generated_traces",
+                        connection_id
+                    )
+                    .as_str(),
+                );
+                *num += 1;
+
+                // alternatives under the same obstacles, each avoiding the candidates before it
+                let num_alternatives = usize::min(diversity_alternatives, self.max_num_traces - *num);
+                for _ in 0..num_alternatives {
+                    let astar_model = build_astar_model(current_connection_visited_traces);
+                    NUM_BAYESIAN_PATH_FINDING_CALLS.fetch_add(1, Ordering::Relaxed);
+                    let trace_path = match astar_model.run(display_injection) {
+                        Ok(result) => result.trace_path,
+                        Err(err) => {
+                            println!("A* algorithm failed to find an alternative: {}", err);
+                            break;
+                        }
+                    };
+                    if is_near_duplicate(&trace_path, current_connection_visited_traces, diversity_min_distance, cell_size) {
+                        println!("Alternative for ConnectionID {:?} is a near duplicate, dropping it", connection_id);
+                        break; // the penalty did not push the search anywhere new
+                    }
+                    current_connection_visited_traces.push(trace_path.clone());
+                    new_traces.push((*connection_id, trace_path));
+                    *num += 1;
+                }
+            }
+        }
+        new_traces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, atomic::{AtomicBool, Ordering}},
+    };
+
+    use parser::parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end};
+    use shared::{
+        hyperparameters::{SamplingRound, SAMPLING_THREADS},
+        pcb_problem::{ConnectionID, NetName, PcbProblem},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath, Via},
+        trace_scorer::{BendTerm, ClearanceMarginTerm, LengthTerm, ScoringContext, TraceScorer, ViaTerm, WeightedScorer},
+        vec2::FloatVec2,
+    };
+
+    use super::ProbaModel;
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        test_pcb_problem::examples::{example_problem, AutoCommandLevel},
+    };
+
+    /// puts the number of sampling threads back when dropped
+    struct RestoreSamplingThreads(usize);
+
+    impl Drop for RestoreSamplingThreads {
+        fn drop(&mut self) {
+            SAMPLING_THREADS.store(self.0, Ordering::Relaxed);
+        }
+    }
+
+    /// the anchors of every trace sampled for the board, in the order they were cached
+    fn sampled_anchors(problem: &PcbProblem, num_threads: usize) -> HashMap<ConnectionID, Vec<TraceAnchors>> {
+        let mut trace_cache = TraceCache::default();
+        for net_info in problem.nets.values() {
+            for connection_id in net_info.connections.keys() {
+                trace_cache.traces.insert(*connection_id, Vec::new());
+            }
+        }
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), false);
+        let _restore_sampling_threads = RestoreSamplingThreads(SAMPLING_THREADS.swap(num_threads, Ordering::Relaxed));
+        ProbaModel::create_and_solve(problem, &HashMap::new(), Vec::new(), &mut trace_cache, &mut display_injection).unwrap();
+        trace_cache
+            .traces
+            .into_iter()
+            .map(|(connection_id, trace_paths)| {
+                (connection_id, trace_paths.into_iter().map(|trace_path| trace_path.anchors).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sampling_does_not_depend_on_the_number_of_threads() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        // connection ids are handed out while parsing, so both runs share one parse
+        let dsn_struct = parse_start_to_dsn_struct(include_str!("../../examples/ping.dsn").to_string()).unwrap();
+        let problem = parse_struct_to_end(&dsn_struct).unwrap();
+        let single_threaded = sampled_anchors(&problem, 1);
+        assert!(single_threaded.values().any(|anchors| !anchors.is_empty()));
+        assert_eq!(single_threaded, sampled_anchors(&problem, 4));
+    }
+
+    #[test]
+    fn the_schedule_stops_early_once_the_posteriors_are_stable() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 2);
+        let sampling_schedule = vec![SamplingRound { num_traces: 1, prior: 0.8 }; 4];
+        let num_rounds_run = |stable_tolerance: f64| {
+            let mut trace_cache = TraceCache::default();
+            for net_info in problem.nets.values() {
+                for connection_id in net_info.connections.keys() {
+                    trace_cache.traces.insert(*connection_id, Vec::new());
+                }
+            }
+            let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), false);
+            let proba_model = ProbaModel::create_and_solve_with_schedule(
+                &problem,
+                &HashMap::new(),
+                Vec::new(),
+                &mut trace_cache,
+                &mut display_injection,
+                &sampling_schedule,
+                stable_tolerance,
+            )
+            .unwrap();
+            proba_model.inference_diagnostics.len()
+        };
+        // any change is below an infinite tolerance, the first round has nothing to compare with
+        assert_eq!(num_rounds_run(f64::INFINITY), 2);
+        assert_eq!(num_rounds_run(0.0), sampling_schedule.len());
+    }
+
+    /// a trace of width and clearance 0.2 through the points, (x, y, layer), with a via at every layer change
+    fn trace_through(points: &[(f32, f32, usize)]) -> TracePath {
+        let mut anchors: Vec<TraceAnchor> = Vec::new();
+        let mut vias: Vec<Via> = Vec::new();
+        for (index, &(x, y, layer)) in points.iter().enumerate() {
+            let position = FloatVec2::new(x, y).to_fixed();
+            let next_layer = points.get(index + 1).map_or(layer, |next| next.2);
+            if next_layer != layer {
+                vias.push(Via {
+                    position,
+                    diameter: 0.6,
+                    clearance: 0.2,
+                    min_layer: layer.min(next_layer),
+                    max_layer: layer.max(next_layer),
+                    padstack: String::new(),
+                });
+            }
+            anchors.push(TraceAnchor { position, start_layer: layer, end_layer: next_layer });
+        }
+        TracePath::from_anchors(TraceAnchors(anchors), 0.2, 0.2, vias)
+    }
+
+    /// the better trace has a lower raw score and a higher score under the term
+    fn assert_ranks_ahead(term: &dyn TraceScorer, better: &TracePath, worse: &TracePath, context: &ScoringContext) {
+        assert!(term.raw_score(better, context) < term.raw_score(worse, context));
+        assert!(term.score(better, context) > term.score(worse, context));
+    }
+
+    #[test]
+    fn each_term_ranks_the_better_of_two_traces_ahead() {
+        let problem = example_problem(include_str!("../../examples/diff_pair.dsn"), 2);
+        let net_name = NetName("USB_P".to_string());
+        let context = ScoringContext { problem: &problem, net_name: &net_name };
+
+        let straight = trace_through(&[(0.0, 0.0, 0), (4.0, 0.0, 0)]);
+        let detour = trace_through(&[(0.0, 0.0, 0), (2.0, 2.0, 0), (4.0, 0.0, 0)]);
+        assert_ranks_ahead(&LengthTerm, &straight, &detour, &context);
+        assert_ranks_ahead(&BendTerm, &straight, &detour, &context);
+
+        let through_the_back = trace_through(&[(0.0, 0.0, 0), (2.0, 0.0, 1), (4.0, 0.0, 0)]);
+        assert_eq!(LengthTerm.raw_score(&straight, &context), LengthTerm.raw_score(&through_the_back, &context));
+        assert_ranks_ahead(&ViaTerm, &straight, &through_the_back, &context);
+
+        // a trace of USB_P passes the USB_N pad of J1 on the side away from the USB_P pad
+        let pad_of = |net: &str| {
+            let pads = &problem.nets[&NetName(net.to_string())].pads;
+            pads.values().min_by(|pad, other| pad.position.x.total_cmp(&other.position.x)).unwrap().position
+        };
+        let (own_pad, other_pad) = (pad_of("USB_P"), pad_of("USB_N"));
+        let away = (other_pad.y - own_pad.y).signum();
+        let passing_at = |offset: f32| {
+            let y = other_pad.y + away * offset;
+            trace_through(&[(other_pad.x - 1.0, y, 0), (other_pad.x + 1.0, y, 0)])
+        };
+        // the pad reaches 0.3 from its center, the grown clearance of the trace 0.1 + 0.2 + 0.3 from its center line
+        let wide_margin = passing_at(1.5);
+        let narrow_margin = passing_at(0.7);
+        let margin_term = ClearanceMarginTerm::new(0.3);
+        assert_eq!(margin_term.raw_score(&narrow_margin, &context), 1.0);
+        assert_ranks_ahead(&margin_term, &wide_margin, &narrow_margin, &context);
+        // its own pads are never in its way
+        let own_context = ScoringContext { problem: &problem, net_name: &NetName("USB_N".to_string()) };
+        assert_eq!(margin_term.raw_score(&narrow_margin, &own_context), 0.0);
+
+        let weighted = WeightedScorer::default().with_term(1.0, LengthTerm).with_term(5.0, ViaTerm);
+        assert_ranks_ahead(&weighted, &straight, &through_the_back, &context);
+        assert_ranks_ahead(&weighted, &straight, &detour, &context);
     }
 }
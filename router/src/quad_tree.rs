@@ -8,6 +8,7 @@ use shared::{
 const MAX_OBJECTS: usize = 4;
 const MAX_DEPTH: usize = 10;
 
+#[derive(Clone)]
 pub struct QuadTreeChildren {
     top_left: Box<QuadTreeNode>,
     top_right: Box<QuadTreeNode>,
@@ -83,6 +84,7 @@ impl QuadTreeChildren {
         false
     }
 }
+#[derive(Clone)]
 pub struct QuadTreeNode {
     pub depth: usize,
     pub x_min: f32,
@@ -196,6 +198,20 @@ impl QuadTreeNode {
             self.insert(collider);
         }
     }
+    /// removes one collider equal to the given one, false if there is none
+    pub fn remove(&mut self, collider: &Collider) -> bool {
+        if !self.fully_contained_in_boundary(collider) {
+            return false; // insert never keeps a shape outside this node's boundary
+        }
+        if let Some(index) = self.objects.iter().position(|object| object == collider) {
+            self.objects.swap_remove(index);
+            return true;
+        }
+        match self.children.as_mut() {
+            Some(children) => children.iter_mut().any(|child| child.remove(collider)),
+            None => false,
+        }
+    }
 
     pub fn collides_with(&self, collider: &Collider) -> bool {
         // query all the shapes that have a potential to collide with the given shape
@@ -216,6 +232,22 @@ impl QuadTreeNode {
         }
         false
     }
+    /// pushes every stored collider that collides with the given one and is not in found yet
+    pub fn colliding_objects(&self, collider: &Collider, found: &mut Vec<Collider>) {
+        if !self.partially_contained_in_boundary(collider) {
+            return;
+        }
+        for object in &self.objects {
+            if object.collides_with(collider) && !found.contains(object) {
+                found.push(object.clone());
+            }
+        }
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.colliding_objects(collider, found);
+            }
+        }
+    }
     pub fn collides_with_set<'a>(&self, colliders: impl Iterator<Item = &'a Collider>) -> bool {
         // query all the shapes that have a potential to collide with the given set of shapes
         for collider in colliders {
@@ -0,0 +1,466 @@
+use std::{
+    collections::{BTreeSet, BinaryHeap, HashMap},
+    sync::atomic::Ordering,
+};
+
+use ordered_float::NotNan;
+use shared::{
+    binary_heap_item::BinaryHeapItem,
+    hyperparameters::{
+        BEAM_WIDTH, LDS_MAX_DISCREPANCIES, SEARCH_MAX_CHILDREN, SEARCH_MAX_NODES, SEARCH_STRATEGY,
+        UPDATE_PROBA_SKIP_STRIDE,
+    },
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
+    trace_path::TraceAnchors,
+};
+
+use crate::{
+    backjumping::{exhausted_conflict, Conflict, Nogoods}, backtrack_node::BacktrackNode, bayesian_backtrack_algo::TraceCache,
+    display_injection::DisplayInjection, partial_solution::BestPartialState, shove::ShoveObstacles,
+};
+
+/// a partial routing on the frontier of a search over backtrack nodes
+#[derive(Debug, Clone)]
+pub struct SearchNode {
+    pub backtrack_node: BacktrackNode,
+    pub log_probability: f64, // summed log posteriors of the candidates the search fixed
+    pub estimate: f64,        // log_probability plus the log posterior of the best remaining candidate of every open connection
+    pub depth: usize,
+    pub discrepancies: usize, // children taken that were not the most probable one
+}
+
+impl SearchNode {
+    fn new(problem: &PcbProblem, backtrack_node: BacktrackNode, log_probability: f64, depth: usize, discrepancies: usize) -> Self {
+        let estimate = log_probability + completion_estimate(problem, &backtrack_node);
+        SearchNode { backtrack_node, log_probability, estimate, depth, discrepancies }
+    }
+
+    fn is_dead_end(&self) -> bool {
+        self.estimate == f64::NEG_INFINITY
+    }
+
+    /// the fixed traces, for recognizing the same partial routing reached in another order
+    fn signature(&self) -> Vec<(ConnectionID, TraceAnchors)> {
+        let mut signature: Vec<(ConnectionID, TraceAnchors)> = self
+            .backtrack_node
+            .fixed_traces
+            .iter()
+            .map(|(connection_id, fixed_trace)| (*connection_id, fixed_trace.trace_path.anchors.clone()))
+            .collect();
+        signature.sort();
+        signature
+    }
+}
+
+/// the log posterior of the best remaining candidate of every open connection,
+/// an open connection without candidates makes it a dead end once the probabilistic model is up to date, and costs the lowest posterior until then
+fn completion_estimate(problem: &PcbProblem, backtrack_node: &BacktrackNode) -> f64 {
+    let mut best_posteriors: HashMap<ConnectionID, f64> = HashMap::new();
+    for candidate in backtrack_node.remaining_trace_candidates.iter() {
+        let best_posterior = best_posteriors.entry(candidate.value.connection_id).or_insert(0.0);
+        *best_posterior = best_posterior.max(candidate.key.into_inner());
+    }
+    problem
+        .nets
+        .values()
+        .flat_map(|net_info| net_info.connections.keys())
+        .filter(|connection_id| !backtrack_node.fixed_traces.contains_key(connection_id))
+        .map(|connection_id| match best_posteriors.get(connection_id) {
+            Some(posterior) => posterior.max(1e-9).ln(),
+            None if backtrack_node.prob_up_to_date => f64::NEG_INFINITY,
+            None => 1e-9_f64.ln(),
+        })
+        .sum()
+}
+
+/// the order in which a search visits the partial routings
+pub trait SearchFrontier {
+    fn name(&self) -> &'static str;
+
+    fn push_root(&mut self, root: SearchNode);
+
+    /// the children come ranked, the most probable first
+    fn push_children(&mut self, parent: &SearchNode, children: Vec<SearchNode>);
+
+    fn pop(&mut self) -> Option<SearchNode>;
+}
+
+/// which search strategy the Bayesian backtrack uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStrategyKind {
+    DepthFirst,
+    Beam,
+    BestFirst,
+    LimitedDiscrepancy,
+}
+
+impl SearchStrategyKind {
+    pub fn from_setting(value: usize) -> Self {
+        match value {
+            1 => SearchStrategyKind::Beam,
+            2 => SearchStrategyKind::BestFirst,
+            3 => SearchStrategyKind::LimitedDiscrepancy,
+            _ => SearchStrategyKind::DepthFirst,
+        }
+    }
+
+    /// none for depth first, which is the backtracking stack of the Bayesian backtrack itself
+    pub fn to_frontier(self) -> Option<Box<dyn SearchFrontier>> {
+        match self {
+            SearchStrategyKind::DepthFirst => None,
+            SearchStrategyKind::Beam => Some(Box::new(BeamFrontier::new(BEAM_WIDTH.load(Ordering::Relaxed).max(1)))),
+            SearchStrategyKind::BestFirst => Some(Box::new(BestFirstFrontier::default())),
+            SearchStrategyKind::LimitedDiscrepancy => {
+                Some(Box::new(LimitedDiscrepancyFrontier::new(LDS_MAX_DISCREPANCIES.load(Ordering::Relaxed))))
+            }
+        }
+    }
+}
+
+/// the frontier selected by SEARCH_STRATEGY
+pub fn search_frontier_from_settings() -> Option<Box<dyn SearchFrontier>> {
+    SearchStrategyKind::from_setting(SEARCH_STRATEGY.load(Ordering::Relaxed)).to_frontier()
+}
+
+/// keeps the best width partial routings of every depth by their estimate and expands them level by level
+pub struct BeamFrontier {
+    pub width: usize,
+    current_level: Vec<SearchNode>,
+    next_level: Vec<SearchNode>,
+}
+
+impl BeamFrontier {
+    pub fn new(width: usize) -> Self {
+        BeamFrontier { width, current_level: Vec::new(), next_level: Vec::new() }
+    }
+}
+
+impl SearchFrontier for BeamFrontier {
+    fn name(&self) -> &'static str {
+        "beam"
+    }
+
+    fn push_root(&mut self, root: SearchNode) {
+        self.current_level.push(root);
+    }
+
+    fn push_children(&mut self, _parent: &SearchNode, children: Vec<SearchNode>) {
+        self.next_level.extend(children);
+    }
+
+    fn pop(&mut self) -> Option<SearchNode> {
+        if self.current_level.is_empty() {
+            // the best of the next level go last, so they are expanded first
+            let mut seen: BTreeSet<Vec<(ConnectionID, TraceAnchors)>> = BTreeSet::new();
+            let mut next_level: Vec<SearchNode> = std::mem::take(&mut self.next_level);
+            next_level.sort_by(|node1, node2| node2.estimate.total_cmp(&node1.estimate));
+            next_level.retain(|node| seen.insert(node.signature()));
+            next_level.truncate(self.width);
+            next_level.reverse();
+            self.current_level = next_level;
+        }
+        self.current_level.pop()
+    }
+}
+
+/// always expands the partial routing with the best estimate, the deeper one on a tie
+#[derive(Default)]
+pub struct BestFirstFrontier {
+    heap: BinaryHeap<BinaryHeapItem<(NotNan<f64>, usize), SearchNode>>,
+    seen: BTreeSet<Vec<(ConnectionID, TraceAnchors)>>,
+}
+
+impl BestFirstFrontier {
+    fn push(&mut self, node: SearchNode) {
+        if self.seen.insert(node.signature()) {
+            let estimate = NotNan::new(node.estimate).expect("Estimate must be non-NaN");
+            self.heap.push(BinaryHeapItem::new((estimate, node.depth), node));
+        }
+    }
+}
+
+impl SearchFrontier for BestFirstFrontier {
+    fn name(&self) -> &'static str {
+        "best-first"
+    }
+
+    fn push_root(&mut self, root: SearchNode) {
+        self.push(root);
+    }
+
+    fn push_children(&mut self, _parent: &SearchNode, children: Vec<SearchNode>) {
+        for child in children {
+            self.push(child);
+        }
+    }
+
+    fn pop(&mut self) -> Option<SearchNode> {
+        self.heap.pop().map(|item| item.value)
+    }
+}
+
+/// depth first, but a path may leave the most probable child at most a limited number of times,
+/// the limit starts at 0 and grows by one every time the search runs out, up to max_discrepancies
+pub struct LimitedDiscrepancyFrontier {
+    pub max_discrepancies: usize,
+    limit: usize,
+    root: Option<SearchNode>,
+    stack: Vec<SearchNode>,
+}
+
+impl LimitedDiscrepancyFrontier {
+    pub fn new(max_discrepancies: usize) -> Self {
+        LimitedDiscrepancyFrontier { max_discrepancies, limit: 0, root: None, stack: Vec::new() }
+    }
+}
+
+impl SearchFrontier for LimitedDiscrepancyFrontier {
+    fn name(&self) -> &'static str {
+        "limited discrepancy"
+    }
+
+    fn push_root(&mut self, root: SearchNode) {
+        self.root = Some(root.clone());
+        self.stack.push(root);
+    }
+
+    fn push_children(&mut self, parent: &SearchNode, children: Vec<SearchNode>) {
+        // the most probable child goes last, so it is expanded first
+        for (rank, mut child) in children.into_iter().enumerate().rev() {
+            child.discrepancies = parent.discrepancies + usize::from(rank > 0);
+            if child.discrepancies <= self.limit {
+                self.stack.push(child);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<SearchNode> {
+        if self.stack.is_empty() && self.limit < self.max_discrepancies {
+            self.limit += 1;
+            println!("Limited discrepancy search ran out, allowing {} discrepancies", self.limit);
+            self.stack.extend(self.root.clone());
+        }
+        self.stack.pop()
+    }
+}
+
+/// how a search over a frontier ended
+pub enum SearchOutcome {
+    Solved(BacktrackNode),
+    Stopped,
+    Exhausted(BacktrackNode), // the deepest node the search reached
+}
+
+/// the children of a node, one per candidate that can be fixed, taken in the order of their posteriors,
+/// a node without children leaves a nogood for each connection of its candidates that A* finds no path for either
+fn expand(
+    search_node: &SearchNode,
+    problem: &PcbProblem,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    shove_obstacles: &ShoveObstacles,
+    nogoods: &mut Nogoods,
+    max_children: usize,
+    display_injection: &mut DisplayInjection,
+) -> Vec<SearchNode> {
+    let mut parent = search_node.backtrack_node.clone();
+    let mut children: Vec<SearchNode> = Vec::new();
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    while children.len() < max_children {
+        let Some(top_ranked_candidate) = parent.remaining_trace_candidates.peek() else {
+            break;
+        };
+        let posterior = top_ranked_candidate.key.into_inner();
+        match parent.try_fix_top_k_ranked_trace(problem, prerouted_traces, shove_obstacles, nogoods, |_| {}, 1) {
+            Ok(child) => {
+                let log_probability = search_node.log_probability + posterior.max(1e-9).ln();
+                let child = SearchNode::new(problem, child, log_probability, search_node.depth + 1, search_node.discrepancies);
+                if !child.is_dead_end() {
+                    children.push(child);
+                }
+            }
+            Err(candidate_conflicts) => conflicts.extend(candidate_conflicts),
+        }
+    }
+    if children.is_empty() {
+        let fixed_traces = &search_node.backtrack_node.fixed_traces;
+        for conflict in Conflict::merge(conflicts) {
+            if let Some(conflict) = exhausted_conflict(problem, conflict.connection_id, fixed_traces, display_injection) {
+                nogoods.learn(&conflict);
+            }
+        }
+    }
+    children
+}
+
+/// what a search over a frontier works with, shared with the Bayesian backtrack that runs it
+pub struct SearchContext<'a> {
+    pub problem: &'a PcbProblem,
+    pub trace_cache: &'a mut TraceCache,
+    pub prerouted_traces: &'a HashMap<ConnectionID, FixedTrace>,
+    pub shove_obstacles: &'a ShoveObstacles,
+    pub nogoods: &'a mut Nogoods, // learned here and kept for the backtracking that takes over
+    pub best_partial_state: &'a mut BestPartialState,
+    pub display_injection: &'a mut DisplayInjection,
+}
+
+/// searches from the first node in the order of the frontier, reusing the expansion and the posteriors of the Bayesian backtrack,
+/// gives up after SEARCH_MAX_NODES expansions
+pub fn search_with_frontier(
+    context: SearchContext,
+    first_node: BacktrackNode,
+    mut frontier: Box<dyn SearchFrontier>,
+    display_node: impl Fn(&BacktrackNode, &mut DisplayInjection),
+) -> Result<SearchOutcome, String> {
+    let SearchContext { problem, trace_cache, prerouted_traces, shove_obstacles, nogoods, best_partial_state, display_injection } =
+        context;
+    println!("Searching with the {} strategy", frontier.name());
+    let max_nodes = SEARCH_MAX_NODES.load(Ordering::Relaxed);
+    let max_children = SEARCH_MAX_CHILDREN.load(Ordering::Relaxed).max(1);
+    let update_stride = UPDATE_PROBA_SKIP_STRIDE.load(Ordering::Relaxed).max(1);
+    let mut deepest_node = first_node.clone();
+    frontier.push_root(SearchNode::new(problem, first_node, 0.0, 0, 0));
+    let mut num_expanded = 0;
+    while let Some(mut search_node) = frontier.pop() {
+        display_injection.budget.check();
+        if display_injection.is_stopped() {
+            println!("Stop requested, exiting the {} search", frontier.name());
+            return Ok(SearchOutcome::Stopped);
+        }
+        if search_node.backtrack_node.is_solution(problem) {
+            return Ok(SearchOutcome::Solved(search_node.backtrack_node));
+        }
+        if num_expanded >= max_nodes {
+            println!("The {} search expanded {} nodes without a solution", frontier.name(), num_expanded);
+            break;
+        }
+        num_expanded += 1;
+        display_node(&search_node.backtrack_node, display_injection);
+        if search_node.depth % update_stride == 0
+            && !search_node.backtrack_node.prob_up_to_date
+            && let Err(err) = search_node.backtrack_node.try_update_proba_model(problem, trace_cache, display_injection)
+        {
+            if display_injection.is_stopped() {
+                return Ok(SearchOutcome::Stopped);
+            }
+            return Err(err);
+        }
+        let children = expand(&search_node, problem, prerouted_traces, shove_obstacles, nogoods, max_children, display_injection);
+        for child in children.iter() {
+            best_partial_state.offer(&child.backtrack_node.fixed_traces);
+            if child.backtrack_node.fixed_traces.len() > deepest_node.fixed_traces.len() {
+                deepest_node = child.backtrack_node.clone();
+            }
+        }
+        display_injection.budget.add_backtrack_nodes(children.len());
+        frontier.push_children(&search_node, children);
+    }
+    Ok(SearchOutcome::Exhausted(deepest_node))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BinaryHeap, HashMap},
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    use shared::pcb_problem::{ConnectionID, FixedTrace, PcbProblem};
+
+    use super::{
+        search_with_frontier, BeamFrontier, BestFirstFrontier, LimitedDiscrepancyFrontier, SearchContext, SearchFrontier,
+        SearchNode, SearchOutcome,
+    };
+    use crate::{
+        backjumping::Nogoods,
+        backtrack_node::BacktrackNode,
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        partial_solution::BestPartialState,
+        shove::ShoveObstacles,
+        test_pcb_problem::examples::{assert_no_collisions, example_problem, AutoCommandLevel},
+    };
+
+    /// the fixed traces a search over the frontier solves the board with
+    fn solve_with(problem: &PcbProblem, frontier: Box<dyn SearchFrontier>) -> HashMap<ConnectionID, FixedTrace> {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let mut trace_cache = TraceCache::default();
+        for net_info in problem.nets.values() {
+            for connection_id in net_info.connections.keys() {
+                trace_cache.traces.insert(*connection_id, Vec::new());
+            }
+        }
+        let prerouted_traces = HashMap::new();
+        let mut nogoods = Nogoods::default();
+        let mut best_partial_state = BestPartialState::new(&prerouted_traces);
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        let first_node =
+            BacktrackNode::from_fixed_traces(problem, &prerouted_traces, Vec::new(), &mut trace_cache, &mut display_injection)
+                .unwrap();
+        let context = SearchContext {
+            problem,
+            trace_cache: &mut trace_cache,
+            prerouted_traces: &prerouted_traces,
+            shove_obstacles: &ShoveObstacles::new(problem),
+            nogoods: &mut nogoods,
+            best_partial_state: &mut best_partial_state,
+            display_injection: &mut display_injection,
+        };
+        match search_with_frontier(context, first_node, frontier, |_, _| {}) {
+            Ok(SearchOutcome::Solved(node)) => node.fixed_traces,
+            _ => panic!("the search did not solve the board"),
+        }
+    }
+
+    #[test]
+    fn frontiers_solve_a_small_board_without_collisions() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let num_connections: usize = problem.nets.values().map(|net_info| net_info.connections.len()).sum();
+        let frontiers: Vec<Box<dyn SearchFrontier>> = vec![
+            Box::new(BestFirstFrontier::default()),
+            Box::new(BeamFrontier::new(2)),
+            Box::new(LimitedDiscrepancyFrontier::new(2)),
+        ];
+        for frontier in frontiers {
+            let fixed_traces = solve_with(&problem, frontier);
+            assert_eq!(fixed_traces.len(), num_connections);
+            assert_no_collisions(&fixed_traces);
+        }
+    }
+
+    /// a node told apart by its log probability, the frontier only looks at the ranks of the children
+    fn search_node(log_probability: f64, depth: usize) -> SearchNode {
+        let backtrack_node = BacktrackNode {
+            remaining_trace_candidates: BinaryHeap::new(),
+            fixed_traces: HashMap::new(),
+            fix_sequence: Vec::new(),
+            prob_up_to_date: false,
+        };
+        SearchNode { backtrack_node, log_probability, estimate: log_probability, depth, discrepancies: 0 }
+    }
+
+    #[test]
+    fn limited_discrepancy_allows_one_more_detour_every_time_it_runs_out() {
+        let mut frontier = LimitedDiscrepancyFrontier::new(1);
+        let children = || vec![search_node(-1.0, 1), search_node(-2.0, 1), search_node(-3.0, 1)];
+        let popped = |frontier: &mut LimitedDiscrepancyFrontier| frontier.pop().map(|node| node.log_probability);
+        frontier.push_root(search_node(0.0, 0));
+
+        // without discrepancies only the most probable child is taken
+        assert_eq!(popped(&mut frontier), Some(0.0));
+        frontier.push_children(&search_node(0.0, 0), children());
+        assert_eq!(popped(&mut frontier), Some(-1.0));
+        // running out starts over from the root with one discrepancy allowed
+        assert_eq!(popped(&mut frontier), Some(0.0));
+        frontier.push_children(&search_node(0.0, 0), children());
+        assert_eq!(popped(&mut frontier), Some(-1.0));
+        assert_eq!(popped(&mut frontier), Some(-2.0));
+        // a child that is already one detour deep may only take the most probable of its own children
+        let mut detour = search_node(-3.0, 1);
+        detour.discrepancies = 1;
+        frontier.push_children(&detour, children());
+        assert_eq!(popped(&mut frontier), Some(-1.0));
+        assert_eq!(popped(&mut frontier), Some(-3.0));
+        // at max_discrepancies running out ends the search
+        assert_eq!(popped(&mut frontier), None);
+    }
+}
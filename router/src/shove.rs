@@ -0,0 +1,597 @@
+use std::{cell::RefCell, collections::HashMap, sync::atomic::Ordering};
+
+use shared::{
+    hyperparameters::{SHOVE_MAX_DEPTH, SHOVE_MAX_DISTANCE},
+    pad::PadLayer,
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
+    trace_path::{Direction, TraceAnchor, TraceAnchors, TracePath, TraceSegment, Via},
+    vec2::{FixedPoint, FixedVec2, FloatVec2},
+};
+
+use crate::{
+    astar::AStarModel,
+    obstacles::{build_pad_obstacles, PadObstacles},
+};
+
+/// the pads of every net, built once per solve and lent to every shove,
+/// the shoved net takes its own pads out while it moves and puts them back afterwards
+pub struct ShoveObstacles {
+    obstacles: RefCell<PadObstacles>,
+}
+
+impl ShoveObstacles {
+    pub fn new(problem: &PcbProblem) -> Self {
+        ShoveObstacles { obstacles: RefCell::new(build_pad_obstacles(problem, &[], &HashMap::new())) }
+    }
+}
+
+/// what stays the same while one new trace shoves the fixed traces aside
+struct ShoveContext<'a> {
+    problem: &'a PcbProblem,
+    fixed_traces: &'a HashMap<ConnectionID, FixedTrace>,
+    prerouted_traces: &'a HashMap<ConnectionID, FixedTrace>,
+    obstacles: &'a RefCell<PadObstacles>,
+}
+
+/// TracePath::collides_with only compares segments, a shove also moves vias so they are compared as well
+fn paths_collide(a: &TracePath, b: &TracePath, num_layers: usize) -> bool {
+    a.collides_with(b) || vias_collide(a, b, num_layers) || vias_collide(b, a, num_layers)
+}
+
+/// whether a via of the first path collides with anything of the second
+fn vias_collide(a: &TracePath, b: &TracePath, num_layers: usize) -> bool {
+    a.vias.iter().any(|via| via_collides_with_path(via, b, num_layers))
+}
+
+fn via_collides_with_path(via: &Via, path: &TracePath, num_layers: usize) -> bool {
+    let colliders = path.to_colliders(num_layers);
+    let clearance_colliders = path.to_clearance_colliders(num_layers);
+    let via_collider = via.to_collider();
+    let via_clearance_collider = via.to_clearance_collider();
+    (via.min_layer..=via.max_layer).any(|layer| {
+        clearance_colliders[&layer].iter().any(|collider| collider.collides_with(&via_collider))
+            || colliders[&layer].iter().any(|collider| collider.collides_with(&via_clearance_collider))
+    })
+}
+
+fn segment_collides_with_path(segment: &TraceSegment, path: &TracePath) -> bool {
+    if path.segments.iter().any(|other| other.collides_with(segment)) {
+        return true;
+    }
+    let segment_colliders = segment.to_colliders();
+    let segment_clearance_colliders = segment.to_clearance_colliders();
+    path.vias
+        .iter()
+        .filter(|via| via.min_layer <= segment.layer && segment.layer <= via.max_layer)
+        .any(|via| {
+            let via_collider = via.to_collider();
+            let via_clearance_collider = via.to_clearance_collider();
+            segment_clearance_colliders.iter().any(|collider| collider.collides_with(&via_collider))
+                || segment_colliders.iter().any(|collider| collider.collides_with(&via_clearance_collider))
+        })
+}
+
+/// whether the center lines of two segments on the same layer cross each other
+/// moving one of them sideways can never undo a crossing
+fn segments_cross(a: &TraceSegment, b: &TraceSegment) -> bool {
+    if a.layer != b.layer {
+        return false;
+    }
+    let (a_start, a_end) = (a.start.to_float(), a.end.to_float());
+    let (b_start, b_end) = (b.start.to_float(), b.end.to_float());
+    let orientation = |p: FloatVec2, q: FloatVec2, r: FloatVec2| ((q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x)).signum();
+    orientation(a_start, a_end, b_start) * orientation(a_start, a_end, b_end) < 0.0
+        && orientation(b_start, b_end, a_start) * orientation(b_start, b_end, a_end) < 0.0
+}
+
+/// the ways from one point to another with at most one bend, as the bend points
+/// the diagonal part comes either first or last
+fn octilinear_links(from: FixedVec2, to: FixedVec2) -> Vec<Vec<FixedVec2>> {
+    let delta = to - from;
+    let (dx, dy) = (delta.x.abs(), delta.y.abs());
+    if dx == FixedPoint::ZERO || dy == FixedPoint::ZERO || dx == dy {
+        return vec![Vec::new()];
+    }
+    let diagonal = FixedPoint::min(dx, dy);
+    let diagonal_step = FixedVec2::new(diagonal * delta.x.signum(), diagonal * delta.y.signum());
+    vec![vec![from + diagonal_step], vec![to - diagonal_step]]
+}
+
+/// moves the anchors first..=last by the offset and links them back to their neighbours
+/// None if the result has a segment that is not octilinear or that doubles back on the one before
+fn displace_anchors(
+    anchors: &[TraceAnchor],
+    first: usize,
+    last: usize,
+    offset: FixedVec2,
+    link_before: &[FixedVec2],
+    link_after: &[FixedVec2],
+) -> Option<Vec<TraceAnchor>> {
+    let mut displaced: Vec<TraceAnchor> = anchors[..first].to_vec();
+    let layer_before = anchors[first - 1].end_layer;
+    displaced.extend(link_before.iter().map(|position| TraceAnchor {
+        position: *position,
+        start_layer: layer_before,
+        end_layer: layer_before,
+    }));
+    displaced.extend(anchors[first..=last].iter().map(|anchor| TraceAnchor {
+        position: anchor.position + offset,
+        ..anchor.clone()
+    }));
+    let layer_after = anchors[last].end_layer;
+    displaced.extend(link_after.iter().map(|position| TraceAnchor {
+        position: *position,
+        start_layer: layer_after,
+        end_layer: layer_after,
+    }));
+    displaced.extend(anchors[last + 1..].iter().cloned());
+    // a link or an offset can land on the next anchor, the two become one
+    let mut merged: Vec<TraceAnchor> = Vec::new();
+    for anchor in displaced {
+        match merged.last_mut() {
+            Some(previous) if previous.position == anchor.position => previous.end_layer = anchor.end_layer,
+            _ => merged.push(anchor),
+        }
+    }
+    let mut previous_direction: Option<Direction> = None;
+    for window in merged.windows(2) {
+        let direction = match Direction::from_points(window[0].position, window[1].position) {
+            Ok(Some(direction)) => direction,
+            _ => return None,
+        };
+        if previous_direction == Some(direction.opposite()) {
+            return None;
+        }
+        previous_direction = Some(direction);
+    }
+    Some(merged)
+}
+
+/// the interior anchors that have to move for the trace to clear the pusher, the first contiguous run of them
+/// the end anchors sit on the pads and never move
+fn colliding_run(
+    trace_path: &TracePath,
+    pusher: &TracePath,
+    trace_width: f32,
+    trace_clearance: f32,
+    num_layers: usize,
+) -> Option<(usize, usize)> {
+    let anchors = &trace_path.anchors.0;
+    let mut to_move = vec![false; anchors.len()];
+    for (index, window) in anchors.windows(2).enumerate() {
+        if window[0].position == window[1].position {
+            continue;
+        }
+        let segment = TraceSegment {
+            start: window[0].position,
+            end: window[1].position,
+            width: trace_width,
+            clearance: trace_clearance,
+            layer: window[0].end_layer,
+        };
+        if segment_collides_with_path(&segment, pusher) {
+            to_move[index] = true;
+            to_move[index + 1] = true;
+        }
+    }
+    for via in trace_path.vias.iter().filter(|via| via_collides_with_path(via, pusher, num_layers)) {
+        if let Some(index) = anchors.iter().position(|anchor| anchor.position == via.position) {
+            to_move[index] = true;
+        }
+    }
+    let first = (1..anchors.len().saturating_sub(1)).find(|index| to_move[*index])?;
+    let last = (first..anchors.len() - 1).take_while(|index| to_move[*index]).last().unwrap();
+    Some((first, last))
+}
+
+/// the nearest displacement of the trace that clears the pusher, run by run
+/// every moved piece has to keep clear of the pads, the border and the traces up the chain
+fn shove_one(context: &ShoveContext, fixed_trace: &FixedTrace, pusher: &FixedTrace, chain: &[FixedTrace]) -> Option<TracePath> {
+    let problem = context.problem;
+    let crosses_pusher = fixed_trace.trace_path.segments.iter().any(|segment| {
+        pusher.trace_path.segments.iter().any(|other| segments_cross(segment, other))
+    });
+    if crosses_pusher {
+        return None;
+    }
+    let net_info = &problem.nets[&fixed_trace.net_name];
+    let mut obstacles = context.obstacles.borrow_mut();
+    obstacles.remove_pads(net_info, problem.num_layers);
+    let trace_path = shove_clear_of_pads(context, &obstacles, fixed_trace, pusher, chain);
+    obstacles.add_pads(net_info, problem.num_layers);
+    trace_path
+}
+
+/// the search of shove_one, the obstacles no longer hold the pads of the shoved net
+fn shove_clear_of_pads(
+    context: &ShoveContext,
+    obstacles: &PadObstacles,
+    fixed_trace: &FixedTrace,
+    pusher: &FixedTrace,
+    chain: &[FixedTrace],
+) -> Option<TracePath> {
+    let problem = context.problem;
+    let net_info = &problem.nets[&fixed_trace.net_name];
+    let anchors = &fixed_trace.trace_path.anchors.0;
+    let collision_model = AStarModel {
+        start: anchors[0].position,
+        end: anchors[anchors.len() - 1].position,
+        start_layers: PadLayer::All,
+        end_layers: PadLayer::All,
+        num_layers: problem.num_layers,
+        trace_width: net_info.trace_width,
+        trace_clearance: net_info.trace_clearance,
+        via_types: net_info.via_types.clone(),
+        width: problem.width,
+        height: problem.height,
+        center: problem.center,
+        obstacle_shapes: obstacles.obstacle_shapes.clone(),
+        obstacle_clearance_shapes: obstacles.obstacle_clearance_shapes.clone(),
+        obstacle_colliders: obstacles.obstacle_colliders.clone(),
+        obstacle_clearance_colliders: obstacles.obstacle_clearance_colliders.clone(),
+        border_colliders_cache: RefCell::new(None),
+        border_shapes_cache: RefCell::new(None),
+        cost_map: None,
+        blocking_colliders: RefCell::new(None),
+    };
+    let max_distance = SHOVE_MAX_DISTANCE.load(Ordering::Relaxed) as f32;
+    let step = (net_info.trace_width + net_info.trace_clearance) / 4.0;
+    let num_steps = (max_distance / step).floor() as usize;
+    // offsets are rounded to an even number of bits, so the moved anchors stay on the grid
+    let to_offset = |distance: f32, direction: Direction| -> FixedVec2 {
+        let unit_length = if direction.is_diagonal() { std::f32::consts::SQRT_2 } else { 1.0 };
+        let bits = (distance / unit_length * (1i64 << FixedPoint::FRAC_NBITS) as f32).round() as i32;
+        direction.to_fixed_vec2(FixedPoint::from_bits(bits + (bits & 1)))
+    };
+    let blocked_by_chain = |path: &TracePath| {
+        chain
+            .iter()
+            .filter(|other| other.net_name != fixed_trace.net_name)
+            .any(|other| paths_collide(path, &other.trace_path, problem.num_layers))
+    };
+    let mut trace_path = fixed_trace.trace_path.clone();
+    for _ in 0..anchors.len() {
+        let Some((first, last)) = colliding_run(
+            &trace_path,
+            &pusher.trace_path,
+            net_info.trace_width,
+            net_info.trace_clearance,
+            problem.num_layers,
+        ) else {
+            break;
+        };
+        let current_anchors = &trace_path.anchors.0;
+        let original_segments: Vec<(FixedVec2, FixedVec2, usize)> = current_anchors
+            .windows(2)
+            .map(|window| (window[0].position, window[1].position, window[0].end_layer))
+            .collect();
+        let mut displaced_path: Option<TracePath> = None;
+        'search: for step_index in 1..=num_steps {
+            for direction in Direction::all_directions() {
+                let offset = to_offset(step * step_index as f32, direction);
+                let links_before = octilinear_links(current_anchors[first - 1].position, current_anchors[first].position + offset);
+                let links_after = octilinear_links(current_anchors[last].position + offset, current_anchors[last + 1].position);
+                for link_before in links_before.iter() {
+                    for link_after in links_after.iter() {
+                        let Some(displaced) = displace_anchors(current_anchors, first, last, offset, link_before, link_after) else {
+                            continue;
+                        };
+                        // only the new segments are checked, the rest of the trace was valid before
+                        let new_segments: Vec<TraceSegment> = displaced
+                            .windows(2)
+                            .filter(|window| {
+                                !original_segments.contains(&(window[0].position, window[1].position, window[0].end_layer))
+                            })
+                            .map(|window| TraceSegment {
+                                start: window[0].position,
+                                end: window[1].position,
+                                width: net_info.trace_width,
+                                clearance: net_info.trace_clearance,
+                                layer: window[0].end_layer,
+                            })
+                            .collect();
+                        let blocked_segment = new_segments.iter().any(|segment| {
+                            segment_collides_with_path(segment, &pusher.trace_path)
+                                || collision_model.check_collision_for_optimization(
+                                    segment.start,
+                                    segment.end,
+                                    segment.width,
+                                    segment.clearance,
+                                    segment.layer,
+                                )
+                        });
+                        if blocked_segment {
+                            continue;
+                        }
+                        // the vias on the moved anchors move along with them
+                        let moved_positions: Vec<FixedVec2> =
+                            current_anchors[first..=last].iter().map(|anchor| anchor.position).collect();
+                        let mut vias = trace_path.vias.clone();
+                        let mut blocked_via = false;
+                        for via in vias.iter_mut().filter(|via| moved_positions.contains(&via.position)) {
+                            via.position = via.position + offset;
+                            blocked_via |= via_collides_with_path(via, &pusher.trace_path, problem.num_layers)
+                                || (via.min_layer..=via.max_layer).any(|layer| {
+                                    collision_model.check_collision_for_via_layer(via.position, via.diameter, via.clearance, layer)
+                                });
+                        }
+                        if blocked_via {
+                            continue;
+                        }
+                        let candidate = TracePath::from_anchors(
+                            TraceAnchors(displaced),
+                            net_info.trace_width,
+                            net_info.trace_clearance,
+                            vias,
+                        );
+                        if blocked_by_chain(&candidate) {
+                            continue;
+                        }
+                        displaced_path = Some(candidate);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        trace_path = displaced_path?;
+    }
+    if paths_collide(&trace_path, &pusher.trace_path, problem.num_layers) {
+        return None;
+    }
+    Some(trace_path)
+}
+
+/// shoves every fixed trace of another net out of the way of the pusher, and what those hit in turn, depth levels deep
+fn shove_colliding(
+    context: &ShoveContext,
+    moved: &mut HashMap<ConnectionID, FixedTrace>,
+    pusher: &FixedTrace,
+    chain: &[FixedTrace],
+    depth: usize,
+) -> bool {
+    let num_layers = context.problem.num_layers;
+    let mut colliding_ids: Vec<ConnectionID> = context
+        .fixed_traces
+        .keys()
+        .filter(|connection_id| chain.iter().all(|link| link.connection_id != **connection_id))
+        .filter(|connection_id| {
+            let current = moved.get(connection_id).unwrap_or(&context.fixed_traces[connection_id]);
+            current.net_name != pusher.net_name && paths_collide(&current.trace_path, &pusher.trace_path, num_layers)
+        })
+        .cloned()
+        .collect();
+    colliding_ids.sort();
+    for connection_id in colliding_ids {
+        if depth == 0 || context.prerouted_traces.contains_key(&connection_id) {
+            return false;
+        }
+        let current = moved.get(&connection_id).unwrap_or(&context.fixed_traces[&connection_id]).clone();
+        if !paths_collide(&current.trace_path, &pusher.trace_path, num_layers) {
+            continue; // a trace shoved for an earlier one moved it out of the way already
+        }
+        let Some(trace_path) = shove_one(context, &current, pusher, chain) else {
+            return false;
+        };
+        let shoved = FixedTrace { trace_path, ..current };
+        moved.insert(connection_id, shoved.clone());
+        let mut longer_chain = chain.to_vec();
+        longer_chain.push(shoved.clone());
+        if !shove_colliding(context, moved, &shoved, &longer_chain, depth - 1) {
+            return false;
+        }
+    }
+    true
+}
+
+/// makes room for a proposed trace by moving the segments and vias of the fixed traces it collides with sideways
+/// returns the traces that moved, with their new paths, or None if some collision cannot be shoved away
+/// prerouted traces are never moved, and neither are the traces up the chain of pushers
+pub fn shove_traces(
+    problem: &PcbProblem,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    shove_obstacles: &ShoveObstacles,
+    proposed_trace: &FixedTrace,
+) -> Option<HashMap<ConnectionID, FixedTrace>> {
+    let max_depth = SHOVE_MAX_DEPTH.load(Ordering::Relaxed);
+    shove_traces_to_depth(problem, fixed_traces, prerouted_traces, shove_obstacles, proposed_trace, max_depth)
+}
+
+/// shoves with each shoved trace pushing the next up to max_depth traces deep, 0 shoves nothing
+pub fn shove_traces_to_depth(
+    problem: &PcbProblem,
+    fixed_traces: &HashMap<ConnectionID, FixedTrace>,
+    prerouted_traces: &HashMap<ConnectionID, FixedTrace>,
+    shove_obstacles: &ShoveObstacles,
+    proposed_trace: &FixedTrace,
+    max_depth: usize,
+) -> Option<HashMap<ConnectionID, FixedTrace>> {
+    if max_depth == 0 {
+        return None;
+    }
+    let context = ShoveContext {
+        problem,
+        fixed_traces,
+        prerouted_traces,
+        obstacles: &shove_obstacles.obstacles,
+    };
+    let mut moved: HashMap<ConnectionID, FixedTrace> = HashMap::new();
+    let chain = vec![proposed_trace.clone()];
+    if shove_colliding(&context, &mut moved, proposed_trace, &chain, max_depth) {
+        Some(moved)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use shared::{
+        pad::PadLayer,
+        pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::FloatVec2,
+    };
+
+    use super::{paths_collide, shove_colliding, shove_traces_to_depth, ShoveContext, ShoveObstacles};
+    use crate::{obstacles::build_pad_obstacles, test_pcb_problem::examples::example_problem};
+
+    /// the first three nets of echo, in name order
+    fn problem_and_nets() -> (PcbProblem, Vec<NetName>) {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let mut net_names: Vec<NetName> = problem.nets.keys().cloned().collect();
+        net_names.sort();
+        (problem, net_names)
+    }
+
+    /// a trace on the front layer through the points, which are taken as offsets from the origin
+    fn front_trace(problem: &PcbProblem, net_name: &NetName, connection_id: usize, origin: FloatVec2, points: &[(f32, f32)]) -> FixedTrace {
+        let net_info = &problem.nets[net_name];
+        let anchors = points
+            .iter()
+            .map(|(x, y)| TraceAnchor {
+                position: FloatVec2::new(origin.x + x, origin.y + y).to_fixed().to_nearest_even_even(),
+                start_layer: 0,
+                end_layer: 0,
+            })
+            .collect();
+        FixedTrace {
+            net_name: net_name.clone(),
+            connection_id: ConnectionID(connection_id),
+            trace_path: TracePath::from_anchors(TraceAnchors(anchors), net_info.trace_width, net_info.trace_clearance, vec![]),
+        }
+    }
+
+    /// a straight fixed trace at height y, the anchors in the middle let a shove move the part between them
+    fn shovable_trace(problem: &PcbProblem, net_name: &NetName, connection_id: usize, origin: FloatVec2, y: f32) -> FixedTrace {
+        front_trace(problem, net_name, connection_id, origin, &[(-5.0, y), (-3.0, y), (3.0, y), (5.0, y)])
+    }
+
+    /// the point of the board farthest from every pad
+    fn empty_spot(problem: &PcbProblem) -> FloatVec2 {
+        let pads: Vec<FloatVec2> =
+            problem.nets.values().flat_map(|net_info| net_info.pads.values().map(|pad| pad.position)).collect();
+        let mut best = (f32::MIN, problem.center);
+        for i in 0..20 {
+            for j in 0..20 {
+                let spot = FloatVec2::new(
+                    problem.center.x - problem.width / 4.0 + problem.width / 2.0 * i as f32 / 19.0,
+                    problem.center.y - problem.height / 4.0 + problem.height / 2.0 * j as f32 / 19.0,
+                );
+                let distance = pads.iter().map(|pad| (*pad - spot).length()).fold(f32::MAX, f32::min);
+                if distance > best.0 {
+                    best = (distance, spot);
+                }
+            }
+        }
+        best.1
+    }
+
+    fn collides_with_pads(problem: &PcbProblem, trace_path: &TracePath) -> bool {
+        let obstacles = build_pad_obstacles(problem, &[], &HashMap::new());
+        let colliders = trace_path.to_colliders(problem.num_layers);
+        let clearance_colliders = trace_path.to_clearance_colliders(problem.num_layers);
+        (0..problem.num_layers).any(|layer| {
+            obstacles.obstacle_clearance_colliders[&layer].collides_with_set(colliders[&layer].iter())
+                || obstacles.obstacle_colliders[&layer].collides_with_set(clearance_colliders[&layer].iter())
+        })
+    }
+
+    #[test]
+    fn a_colliding_trace_is_moved_clear_of_the_new_one() {
+        let (problem, net_names) = problem_and_nets();
+        let origin = empty_spot(&problem);
+        let fixed_trace = shovable_trace(&problem, &net_names[1], 1000, origin, 0.0);
+        let proposed_trace = front_trace(&problem, &net_names[0], 1001, origin, &[(-2.0, 0.3), (2.0, 0.3)]);
+        assert!(paths_collide(&fixed_trace.trace_path, &proposed_trace.trace_path, problem.num_layers));
+        let fixed_traces = HashMap::from([(fixed_trace.connection_id, fixed_trace.clone())]);
+
+        let moved = shove_traces_to_depth(&problem, &fixed_traces, &HashMap::new(), &ShoveObstacles::new(&problem), &proposed_trace, 2)
+            .expect("an empty spot leaves room to shove");
+        let shoved = &moved[&fixed_trace.connection_id];
+        assert_ne!(shoved.trace_path.anchors, fixed_trace.trace_path.anchors);
+        let (anchors, shoved_anchors) = (&fixed_trace.trace_path.anchors.0, &shoved.trace_path.anchors.0);
+        assert_eq!(anchors.first(), shoved_anchors.first());
+        assert_eq!(anchors.last(), shoved_anchors.last());
+        assert!(!paths_collide(&shoved.trace_path, &proposed_trace.trace_path, problem.num_layers));
+    }
+
+    #[test]
+    fn a_chain_of_shoves_stops_at_the_depth() {
+        let (problem, net_names) = problem_and_nets();
+        let origin = empty_spot(&problem);
+        // the first trace is clear of the second until it is shoved onto it
+        let first = shovable_trace(&problem, &net_names[1], 1000, origin, 0.0);
+        let second = front_trace(&problem, &net_names[2], 1001, origin, &[(-7.0, -0.5), (-5.0, -0.5), (5.0, -0.5), (7.0, -0.5)]);
+        let proposed_trace = front_trace(&problem, &net_names[0], 1002, origin, &[(-2.0, 0.3), (2.0, 0.3)]);
+        assert!(!paths_collide(&first.trace_path, &second.trace_path, problem.num_layers));
+        let fixed_traces = HashMap::from([(first.connection_id, first.clone()), (second.connection_id, second.clone())]);
+        let shove_obstacles = ShoveObstacles::new(&problem);
+        let prerouted_traces = HashMap::new();
+        let context = ShoveContext {
+            problem: &problem,
+            fixed_traces: &fixed_traces,
+            prerouted_traces: &prerouted_traces,
+            obstacles: &shove_obstacles.obstacles,
+        };
+        let chain = vec![proposed_trace.clone()];
+
+        let mut moved = HashMap::new();
+        assert!(!shove_colliding(&context, &mut moved, &proposed_trace, &chain, 1));
+        let mut moved = HashMap::new();
+        assert!(shove_colliding(&context, &mut moved, &proposed_trace, &chain, 2));
+        assert_eq!(moved.len(), 2);
+        let shoved_first = &moved[&first.connection_id].trace_path;
+        let shoved_second = &moved[&second.connection_id].trace_path;
+        assert!(!paths_collide(shoved_first, &proposed_trace.trace_path, problem.num_layers));
+        assert!(!paths_collide(shoved_second, shoved_first, problem.num_layers));
+    }
+
+    #[test]
+    fn a_shove_keeps_clear_of_the_pads() {
+        let (problem, net_names) = problem_and_nets();
+        let pad = problem.nets[&net_names[2]]
+            .pads
+            .values()
+            .filter(|pad| matches!(pad.pad_layer, PadLayer::Front | PadLayer::All))
+            .min_by(|pad1, pad2| pad1.name.cmp(&pad2.name))
+            .unwrap();
+        let origin = pad.position;
+        // the closest the fixed trace and the new one above it get to the pad without touching a pad
+        let (fixed_trace, proposed_trace) = (1..100)
+            .map(|step| step as f32 * 0.05)
+            .map(|y| {
+                (
+                    shovable_trace(&problem, &net_names[1], 1000, origin, y),
+                    front_trace(&problem, &net_names[0], 1001, origin, &[(-2.0, y + 0.3), (2.0, y + 0.3)]),
+                )
+            })
+            .find(|(fixed_trace, proposed_trace)| {
+                !collides_with_pads(&problem, &fixed_trace.trace_path) && !collides_with_pads(&problem, &proposed_trace.trace_path)
+            })
+            .unwrap();
+        let fixed_traces = HashMap::from([(fixed_trace.connection_id, fixed_trace.clone())]);
+
+        // without the pads the trace is shoved onto the pad
+        let (mut padless_problem, _) = problem_and_nets();
+        for net_info in padless_problem.nets.values_mut() {
+            net_info.pads.clear();
+        }
+        let padless_moved = shove_traces_to_depth(
+            &padless_problem,
+            &fixed_traces,
+            &HashMap::new(),
+            &ShoveObstacles::new(&padless_problem),
+            &proposed_trace,
+            2,
+        )
+        .unwrap();
+        assert!(collides_with_pads(&problem, &padless_moved[&fixed_trace.connection_id].trace_path));
+
+        let moved = shove_traces_to_depth(&problem, &fixed_traces, &HashMap::new(), &ShoveObstacles::new(&problem), &proposed_trace, 2);
+        // the only way clear of the new trace is onto the pad, so the shove is refused
+        assert!(moved.is_none());
+    }
+}
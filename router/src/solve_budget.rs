@@ -0,0 +1,139 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use shared::hyperparameters::{SOLVE_MAX_BACKTRACK_NODES, SOLVE_MAX_EXPANSIONS, SOLVE_TIME_LIMIT};
+
+/// the clock and the counters of one solve, shared by the runs of its portfolio and by no other solve
+/// an exhausted budget does not touch the stop flag of the user, the solvers look at both through DisplayInjection::is_stopped
+#[derive(Debug)]
+pub struct SolveBudget {
+    start: Instant,
+    time_limit: f64,       // seconds, 0 for no limit
+    max_expansions: usize, // 0 for no limit
+    max_backtrack_nodes: usize,
+    num_astar_expansions: AtomicUsize,
+    num_backtrack_nodes: AtomicUsize,
+    exhausted: AtomicBool,
+}
+
+impl SolveBudget {
+    /// starts the clock and the counters of a new solve, with the limits of SOLVE_TIME_LIMIT, SOLVE_MAX_EXPANSIONS and SOLVE_MAX_BACKTRACK_NODES
+    pub fn start() -> Self {
+        SolveBudget::with_limits(
+            SOLVE_TIME_LIMIT.load(Ordering::Relaxed),
+            SOLVE_MAX_EXPANSIONS.load(Ordering::Relaxed),
+            SOLVE_MAX_BACKTRACK_NODES.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn with_limits(time_limit: f64, max_expansions: usize, max_backtrack_nodes: usize) -> Self {
+        SolveBudget {
+            start: Instant::now(),
+            time_limit,
+            max_expansions,
+            max_backtrack_nodes,
+            num_astar_expansions: AtomicUsize::new(0),
+            num_backtrack_nodes: AtomicUsize::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn add_astar_expansions(&self, num_expansions: usize) {
+        self.num_astar_expansions.fetch_add(num_expansions, Ordering::Relaxed);
+    }
+
+    pub fn add_backtrack_nodes(&self, num_nodes: usize) {
+        self.num_backtrack_nodes.fetch_add(num_nodes, Ordering::Relaxed);
+    }
+
+    /// whether a check found the budget run out, without reading the clock
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// the first limit that has run out, if any
+    fn exhausted_budget(&self) -> Option<String> {
+        if self.time_limit > 0.0 {
+            let elapsed = self.start.elapsed().as_secs_f64();
+            if elapsed > self.time_limit {
+                return Some(format!("{:.2} s of {:.2} s", elapsed, self.time_limit));
+            }
+        }
+        let num_expansions = self.num_astar_expansions.load(Ordering::Relaxed);
+        if self.max_expansions > 0 && num_expansions > self.max_expansions {
+            return Some(format!("{} of {} A* expansions", num_expansions, self.max_expansions));
+        }
+        let num_nodes = self.num_backtrack_nodes.load(Ordering::Relaxed);
+        if self.max_backtrack_nodes > 0 && num_nodes > self.max_backtrack_nodes {
+            return Some(format!("{} of {} backtrack nodes", num_nodes, self.max_backtrack_nodes));
+        }
+        None
+    }
+
+    /// reads the clock and the counters, once a budget runs out every solver winds down the way it does for a stop from the user
+    /// returns whether the budget is exhausted
+    pub fn check(&self) -> bool {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return true;
+        }
+        let Some(exhausted) = self.exhausted_budget() else {
+            return false;
+        };
+        if !self.exhausted.swap(true, Ordering::Relaxed) {
+            println!("Solve budget exhausted after {}, stopping with the best solution so far", exhausted);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use shared::pcb_problem::UnroutedReason;
+
+    use super::SolveBudget;
+    use crate::{
+        bayesian_backtrack_algo::TraceCache,
+        display_injection::DisplayInjection,
+        naive_backtrack_algo::naive_backtrack,
+        test_pcb_problem::examples::{example_problem, AutoCommandLevel},
+    };
+
+    #[test]
+    fn an_exhausted_budget_stops_only_its_own_solve() {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let stop_requested = Arc::new(AtomicBool::new(false));
+        let mut display_injection = DisplayInjection {
+            budget: Arc::new(SolveBudget::with_limits(0.0, 10, 0)),
+            ..DisplayInjection::without_display(stop_requested.clone(), true)
+        };
+        // another solve that shares the stop flag of the user
+        let other_display_injection = DisplayInjection::without_display(stop_requested.clone(), true);
+
+        let mut trace_cache = TraceCache::default();
+        for net_info in problem.nets.values() {
+            for connection_id in net_info.connections.keys() {
+                trace_cache.traces.insert(*connection_id, Vec::new());
+            }
+        }
+        let solution = naive_backtrack(&problem, &mut trace_cache, None, &HashMap::new(), &mut display_injection).unwrap();
+        assert!(!solution.unrouted_connections.is_empty());
+        assert!(solution
+            .unrouted_connections
+            .iter()
+            .all(|unrouted_connection| unrouted_connection.reason == UnroutedReason::BudgetExhausted));
+        assert!(display_injection.budget.is_exhausted());
+        assert!(!stop_requested.load(Ordering::Relaxed));
+        assert!(!other_display_injection.is_stopped());
+    }
+}
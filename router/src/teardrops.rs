@@ -0,0 +1,239 @@
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+use shared::{
+    hyperparameters::{TEARDROP_LENGTH_RATIO, TEARDROP_WIDTH_RATIO},
+    pad::{Pad, PadShape},
+    pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+    teardrop::Teardrop,
+    vec2::{FixedVec2, FloatVec2},
+};
+
+use crate::obstacles::{build_pad_obstacles, PadObstacles};
+
+/// a teardrop that doesn't fit is shrunk step by step before it is given up
+const TEARDROP_SCALES: [f32; 3] = [1.0, 0.75, 0.5];
+
+/// a place where a trace segment leaves a pad or a via
+struct Junction {
+    center: FixedVec2,
+    toward: FixedVec2, // the other end of the segment
+    layer: usize,
+    size: f32, // the diameter of the pad or via
+}
+
+/// the size of a pad across, the smaller side of a rectangle so the teardrop stays on the copper whatever the rotation
+fn pad_size(pad: &Pad) -> f32 {
+    match pad.shape {
+        PadShape::Circle { diameter } => diameter,
+        PadShape::Rectangle { width, height } | PadShape::RoundRect { width, height, .. } => f32::min(width, height),
+    }
+}
+
+fn find_pad_at<'a>(pads: &[&'a Pad], position: FixedVec2, layer: usize, num_layers: usize) -> Option<&'a Pad> {
+    pads.iter().copied().find(|pad| {
+        let on_layer = pad.pad_layer.get_iter(num_layers).any(|pad_layer| pad_layer == layer);
+        on_layer && (position.to_float() - pad.position).length() < pad_size(pad) / 2.0
+    })
+}
+
+fn junctions(problem: &PcbProblem, fixed_trace: &FixedTrace) -> Vec<Junction> {
+    let net_info = &problem.nets[&fixed_trace.net_name];
+    let connection = &net_info.connections[&fixed_trace.connection_id];
+    let pads = [&net_info.pads[&connection.start_pad], &net_info.pads[&connection.end_pad]];
+    let trace_path = &fixed_trace.trace_path;
+    let mut junctions: Vec<Junction> = Vec::new();
+    if let (Some(first), Some(last)) = (trace_path.segments.first(), trace_path.segments.last()) {
+        if let Some(pad) = find_pad_at(&pads, first.start, first.layer, problem.num_layers) {
+            junctions.push(Junction {
+                center: first.start,
+                toward: first.end,
+                layer: first.layer,
+                size: pad_size(pad),
+            });
+        }
+        if let Some(pad) = find_pad_at(&pads, last.end, last.layer, problem.num_layers) {
+            junctions.push(Junction {
+                center: last.end,
+                toward: last.start,
+                layer: last.layer,
+                size: pad_size(pad),
+            });
+        }
+    }
+    for via in trace_path.vias.iter() {
+        for segment in trace_path.segments.iter() {
+            let toward = if segment.start == via.position {
+                segment.end
+            } else if segment.end == via.position {
+                segment.start
+            } else {
+                continue;
+            };
+            junctions.push(Junction {
+                center: via.position,
+                toward,
+                layer: segment.layer,
+                size: via.diameter,
+            });
+        }
+    }
+    junctions
+}
+
+/// a trapezoid from the junction center, where it is almost as wide as the junction,
+/// to a point on the segment past the junction edge, where it is as wide as the trace
+/// None if the trace is as wide as the junction or the segment ends inside it
+fn teardrop_outline(junction: &Junction, trace_width: f32, scale: f32) -> Option<Vec<FloatVec2>> {
+    let length_ratio = TEARDROP_LENGTH_RATIO.load(Ordering::Relaxed) as f32;
+    let width_ratio = TEARDROP_WIDTH_RATIO.load(Ordering::Relaxed) as f32;
+    let center = junction.center.to_float();
+    let segment = junction.toward.to_float() - center;
+    let segment_length = segment.length();
+    let half_width = f32::min(junction.size * width_ratio, junction.size) / 2.0;
+    let half_width = trace_width / 2.0 + (half_width - trace_width / 2.0) * scale;
+    let reach = f32::min(
+        junction.size / 2.0 + junction.size * length_ratio * scale,
+        segment_length,
+    );
+    if half_width <= trace_width / 2.0 || reach <= junction.size / 2.0 {
+        return None;
+    }
+    let along = segment.normalize();
+    let across = along.perp();
+    let point = |distance: f32, offset: f32| {
+        FloatVec2::new(
+            center.x + along.x * distance + across.x * offset,
+            center.y + along.y * distance + across.y * offset,
+        )
+    };
+    Some(vec![
+        point(0.0, -half_width),
+        point(reach, -trace_width / 2.0),
+        point(reach, trace_width / 2.0),
+        point(0.0, half_width),
+    ])
+}
+
+fn collides_with_obstacles(teardrop: &Teardrop, obstacles: &PadObstacles) -> bool {
+    let obstacle_colliders = &obstacles.obstacle_colliders[&teardrop.layer];
+    let obstacle_clearance_colliders = &obstacles.obstacle_clearance_colliders[&teardrop.layer];
+    obstacle_colliders.collides_with_set(teardrop.to_clearance_colliders().iter())
+        || obstacle_clearance_colliders.collides_with_set(teardrop.to_colliders().iter())
+}
+
+/// the finishing pass that adds a teardrop wherever a trace segment leaves a pad or a via
+/// a teardrop that would violate the clearance of another net is shrunk, and left out if even the smallest one does
+pub fn generate_teardrops(
+    problem: &PcbProblem,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+) -> Vec<Teardrop> {
+    let mut connection_ids: Vec<&ConnectionID> = determined_traces.keys().collect();
+    connection_ids.sort();
+    let mut obstacles_by_net: HashMap<NetName, PadObstacles> = HashMap::new();
+    let mut teardrops: Vec<Teardrop> = Vec::new();
+    for connection_id in connection_ids {
+        let fixed_trace = &determined_traces[connection_id];
+        let net_info = &problem.nets[&fixed_trace.net_name];
+        let obstacles = obstacles_by_net
+            .entry(fixed_trace.net_name.clone())
+            .or_insert_with(|| build_pad_obstacles(problem, &[&fixed_trace.net_name], determined_traces));
+        for junction in junctions(problem, fixed_trace) {
+            for scale in TEARDROP_SCALES {
+                let Some(outline) = teardrop_outline(&junction, net_info.trace_width, scale) else {
+                    break;
+                };
+                let teardrop = Teardrop {
+                    net_name: fixed_trace.net_name.clone(),
+                    layer: junction.layer,
+                    outline,
+                    clearance: net_info.trace_clearance,
+                };
+                let collides_with_other_nets = collides_with_obstacles(&teardrop, obstacles)
+                    || teardrops
+                        .iter()
+                        .any(|other| other.net_name != teardrop.net_name && other.collides_with(&teardrop));
+                if !collides_with_other_nets {
+                    teardrops.push(teardrop);
+                    break;
+                }
+            }
+        }
+    }
+    teardrops
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use shared::{
+        pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
+        teardrop::Teardrop,
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::FloatVec2,
+    };
+
+    use super::{collides_with_obstacles, generate_teardrops};
+    use crate::{
+        obstacles::build_pad_obstacles,
+        pcb_problem_solve::SolverAlgorithm,
+        test_pcb_problem::examples::{example_problem, route_example},
+    };
+
+    /// every teardrop keeps the clearance to the pads, the traces and the teardrops of the other nets
+    fn assert_clear_of_other_nets(
+        problem: &PcbProblem,
+        determined_traces: &HashMap<ConnectionID, FixedTrace>,
+        teardrops: &[Teardrop],
+    ) {
+        for (index, teardrop) in teardrops.iter().enumerate() {
+            let obstacles = build_pad_obstacles(problem, &[&teardrop.net_name], determined_traces);
+            assert!(!collides_with_obstacles(teardrop, &obstacles));
+            for other in teardrops[index + 1..].iter().filter(|other| other.net_name != teardrop.net_name) {
+                assert!(!teardrop.collides_with(other));
+            }
+        }
+    }
+
+    #[test]
+    fn a_teardrop_in_the_way_of_another_net_is_shrunk_or_left_out() {
+        let problem = example_problem(include_str!("../../examples/echo.dsn"), 3);
+        let mut determined_traces = route_example(&problem, SolverAlgorithm::NaiveBacktrack).determined_traces;
+        let teardrops = generate_teardrops(&problem, &determined_traces);
+        assert!(!teardrops.is_empty());
+        assert_clear_of_other_nets(&problem, &determined_traces, &teardrops);
+
+        // a straight trace of another net runs through the wide end of the first teardrop
+        let teardrop = &teardrops[0];
+        let mut other_connection_ids: Vec<&ConnectionID> = determined_traces
+            .iter()
+            .filter(|(_, fixed_trace)| fixed_trace.net_name != teardrop.net_name)
+            .map(|(connection_id, _)| connection_id)
+            .collect();
+        other_connection_ids.sort();
+        let other_connection_id = *other_connection_ids[0];
+        let other_net_name = determined_traces[&other_connection_id].net_name.clone();
+        let other_net_info = &problem.nets[&other_net_name];
+        let wide_corner = teardrop.outline[3];
+        let anchors = [-0.5, 0.5]
+            .into_iter()
+            .map(|offset| TraceAnchor {
+                position: FloatVec2::new(wide_corner.x + offset, wide_corner.y).to_fixed().to_nearest_even_even(),
+                start_layer: teardrop.layer,
+                end_layer: teardrop.layer,
+            })
+            .collect();
+        let trace_path =
+            TracePath::from_anchors(TraceAnchors(anchors), other_net_info.trace_width, other_net_info.trace_clearance, vec![]);
+        determined_traces.insert(
+            other_connection_id,
+            FixedTrace { net_name: other_net_name, connection_id: other_connection_id, trace_path },
+        );
+
+        let clipped_teardrops = generate_teardrops(&problem, &determined_traces);
+        assert!(!clipped_teardrops
+            .iter()
+            .any(|clipped| clipped.layer == teardrop.layer && clipped.outline == teardrop.outline));
+        assert_clear_of_other_nets(&problem, &determined_traces, &clipped_teardrops);
+    }
+}
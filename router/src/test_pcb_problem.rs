@@ -265,3 +265,89 @@
 //     pcb_problem.add_connection(yellow_net_name.clone(), yellow_sink_pad, 0.4, 0.1);
 //     pcb_problem
 // }
+
+/// boards of examples/ for the tests of the solvers and the post routing passes
+#[cfg(test)]
+pub(crate) mod examples {
+    use std::{
+        collections::HashMap,
+        sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex},
+    };
+
+    use parser::parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end};
+    use shared::pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem, PcbSolution};
+
+    use crate::{
+        command_flags::{CommandFlag, TARGET_COMMAND_LEVEL},
+        display_injection::DisplayInjection,
+        pcb_problem_solve::{solve_pcb_problem, SolverAlgorithm},
+    };
+
+    /// how many tests hold the command level at auto, and the level before the first of them
+    static AUTO_COMMAND_LEVEL_HOLDERS: Mutex<(usize, u8)> = Mutex::new((0, 0));
+
+    /// holds the command level at auto while alive, below it A* is stepped through and waits for a display
+    /// the level is put back when the last holder is dropped, the tests run in parallel
+    pub(crate) struct AutoCommandLevel;
+
+    impl AutoCommandLevel {
+        pub(crate) fn hold() -> Self {
+            let mut holders = AUTO_COMMAND_LEVEL_HOLDERS.lock().unwrap();
+            if holders.0 == 0 {
+                holders.1 = TARGET_COMMAND_LEVEL.load(Ordering::Relaxed);
+                TARGET_COMMAND_LEVEL.store(CommandFlag::Auto.get_level(), Ordering::Relaxed);
+            }
+            holders.0 += 1;
+            AutoCommandLevel
+        }
+    }
+
+    impl Drop for AutoCommandLevel {
+        fn drop(&mut self) {
+            let mut holders = AUTO_COMMAND_LEVEL_HOLDERS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            holders.0 -= 1;
+            if holders.0 == 0 {
+                TARGET_COMMAND_LEVEL.store(holders.1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// a board with only its first nets in name order that have connections, so a test can route it quickly
+    /// the other nets are left out entirely, their pads are no obstacles either
+    pub(crate) fn example_problem(dsn_file_content: &str, num_nets: usize) -> PcbProblem {
+        let dsn_struct = parse_start_to_dsn_struct(dsn_file_content.to_string()).unwrap();
+        let mut problem = parse_struct_to_end(&dsn_struct).unwrap();
+        let mut net_names: Vec<NetName> = problem
+            .nets
+            .values()
+            .filter(|net_info| !net_info.connections.is_empty())
+            .map(|net_info| net_info.net_name.clone())
+            .collect();
+        net_names.sort();
+        net_names.truncate(num_nets);
+        problem.nets.retain(|net_name, _| net_names.contains(net_name));
+        problem
+    }
+
+    /// routes a board on the calling thread without a display
+    pub(crate) fn route_example(problem: &PcbProblem, algorithm: SolverAlgorithm) -> PcbSolution {
+        let _auto_command_level = AutoCommandLevel::hold();
+        let mut display_injection = DisplayInjection::without_display(Arc::new(AtomicBool::new(false)), true);
+        solve_pcb_problem(problem, algorithm, &mut display_injection).unwrap()
+    }
+
+    /// no trace collides with a trace of another net
+    pub(crate) fn assert_no_collisions(fixed_traces: &HashMap<ConnectionID, FixedTrace>) {
+        let fixed_traces: Vec<&FixedTrace> = fixed_traces.values().collect();
+        for (index, fixed_trace) in fixed_traces.iter().enumerate() {
+            for other in fixed_traces[index + 1..].iter().filter(|other| other.net_name != fixed_trace.net_name) {
+                assert!(
+                    !fixed_trace.trace_path.collides_with(&other.trace_path),
+                    "the traces of {} and {} collide",
+                    fixed_trace.net_name.0,
+                    other.net_name.0
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::atomic::Ordering,
+};
+
+use cgmath::{Deg, Matrix2, Vector2};
+use shared::{
+    collider::Collider,
+    hyperparameters::{ZONE_FILL_RESOLUTION, ZONE_MIN_WIDTH},
+    pad::{Pad, PadShape},
+    pcb_problem::{ConnectionID, FixedTrace, PcbProblem},
+    prim_shape::{PrimShape, RectangleShape},
+    teardrop::Teardrop,
+    vec2::FloatVec2,
+    zone::{Zone, ZoneFill, ZoneOutline},
+};
+
+use crate::{astar::AStarModel, obstacles::build_pad_obstacles, quad_tree::QuadTreeNode};
+
+/// the zone is sampled on a grid, a cell is copper or it isn't
+struct ZoneGrid {
+    min: FloatVec2,
+    resolution: f32,
+    num_columns: usize,
+    num_rows: usize,
+}
+
+impl ZoneGrid {
+    fn cell_center(&self, column: usize, row: usize) -> FloatVec2 {
+        FloatVec2::new(
+            self.min.x + (column as f32 + 0.5) * self.resolution,
+            self.min.y + (row as f32 + 0.5) * self.resolution,
+        )
+    }
+
+    fn cell_shape(&self, column: usize, row: usize, margin: f32) -> PrimShape {
+        PrimShape::Rectangle(RectangleShape {
+            position: self.cell_center(column, row),
+            width: self.resolution + margin * 2.0,
+            height: self.resolution + margin * 2.0,
+            rotation_in_degs: 0.0,
+        })
+    }
+
+    fn index(&self, column: usize, row: usize) -> usize {
+        row * self.num_columns + column
+    }
+
+    /// the columns of the cells between two x coordinates, clamped to the grid
+    fn column_range(&self, x_min: f32, x_max: f32) -> (usize, usize) {
+        let start = ((x_min - self.min.x) / self.resolution).floor().max(0.0) as usize;
+        let end = ((x_max - self.min.x) / self.resolution).ceil().max(0.0) as usize;
+        (usize::min(start, self.num_columns), usize::min(end, self.num_columns))
+    }
+
+    fn row_range(&self, y_min: f32, y_max: f32) -> (usize, usize) {
+        let start = ((y_min - self.min.y) / self.resolution).floor().max(0.0) as usize;
+        let end = ((y_max - self.min.y) / self.resolution).ceil().max(0.0) as usize;
+        (usize::min(start, self.num_rows), usize::min(end, self.num_rows))
+    }
+}
+
+fn new_quad_tree(problem: &PcbProblem) -> QuadTreeNode {
+    let side_length = f32::max(problem.width, problem.height);
+    QuadTreeNode::new(
+        problem.center.x - side_length / 2.0,
+        problem.center.x + side_length / 2.0,
+        problem.center.y - side_length / 2.0,
+        problem.center.y + side_length / 2.0,
+        0,
+    )
+}
+
+/// the number of set cells in every box from (0, 0) to (column, row), exclusive
+fn prefix_sums(grid: &ZoneGrid, cells: &[bool]) -> Vec<usize> {
+    let width = grid.num_columns + 1;
+    let mut sums: Vec<usize> = vec![0; width * (grid.num_rows + 1)];
+    for row in 0..grid.num_rows {
+        for column in 0..grid.num_columns {
+            let cell = cells[grid.index(column, row)] as usize;
+            sums[(row + 1) * width + column + 1] =
+                cell + sums[row * width + column + 1] + sums[(row + 1) * width + column] - sums[row * width + column];
+        }
+    }
+    sums
+}
+
+fn box_sum(grid: &ZoneGrid, sums: &[usize], column_range: (usize, usize), row_range: (usize, usize)) -> usize {
+    let width = grid.num_columns + 1;
+    let (column_start, column_end) = column_range;
+    let (row_start, row_end) = row_range;
+    sums[row_end * width + column_end] + sums[row_start * width + column_start]
+        - sums[row_start * width + column_end]
+        - sums[row_end * width + column_start]
+}
+
+/// a morphological opening with a square of the minimum width,
+/// copper that a square of that size can't be moved through is removed
+fn remove_narrow_copper(grid: &ZoneGrid, cells: &[bool], min_width: f32) -> Vec<bool> {
+    let size = (min_width / grid.resolution).ceil() as usize;
+    if size <= 1 {
+        return cells.to_vec();
+    }
+    let sums = prefix_sums(grid, cells);
+    // a core cell is the corner of a full square of copper
+    let mut cores: Vec<bool> = vec![false; cells.len()];
+    for row in 0..grid.num_rows.saturating_sub(size - 1) {
+        for column in 0..grid.num_columns.saturating_sub(size - 1) {
+            cores[grid.index(column, row)] =
+                box_sum(grid, &sums, (column, column + size), (row, row + size)) == size * size;
+        }
+    }
+    let core_sums = prefix_sums(grid, &cores);
+    let mut opened: Vec<bool> = vec![false; cells.len()];
+    for row in 0..grid.num_rows {
+        for column in 0..grid.num_columns {
+            let index = grid.index(column, row);
+            let column_range = (column.saturating_sub(size - 1), column + 1);
+            let row_range = (row.saturating_sub(size - 1), row + 1);
+            opened[index] = cells[index] && box_sum(grid, &core_sums, column_range, row_range) > 0;
+        }
+    }
+    opened
+}
+
+/// the radius of a circle around the pad center that covers the whole pad
+fn pad_radius(pad: &Pad) -> f32 {
+    match pad.shape {
+        PadShape::Circle { diameter } => diameter / 2.0,
+        PadShape::Rectangle { width, height } | PadShape::RoundRect { width, height, .. } => {
+            FloatVec2::new(width, height).length() / 2.0
+        }
+    }
+}
+
+/// whether a point is within the gap around a pad, in the frame of the pad
+fn in_thermal_gap(pad: &Pad, offset: Vector2<f32>, gap: f32) -> bool {
+    match pad.shape {
+        PadShape::Circle { diameter } => (offset.x * offset.x + offset.y * offset.y).sqrt() <= diameter / 2.0 + gap,
+        PadShape::Rectangle { width, height } | PadShape::RoundRect { width, height, .. } => {
+            offset.x.abs() <= width / 2.0 + gap && offset.y.abs() <= height / 2.0 + gap
+        }
+    }
+}
+
+/// clears a gap around every pad of the net, except for four spokes along the axes of the pad
+fn cut_thermal_reliefs(
+    grid: &ZoneGrid,
+    cells: &mut [bool],
+    pads: &[&Pad],
+    gap: f32,
+    spoke_width: f32,
+) {
+    let half_spoke_width = f32::max(spoke_width, grid.resolution) / 2.0;
+    // the whole cell has to be clear of the pad, not just its center
+    let cell_margin = grid.resolution * std::f32::consts::FRAC_1_SQRT_2;
+    for pad in pads {
+        let rotation = Matrix2::from_angle(-Deg(pad.rotation.0));
+        let reach = pad_radius(pad) + gap + grid.resolution;
+        let column_range = grid.column_range(pad.position.x - reach, pad.position.x + reach);
+        let row_range = grid.row_range(pad.position.y - reach, pad.position.y + reach);
+        for row in row_range.0..row_range.1 {
+            for column in column_range.0..column_range.1 {
+                let index = grid.index(column, row);
+                if !cells[index] {
+                    continue;
+                }
+                let center = grid.cell_center(column, row);
+                let offset = rotation * Vector2::new(center.x - pad.position.x, center.y - pad.position.y);
+                let in_spoke = offset.x.abs() <= half_spoke_width || offset.y.abs() <= half_spoke_width;
+                if !in_spoke && in_thermal_gap(pad, offset, gap + cell_margin) {
+                    cells[index] = false;
+                }
+            }
+        }
+    }
+}
+
+/// the 4-connected groups of set cells, as lists of cell indices
+fn find_islands(grid: &ZoneGrid, cells: &[bool]) -> Vec<Vec<usize>> {
+    let mut islands: Vec<Vec<usize>> = Vec::new();
+    let mut visited: Vec<bool> = vec![false; cells.len()];
+    for start in 0..cells.len() {
+        if !cells[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut island: Vec<usize> = Vec::new();
+        let mut queue: VecDeque<usize> = VecDeque::from([start]);
+        while let Some(index) = queue.pop_front() {
+            island.push(index);
+            let (column, row) = (index % grid.num_columns, index / grid.num_columns);
+            let mut neighbors: Vec<usize> = Vec::new();
+            if column > 0 {
+                neighbors.push(index - 1);
+            }
+            if column + 1 < grid.num_columns {
+                neighbors.push(index + 1);
+            }
+            if row > 0 {
+                neighbors.push(index - grid.num_columns);
+            }
+            if row + 1 < grid.num_rows {
+                neighbors.push(index + grid.num_columns);
+            }
+            for neighbor in neighbors {
+                if cells[neighbor] && !visited[neighbor] {
+                    visited[neighbor] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        islands.push(island);
+    }
+    islands
+}
+
+/// keeps the islands that touch copper of the net, the others would be floating copper
+fn remove_islands(grid: &ZoneGrid, cells: &[bool], anchors: &[bool]) -> Vec<bool> {
+    let mut kept: Vec<bool> = vec![false; cells.len()];
+    for island in find_islands(grid, cells) {
+        if island.iter().any(|&index| anchors[index]) {
+            for index in island {
+                kept[index] = true;
+            }
+        }
+    }
+    kept
+}
+
+/// the signed area of a closed polygon, positive when it is counterclockwise
+fn signed_area(polygon: &[FloatVec2]) -> f32 {
+    let mut area = 0.0;
+    for (index, start) in polygon.iter().enumerate() {
+        let end = polygon[(index + 1) % polygon.len()];
+        area += start.x * end.y - end.x * start.y;
+    }
+    area / 2.0
+}
+
+/// the boundary of every island, traced along the edges between set and clear cells
+/// each edge keeps the copper on its left, so outlines come out counterclockwise and holes clockwise
+fn cells_to_outlines(grid: &ZoneGrid, cells: &[bool]) -> Vec<ZoneOutline> {
+    // the corners of the cells, and the directions +x, +y, -x, -y
+    const STEPS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let is_set = |column: isize, row: isize| {
+        column >= 0
+            && row >= 0
+            && (column as usize) < grid.num_columns
+            && (row as usize) < grid.num_rows
+            && cells[grid.index(column as usize, row as usize)]
+    };
+    let corner_position = |(column, row): (isize, isize)| {
+        FloatVec2::new(
+            grid.min.x + column as f32 * grid.resolution,
+            grid.min.y + row as f32 * grid.resolution,
+        )
+    };
+
+    let mut outlines: Vec<ZoneOutline> = Vec::new();
+    for island in find_islands(grid, cells) {
+        // the boundary edges of the island, by the corner they start from
+        let mut edges: HashMap<(isize, isize), Vec<usize>> = HashMap::new();
+        for &index in island.iter() {
+            let (column, row) = ((index % grid.num_columns) as isize, (index / grid.num_columns) as isize);
+            let sides = [
+                (!is_set(column, row - 1), (column, row), 0),
+                (!is_set(column + 1, row), (column + 1, row), 1),
+                (!is_set(column, row + 1), (column + 1, row + 1), 2),
+                (!is_set(column - 1, row), (column, row + 1), 3),
+            ];
+            for (is_boundary, start, direction) in sides {
+                if is_boundary {
+                    edges.entry(start).or_default().push(direction);
+                }
+            }
+        }
+
+        let mut loops: Vec<Vec<FloatVec2>> = Vec::new();
+        let mut starts: Vec<(isize, isize)> = edges.keys().cloned().collect();
+        starts.sort();
+        for start in starts {
+            while let Some(first_direction) = edges.get_mut(&start).and_then(|directions| directions.pop()) {
+                let mut polygon: Vec<FloatVec2> = Vec::new();
+                let mut corner = start;
+                let mut direction = first_direction;
+                loop {
+                    corner = (corner.0 + STEPS[direction].0, corner.1 + STEPS[direction].1);
+                    let directions = edges.entry(corner).or_default();
+                    // where two cells touch only at a corner, turning left keeps the loops apart
+                    let turn = [(direction + 1) % 4, direction, (direction + 3) % 4]
+                        .into_iter()
+                        .find(|turn| directions.contains(turn) || (corner == start && *turn == first_direction));
+                    let Some(next_direction) = turn else {
+                        break;
+                    };
+                    if next_direction != direction {
+                        polygon.push(corner_position(corner));
+                    }
+                    if corner == start && next_direction == first_direction {
+                        break;
+                    }
+                    directions.retain(|candidate| *candidate != next_direction);
+                    direction = next_direction;
+                }
+                loops.push(polygon);
+            }
+        }
+
+        // the cells of an island are 4-connected, so it has exactly one outer boundary
+        let Some(boundary_index) = loops.iter().position(|polygon| signed_area(polygon) > 0.0) else {
+            continue;
+        };
+        let boundary = loops.swap_remove(boundary_index);
+        outlines.push(ZoneOutline { boundary, holes: loops });
+    }
+    outlines
+}
+
+/// runs of copper along each row, merged with the same run on the rows above
+fn cells_to_rectangles(grid: &ZoneGrid, cells: &[bool]) -> Vec<RectangleShape> {
+    // (first column, last column exclusive) to the first row of the open rectangle
+    let mut open: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut rectangles: Vec<RectangleShape> = Vec::new();
+    let mut close = |(column_start, column_end): (usize, usize), row_start: usize, row_end: usize| {
+        let width = (column_end - column_start) as f32 * grid.resolution;
+        let height = (row_end - row_start) as f32 * grid.resolution;
+        rectangles.push(RectangleShape {
+            position: FloatVec2::new(
+                grid.min.x + column_start as f32 * grid.resolution + width / 2.0,
+                grid.min.y + row_start as f32 * grid.resolution + height / 2.0,
+            ),
+            width,
+            height,
+            rotation_in_degs: 0.0,
+        });
+    };
+    for row in 0..=grid.num_rows {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        if row < grid.num_rows {
+            let mut column = 0;
+            while column < grid.num_columns {
+                if cells[grid.index(column, row)] {
+                    let column_start = column;
+                    while column < grid.num_columns && cells[grid.index(column, row)] {
+                        column += 1;
+                    }
+                    runs.push((column_start, column));
+                } else {
+                    column += 1;
+                }
+            }
+        }
+        let mut ended: Vec<(usize, usize)> = open.keys().filter(|run| !runs.contains(run)).cloned().collect();
+        ended.sort();
+        for run in ended {
+            let row_start = open.remove(&run).unwrap();
+            close(run, row_start, row);
+        }
+        for run in runs {
+            open.entry(run).or_insert(row);
+        }
+    }
+    rectangles
+}
+
+fn fill_zone(
+    problem: &PcbProblem,
+    zone: &Zone,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+    teardrops: &[Teardrop],
+    other_fills: &[ZoneFill],
+) -> ZoneFill {
+    let net_info = &problem.nets[&zone.net_name];
+    let clearance = net_info.trace_clearance;
+    let min_width = ZONE_MIN_WIDTH.load(Ordering::Relaxed) as f32;
+    let (min, max) = zone.bounding_box();
+    let resolution = ZONE_FILL_RESOLUTION.load(Ordering::Relaxed) as f32;
+    let grid = ZoneGrid {
+        min,
+        resolution,
+        num_columns: ((max.x - min.x) / resolution).ceil() as usize,
+        num_rows: ((max.y - min.y) / resolution).ceil() as usize,
+    };
+
+    // the copper of the other nets, with the teardrops and the zones filled before this one
+    let obstacles = build_pad_obstacles(problem, &[&zone.net_name], determined_traces);
+    let obstacle_colliders = &obstacles.obstacle_colliders[&zone.layer];
+    let obstacle_clearance_colliders = &obstacles.obstacle_clearance_colliders[&zone.layer];
+    let mut finishing_colliders = new_quad_tree(problem);
+    let mut finishing_clearance_colliders = new_quad_tree(problem);
+    for teardrop in teardrops
+        .iter()
+        .filter(|teardrop| teardrop.layer == zone.layer && teardrop.net_name != zone.net_name)
+    {
+        finishing_colliders.extend(teardrop.to_colliders().into_iter());
+        finishing_clearance_colliders.extend(teardrop.to_clearance_colliders().into_iter());
+    }
+    for zone_fill in other_fills
+        .iter()
+        .filter(|zone_fill| zone_fill.layer == zone.layer && zone_fill.net_name != zone.net_name)
+    {
+        let other_clearance = problem.nets[&zone_fill.net_name].trace_clearance;
+        for rectangle in zone_fill.rectangles.iter() {
+            finishing_colliders.insert(Collider::from_prim_shape(&PrimShape::Rectangle(rectangle.clone())));
+            finishing_clearance_colliders.insert(Collider::from_prim_shape(&PrimShape::Rectangle(RectangleShape {
+                width: rectangle.width + other_clearance * 2.0,
+                height: rectangle.height + other_clearance * 2.0,
+                ..rectangle.clone()
+            })));
+        }
+    }
+    let border_colliders = AStarModel::calculate_border_colliders(problem.width, problem.height, problem.center);
+
+    let mut cells: Vec<bool> = vec![false; grid.num_columns * grid.num_rows];
+    for row in 0..grid.num_rows {
+        for column in 0..grid.num_columns {
+            if !zone.contains(grid.cell_center(column, row)) {
+                continue;
+            }
+            let cell_collider = Collider::from_prim_shape(&grid.cell_shape(column, row, 0.0));
+            let cell_clearance_collider = Collider::from_prim_shape(&grid.cell_shape(column, row, clearance));
+            let blocked = border_colliders.iter().any(|border| border.collides_with(&cell_collider))
+                || obstacle_colliders.collides_with(&cell_clearance_collider)
+                || obstacle_clearance_colliders.collides_with(&cell_collider)
+                || finishing_colliders.collides_with(&cell_clearance_collider)
+                || finishing_clearance_colliders.collides_with(&cell_collider);
+            cells[grid.index(column, row)] = !blocked;
+        }
+    }
+
+    let mut cells = remove_narrow_copper(&grid, &cells, min_width);
+    let pads: Vec<&Pad> = net_info
+        .pads
+        .values()
+        .filter(|pad| pad.pad_layer.get_iter(problem.num_layers).any(|layer| layer == zone.layer))
+        .collect();
+    let spoke_width = f32::max(net_info.trace_width, min_width);
+    cut_thermal_reliefs(&grid, &mut cells, &pads, clearance, spoke_width);
+
+    // the copper of the net the zone connects to
+    let mut net_colliders = new_quad_tree(problem);
+    for pad in pads.iter() {
+        net_colliders.extend(pad.to_shapes().iter().map(Collider::from_prim_shape));
+    }
+    for fixed_trace in determined_traces
+        .values()
+        .filter(|fixed_trace| fixed_trace.net_name == zone.net_name)
+    {
+        let shapes = fixed_trace.trace_path.to_shapes(problem.num_layers);
+        net_colliders.extend(shapes[&zone.layer].iter().map(Collider::from_prim_shape));
+    }
+    let anchors: Vec<bool> = (0..cells.len())
+        .map(|index| {
+            let (column, row) = (index % grid.num_columns, index / grid.num_columns);
+            cells[index] && net_colliders.collides_with(&Collider::from_prim_shape(&grid.cell_shape(column, row, 0.0)))
+        })
+        .collect();
+    let cells = remove_islands(&grid, &cells, &anchors);
+
+    ZoneFill {
+        net_name: zone.net_name.clone(),
+        layer: zone.layer,
+        rectangles: cells_to_rectangles(&grid, &cells),
+        outlines: cells_to_outlines(&grid, &cells),
+    }
+}
+
+/// the finishing pass that pours the zones of the problem, after routing and the teardrops
+/// the fill keeps the clearance to everything of the other nets, connects to the pads of its net with thermal spokes,
+/// drops copper narrower than the minimum width and islands that don't touch its net
+pub fn fill_zones(
+    problem: &PcbProblem,
+    determined_traces: &HashMap<ConnectionID, FixedTrace>,
+    teardrops: &[Teardrop],
+) -> Vec<ZoneFill> {
+    let mut zone_fills: Vec<ZoneFill> = Vec::new();
+    for zone in problem.zones.iter() {
+        let zone_fill = fill_zone(problem, zone, determined_traces, teardrops, &zone_fills);
+        println!(
+            "Filled zone of net {} on layer {} with {} rectangles",
+            zone.net_name.0,
+            zone.layer,
+            zone_fill.rectangles.len()
+        );
+        zone_fills.push(zone_fill);
+    }
+    zone_fills
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use shared::{
+        collider::Collider,
+        pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+        prim_shape::PrimShape,
+        trace_path::{TraceAnchor, TraceAnchors, TracePath},
+        vec2::FloatVec2,
+        zone::{Zone, ZoneFill},
+    };
+
+    use super::{fill_zones, pad_radius};
+    use crate::test_pcb_problem::examples::example_problem;
+
+    /// the first two nets of echo in name order, with a zone of the first around its rightmost pad,
+    /// and a straight trace of the second that cuts the zone in two a little right of that pad
+    fn zone_behind_a_wall() -> (PcbProblem, FixedTrace, f32) {
+        let mut problem = example_problem(include_str!("../../examples/echo.dsn"), 2);
+        let mut net_names: Vec<NetName> = problem.nets.keys().cloned().collect();
+        net_names.sort();
+        let pad = problem.nets[&net_names[0]]
+            .pads
+            .values()
+            .max_by(|pad1, pad2| pad1.position.x.total_cmp(&pad2.position.x))
+            .unwrap()
+            .clone();
+        let layer = pad.pad_layer.get_iter(problem.num_layers).next().unwrap();
+        let wall_x = pad.position.x + pad_radius(&pad) + 1.0;
+        assert!(wall_x + 2.0 < problem.center.x + problem.width / 2.0);
+        problem.zones = vec![Zone {
+            net_name: net_names[0].clone(),
+            layer,
+            outline: vec![
+                FloatVec2::new(pad.position.x - 3.0, pad.position.y - 3.0),
+                FloatVec2::new(wall_x + 2.0, pad.position.y - 3.0),
+                FloatVec2::new(wall_x + 2.0, pad.position.y + 3.0),
+                FloatVec2::new(pad.position.x - 3.0, pad.position.y + 3.0),
+            ],
+        }];
+
+        let wall_net_info = &problem.nets[&net_names[1]];
+        let anchors = [-4.0, 4.0]
+            .into_iter()
+            .map(|offset| TraceAnchor {
+                position: FloatVec2::new(wall_x, pad.position.y + offset).to_fixed().to_nearest_even_even(),
+                start_layer: layer,
+                end_layer: layer,
+            })
+            .collect();
+        let connection_id = *wall_net_info.connections.keys().min().unwrap();
+        let trace_path =
+            TracePath::from_anchors(TraceAnchors(anchors), wall_net_info.trace_width, wall_net_info.trace_clearance, vec![]);
+        let wall = FixedTrace { net_name: net_names[1].clone(), connection_id, trace_path };
+        (problem, wall, wall_x)
+    }
+
+    /// whether any copper of the fill reaches right of x
+    fn copper_beyond(zone_fill: &ZoneFill, x: f32) -> bool {
+        zone_fill.rectangles.iter().any(|rectangle| rectangle.position.x + rectangle.width / 2.0 > x)
+    }
+
+    #[test]
+    fn copper_cut_off_from_its_net_is_removed() {
+        let (problem, wall, wall_x) = zone_behind_a_wall();
+        let without_wall = fill_zones(&problem, &HashMap::new(), &[]);
+        assert!(copper_beyond(&without_wall[0], wall_x));
+
+        // nothing of the zone net is right of the wall, so the copper there is an island
+        let determined_traces: HashMap<ConnectionID, FixedTrace> = HashMap::from([(wall.connection_id, wall.clone())]);
+        let with_wall = fill_zones(&problem, &determined_traces, &[]);
+        let zone_fill = &with_wall[0];
+        assert!(!zone_fill.rectangles.is_empty());
+        assert!(!zone_fill.outlines.is_empty());
+        assert!(!copper_beyond(zone_fill, wall_x));
+        // and the copper that is left keeps its clearance to the wall
+        let wall_clearance_colliders = &wall.trace_path.to_clearance_colliders(problem.num_layers)[&zone_fill.layer];
+        for rectangle in zone_fill.rectangles.iter() {
+            let collider = Collider::from_prim_shape(&PrimShape::Rectangle(rectangle.clone()));
+            assert!(!wall_clearance_colliders.iter().any(|wall_collider| wall_collider.collides_with(&collider)));
+        }
+    }
+}
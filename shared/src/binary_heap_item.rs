@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryHeapItem<T, U> {
     pub key: T,   // used for ordering
     pub value: U, // not used for ordering
@@ -5,7 +5,7 @@ use crate::{
     vec2::FloatVec2,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CircleCollider {
     pub position: FloatVec2,
     pub diameter: f32,
@@ -13,16 +13,16 @@ pub struct CircleCollider {
 
 /// polygon is used only for collision detection, not for rendering
 /// a line is a special polygon
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PolygonCollider(pub Vec<FloatVec2>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BorderCollider {
     pub point_on_border: FloatVec2,
     pub normal: FloatVec2,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Collider {
     Circle(CircleCollider),
     Polygon(PolygonCollider),
@@ -78,6 +78,7 @@ impl Collider {
                 Collider::Polygon(Collider::rectangle_to_polygon(&rectangle))
             }
             PrimShape::Line(line) => Collider::Polygon(PolygonCollider(vec![line.start, line.end])),
+            PrimShape::Polygon(polygon) => Collider::Polygon(PolygonCollider(polygon.vertices.clone())),
         }
     }
     fn circle_circle(circle1: &CircleCollider, circle2: &CircleCollider) -> bool {
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, num::NonZeroUsize, sync::{atomic::AtomicUsize, Mutex}};
+use std::{collections::HashMap, num::NonZeroUsize, sync::{atomic::{AtomicBool, AtomicUsize}, Mutex}};
 
 use atomic_float::AtomicF64;
 use lazy_static::lazy_static;
@@ -8,31 +8,109 @@ use crate::{color_float3::ColorFloat3, vec2::FixedPoint};
 pub static HALF_PROBABILITY_RAW_SCORE: AtomicF64 = AtomicF64::new(10.0);
 pub static HALF_PROBABILITY_OPPORTUNITY_COST: AtomicF64 = AtomicF64::new(0.5);
 
+// weights of the default trace scorer, the raw score of a trace is their weighted sum
+// by default only the length counts, which ranks the candidates as the length score always did
+pub static SCORE_LENGTH_WEIGHT: AtomicF64 = AtomicF64::new(1.0); // Raw score per unit of trace length
+pub static SCORE_VIA_WEIGHT: AtomicF64 = AtomicF64::new(0.0); // Raw score per via
+pub static SCORE_BEND_WEIGHT: AtomicF64 = AtomicF64::new(0.0); // Raw score per turn between two segments on a layer
+pub static SCORE_CLEARANCE_WEIGHT: AtomicF64 = AtomicF64::new(0.0); // Raw score per pad of another net within the clearance margin
+pub static SCORE_CLEARANCE_MARGIN: AtomicF64 = AtomicF64::new(0.1); // Spacing beyond the clearance that a trace should keep from the pads of other nets
+
+// inference of the posteriors of the probabilistic model
+pub static INFERENCE_ENGINE: AtomicUsize = AtomicUsize::new(0); // 0 fixed point, 1 loopy belief propagation, 2 Gibbs sampling, 3 mean field
+pub static INFERENCE_MAX_ITERATIONS: AtomicUsize = AtomicUsize::new(100); // Iterations of belief propagation and mean field, sweeps of Gibbs sampling
+pub static INFERENCE_TOLERANCE: AtomicF64 = AtomicF64::new(1e-4); // Largest change of a posterior or message at which an iterative engine stops
+pub static FIXED_POINT_ITERATIONS: AtomicUsize = AtomicUsize::new(10); // Synchronous posterior updates of the fixed point engine, it runs all of them whatever the residual
+
 // pub const MAX_TRACES_PER_ITERATION: usize = 4; // Maximum number of traces per iteration
 pub static MAX_GENERATION_ATTEMPTS: AtomicUsize = AtomicUsize::new(4); // Maximum number of attempts to generate a trace
 
-pub static FIRST_ITERATION_PROBABILITY: AtomicF64 = AtomicF64::new(0.8); // Probability for the first iteration
-pub static SECOND_ITERATION_PROBABILITY: AtomicF64 = AtomicF64::new(0.6); // Probability for the second iteration
-
-pub static FIRST_ITERATION_NUM_TRACES: AtomicUsize = AtomicUsize::new(1); // this is immutable, just for consistency
+// diversity of the candidate traces sampled for a connection
+pub static DIVERSITY_PENALTY: AtomicF64 = AtomicF64::new(1.0); // Extra A* cost per unit of length through cells used by earlier candidates of the same connection, 0 turns it off
+pub static DIVERSITY_ALTERNATIVES: AtomicUsize = AtomicUsize::new(0); // Extra candidates searched right after a new one, each avoiding the ones before it
+pub static DIVERSITY_MIN_DISTANCE: AtomicF64 = AtomicF64::new(0.2); // An alternative closer than this (mm, Hausdorff distance) to an earlier candidate is dropped
 
-pub static SECOND_ITERATION_NUM_TRACES: AtomicUsize = AtomicUsize::new(3);
+// the sampling rounds of the probabilistic model are set by SAMPLING_SCHEDULE below
+pub static SCHEDULE_STABLE_TOLERANCE: AtomicF64 = AtomicF64::new(0.01); // The schedule stops early once no posterior changed more than this over a round, 0 runs every round
 
 // pub const BLOCK_THREAD: bool = true; // Whether to block the thread when waiting for a trace to be generated
 // pub const DISPLAY_ASTAR: bool = true; // Whether to display the A* search process
 
 pub static ASTAR_MAX_EXPANSIONS: AtomicUsize = AtomicUsize::new(3000); // Maximum number of trials to find a trace
 
+// global budgets of one solve, when one runs out the solver stops and returns the best solution it has so far
+pub static SOLVE_TIME_LIMIT: AtomicF64 = AtomicF64::new(0.0); // Wall-clock limit (s) of a whole solve, 0 means no limit
+pub static SOLVE_MAX_EXPANSIONS: AtomicUsize = AtomicUsize::new(0); // Limit on the A* expansions summed over all calls of a solve, 0 means no limit
+pub static SOLVE_MAX_BACKTRACK_NODES: AtomicUsize = AtomicUsize::new(0); // Limit on the nodes the backtracking solvers push, 0 means no limit
+
+pub static CHECKPOINT_INTERVAL: AtomicF64 = AtomicF64::new(60.0); // Seconds between checkpoints of a backtracking search, 0 only writes one when the solve stops
+
 pub static VIA_COST: AtomicF64 = AtomicF64::new(5.0); // Cost of placing a via
+pub static BLIND_VIA_COST: AtomicF64 = AtomicF64::new(4.0); // Cost of placing a blind via
+pub static BURIED_VIA_COST: AtomicF64 = AtomicF64::new(4.0); // Cost of placing a buried via
+pub static MICRO_VIA_COST: AtomicF64 = AtomicF64::new(3.0); // Cost of placing a laser micro via
+
+pub static DIFF_PAIR_MAX_UNCOUPLED_LENGTH: AtomicF64 = AtomicF64::new(2.0); // Longest fanout (mm) from a pad to the coupled section of a differential pair
+pub static USE_TEARDROPS: AtomicBool = AtomicBool::new(false); // Whether to add teardrops where traces meet pads and vias after routing
+pub static TEARDROP_LENGTH_RATIO: AtomicF64 = AtomicF64::new(0.5); // How far a teardrop reaches past the pad or via edge, relative to its size
+pub static TEARDROP_WIDTH_RATIO: AtomicF64 = AtomicF64::new(0.9); // Width of a teardrop at the pad or via center, relative to its size
+pub static USE_FANOUT: AtomicBool = AtomicBool::new(false); // Whether to fan out dense SMD and BGA footprints with stubs and vias before routing
+pub static FANOUT_MAX_PITCH: AtomicF64 = AtomicF64::new(1.0); // Pads closer than this (mm) to each other are treated as a dense array and fanned out
+pub static ZONE_FILL_RESOLUTION: AtomicF64 = AtomicF64::new(0.1); // Size (mm) of the grid cells a zone is filled with
+pub static ZONE_MIN_WIDTH: AtomicF64 = AtomicF64::new(0.2); // Copper of a zone fill narrower than this (mm) is removed
+pub static MEANDER_MAX_AMPLITUDE: AtomicF64 = AtomicF64::new(1.0); // Largest distance (mm) a meander inserted by length tuning moves away from the trace
+pub static ARC_CORNER_RADIUS: AtomicF64 = AtomicF64::new(0.0); // Radius (mm) of the arcs that replace the corners of the traces after routing, 0 keeps the corners sharp
+pub static SHOVE_MAX_DEPTH: AtomicUsize = AtomicUsize::new(0); // How many traces deep a new trace may shove fixed traces aside, each shoved trace pushing the next, 0 turns shoving off, 2 is a good depth to turn it on with
+pub static SHOVE_MAX_DISTANCE: AtomicF64 = AtomicF64::new(0.5); // Farthest distance (mm) a shoved segment or via is moved from where it was
 
 pub static NUM_TOP_RANKED_TO_TRY: AtomicUsize = AtomicUsize::new(3); // Number of top-ranked traces to try fixing in each iteration
-
-pub static SAMPLE_ITERATIONS: AtomicUsize = AtomicUsize::new(2);
+pub static USE_BACKJUMPING: AtomicBool = AtomicBool::new(true); // Whether a failed connection makes the backtracking searches jump back to the node that fixed the traces in its way, learning nogoods on the way
+pub static MAX_BAYESIAN_BACKJUMPS: AtomicUsize = AtomicUsize::new(20); // Backjumps of the Bayesian backtrack before it hands over to the naive backtrack
+
+// the order in which the Bayesian backtrack visits partial routings
+pub static SEARCH_STRATEGY: AtomicUsize = AtomicUsize::new(0); // 0 depth first, 1 beam, 2 best first, 3 limited discrepancy
+pub static SEARCH_MAX_CHILDREN: AtomicUsize = AtomicUsize::new(3); // Children a beam, best-first or limited-discrepancy search expands a node into
+pub static SEARCH_MAX_NODES: AtomicUsize = AtomicUsize::new(2000); // Nodes a beam, best-first or limited-discrepancy search expands before handing over to the naive backtrack
+pub static BEAM_WIDTH: AtomicUsize = AtomicUsize::new(4); // Partial routings a beam search keeps at every depth
+pub static LDS_MAX_DISCREPANCIES: AtomicUsize = AtomicUsize::new(2); // Times a limited-discrepancy search may leave the most probable child on one path
+
+// exact selection of one candidate per connection by a MaxSAT solve
+pub static EXACT_SELECTION_MAX_NODES: AtomicUsize = AtomicUsize::new(200000); // Branches of the MaxSAT solve before it settles for the best selection it found
+pub static EXACT_SELECTION_EXTRA_ROUNDS: AtomicUsize = AtomicUsize::new(3); // Extra sampling rounds while the candidates cannot all fit, before handing over to the Bayesian backtrack
+
+// Monte Carlo tree search over the order in which the connections are routed
+pub static MCTS_ITERATIONS: AtomicUsize = AtomicUsize::new(200); // Rollouts of the search, it stops earlier once a rollout routes every connection
+pub static MCTS_EXPLORATION: AtomicF64 = AtomicF64::new(1.4); // Exploration constant of UCT, higher tries more orders that routed few connections so far
+pub static MCTS_ROLLOUT_RANDOMNESS: AtomicF64 = AtomicF64::new(0.1); // Chance that a rollout routes a random connection next instead of the shortest one left
+
+// simulated annealing over a complete solution, between the routing and the length tuning
+pub static USE_ANNEALING: AtomicBool = AtomicBool::new(false); // Whether a fully routed board is improved by ripping up and rerouting a few connections at a time
+pub static ANNEALING_ITERATIONS: AtomicUsize = AtomicUsize::new(300); // Rip-up and reroute moves of the annealing pass
+pub static ANNEALING_TIME_LIMIT: AtomicF64 = AtomicF64::new(30.0); // Wall-clock limit (s) of the annealing pass, 0 means no limit
+pub static ANNEALING_START_TEMPERATURE: AtomicF64 = AtomicF64::new(1.0); // Temperature of the first move, a move that worsens the objective by this much is accepted with chance 1/e
+pub static ANNEALING_COOLING: AtomicF64 = AtomicF64::new(0.99); // Factor the temperature is multiplied by after every move
+pub static ANNEALING_MAX_RIPUP: AtomicUsize = AtomicUsize::new(2); // Most connections ripped up and rerouted together in one move
+pub static USE_PARETO_FRONT: AtomicBool = AtomicBool::new(false); // Whether a solve keeps the complete solutions of its portfolio runs and annealing moves that no other beats on length, vias, bends and clearance slack
 
 pub static UPDATE_PROBA_SKIP_STRIDE: AtomicUsize = AtomicUsize::new(2); // Number of traces to skip when updating the probability
 
+pub static SAMPLING_THREADS: AtomicUsize = AtomicUsize::new(0); // Number of threads for sampling candidate traces, 0 uses all cores
+
+// seeds and the restart portfolio, which runs the solve with several seeds and keeps the best solution
+pub static RANDOM_SEED: AtomicUsize = AtomicUsize::new(42); // Seed of the weighted sampling, the first seed of a portfolio
+pub static PORTFOLIO_NUM_SEEDS: AtomicUsize = AtomicUsize::new(1); // Number of seeds a solve is run with, 1 runs it once
+pub static PORTFOLIO_THREADS: AtomicUsize = AtomicUsize::new(1); // Number of portfolio runs at the same time, 1 runs the seeds one after another
+pub static PORTFOLIO_METRIC: AtomicUsize = AtomicUsize::new(0); // Which solution of a portfolio is kept: 0 most connections routed, 1 shortest, 2 fewest vias
+
+// negotiated congestion (PathFinder-style) rip-up and reroute
+pub static NEGOTIATED_CONGESTION_MAX_ITERATIONS: AtomicUsize = AtomicUsize::new(30); // Maximum number of rip-up and reroute rounds
+pub static PRESENT_CONGESTION_INITIAL_FACTOR: AtomicF64 = AtomicF64::new(0.5); // Penalty factor for cells shared with other nets in the first round
+pub static PRESENT_CONGESTION_GROWTH: AtomicF64 = AtomicF64::new(1.5); // The present congestion factor is multiplied by this after every round
+pub static HISTORY_CONGESTION_INCREMENT: AtomicF64 = AtomicF64::new(1.0); // Added to the history cost of a cell every round it stays overused
+
 pub static NUM_BAYESIAN_PATH_FINDING_CALLS: AtomicUsize = AtomicUsize::new(0);
 pub static NUM_NAIVE_PATH_FINDING_CALLS: AtomicUsize = AtomicUsize::new(0);
+pub static NUM_NEGOTIATED_PATH_FINDING_CALLS: AtomicUsize = AtomicUsize::new(0);
 
 pub const LAYER_TO_TRACE_COLOR: [ColorFloat3; 6] = [
     ColorFloat3::new(1.0, 0.0, 0.0), // Red for front layer
@@ -53,7 +131,55 @@ pub fn astar_stride_from_raw(raw_stride: f64) -> FixedPoint{
     result
 }
 
+/// one round of sampling candidate traces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingRound {
+    pub num_traces: usize, // new traces sampled per connection
+    pub prior: f64, // normalized prior of the traces sampled in this round
+}
+
+/// parses a schedule like "1:0.8, 3:0.6", one num_traces:prior pair per round
+pub fn parse_sampling_schedule(text: &str) -> Result<Vec<SamplingRound>, String> {
+    let mut schedule: Vec<SamplingRound> = Vec::new();
+    for round in text.split(',').map(str::trim).filter(|round| !round.is_empty()) {
+        let (num_traces, prior) = round
+            .split_once(':')
+            .ok_or(format!("Sampling round '{}' is not num_traces:prior", round))?;
+        let num_traces: usize = num_traces
+            .trim()
+            .parse()
+            .map_err(|_| format!("Number of traces '{}' is not a positive integer", num_traces.trim()))?;
+        let prior: f64 = prior
+            .trim()
+            .parse()
+            .map_err(|_| format!("Prior '{}' is not a number", prior.trim()))?;
+        if num_traces == 0 {
+            return Err(format!("Sampling round '{}' samples no traces", round));
+        }
+        if !(prior > 0.0 && prior <= 1.0) {
+            return Err(format!("Prior {} of sampling round '{}' must be above 0 and at most 1", prior, round));
+        }
+        schedule.push(SamplingRound { num_traces, prior });
+    }
+    if schedule.is_empty() {
+        return Err("Sampling schedule has no rounds".to_string());
+    }
+    Ok(schedule)
+}
+
+pub fn format_sampling_schedule(schedule: &[SamplingRound]) -> String {
+    schedule
+        .iter()
+        .map(|round| format!("{}:{}", round.num_traces, round.prior))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 lazy_static! {
+    pub static ref SAMPLING_SCHEDULE: Mutex<Vec<SamplingRound>> = Mutex::new(vec![
+        SamplingRound { num_traces: 1, prior: 0.8 },
+        SamplingRound { num_traces: 3, prior: 0.6 },
+    ]); // Sampling rounds of the probabilistic model, in order
         pub static ref ASTAR_STRIDE: Mutex<FixedPoint> = {
         let raw_stride: f64 = 1.00;
         Mutex::new(astar_stride_from_raw(raw_stride))
@@ -94,3 +220,32 @@ lazy_static! {
     // .into_iter()
     // .collect();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{format_sampling_schedule, parse_sampling_schedule, SamplingRound};
+
+    #[test]
+    fn a_sampling_schedule_survives_formatting_and_parsing() {
+        let schedule = vec![
+            SamplingRound { num_traces: 1, prior: 0.8 },
+            SamplingRound { num_traces: 3, prior: 0.6 },
+            SamplingRound { num_traces: 12, prior: 0.05 },
+        ];
+        let text = format_sampling_schedule(&schedule);
+        assert_eq!(text, "1:0.8, 3:0.6, 12:0.05");
+        assert_eq!(parse_sampling_schedule(&text), Ok(schedule));
+        // spaces and a trailing comma are allowed
+        assert_eq!(
+            parse_sampling_schedule(" 2 : 1 ,"),
+            Ok(vec![SamplingRound { num_traces: 2, prior: 1.0 }])
+        );
+    }
+
+    #[test]
+    fn a_malformed_sampling_schedule_is_rejected() {
+        for text in ["", " , ", "3", "3:0.5:1", "x:0.5", "-1:0.5", "0:0.5", "3:x", "3:0", "3:1.5", "3:-0.2", "3:NaN", "1:0.8, 3"] {
+            assert!(parse_sampling_schedule(text).is_err(), "'{}' parsed", text);
+        }
+    }
+}
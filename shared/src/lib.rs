@@ -8,8 +8,13 @@ pub mod pcb_problem;
 pub mod pcb_render_model;
 pub mod prim_shape;
 pub mod trace_path;
+pub mod trace_scorer;
 pub mod vec2;
 pub mod octile_distance;
 pub mod my_result;
 pub mod settings_enum;
-pub mod stats_enum;
\ No newline at end of file
+pub mod stats_enum;
+pub mod via_type;
+pub mod teardrop;
+pub mod zone;
+pub mod pareto;
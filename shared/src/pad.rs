@@ -1,6 +1,7 @@
 use std::f32::consts::PI;
 
 use cgmath::{Rad, Vector2};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     pcb_render_model::ShapeRenderable,
@@ -24,7 +25,7 @@ pub enum PadShape {
     },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PadName(pub String);
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collider::Collider,
+    pcb_problem::{ConnectionID, FixedTrace, NetName, PcbProblem},
+    prim_shape::PrimShape,
+    trace_scorer::{BendTerm, ScoringContext, TraceScorer},
+    vec2::FloatVec2,
+};
+
+/// what a complete solution is judged on, the length, vias and bends are better lower and the clearance slack higher
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SolutionObjectives {
+    pub total_length: f64,
+    pub num_vias: usize,
+    pub num_bends: usize,
+    pub min_clearance_slack: f64, // the smallest spacing beyond the clearance between a trace and the copper of another net
+}
+
+impl SolutionObjectives {
+    pub fn from_traces(problem: &PcbProblem, determined_traces: &HashMap<ConnectionID, FixedTrace>) -> Self {
+        let mut num_bends = 0;
+        for fixed_trace in determined_traces.values() {
+            let context = ScoringContext { problem, net_name: &fixed_trace.net_name };
+            num_bends += BendTerm.raw_score(&fixed_trace.trace_path, &context) as usize;
+        }
+        SolutionObjectives {
+            total_length: determined_traces.values().map(|fixed_trace| fixed_trace.trace_path.total_length).sum(),
+            num_vias: determined_traces.values().map(|fixed_trace| fixed_trace.trace_path.vias.len()).sum(),
+            num_bends,
+            min_clearance_slack: min_clearance_slack(problem, determined_traces),
+        }
+    }
+
+    /// at least as good on every objective and better on one
+    pub fn dominates(&self, other: &SolutionObjectives) -> bool {
+        let at_least_as_good = self.total_length <= other.total_length
+            && self.num_vias <= other.num_vias
+            && self.num_bends <= other.num_bends
+            && self.min_clearance_slack >= other.min_clearance_slack;
+        at_least_as_good && self != other
+    }
+}
+
+/// a piece of copper on one layer, a segment or a point grown by a radius, or a polygon
+struct Copper<'a> {
+    net_name: &'a NetName,
+    layer: usize,
+    shape: CopperShape,
+    clearance: f64,
+}
+
+enum CopperShape {
+    Capsule { start: FloatVec2, end: FloatVec2, radius: f64 },
+    Polygon(Vec<FloatVec2>),
+}
+
+fn point_segment_distance(point: FloatVec2, start: FloatVec2, end: FloatVec2) -> f64 {
+    let direction = end - start;
+    let length2 = direction.magnitude2();
+    let t = if length2 > 0.0 { ((point - start).dot(direction) / length2).clamp(0.0, 1.0) } else { 0.0 };
+    let closest = FloatVec2::new(start.x + direction.x * t, start.y + direction.y * t);
+    (point - closest).length() as f64
+}
+
+fn cross(a: FloatVec2, b: FloatVec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn segments_intersect(start1: FloatVec2, end1: FloatVec2, start2: FloatVec2, end2: FloatVec2) -> bool {
+    let d1 = cross(end2 - start2, start1 - start2);
+    let d2 = cross(end2 - start2, end1 - start2);
+    let d3 = cross(end1 - start1, start2 - start1);
+    let d4 = cross(end1 - start1, end2 - start1);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+fn segment_segment_distance(start1: FloatVec2, end1: FloatVec2, start2: FloatVec2, end2: FloatVec2) -> f64 {
+    if segments_intersect(start1, end1, start2, end2) {
+        return 0.0;
+    }
+    point_segment_distance(start1, start2, end2)
+        .min(point_segment_distance(end1, start2, end2))
+        .min(point_segment_distance(start2, start1, end1))
+        .min(point_segment_distance(end2, start1, end1))
+}
+
+fn is_inside_polygon(point: FloatVec2, polygon: &[FloatVec2]) -> bool {
+    let mut is_inside = false;
+    for (index, vertex) in polygon.iter().enumerate() {
+        let next = polygon[(index + 1) % polygon.len()];
+        if (vertex.y > point.y) != (next.y > point.y)
+            && point.x < vertex.x + (point.y - vertex.y) * (next.x - vertex.x) / (next.y - vertex.y)
+        {
+            is_inside = !is_inside;
+        }
+    }
+    is_inside
+}
+
+/// the gap between the copper of a trace, always a capsule, and another piece of copper, 0 if they overlap
+fn gap(capsule: &CopperShape, other: &CopperShape) -> f64 {
+    let CopperShape::Capsule { start, end, radius } = capsule else {
+        unreachable!("Traces are made of capsules");
+    };
+    let distance = match other {
+        CopperShape::Capsule { start: other_start, end: other_end, radius: other_radius } => {
+            segment_segment_distance(*start, *end, *other_start, *other_end) - other_radius
+        }
+        CopperShape::Polygon(polygon) => {
+            if is_inside_polygon(*start, polygon) || is_inside_polygon(*end, polygon) {
+                0.0
+            } else {
+                (0..polygon.len())
+                    .map(|index| {
+                        segment_segment_distance(*start, *end, polygon[index], polygon[(index + 1) % polygon.len()])
+                    })
+                    .fold(f64::MAX, f64::min)
+            }
+        }
+    };
+    (distance - radius).max(0.0)
+}
+
+fn trace_copper(fixed_trace: &FixedTrace, num_layers: usize) -> Vec<Copper<'_>> {
+    let trace_path = &fixed_trace.trace_path;
+    let mut copper: Vec<Copper> = Vec::new();
+    for segment in trace_path.segments.iter() {
+        copper.push(Copper {
+            net_name: &fixed_trace.net_name,
+            layer: segment.layer,
+            shape: CopperShape::Capsule {
+                start: segment.start.to_float(),
+                end: segment.end.to_float(),
+                radius: segment.width as f64 / 2.0,
+            },
+            clearance: segment.clearance as f64,
+        });
+    }
+    for arc in trace_path.arcs.iter() {
+        for points in arc.to_points().windows(2) {
+            copper.push(Copper {
+                net_name: &fixed_trace.net_name,
+                layer: arc.layer,
+                shape: CopperShape::Capsule { start: points[0], end: points[1], radius: arc.width as f64 / 2.0 },
+                clearance: arc.clearance as f64,
+            });
+        }
+    }
+    for via in trace_path.vias.iter() {
+        let position = via.position.to_float();
+        for layer in via.min_layer..=via.max_layer.min(num_layers - 1) {
+            copper.push(Copper {
+                net_name: &fixed_trace.net_name,
+                layer,
+                shape: CopperShape::Capsule { start: position, end: position, radius: via.diameter as f64 / 2.0 },
+                clearance: via.clearance as f64,
+            });
+        }
+    }
+    copper
+}
+
+fn pad_copper(problem: &PcbProblem) -> Vec<Copper<'_>> {
+    let mut copper: Vec<Copper> = Vec::new();
+    for net_info in problem.nets.values() {
+        for pad in net_info.pads.values() {
+            for layer in pad.pad_layer.get_iter(problem.num_layers) {
+                for shape in pad.to_shapes() {
+                    let shape = match &shape {
+                        PrimShape::Circle(circle) => CopperShape::Capsule {
+                            start: circle.position,
+                            end: circle.position,
+                            radius: circle.diameter as f64 / 2.0,
+                        },
+                        _ => match Collider::from_prim_shape(&shape) {
+                            Collider::Polygon(polygon) => CopperShape::Polygon(polygon.0),
+                            _ => continue,
+                        },
+                    };
+                    copper.push(Copper { net_name: &net_info.net_name, layer, shape, clearance: pad.clearance as f64 });
+                }
+            }
+        }
+    }
+    copper
+}
+
+/// the smallest gap beyond the larger clearance between the traces and the traces and pads of other nets,
+/// negative where the clearance is broken, and the size of the board if no copper of two nets shares a layer
+fn min_clearance_slack(problem: &PcbProblem, determined_traces: &HashMap<ConnectionID, FixedTrace>) -> f64 {
+    let mut connection_ids: Vec<&ConnectionID> = determined_traces.keys().collect();
+    connection_ids.sort();
+    let traces: Vec<Vec<Copper>> =
+        connection_ids.iter().map(|connection_id| trace_copper(&determined_traces[connection_id], problem.num_layers)).collect();
+    let pads = pad_copper(problem);
+    let mut min_slack = problem.width.max(problem.height) as f64;
+    for (index, trace) in traces.iter().enumerate() {
+        let others = traces[index + 1..].iter().flatten().chain(pads.iter());
+        for other in others {
+            for piece in trace.iter() {
+                if piece.layer != other.layer || piece.net_name == other.net_name {
+                    continue;
+                }
+                let slack = gap(&piece.shape, &other.shape) - piece.clearance.max(other.clearance);
+                min_slack = min_slack.min(slack);
+            }
+        }
+    }
+    min_slack
+}
@@ -1,13 +1,19 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     collider::{BorderCollider, PolygonCollider},
     color_float3::ColorFloat3,
     distinct_color_generator::DistinctColorGenerator,
     pad::{Pad, PadName},
+    pareto::SolutionObjectives,
     prim_shape::Line,
+    teardrop::Teardrop,
     trace_path::TracePath,
+    via_type::ViaType,
     vec2::FloatVec2,
+    zone::{Zone, ZoneFill},
 };
 
 // use shared::interface_types::{Color, ColorGrid};
@@ -29,15 +35,34 @@ pub struct NetInfo {
     pub pads: HashMap<PadName, Pad>,
     pub trace_width: f32,     // Width of the trace from the source pad
     pub trace_clearance: f32, // Clearance around the trace from the source pad
-    pub via_diameter: f32, // Diameter of the via, obtained from via name and accessed through padstacks
-    pub connections: HashMap<ConnectionID, Rc<Connection>>, // List of connections in the net, the source pad is the same
+    pub via_types: Vec<ViaType>, // Via catalog of the net, sorted by cost, the netclass via comes first among equals
+    pub connections: HashMap<ConnectionID, Arc<Connection>>, // List of connections in the net, the source pad is the same
 }
 
-#[derive(Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
+/// two nets routed as coupled traces, the positive and negative halves keep a fixed gap
+#[derive(Debug, Clone)]
+pub struct DiffPair {
+    pub positive_net: NetName,
+    pub negative_net: NetName,
+    pub gap: f32,                  // Edge to edge distance between the two traces of the coupled section
+    pub max_uncoupled_length: f32, // Longest fanout allowed at each end before the traces are coupled
+}
+
+/// a length rule for one net, or for a group of nets that are matched to each other
+#[derive(Debug, Clone)]
+pub struct LengthConstraint {
+    pub name: String,
+    pub net_names: Vec<NetName>,
+    pub min_length: f32,             // 0.0 if there is no minimum
+    pub max_length: Option<f32>,     // tuning never goes past this, it is only reported if routing already exceeds it
+    pub match_tolerance: Option<f32>, // every net is tuned to within this of the longest net in the group
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct NetName(pub String);
 #[derive(Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
 pub struct NetClassName(pub String);
-#[derive(Copy, Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord)]
+#[derive(Copy, Debug, Clone, PartialEq, Hash, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ConnectionID(pub usize);
 
 // backtrack search:
@@ -57,19 +82,51 @@ pub struct PcbProblem {
     pub obstacle_border_outlines: Vec<Line>,   // Outlines of the borders, used for rendering
     pub obstacle_polygons: Vec<PolygonCollider>, // Polygons that represent obstacles in the PCB
     pub nets: HashMap<NetName, NetInfo>,       // NetID to NetInfo
+    pub diff_pairs: Vec<DiffPair>,             // Pairs of nets that are routed as coupled traces
+    pub length_constraints: Vec<LengthConstraint>, // Length rules that the tuning pass meets with meanders
+    pub zones: Vec<Zone>,                      // Copper pours that are filled after routing
     pub scale_down_factor: f32, // Scale down factor to convert specctra dsn units to float units
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FixedTrace {
     pub net_name: NetName,           // The net that the trace belongs to
     pub connection_id: ConnectionID, // The connection that the trace belongs to
     pub trace_path: TracePath,
 }
 
+/// why a partial solution leaves a connection out
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnroutedReason {
+    Stopped, // the run was stopped before the connection was routed
+    BudgetExhausted, // the time, expansion or node budget of the solve ran out before the connection was routed
+    ExpansionsExhausted, // A* ran out of expansions, even with only the pads in its way
+    BlockedByNet(NetName), // there is a path around the pads, but the traces of this net are in its way
+    NoPath, // the pads and the border leave no path at all
+}
+
+#[derive(Debug, Clone)]
+pub struct UnroutedConnection {
+    pub connection_id: ConnectionID,
+    pub net_name: NetName,
+    pub reason: UnroutedReason,
+}
+
 pub struct PcbSolution {
     pub determined_traces: HashMap<ConnectionID, FixedTrace>, // NetID to ConnectionID to FixedTrace
+    pub unrouted_connections: Vec<UnroutedConnection>, // the connections a partial solution leaves out, empty if every connection is routed
+    pub diff_pair_skews: HashMap<NetName, f64>, // positive net of a differential pair to the length difference of its two traces
+    pub teardrops: Vec<Teardrop>, // filled in by the teardrop pass, empty if it is turned off
+    pub zone_fills: Vec<ZoneFill>, // one per zone of the problem, filled in after routing
+    pub seed: u64, // the seed of the run that found the solution, to reproduce it
     pub scale_down_factor: f32, // Scale down factor to convert specctra dsn units to float units
+    pub pareto_front: Vec<ParetoSolution>, // the complete solutions no other beats on every objective, empty unless USE_PARETO_FRONT is set
+}
+
+/// a complete solution of the Pareto front, with the post routing passes applied, and its objectives
+pub struct ParetoSolution {
+    pub objectives: SolutionObjectives,
+    pub solution: PcbSolution,
 }
 
 impl PcbProblem {
@@ -144,7 +201,7 @@ impl PcbProblem {
     //     };
     //     net_info
     //         .connections
-    //         .insert(connection_id, Rc::new(connection));
+    //         .insert(connection_id, Arc::new(connection));
     //     connection_id
     // }
 }
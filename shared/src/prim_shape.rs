@@ -3,12 +3,12 @@ use serde::{Deserialize, Serialize};
 
 use crate::vec2::FloatVec2;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CircleShape {
     pub position: FloatVec2,
     pub diameter: f32,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RectangleShape {
     pub position: FloatVec2, // center position of the rectangle
     pub width: f32,
@@ -16,15 +16,22 @@ pub struct RectangleShape {
     pub rotation_in_degs: f32, // Rotation counterclockwise in degrees
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Line {
     pub start: FloatVec2,
     pub end: FloatVec2,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// a convex polygon, vertices in counterclockwise order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolygonShape {
+    pub vertices: Vec<FloatVec2>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PrimShape {
     Circle(CircleShape),
     Rectangle(RectangleShape),
     Line(Line),
+    Polygon(PolygonShape),
 }
@@ -7,6 +7,7 @@ pub enum SettingsEnum{
     Bool(bool),
     Usize(usize),
     Float(f64),
+    Text(String),
 }
 
 impl SettingsEnum {
@@ -33,6 +34,14 @@ impl SettingsEnum {
             None
         }
     }
+
+    pub fn as_text(&self) -> Option<String> {
+        if let SettingsEnum::Text(value) = self {
+            Some(value.clone())
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
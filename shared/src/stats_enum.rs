@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::pareto::SolutionObjectives;
+
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StatsArgs{
@@ -12,10 +14,22 @@ impl StatsArgs {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelectParetoSolutionArg{
+    pub index: usize,
+}
+
+impl SelectParetoSolutionArg {
+    pub fn new(index: usize) -> Self {
+        SelectParetoSolutionArg { index }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StatsEnum{
     Float(f64),
     Usize(usize),
+    ParetoFront(Vec<SolutionObjectives>),
 }
 
 impl StatsEnum {
@@ -34,4 +48,12 @@ impl StatsEnum {
             None
         }
     }
+
+    pub fn as_pareto_front(&self) -> Option<Vec<SolutionObjectives>> {
+        if let StatsEnum::ParetoFront(value) = self {
+            Some(value.clone())
+        } else {
+            None
+        }
+    }
 }
\ No newline at end of file
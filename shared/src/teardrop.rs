@@ -0,0 +1,75 @@
+use crate::{
+    collider::Collider,
+    hyperparameters::LAYER_TO_TRACE_COLOR,
+    pcb_problem::NetName,
+    pcb_render_model::{RenderableBatch, ShapeRenderable},
+    prim_shape::{CircleShape, PolygonShape, PrimShape, RectangleShape},
+    vec2::FloatVec2,
+};
+
+/// copper that widens a trace where it meets a pad or a via
+/// the outline is a convex polygon, from the wide end inside the pad or via to the trace width
+#[derive(Debug, Clone)]
+pub struct Teardrop {
+    pub net_name: NetName,
+    pub layer: usize,
+    pub outline: Vec<FloatVec2>, // counterclockwise
+    pub clearance: f32,          // the clearance of the trace it belongs to
+}
+
+impl Teardrop {
+    pub fn to_shape(&self) -> PrimShape {
+        PrimShape::Polygon(PolygonShape {
+            vertices: self.outline.clone(),
+        })
+    }
+    /// the outline grown by the clearance, an edge becomes a rectangle and a vertex a circle
+    pub fn to_clearance_shapes(&self) -> Vec<PrimShape> {
+        let mut shapes = vec![self.to_shape()];
+        for (index, start) in self.outline.iter().enumerate() {
+            let end = self.outline[(index + 1) % self.outline.len()];
+            let edge = end - *start;
+            shapes.push(PrimShape::Rectangle(RectangleShape {
+                position: (*start + end) / 2.0,
+                width: edge.length(),
+                height: self.clearance * 2.0,
+                rotation_in_degs: edge.y.atan2(edge.x).to_degrees(),
+            }));
+            shapes.push(PrimShape::Circle(CircleShape {
+                position: *start,
+                diameter: self.clearance * 2.0,
+            }));
+        }
+        shapes
+    }
+    pub fn to_colliders(&self) -> Vec<Collider> {
+        vec![Collider::from_prim_shape(&self.to_shape())]
+    }
+    pub fn to_clearance_colliders(&self) -> Vec<Collider> {
+        self.to_clearance_shapes()
+            .iter()
+            .map(Collider::from_prim_shape)
+            .collect()
+    }
+    /// drawn in the color of the layer, like the trace it belongs to
+    pub fn to_renderables(&self, alpha: f32) -> RenderableBatch {
+        RenderableBatch(vec![ShapeRenderable {
+            shape: self.to_shape(),
+            color: LAYER_TO_TRACE_COLOR[self.layer].to_float4(alpha / 2.0),
+        }])
+    }
+    pub fn collides_with(&self, other: &Teardrop) -> bool {
+        if self.layer != other.layer {
+            return false;
+        }
+        let other_colliders = other.to_colliders();
+        let other_clearance_colliders = other.to_clearance_colliders();
+        self.to_colliders()
+            .iter()
+            .any(|collider| other_clearance_colliders.iter().any(|other| collider.collides_with(other)))
+            || self
+                .to_clearance_colliders()
+                .iter()
+                .any(|collider| other_colliders.iter().any(|other| collider.collides_with(other)))
+    }
+}
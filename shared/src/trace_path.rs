@@ -1,8 +1,10 @@
-use std::{collections::HashMap, sync::atomic::Ordering};
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 use crate::{
     collider::Collider,
-    hyperparameters::{HALF_PROBABILITY_RAW_SCORE, LAYER_TO_TRACE_COLOR},
+    hyperparameters::LAYER_TO_TRACE_COLOR,
     pcb_render_model::{RenderableBatch, ShapeRenderable},
     prim_shape::{CircleShape, PrimShape, RectangleShape},
     vec2::{FixedPoint, FixedVec2, FloatVec2, IntVec2},
@@ -286,7 +288,7 @@ impl Direction {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraceSegment {
     pub start: FixedVec2, // Start point of the trace segment
     pub end: FixedVec2,   // End point of the trace segment
@@ -409,13 +411,127 @@ impl TraceSegment {
     }
 }
 
-#[derive(Debug, Clone)]
+/// a rounded corner between two segments of the same layer, tangent to both of them
+/// start and end are where the trimmed segments stop, the arc turns around center from start to end
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceArc {
+    pub start: FixedVec2,       // End point of the segment before the corner
+    pub end: FixedVec2,         // Start point of the segment after the corner
+    pub center: FloatVec2,      // Center of the circle the arc lies on
+    pub radius: f32,            // Radius of the center line of the arc
+    pub counterclockwise: bool, // Turning direction from start to end
+    pub width: f32,             // Width of the trace
+    pub clearance: f32,         // Clearance around the trace
+    pub layer: usize,           // Layer of the arc
+}
+
+impl TraceArc {
+    // the chords cut inside the arc by at most this much (mm)
+    const MAX_SAGITTA: f32 = 0.001;
+
+    /// the signed angle from start to end around the center, positive if counterclockwise
+    pub fn sweep_in_rads(&self) -> f32 {
+        let start = self.start.to_float() - self.center;
+        let end = self.end.to_float() - self.center;
+        let mut sweep = end.y.atan2(end.x) - start.y.atan2(start.x);
+        if self.counterclockwise && sweep < 0.0 {
+            sweep += std::f32::consts::TAU;
+        } else if !self.counterclockwise && sweep > 0.0 {
+            sweep -= std::f32::consts::TAU;
+        }
+        sweep
+    }
+    /// points along the center line, from start to end, close enough for the chords to follow the arc
+    pub fn to_points(&self) -> Vec<FloatVec2> {
+        let sweep = self.sweep_in_rads();
+        let max_chord_angle = 2.0 * (1.0 - Self::MAX_SAGITTA / self.radius).max(0.0).acos();
+        let num_chords = (sweep.abs() / max_chord_angle).ceil().max(1.0) as usize;
+        let start = self.start.to_float() - self.center;
+        let start_angle = start.y.atan2(start.x);
+        let mut points = vec![self.start.to_float()];
+        for i in 1..num_chords {
+            let angle = start_angle + sweep * i as f32 / num_chords as f32;
+            points.push(FloatVec2::new(
+                self.center.x + self.radius * angle.cos(),
+                self.center.y + self.radius * angle.sin(),
+            ));
+        }
+        points.push(self.end.to_float());
+        points
+    }
+    fn chord_shapes(&self, width: f32) -> Vec<PrimShape> {
+        // an arc is drawn as a chain of short straight pieces with round joints
+        let points = self.to_points();
+        let mut shapes = vec![PrimShape::Circle(CircleShape {
+            position: points[0],
+            diameter: width,
+        })];
+        for chord in points.windows(2) {
+            let (start, end) = (chord[0], chord[1]);
+            shapes.push(PrimShape::Rectangle(RectangleShape {
+                position: FloatVec2 {
+                    x: (start.x + end.x) / 2.0,
+                    y: (start.y + end.y) / 2.0,
+                },
+                width: (end - start).length(),
+                height: width,
+                rotation_in_degs: (end.y - start.y).atan2(end.x - start.x).to_degrees(),
+            }));
+            shapes.push(PrimShape::Circle(CircleShape {
+                position: end,
+                diameter: width,
+            }));
+        }
+        shapes
+    }
+    pub fn to_shapes(&self) -> Vec<PrimShape> {
+        self.chord_shapes(self.width)
+    }
+    pub fn to_clearance_shapes(&self) -> Vec<PrimShape> {
+        // the chords bring the inner edge closer to the center by up to the sagitta,
+        // the clearance is narrowed by as much so it keeps the exact distance on that side
+        let chord_angle = self.sweep_in_rads().abs() / (self.to_points().len() - 1) as f32;
+        let sagitta = self.radius * (1.0 - (chord_angle / 2.0).cos());
+        self.chord_shapes(self.width + self.clearance * 2.0 - sagitta * 2.0)
+    }
+    pub fn to_colliders(&self) -> Vec<Collider> {
+        let shapes = self.to_shapes();
+        shapes.iter().map(Collider::from_prim_shape).collect()
+    }
+    pub fn to_clearance_colliders(&self) -> Vec<Collider> {
+        let clearance_shapes = self.to_clearance_shapes();
+        clearance_shapes
+            .iter()
+            .map(Collider::from_prim_shape)
+            .collect()
+    }
+    pub fn to_renderables(&self, color: [f32; 4]) -> Vec<ShapeRenderable> {
+        let shapes = self.to_shapes();
+        shapes
+            .into_iter()
+            .map(|shape| ShapeRenderable { shape, color })
+            .collect()
+    }
+    pub fn to_clearance_renderables(&self, color: [f32; 4]) -> Vec<ShapeRenderable> {
+        let clearance_shapes = self.to_clearance_shapes();
+        clearance_shapes
+            .into_iter()
+            .map(|shape| ShapeRenderable { shape, color })
+            .collect()
+    }
+    pub fn calculate_length(&self) -> f64 {
+        self.radius as f64 * self.sweep_in_rads().abs() as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Via {
     pub position: FixedVec2, // Position of the via
     pub diameter: f32,       // Diameter of the via
     pub clearance: f32,      // Clearance around the via
     pub min_layer: usize,    // Inclusive, the layer where the via starts
     pub max_layer: usize,    // Inclusive, the layer where the via ends
+    pub padstack: String,    // Name of the via type in the catalog, written to the session file
 }
 
 impl Via {
@@ -472,21 +588,22 @@ impl Via {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TraceAnchor {
     pub position: FixedVec2,
     pub start_layer: usize, // Inclusive, the layer where the trace starts
     pub end_layer: usize,   // Inclusive, the layer where the trace ends
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TraceAnchors(pub Vec<TraceAnchor>); // List of turning points in the trace path, including start and end
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TracePath {
     pub anchors: TraceAnchors, // List of turning points in the trace path, including start and end
     pub segments: Vec<TraceSegment>, // List of segments in the trace path
     pub vias: Vec<Via>,        // List of vias in the trace path
+    pub arcs: Vec<TraceArc>,   // Rounded corners between the segments, empty unless the corners were rounded
     pub total_length: f64,
 }
 // shrink?
@@ -496,11 +613,10 @@ impl TracePath {
         anchors: TraceAnchors,
         trace_width: f32,
         trace_clearance: f32,
-        via_diameter: f32,
+        vias: Vec<Via>, // layer changes keep their anchors, so the vias carry over unchanged
     ) -> Self{
         let anchors_vec = &anchors.0;
         let mut segments = Vec::new();
-        let mut vias = Vec::new();
         let mut total_length = 0.0;
         for i in 0..anchors_vec.len() - 1 {
             let start = anchors_vec[i].position;
@@ -523,22 +639,18 @@ impl TracePath {
         for i in 1..anchors_vec.len() - 1{
             let anchor = &anchors_vec[i];            
             if anchor.start_layer != anchor.end_layer {
-                let min_layer = usize::min(anchor.start_layer, anchor.end_layer);
-                let max_layer = usize::max(anchor.start_layer, anchor.end_layer);
-                let via = Via {
-                    position: anchor.position,
-                    diameter: via_diameter,
-                    clearance: trace_clearance,
-                    min_layer,
-                    max_layer,
-                };
-                vias.push(via);
+                assert!(
+                    vias.iter().any(|via| via.position == anchor.position),
+                    "No via found for the layer change at {:?}",
+                    anchor.position
+                );
             }
         }
         Self {
             anchors,
             segments,
             vias,
+            arcs: Vec::new(),
             total_length,
         }
     }
@@ -552,6 +664,9 @@ impl TracePath {
                 .unwrap()
                 .extend(segment_shapes);
         }
+        for arc in &self.arcs {
+            shapes.get_mut(&arc.layer).unwrap().extend(arc.to_shapes());
+        }
         for via in &self.vias {
             let via_shape = via.to_shape();
             for layer in via.min_layer..=via.max_layer {
@@ -569,6 +684,9 @@ impl TracePath {
                 .get_mut(&segment.layer)
                 .unwrap().extend(segment_clearance_shapes);
         }
+        for arc in &self.arcs {
+            shapes.get_mut(&arc.layer).unwrap().extend(arc.to_clearance_shapes());
+        }
         for via in &self.vias {
             let clearance_shape = via.to_clearance_shape();
             for layer in via.min_layer..=via.max_layer {
@@ -587,6 +705,9 @@ impl TracePath {
                 .unwrap()
                 .extend(segment_colliders);
         }
+        for arc in &self.arcs {
+            colliders.get_mut(&arc.layer).unwrap().extend(arc.to_colliders());
+        }
         for via in &self.vias {
             let collider = via.to_collider();
             for layer in via.min_layer..=via.max_layer {
@@ -605,6 +726,12 @@ impl TracePath {
                 .unwrap()
                 .extend(segment_clearance_colliders);
         }
+        for arc in &self.arcs {
+            colliders
+                .get_mut(&arc.layer)
+                .unwrap()
+                .extend(arc.to_clearance_colliders());
+        }
         for via in &self.vias {
             let clearance_collider = via.to_clearance_collider();
             for layer in via.min_layer..=via.max_layer {
@@ -625,21 +752,45 @@ impl TracePath {
                 }
             }
         }
+        if self.arcs.is_empty() && other.arcs.is_empty() {
+            return false;
+        }
+        // arcs are compared piece by piece, against everything of the other path on their layer
+        let self_pieces = self.segment_and_arc_colliders();
+        let other_pieces = other.segment_and_arc_colliders();
+        for (layer, self_colliders, self_clearance_colliders) in &self_pieces {
+            for (other_layer, other_colliders, other_clearance_colliders) in &other_pieces {
+                if layer != other_layer {
+                    continue;
+                }
+                let collides = self_colliders.iter().any(|self_collider| {
+                    other_clearance_colliders
+                        .iter()
+                        .any(|other_clearance_collider| self_collider.collides_with(other_clearance_collider))
+                }) || self_clearance_colliders.iter().any(|self_clearance_collider| {
+                    other_colliders
+                        .iter()
+                        .any(|other_collider| self_clearance_collider.collides_with(other_collider))
+                });
+                if collides {
+                    return true;
+                }
+            }
+        }
         false
     }
 
-    pub fn get_score(&self) -> f64 {
-        // to do
-        let score_raw = self.total_length; // placeholder for actual score calculation
-        let k = f64::ln(2.0) / HALF_PROBABILITY_RAW_SCORE.load(Ordering::Relaxed);
-        let score = f64::exp(-k * score_raw);
-        // println!("total length: {}, score: {}", self.total_length, score);
-        assert!(
-            score >= 0.0 && score <= 1.0,
-            "Score must be between 0 and 1, got: {}",
-            score
-        );
-        score
+    /// (layer, colliders, clearance colliders) for every segment and arc
+    fn segment_and_arc_colliders(&self) -> Vec<(usize, Vec<Collider>, Vec<Collider>)> {
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| (segment.layer, segment.to_colliders(), segment.to_clearance_colliders()));
+        let arcs = self
+            .arcs
+            .iter()
+            .map(|arc| (arc.layer, arc.to_colliders(), arc.to_clearance_colliders()));
+        segments.chain(arcs).collect()
     }
 
     pub fn to_renderables(&self, color: [f32; 4]) -> [RenderableBatch; 2] {
@@ -654,6 +805,11 @@ impl TracePath {
             renderables.extend(segment_renderables);
             clearance_renderables.extend(segment_clearance_renderables);
         }
+        for arc in &self.arcs {
+            let arc_color = LAYER_TO_TRACE_COLOR[arc.layer].to_float4(color[3]/2.0);
+            renderables.extend(arc.to_renderables(arc_color));
+            clearance_renderables.extend(arc.to_clearance_renderables(clearance_color));
+        }
         for via in &self.vias {
             let via_renderables = via.to_renderables(color);
             let via_clearance_renderables = via.to_clearance_renderables(clearance_color); // semi-transparent color
@@ -670,6 +826,9 @@ impl TracePath {
         for segment in &self.segments {
             total_length += segment.calculate_length();
         }
+        for arc in &self.arcs {
+            total_length += arc.calculate_length();
+        }
         total_length
     }
     pub fn get_num_vias(&self) -> usize {
@@ -0,0 +1,243 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{atomic::Ordering, Arc, OnceLock, RwLock},
+};
+
+use lazy_static::lazy_static;
+
+use crate::{
+    collider::Collider,
+    hyperparameters::{
+        HALF_PROBABILITY_RAW_SCORE, SCORE_BEND_WEIGHT, SCORE_CLEARANCE_MARGIN, SCORE_CLEARANCE_WEIGHT,
+        SCORE_LENGTH_WEIGHT, SCORE_VIA_WEIGHT,
+    },
+    pcb_problem::{NetName, PcbProblem},
+    trace_path::{TracePath, TraceSegment, Via},
+    vec2::FloatVec2,
+};
+
+/// what a scorer may look at besides the trace itself
+pub struct ScoringContext<'a> {
+    pub problem: &'a PcbProblem,
+    pub net_name: &'a NetName, // the net of the trace, its own pads are not in its way
+}
+
+/// ranks the candidate traces of a connection
+pub trait TraceScorer: Send + Sync {
+    /// the cost of the trace, 0 for a perfect one
+    fn raw_score(&self, trace_path: &TracePath, context: &ScoringContext) -> f64;
+
+    /// the cost mapped into (0, 1], halved every HALF_PROBABILITY_RAW_SCORE, higher is better
+    fn score(&self, trace_path: &TracePath, context: &ScoringContext) -> f64 {
+        let score_raw = self.raw_score(trace_path, context);
+        assert!(score_raw >= 0.0, "Raw score must not be negative, got: {}", score_raw);
+        let k = f64::ln(2.0) / HALF_PROBABILITY_RAW_SCORE.load(Ordering::Relaxed);
+        let score = f64::exp(-k * score_raw);
+        assert!((0.0..=1.0).contains(&score), "Score must be between 0 and 1, got: {}", score);
+        score
+    }
+}
+
+/// the total length of the trace
+pub struct LengthTerm;
+
+impl TraceScorer for LengthTerm {
+    fn raw_score(&self, trace_path: &TracePath, _context: &ScoringContext) -> f64 {
+        trace_path.total_length
+    }
+}
+
+/// the number of vias of the trace
+pub struct ViaTerm;
+
+impl TraceScorer for ViaTerm {
+    fn raw_score(&self, trace_path: &TracePath, _context: &ScoringContext) -> f64 {
+        trace_path.vias.len() as f64
+    }
+}
+
+/// the number of turns between consecutive segments on the same layer
+pub struct BendTerm;
+
+impl TraceScorer for BendTerm {
+    fn raw_score(&self, trace_path: &TracePath, _context: &ScoringContext) -> f64 {
+        trace_path
+            .segments
+            .windows(2)
+            .filter(|pair| pair[0].layer == pair[1].layer && is_bend(&pair[0], &pair[1]))
+            .count() as f64
+    }
+}
+
+fn is_bend(segment: &TraceSegment, next_segment: &TraceSegment) -> bool {
+    let direction = segment.end.to_float() - segment.start.to_float();
+    let next_direction = next_segment.end.to_float() - next_segment.start.to_float();
+    let cross = direction.x * next_direction.y - direction.y * next_direction.x;
+    let lengths = direction.length() * next_direction.length();
+    lengths > 0.0 && (cross / lengths).abs() > 1e-3
+}
+
+// the side of a cell of the pad index, about the size of a pad so a trace only looks at a few pads per cell
+const PAD_INDEX_CELL_SIZE: f32 = 1.0;
+
+/// a pad of the problem with what the clearance margin term checks against
+struct IndexedPad {
+    net_name: NetName,
+    layers: Vec<usize>,
+    colliders: Vec<Collider>,
+}
+
+/// the pads of a problem in the cells of a uniform grid, so a trace is only checked against the pads near it
+struct PadIndex {
+    pads: Vec<IndexedPad>,
+    cells: HashMap<(i32, i32), Vec<usize>>, // cell to the indices of the pads whose bounds overlap it
+}
+
+/// the cells that the bounds of a collider overlap, none for a border
+fn cells_of(collider: &Collider) -> Vec<(i32, i32)> {
+    let points: Vec<FloatVec2> = match collider {
+        Collider::Circle(circle) => {
+            let radius = circle.diameter / 2.0;
+            vec![
+                FloatVec2::new(circle.position.x - radius, circle.position.y - radius),
+                FloatVec2::new(circle.position.x + radius, circle.position.y + radius),
+            ]
+        }
+        Collider::Polygon(polygon) => polygon.0.clone(),
+        Collider::Border(_) => return Vec::new(),
+    };
+    let cell = |value: f32| (value / PAD_INDEX_CELL_SIZE).floor() as i32;
+    let min_x = points.iter().map(|point| point.x).fold(f32::INFINITY, f32::min);
+    let max_x = points.iter().map(|point| point.x).fold(f32::NEG_INFINITY, f32::max);
+    let min_y = points.iter().map(|point| point.y).fold(f32::INFINITY, f32::min);
+    let max_y = points.iter().map(|point| point.y).fold(f32::NEG_INFINITY, f32::max);
+    (cell(min_x)..=cell(max_x)).flat_map(|x| (cell(min_y)..=cell(max_y)).map(move |y| (x, y))).collect()
+}
+
+impl PadIndex {
+    fn new(problem: &PcbProblem) -> Self {
+        let mut pads: Vec<IndexedPad> = Vec::new();
+        let mut cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for net_info in problem.nets.values() {
+            for pad in net_info.pads.values() {
+                let colliders: Vec<Collider> = pad.to_shapes().iter().map(Collider::from_prim_shape).collect();
+                let mut pad_cells: Vec<(i32, i32)> = colliders.iter().flat_map(cells_of).collect();
+                pad_cells.sort();
+                pad_cells.dedup();
+                for pad_cell in pad_cells {
+                    cells.entry(pad_cell).or_default().push(pads.len());
+                }
+                pads.push(IndexedPad {
+                    net_name: net_info.net_name.clone(),
+                    layers: pad.pad_layer.get_iter(problem.num_layers).collect(),
+                    colliders,
+                });
+            }
+        }
+        PadIndex { pads, cells }
+    }
+
+    /// the indices of the pads whose bounds share a cell with the bounds of the collider
+    fn pads_near(&self, collider: &Collider) -> BTreeSet<usize> {
+        cells_of(collider)
+            .iter()
+            .filter_map(|cell| self.cells.get(cell))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+/// the number of pads of other nets that the trace passes closer than its clearance plus the margin
+/// a term scores the traces of one problem, its pads are indexed when it scores the first trace
+pub struct ClearanceMarginTerm {
+    pub margin: f32,
+    pad_index: OnceLock<PadIndex>,
+}
+
+impl ClearanceMarginTerm {
+    pub fn new(margin: f32) -> Self {
+        ClearanceMarginTerm { margin, pad_index: OnceLock::new() }
+    }
+}
+
+impl TraceScorer for ClearanceMarginTerm {
+    fn raw_score(&self, trace_path: &TracePath, context: &ScoringContext) -> f64 {
+        let pad_index = self.pad_index.get_or_init(|| PadIndex::new(context.problem));
+        // the layer and the clearance collider grown by the margin of every piece of the trace
+        let mut margin_colliders: Vec<(usize, Collider)> = Vec::new();
+        for segment in trace_path.segments.iter() {
+            let grown_segment = TraceSegment { clearance: segment.clearance + self.margin, ..segment.clone() };
+            margin_colliders.extend(grown_segment.to_clearance_colliders().into_iter().map(|collider| (segment.layer, collider)));
+        }
+        for via in trace_path.vias.iter() {
+            let grown_via = Via { clearance: via.clearance + self.margin, ..via.clone() };
+            for layer in via.min_layer..=via.max_layer {
+                margin_colliders.push((layer, grown_via.to_clearance_collider()));
+            }
+        }
+        let mut close_pads: BTreeSet<usize> = BTreeSet::new();
+        for (layer, collider) in margin_colliders.iter() {
+            for pad in pad_index.pads_near(collider) {
+                let indexed_pad = &pad_index.pads[pad];
+                if close_pads.contains(&pad) || indexed_pad.net_name == *context.net_name || !indexed_pad.layers.contains(layer) {
+                    continue;
+                }
+                if indexed_pad.colliders.iter().any(|pad_collider| collider.collides_with(pad_collider)) {
+                    close_pads.insert(pad);
+                }
+            }
+        }
+        close_pads.len() as f64
+    }
+}
+
+/// the weighted sum of its terms
+#[derive(Default)]
+pub struct WeightedScorer {
+    pub terms: Vec<(f64, Box<dyn TraceScorer>)>,
+}
+
+impl WeightedScorer {
+    pub fn with_term(mut self, weight: f64, term: impl TraceScorer + 'static) -> Self {
+        self.terms.push((weight, Box::new(term)));
+        self
+    }
+
+    /// length, vias, bends and clearance margin, weighted by the settings, a term with weight 0 is left out
+    pub fn from_settings() -> Self {
+        let terms: Vec<(f64, Box<dyn TraceScorer>)> = vec![
+            (SCORE_LENGTH_WEIGHT.load(Ordering::Relaxed), Box::new(LengthTerm)),
+            (SCORE_VIA_WEIGHT.load(Ordering::Relaxed), Box::new(ViaTerm)),
+            (SCORE_BEND_WEIGHT.load(Ordering::Relaxed), Box::new(BendTerm)),
+            (
+                SCORE_CLEARANCE_WEIGHT.load(Ordering::Relaxed),
+                Box::new(ClearanceMarginTerm::new(SCORE_CLEARANCE_MARGIN.load(Ordering::Relaxed) as f32)),
+            ),
+        ];
+        WeightedScorer { terms: terms.into_iter().filter(|(weight, _)| *weight != 0.0).collect() }
+    }
+}
+
+impl TraceScorer for WeightedScorer {
+    fn raw_score(&self, trace_path: &TracePath, context: &ScoringContext) -> f64 {
+        self.terms.iter().map(|(weight, term)| weight * term.raw_score(trace_path, context)).sum()
+    }
+}
+
+lazy_static! {
+    static ref CUSTOM_TRACE_SCORER: RwLock<Option<Arc<dyn TraceScorer>>> = RwLock::new(None);
+}
+
+/// replaces the scorer built from the settings, None goes back to it
+pub fn set_trace_scorer(scorer: Option<Arc<dyn TraceScorer>>) {
+    *CUSTOM_TRACE_SCORER.write().unwrap() = scorer;
+}
+
+/// the scorer the solvers rank the candidate traces with
+pub fn trace_scorer() -> Arc<dyn TraceScorer> {
+    match CUSTOM_TRACE_SCORER.read().unwrap().as_ref() {
+        Some(scorer) => scorer.clone(),
+        None => Arc::new(WeightedScorer::from_settings()),
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 pub type FixedPoint = fixed::types::I16F16;
 
-#[derive(Debug, Clone, PartialEq, Hash, Eq, Copy, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Hash, Eq, Copy, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct FixedVec2 {
     pub x: FixedPoint,
     pub y: FixedPoint,
@@ -110,7 +110,7 @@ impl Neg for FixedVec2 {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
 pub struct FloatVec2 {
     pub x: f32,
     pub y: f32,
@@ -0,0 +1,87 @@
+use std::sync::atomic::Ordering;
+
+use crate::hyperparameters::{BLIND_VIA_COST, BURIED_VIA_COST, MICRO_VIA_COST, VIA_COST};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViaKind {
+    Through, // spans every layer
+    Blind,   // starts on an outer layer and ends on an inner layer
+    Buried,  // spans inner layers only
+    Micro,   // laser drilled from an outer layer to the layer next to it
+}
+
+/// an entry of the via catalog, the router picks from it whenever a trace changes layers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViaType {
+    pub name: String,     // Name of the padstack, written to the session file
+    pub min_layer: usize, // Inclusive, the first layer the via spans
+    pub max_layer: usize, // Inclusive, the last layer the via spans
+    pub diameter: f32,
+    pub clearance: f32,
+    pub cost: f64, // Cost of placing the via, replaces the length A* would otherwise pay
+}
+
+impl ViaType {
+    /// a via that does not reach an outer layer is buried, however few layers it spans
+    pub fn kind(&self, num_layers: usize) -> ViaKind {
+        let touches_outer_layer = self.min_layer == 0 || self.max_layer == num_layers - 1;
+        if self.min_layer == 0 && self.max_layer == num_layers - 1 {
+            ViaKind::Through
+        } else if !touches_outer_layer {
+            ViaKind::Buried
+        } else if self.max_layer - self.min_layer == 1 {
+            ViaKind::Micro
+        } else {
+            ViaKind::Blind
+        }
+    }
+
+    /// whether a trace can use this via to go from one layer to the other
+    pub fn connects(&self, from_layer: usize, to_layer: usize) -> bool {
+        let min_layer = usize::min(from_layer, to_layer);
+        let max_layer = usize::max(from_layer, to_layer);
+        self.min_layer <= min_layer && max_layer <= self.max_layer
+    }
+
+    pub fn default_cost(kind: ViaKind) -> f64 {
+        match kind {
+            ViaKind::Through => VIA_COST.load(Ordering::Relaxed),
+            ViaKind::Blind => BLIND_VIA_COST.load(Ordering::Relaxed),
+            ViaKind::Buried => BURIED_VIA_COST.load(Ordering::Relaxed),
+            ViaKind::Micro => MICRO_VIA_COST.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ViaKind, ViaType};
+
+    fn via_type(min_layer: usize, max_layer: usize) -> ViaType {
+        ViaType { name: String::new(), min_layer, max_layer, diameter: 0.6, clearance: 0.2, cost: 0.0 }
+    }
+
+    #[test]
+    fn a_via_is_classified_by_whether_it_reaches_an_outer_layer() {
+        // on six layers, 0 and 5 are the outer ones
+        assert_eq!(via_type(0, 5).kind(6), ViaKind::Through);
+        assert_eq!(via_type(0, 1).kind(6), ViaKind::Micro);
+        assert_eq!(via_type(4, 5).kind(6), ViaKind::Micro);
+        assert_eq!(via_type(0, 3).kind(6), ViaKind::Blind);
+        assert_eq!(via_type(2, 5).kind(6), ViaKind::Blind);
+        assert_eq!(via_type(1, 4).kind(6), ViaKind::Buried);
+        // two adjacent inner layers are no micro via, a laser only drills from the surface
+        assert_eq!(via_type(2, 3).kind(6), ViaKind::Buried);
+        // on two layers the only via goes through
+        assert_eq!(via_type(0, 1).kind(2), ViaKind::Through);
+    }
+
+    #[test]
+    fn a_via_connects_the_layers_within_its_span() {
+        let blind = via_type(0, 3);
+        assert!(blind.connects(0, 3));
+        assert!(blind.connects(2, 1));
+        assert!(!blind.connects(3, 4));
+        assert!(!blind.connects(0, 5));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{
+    hyperparameters::LAYER_TO_TRACE_COLOR,
+    pcb_problem::NetName,
+    pcb_render_model::{RenderableBatch, ShapeRenderable},
+    prim_shape::{PrimShape, RectangleShape},
+    vec2::FloatVec2,
+};
+
+/// an area poured with copper of one net on one layer, from a plane of the DSN or chosen by the user
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub net_name: NetName,
+    pub layer: usize,
+    pub outline: Vec<FloatVec2>, // a simple polygon, it doesn't have to be convex
+}
+
+impl Zone {
+    /// even-odd rule, a point on the outline may count either way
+    pub fn contains(&self, point: FloatVec2) -> bool {
+        let mut inside = false;
+        for (index, start) in self.outline.iter().enumerate() {
+            let end = self.outline[(index + 1) % self.outline.len()];
+            if (start.y > point.y) != (end.y > point.y) {
+                let crossing_x = start.x + (point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+                if point.x < crossing_x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// the smallest axis aligned box around the outline, as (min, max)
+    pub fn bounding_box(&self) -> (FloatVec2, FloatVec2) {
+        let mut min = FloatVec2::new(f32::MAX, f32::MAX);
+        let mut max = FloatVec2::new(f32::MIN, f32::MIN);
+        for vertex in self.outline.iter() {
+            min = FloatVec2::new(f32::min(min.x, vertex.x), f32::min(min.y, vertex.y));
+            max = FloatVec2::new(f32::max(max.x, vertex.x), f32::max(max.y, vertex.y));
+        }
+        (min, max)
+    }
+}
+
+/// one island of a zone fill, its outer boundary counterclockwise and the holes in it clockwise
+#[derive(Debug, Clone)]
+pub struct ZoneOutline {
+    pub boundary: Vec<FloatVec2>,
+    pub holes: Vec<Vec<FloatVec2>>,
+}
+
+/// the copper of a zone after the fill, what is left once the other nets are cut out
+/// the fill is a set of axis aligned rectangles that touch each other, together they form the copper area
+#[derive(Debug, Clone)]
+pub struct ZoneFill {
+    pub net_name: NetName,
+    pub layer: usize,
+    pub rectangles: Vec<RectangleShape>,
+    pub outlines: Vec<ZoneOutline>, // the same copper traced as polygons, for export
+}
+
+impl ZoneFill {
+    pub fn to_shapes(&self) -> Vec<PrimShape> {
+        self.rectangles
+            .iter()
+            .map(|rectangle| PrimShape::Rectangle(rectangle.clone()))
+            .collect()
+    }
+
+    /// drawn fainter than the traces of the layer, so the traces stay visible on top
+    pub fn to_renderables(&self, alpha: f32) -> RenderableBatch {
+        let color = LAYER_TO_TRACE_COLOR[self.layer].to_float4(alpha / 4.0);
+        RenderableBatch(
+            self.to_shapes()
+                .into_iter()
+                .map(|shape| ShapeRenderable { shape, color })
+                .collect(),
+        )
+    }
+}
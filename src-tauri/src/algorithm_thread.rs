@@ -1,11 +1,12 @@
 use std::{collections::HashMap, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, thread::JoinHandle, time::Instant};
 
 use parser::{parse_end_to_end::{parse_start_to_dsn_struct, parse_struct_to_end}, write_ses::write_ses_to_string};
-use router::{display_injection::DisplayInjection, pcb_problem_solve::solve_pcb_problem};
-use shared::{color_float3::ColorFloat3, hyperparameters::{NUM_BAYESIAN_PATH_FINDING_CALLS, NUM_NAIVE_PATH_FINDING_CALLS}, pcb_problem::{NetName, PcbProblem}, pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable}, prim_shape::PrimShape};
+use router::{display_injection::DisplayInjection, pcb_problem_solve::{resume_pcb_problem, solve_pcb_problem, solve_pcb_problem_with_checkpoints, SolverAlgorithm}, solve_budget::SolveBudget};
+use shared::{color_float3::ColorFloat3, hyperparameters::{NUM_BAYESIAN_PATH_FINDING_CALLS, NUM_NAIVE_PATH_FINDING_CALLS, NUM_NEGOTIATED_PATH_FINDING_CALLS}, pcb_problem::{NetName, PcbProblem}, pcb_render_model::{PcbRenderModel, RenderableBatch, ShapeRenderable}, prim_shape::PrimShape};
 use tauri::{AppHandle, Emitter};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
 
-use crate::{global::{SES_STRING, SUBMIT_RENDER_MODEL_CV, SUBMIT_RENDER_MODEL_MUTEX, TIME_ELAPSED}, submit_pcb_render_model::{self, block_until_signal, can_submit_render_model, submit_render_model}};
+use crate::{global::{ParetoEntry, PARETO_FRONT, SES_STRING, SUBMIT_RENDER_MODEL_CV, SUBMIT_RENDER_MODEL_MUTEX, TIME_ELAPSED}, submit_pcb_render_model::{self, block_until_signal, can_submit_render_model, submit_render_model}};
 
 
 
@@ -96,6 +97,7 @@ pub fn algorithm_thread(
         let mut ses_string = SES_STRING.lock().unwrap();
         *ses_string = None; // Clear previous SES string
     }
+    PARETO_FRONT.lock().unwrap().clear();
     // pcb_problem.num_layers = 1; // Set to 1 for single layer PCB
     
     let can_submit_render_model_closure = ||{
@@ -112,9 +114,12 @@ pub fn algorithm_thread(
         block_until_signal: Box::new(block_until_signal_closure),
         submit_render_model: Box::new(submit_pcb_render_model_closure),
         stop_requested: stop_requested.clone(),
+        budget: Arc::new(SolveBudget::start()), // the solve starts its own
+        checkpointing: None, // the solves with checkpoints set it
     };
     NUM_BAYESIAN_PATH_FINDING_CALLS.store(0, Ordering::Relaxed);
     NUM_NAIVE_PATH_FINDING_CALLS.store(0, Ordering::Relaxed);
+    NUM_NEGOTIATED_PATH_FINDING_CALLS.store(0, Ordering::Relaxed);
     let app_handle = {
         let app_handle = crate::global::APP_HANDLE.lock().unwrap();
         app_handle.clone().unwrap()
@@ -129,12 +134,50 @@ pub fn algorithm_thread(
    
     let start = Instant::now();
     let use_bayesian = crate::global::USE_BAYESIAN.load(Ordering::Relaxed);
-    
+    let solver_algorithm = crate::global::SOLVER_ALGORITHM.load(Ordering::Relaxed);
+    let algorithm = SolverAlgorithm::from_setting(solver_algorithm, use_bayesian);
 
-    let result = solve_pcb_problem(&pcb_problem, use_bayesian, &mut display_injection);
+    // a board opened from disk keeps the checkpoint of its search next to it, the demo boards are not checkpointed
+    let checkpoint_path = file_path.with_extension("checkpoint.json");
+    let resume = file_path.is_file()
+        && checkpoint_path.is_file()
+        && app_handle
+            .dialog()
+            .message("An unfinished search of this board was found. Resume it, or start over?")
+            .title("Resume search")
+            .buttons(MessageDialogButtons::OkCancelCustom("Resume".to_string(), "Start over".to_string()))
+            .blocking_show();
+    let result = if resume {
+        resume_pcb_problem(&pcb_problem, &checkpoint_path, &mut display_injection)
+    } else if file_path.is_file() {
+        solve_pcb_problem_with_checkpoints(&pcb_problem, algorithm, &checkpoint_path, &mut display_injection)
+    } else {
+        solve_pcb_problem(&pcb_problem, algorithm, &mut display_injection)
+    };
     let result = match result {
+        Ok(result) if !result.unrouted_connections.is_empty() => {
+            let num_connections = result.determined_traces.len() + result.unrouted_connections.len();
+            println!(
+                "PCB problem partially solved with seed {}, {} connections are unrouted:",
+                result.seed,
+                result.unrouted_connections.len()
+            );
+            for unrouted_connection in result.unrouted_connections.iter() {
+                println!(
+                    "\t{:?} of net {}: {:?}",
+                    unrouted_connection.connection_id, unrouted_connection.net_name.0, unrouted_connection.reason
+                );
+            }
+            let hint = format!(
+                "Routed {} of {} connections, the partial routes can be saved",
+                result.determined_traces.len(),
+                num_connections
+            );
+            app_handle.emit("string-event", ("hint-message".to_string(), hint)).unwrap();
+            result
+        }
         Ok(result) => {
-            println!("PCB problem solved successfully");
+            println!("PCB problem solved successfully with seed {}", result.seed);
             app_handle.emit("string-event", ("hint-message".to_string(), "PCB problem solved successfully".to_string())).unwrap();
             result
         }
@@ -172,6 +215,11 @@ pub fn algorithm_thread(
         let mut num_vias_lock = crate::global::NUM_VIAS.lock().unwrap();
         *num_vias_lock = num_vias;
     }
+    let max_diff_pair_skew = result.diff_pair_skews.values().cloned().fold(0.0, f64::max);
+    {
+        let mut max_diff_pair_skew_lock = crate::global::MAX_DIFF_PAIR_SKEW.lock().unwrap();
+        *max_diff_pair_skew_lock = max_diff_pair_skew;
+    }
    
 
     let ses_string = match write_ses_to_string(&dsn_struct, &result){
@@ -192,6 +240,22 @@ pub fn algorithm_thread(
         let mut ses_string_lock = SES_STRING.lock().unwrap();
         *ses_string_lock = Some(ses_string);
     }
+    // every solution of the front gets its own session, so the one picked on the stats page can be saved
+    let mut pareto_front: Vec<ParetoEntry> = Vec::new();
+    for pareto_solution in result.pareto_front.iter() {
+        match write_ses_to_string(&dsn_struct, &pareto_solution.solution) {
+            Ok(ses_string) => pareto_front.push(ParetoEntry {
+                objectives: pareto_solution.objectives,
+                max_diff_pair_skew: pareto_solution.solution.diff_pair_skews.values().cloned().fold(0.0, f64::max),
+                ses_string,
+            }),
+            Err(e) => println!("Failed to write SES file of a Pareto solution: {}", e),
+        }
+    }
+    if !pareto_front.is_empty() {
+        println!("Kept {} solutions of the Pareto front", pareto_front.len());
+    }
+    *PARETO_FRONT.lock().unwrap() = pareto_front;
     println!("Auto routing work completed, exiting");
     cleanup();
 }
\ No newline at end of file
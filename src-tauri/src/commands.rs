@@ -7,7 +7,7 @@ use shared::{my_result::MyResult, settings_enum::SettingsEnum};
 use tauri::Emitter;
 use tauri_plugin_dialog::{DialogExt, FilePath};
 
-use crate::global::{APP_HANDLE, COMMAND_CV, NUM_VIAS, SES_STRING, TIME_ELAPSED, TOTAL_LENGTH, USE_BAYESIAN};
+use crate::global::{APP_HANDLE, COMMAND_CV, MAX_DIFF_PAIR_SKEW, NUM_VIAS, PARETO_FRONT, SES_STRING, TIME_ELAPSED, TOTAL_LENGTH, SOLVER_ALGORITHM, USE_BAYESIAN};
 use crate::handle_file_open;
 
 
@@ -135,6 +135,58 @@ pub fn get_settings(setting: &str) -> SettingsEnum{
             let use_bayesian = USE_BAYESIAN.load(Ordering::Relaxed);
             SettingsEnum::Bool(use_bayesian)
         },
+        "solver_algorithm" => {
+            let solver_algorithm = SOLVER_ALGORITHM.load(Ordering::Relaxed);
+            SettingsEnum::Usize(solver_algorithm)
+        },
+        "exact_selection_max_nodes" => {
+            let exact_selection_max_nodes = EXACT_SELECTION_MAX_NODES.load(Ordering::Relaxed);
+            SettingsEnum::Usize(exact_selection_max_nodes)
+        },
+        "exact_selection_extra_rounds" => {
+            let exact_selection_extra_rounds = EXACT_SELECTION_EXTRA_ROUNDS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(exact_selection_extra_rounds)
+        },
+        "mcts_iterations" => {
+            let mcts_iterations = MCTS_ITERATIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(mcts_iterations)
+        },
+        "mcts_exploration" => {
+            let mcts_exploration = MCTS_EXPLORATION.load(Ordering::Relaxed);
+            SettingsEnum::Float(mcts_exploration)
+        },
+        "mcts_rollout_randomness" => {
+            let mcts_rollout_randomness = MCTS_ROLLOUT_RANDOMNESS.load(Ordering::Relaxed);
+            SettingsEnum::Float(mcts_rollout_randomness)
+        },
+        "use_annealing" => {
+            let use_annealing = USE_ANNEALING.load(Ordering::Relaxed);
+            SettingsEnum::Bool(use_annealing)
+        },
+        "annealing_iterations" => {
+            let annealing_iterations = ANNEALING_ITERATIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(annealing_iterations)
+        },
+        "annealing_time_limit" => {
+            let annealing_time_limit = ANNEALING_TIME_LIMIT.load(Ordering::Relaxed);
+            SettingsEnum::Float(annealing_time_limit)
+        },
+        "annealing_start_temperature" => {
+            let annealing_start_temperature = ANNEALING_START_TEMPERATURE.load(Ordering::Relaxed);
+            SettingsEnum::Float(annealing_start_temperature)
+        },
+        "annealing_cooling" => {
+            let annealing_cooling = ANNEALING_COOLING.load(Ordering::Relaxed);
+            SettingsEnum::Float(annealing_cooling)
+        },
+        "annealing_max_ripup" => {
+            let annealing_max_ripup = ANNEALING_MAX_RIPUP.load(Ordering::Relaxed);
+            SettingsEnum::Usize(annealing_max_ripup)
+        },
+        "use_pareto_front" => {
+            let use_pareto_front = USE_PARETO_FRONT.load(Ordering::Relaxed);
+            SettingsEnum::Bool(use_pareto_front)
+        },
         "astar_max_expansions" => {
             let astar_max_expansions = ASTAR_MAX_EXPANSIONS.load(Ordering::Relaxed);
             SettingsEnum::Usize(astar_max_expansions)
@@ -150,42 +202,224 @@ pub fn get_settings(setting: &str) -> SettingsEnum{
             let half_probability_raw_score = HALF_PROBABILITY_RAW_SCORE.load(Ordering::Relaxed);
             SettingsEnum::Float(half_probability_raw_score)
         },
+        "score_length_weight" => {
+            let score_length_weight = SCORE_LENGTH_WEIGHT.load(Ordering::Relaxed);
+            SettingsEnum::Float(score_length_weight)
+        },
+        "score_via_weight" => {
+            let score_via_weight = SCORE_VIA_WEIGHT.load(Ordering::Relaxed);
+            SettingsEnum::Float(score_via_weight)
+        },
+        "score_bend_weight" => {
+            let score_bend_weight = SCORE_BEND_WEIGHT.load(Ordering::Relaxed);
+            SettingsEnum::Float(score_bend_weight)
+        },
+        "score_clearance_weight" => {
+            let score_clearance_weight = SCORE_CLEARANCE_WEIGHT.load(Ordering::Relaxed);
+            SettingsEnum::Float(score_clearance_weight)
+        },
+        "score_clearance_margin" => {
+            let score_clearance_margin = SCORE_CLEARANCE_MARGIN.load(Ordering::Relaxed);
+            SettingsEnum::Float(score_clearance_margin)
+        },
         "opportunity_cost_halved" => {
             let half_probability_opportunity_cost = HALF_PROBABILITY_OPPORTUNITY_COST.load(Ordering::Relaxed);
             SettingsEnum::Float(half_probability_opportunity_cost)
         },
+        "inference_engine" => {
+            let inference_engine = INFERENCE_ENGINE.load(Ordering::Relaxed);
+            SettingsEnum::Usize(inference_engine)
+        },
+        "inference_max_iterations" => {
+            let inference_max_iterations = INFERENCE_MAX_ITERATIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(inference_max_iterations)
+        },
+        "inference_tolerance" => {
+            let inference_tolerance = INFERENCE_TOLERANCE.load(Ordering::Relaxed);
+            SettingsEnum::Float(inference_tolerance)
+        },
+        "fixed_point_iterations" => {
+            let fixed_point_iterations = FIXED_POINT_ITERATIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(fixed_point_iterations)
+        },
         "max_trace_generation_attempts" => {
             let max_generation_attempts = MAX_GENERATION_ATTEMPTS.load(Ordering::Relaxed);
             SettingsEnum::Usize(max_generation_attempts)
         },
-        "first_iteration_prior_probability" => {
-            let first_iteration_probability = FIRST_ITERATION_PROBABILITY.load(Ordering::Relaxed);
-            SettingsEnum::Float(first_iteration_probability)
+        "diversity_penalty" => {
+            let diversity_penalty = DIVERSITY_PENALTY.load(Ordering::Relaxed);
+            SettingsEnum::Float(diversity_penalty)
+        },
+        "diversity_alternatives" => {
+            let diversity_alternatives = DIVERSITY_ALTERNATIVES.load(Ordering::Relaxed);
+            SettingsEnum::Usize(diversity_alternatives)
         },
-        "second_iteration_prior_probability" => {
-            let second_iteration_probability = SECOND_ITERATION_PROBABILITY.load(Ordering::Relaxed);
-            SettingsEnum::Float(second_iteration_probability)
+        "diversity_min_distance" => {
+            let diversity_min_distance = DIVERSITY_MIN_DISTANCE.load(Ordering::Relaxed);
+            SettingsEnum::Float(diversity_min_distance)
         },
-        "second_iteration_num_traces" => {
-            let second_iteration_num_traces = SECOND_ITERATION_NUM_TRACES.load(Ordering::Relaxed);
-            SettingsEnum::Usize(second_iteration_num_traces)
+        "sampling_schedule" => {
+            let sampling_schedule = {
+                format_sampling_schedule(&SAMPLING_SCHEDULE.lock().unwrap())
+            };
+            SettingsEnum::Text(sampling_schedule)
         },
         "via_cost" => {
             let via_cost = VIA_COST.load(Ordering::Relaxed);
             SettingsEnum::Float(via_cost)
         },
+        "blind_via_cost" => {
+            let blind_via_cost = BLIND_VIA_COST.load(Ordering::Relaxed);
+            SettingsEnum::Float(blind_via_cost)
+        },
+        "buried_via_cost" => {
+            let buried_via_cost = BURIED_VIA_COST.load(Ordering::Relaxed);
+            SettingsEnum::Float(buried_via_cost)
+        },
+        "micro_via_cost" => {
+            let micro_via_cost = MICRO_VIA_COST.load(Ordering::Relaxed);
+            SettingsEnum::Float(micro_via_cost)
+        },
+        "diff_pair_max_uncoupled_length" => {
+            let max_uncoupled_length = DIFF_PAIR_MAX_UNCOUPLED_LENGTH.load(Ordering::Relaxed);
+            SettingsEnum::Float(max_uncoupled_length)
+        },
+        "meander_max_amplitude" => {
+            let meander_max_amplitude = MEANDER_MAX_AMPLITUDE.load(Ordering::Relaxed);
+            SettingsEnum::Float(meander_max_amplitude)
+        },
+        "use_teardrops" => {
+            let use_teardrops = USE_TEARDROPS.load(Ordering::Relaxed);
+            SettingsEnum::Bool(use_teardrops)
+        },
+        "teardrop_length_ratio" => {
+            let teardrop_length_ratio = TEARDROP_LENGTH_RATIO.load(Ordering::Relaxed);
+            SettingsEnum::Float(teardrop_length_ratio)
+        },
+        "teardrop_width_ratio" => {
+            let teardrop_width_ratio = TEARDROP_WIDTH_RATIO.load(Ordering::Relaxed);
+            SettingsEnum::Float(teardrop_width_ratio)
+        },
+        "use_fanout" => {
+            let use_fanout = USE_FANOUT.load(Ordering::Relaxed);
+            SettingsEnum::Bool(use_fanout)
+        },
+        "fanout_max_pitch" => {
+            let fanout_max_pitch = FANOUT_MAX_PITCH.load(Ordering::Relaxed);
+            SettingsEnum::Float(fanout_max_pitch)
+        },
+        "zone_fill_resolution" => {
+            let zone_fill_resolution = ZONE_FILL_RESOLUTION.load(Ordering::Relaxed);
+            SettingsEnum::Float(zone_fill_resolution)
+        },
+        "zone_min_width" => {
+            let zone_min_width = ZONE_MIN_WIDTH.load(Ordering::Relaxed);
+            SettingsEnum::Float(zone_min_width)
+        },
+        "arc_corner_radius" => {
+            let arc_corner_radius = ARC_CORNER_RADIUS.load(Ordering::Relaxed);
+            SettingsEnum::Float(arc_corner_radius)
+        },
+        "shove_max_depth" => {
+            let shove_max_depth = SHOVE_MAX_DEPTH.load(Ordering::Relaxed);
+            SettingsEnum::Usize(shove_max_depth)
+        },
+        "shove_max_distance" => {
+            let shove_max_distance = SHOVE_MAX_DISTANCE.load(Ordering::Relaxed);
+            SettingsEnum::Float(shove_max_distance)
+        },
+        "solve_time_limit" => {
+            let solve_time_limit = SOLVE_TIME_LIMIT.load(Ordering::Relaxed);
+            SettingsEnum::Float(solve_time_limit)
+        },
+        "solve_max_expansions" => {
+            let solve_max_expansions = SOLVE_MAX_EXPANSIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(solve_max_expansions)
+        },
+        "solve_max_backtrack_nodes" => {
+            let solve_max_backtrack_nodes = SOLVE_MAX_BACKTRACK_NODES.load(Ordering::Relaxed);
+            SettingsEnum::Usize(solve_max_backtrack_nodes)
+        },
+        "checkpoint_interval" => {
+            let checkpoint_interval = CHECKPOINT_INTERVAL.load(Ordering::Relaxed);
+            SettingsEnum::Float(checkpoint_interval)
+        },
         "num_top_ranked_to_try" => {
             let num_top_ranked_to_try = NUM_TOP_RANKED_TO_TRY.load(Ordering::Relaxed);
             SettingsEnum::Usize(num_top_ranked_to_try)
         },
-        "sample_iterations" => {
-            let sample_iterations = SAMPLE_ITERATIONS.load(Ordering::Relaxed);
-            SettingsEnum::Usize(sample_iterations)
+        "use_backjumping" => {
+            let use_backjumping = USE_BACKJUMPING.load(Ordering::Relaxed);
+            SettingsEnum::Bool(use_backjumping)
+        },
+        "max_bayesian_backjumps" => {
+            let max_bayesian_backjumps = MAX_BAYESIAN_BACKJUMPS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(max_bayesian_backjumps)
+        },
+        "search_strategy" => {
+            let search_strategy = SEARCH_STRATEGY.load(Ordering::Relaxed);
+            SettingsEnum::Usize(search_strategy)
+        },
+        "search_max_children" => {
+            let search_max_children = SEARCH_MAX_CHILDREN.load(Ordering::Relaxed);
+            SettingsEnum::Usize(search_max_children)
+        },
+        "search_max_nodes" => {
+            let search_max_nodes = SEARCH_MAX_NODES.load(Ordering::Relaxed);
+            SettingsEnum::Usize(search_max_nodes)
+        },
+        "beam_width" => {
+            let beam_width = BEAM_WIDTH.load(Ordering::Relaxed);
+            SettingsEnum::Usize(beam_width)
+        },
+        "lds_max_discrepancies" => {
+            let lds_max_discrepancies = LDS_MAX_DISCREPANCIES.load(Ordering::Relaxed);
+            SettingsEnum::Usize(lds_max_discrepancies)
+        },
+        "schedule_stable_tolerance" => {
+            let schedule_stable_tolerance = SCHEDULE_STABLE_TOLERANCE.load(Ordering::Relaxed);
+            SettingsEnum::Float(schedule_stable_tolerance)
         },
         "update_probability_skip_stride" => {
             let update_proba_skip_stride = UPDATE_PROBA_SKIP_STRIDE.load(Ordering::Relaxed);
             SettingsEnum::Usize(update_proba_skip_stride)
         },
+        "sampling_threads" => {
+            let sampling_threads = SAMPLING_THREADS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(sampling_threads)
+        },
+        "random_seed" => {
+            let random_seed = RANDOM_SEED.load(Ordering::Relaxed);
+            SettingsEnum::Usize(random_seed)
+        },
+        "portfolio_num_seeds" => {
+            let portfolio_num_seeds = PORTFOLIO_NUM_SEEDS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(portfolio_num_seeds)
+        },
+        "portfolio_threads" => {
+            let portfolio_threads = PORTFOLIO_THREADS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(portfolio_threads)
+        },
+        "portfolio_metric" => {
+            let portfolio_metric = PORTFOLIO_METRIC.load(Ordering::Relaxed);
+            SettingsEnum::Usize(portfolio_metric)
+        },
+        "negotiated_congestion_max_iterations" => {
+            let max_iterations = NEGOTIATED_CONGESTION_MAX_ITERATIONS.load(Ordering::Relaxed);
+            SettingsEnum::Usize(max_iterations)
+        },
+        "present_congestion_initial_factor" => {
+            let initial_factor = PRESENT_CONGESTION_INITIAL_FACTOR.load(Ordering::Relaxed);
+            SettingsEnum::Float(initial_factor)
+        },
+        "present_congestion_growth" => {
+            let growth = PRESENT_CONGESTION_GROWTH.load(Ordering::Relaxed);
+            SettingsEnum::Float(growth)
+        },
+        "history_congestion_increment" => {
+            let increment = HISTORY_CONGESTION_INCREMENT.load(Ordering::Relaxed);
+            SettingsEnum::Float(increment)
+        },
         _=>{
             panic!("Unknown setting: {}", setting);
         }
@@ -204,6 +438,110 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
+        "solver_algorithm" => {
+            if let SettingsEnum::Usize(val) = value {
+                SOLVER_ALGORITHM.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "exact_selection_max_nodes" => {
+            if let SettingsEnum::Usize(val) = value {
+                EXACT_SELECTION_MAX_NODES.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "exact_selection_extra_rounds" => {
+            if let SettingsEnum::Usize(val) = value {
+                EXACT_SELECTION_EXTRA_ROUNDS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "mcts_iterations" => {
+            if let SettingsEnum::Usize(val) = value {
+                MCTS_ITERATIONS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "mcts_exploration" => {
+            if let SettingsEnum::Float(val) = value {
+                MCTS_EXPLORATION.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "mcts_rollout_randomness" => {
+            if let SettingsEnum::Float(val) = value {
+                MCTS_ROLLOUT_RANDOMNESS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "use_annealing" => {
+            if let SettingsEnum::Bool(val) = value {
+                USE_ANNEALING.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "annealing_iterations" => {
+            if let SettingsEnum::Usize(val) = value {
+                ANNEALING_ITERATIONS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "annealing_time_limit" => {
+            if let SettingsEnum::Float(val) = value {
+                ANNEALING_TIME_LIMIT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "annealing_start_temperature" => {
+            if let SettingsEnum::Float(val) = value {
+                ANNEALING_START_TEMPERATURE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "annealing_cooling" => {
+            if let SettingsEnum::Float(val) = value {
+                ANNEALING_COOLING.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "annealing_max_ripup" => {
+            if let SettingsEnum::Usize(val) = value {
+                ANNEALING_MAX_RIPUP.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "use_pareto_front" => {
+            if let SettingsEnum::Bool(val) = value {
+                USE_PARETO_FRONT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
         "astar_max_expansions" => {
             if let SettingsEnum::Usize(val) = value {
                 ASTAR_MAX_EXPANSIONS.store(val, Ordering::SeqCst);
@@ -230,6 +568,46 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
+        "score_length_weight" => {
+            if let SettingsEnum::Float(val) = value {
+                SCORE_LENGTH_WEIGHT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "score_via_weight" => {
+            if let SettingsEnum::Float(val) = value {
+                SCORE_VIA_WEIGHT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "score_bend_weight" => {
+            if let SettingsEnum::Float(val) = value {
+                SCORE_BEND_WEIGHT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "score_clearance_weight" => {
+            if let SettingsEnum::Float(val) = value {
+                SCORE_CLEARANCE_WEIGHT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "score_clearance_margin" => {
+            if let SettingsEnum::Float(val) = value {
+                SCORE_CLEARANCE_MARGIN.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
         "opportunity_cost_halved" => {
             if let SettingsEnum::Float(val) = value {
                 HALF_PROBABILITY_OPPORTUNITY_COST.store(val, Ordering::SeqCst);
@@ -238,38 +616,84 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
-        "max_trace_generation_attempts" => {
+        "inference_engine" => {
             if let SettingsEnum::Usize(val) = value {
-                MAX_GENERATION_ATTEMPTS.store(val, Ordering::SeqCst);
+                INFERENCE_ENGINE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "inference_max_iterations" => {
+            if let SettingsEnum::Usize(val) = value {
+                INFERENCE_MAX_ITERATIONS.store(val, Ordering::SeqCst);
                 MyResult::Ok(())
             } else {
                 MyResult::Err("Invalid value type".into())
             }
         },
-        "first_iteration_prior_probability" => {
+        "inference_tolerance" => {
             if let SettingsEnum::Float(val) = value {
-                FIRST_ITERATION_PROBABILITY.store(val, Ordering::SeqCst);
+                INFERENCE_TOLERANCE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "fixed_point_iterations" => {
+            if let SettingsEnum::Usize(val) = value {
+                FIXED_POINT_ITERATIONS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "max_trace_generation_attempts" => {
+            if let SettingsEnum::Usize(val) = value {
+                MAX_GENERATION_ATTEMPTS.store(val, Ordering::SeqCst);
                 MyResult::Ok(())
             } else {
                 MyResult::Err("Invalid value type".into())
             }
         },
-        "second_iteration_prior_probability" => {
+        "diversity_penalty" => {
             if let SettingsEnum::Float(val) = value {
-                SECOND_ITERATION_PROBABILITY.store(val, Ordering::SeqCst);
+                DIVERSITY_PENALTY.store(val, Ordering::SeqCst);
                 MyResult::Ok(())
             } else {
                 MyResult::Err("Invalid value type".into())
             }
         },
-        "second_iteration_num_traces" => {
+        "diversity_alternatives" => {
             if let SettingsEnum::Usize(val) = value {
-                SECOND_ITERATION_NUM_TRACES.store(val, Ordering::SeqCst);
+                DIVERSITY_ALTERNATIVES.store(val, Ordering::SeqCst);
                 MyResult::Ok(())
             } else {
                 MyResult::Err("Invalid value type".into())
             }
         },
+        "diversity_min_distance" => {
+            if let SettingsEnum::Float(val) = value {
+                DIVERSITY_MIN_DISTANCE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "sampling_schedule" => {
+            if let SettingsEnum::Text(val) = value {
+                match parse_sampling_schedule(&val) {
+                    Ok(new_sampling_schedule) => {
+                        let mut sampling_schedule = SAMPLING_SCHEDULE.lock().unwrap();
+                        *sampling_schedule = new_sampling_schedule;
+                        MyResult::Ok(())
+                    },
+                    Err(e) => MyResult::Err(e),
+                }
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
         "via_cost" => {
             if let SettingsEnum::Float(val) = value {
                 VIA_COST.store(val, Ordering::SeqCst);
@@ -278,6 +702,158 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
+        "blind_via_cost" => {
+            if let SettingsEnum::Float(val) = value {
+                BLIND_VIA_COST.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "buried_via_cost" => {
+            if let SettingsEnum::Float(val) = value {
+                BURIED_VIA_COST.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "micro_via_cost" => {
+            if let SettingsEnum::Float(val) = value {
+                MICRO_VIA_COST.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "diff_pair_max_uncoupled_length" => {
+            if let SettingsEnum::Float(val) = value {
+                DIFF_PAIR_MAX_UNCOUPLED_LENGTH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "meander_max_amplitude" => {
+            if let SettingsEnum::Float(val) = value {
+                MEANDER_MAX_AMPLITUDE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "use_teardrops" => {
+            if let SettingsEnum::Bool(val) = value {
+                USE_TEARDROPS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "teardrop_length_ratio" => {
+            if let SettingsEnum::Float(val) = value {
+                TEARDROP_LENGTH_RATIO.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "teardrop_width_ratio" => {
+            if let SettingsEnum::Float(val) = value {
+                TEARDROP_WIDTH_RATIO.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "use_fanout" => {
+            if let SettingsEnum::Bool(val) = value {
+                USE_FANOUT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "fanout_max_pitch" => {
+            if let SettingsEnum::Float(val) = value {
+                FANOUT_MAX_PITCH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "zone_fill_resolution" => {
+            if let SettingsEnum::Float(val) = value {
+                ZONE_FILL_RESOLUTION.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "zone_min_width" => {
+            if let SettingsEnum::Float(val) = value {
+                ZONE_MIN_WIDTH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "arc_corner_radius" => {
+            if let SettingsEnum::Float(val) = value {
+                ARC_CORNER_RADIUS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "shove_max_depth" => {
+            if let SettingsEnum::Usize(val) = value {
+                SHOVE_MAX_DEPTH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "shove_max_distance" => {
+            if let SettingsEnum::Float(val) = value {
+                SHOVE_MAX_DISTANCE.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "solve_time_limit" => {
+            if let SettingsEnum::Float(val) = value {
+                SOLVE_TIME_LIMIT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "solve_max_expansions" => {
+            if let SettingsEnum::Usize(val) = value {
+                SOLVE_MAX_EXPANSIONS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "solve_max_backtrack_nodes" => {
+            if let SettingsEnum::Usize(val) = value {
+                SOLVE_MAX_BACKTRACK_NODES.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "checkpoint_interval" => {
+            if let SettingsEnum::Float(val) = value {
+                CHECKPOINT_INTERVAL.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
         "num_top_ranked_to_try" => {
             if let SettingsEnum::Usize(val) = value {
                 NUM_TOP_RANKED_TO_TRY.store(val, Ordering::SeqCst);
@@ -286,9 +862,65 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
-        "sample_iterations" => {
+        "use_backjumping" => {
+            if let SettingsEnum::Bool(val) = value {
+                USE_BACKJUMPING.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "max_bayesian_backjumps" => {
+            if let SettingsEnum::Usize(val) = value {
+                MAX_BAYESIAN_BACKJUMPS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "search_strategy" => {
+            if let SettingsEnum::Usize(val) = value {
+                SEARCH_STRATEGY.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "search_max_children" => {
+            if let SettingsEnum::Usize(val) = value {
+                SEARCH_MAX_CHILDREN.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "search_max_nodes" => {
+            if let SettingsEnum::Usize(val) = value {
+                SEARCH_MAX_NODES.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "beam_width" => {
             if let SettingsEnum::Usize(val) = value {
-                SAMPLE_ITERATIONS.store(val, Ordering::SeqCst);
+                BEAM_WIDTH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "lds_max_discrepancies" => {
+            if let SettingsEnum::Usize(val) = value {
+                LDS_MAX_DISCREPANCIES.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "schedule_stable_tolerance" => {
+            if let SettingsEnum::Float(val) = value {
+                SCHEDULE_STABLE_TOLERANCE.store(val, Ordering::SeqCst);
                 MyResult::Ok(())
             } else {
                 MyResult::Err("Invalid value type".into())
@@ -302,6 +934,78 @@ pub fn set_settings(setting: &str, value: SettingsEnum) -> MyResult<(),String>{
                 MyResult::Err("Invalid value type".into())
             }
         },
+        "sampling_threads" => {
+            if let SettingsEnum::Usize(val) = value {
+                SAMPLING_THREADS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "random_seed" => {
+            if let SettingsEnum::Usize(val) = value {
+                RANDOM_SEED.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "portfolio_num_seeds" => {
+            if let SettingsEnum::Usize(val) = value {
+                PORTFOLIO_NUM_SEEDS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "portfolio_threads" => {
+            if let SettingsEnum::Usize(val) = value {
+                PORTFOLIO_THREADS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "portfolio_metric" => {
+            if let SettingsEnum::Usize(val) = value {
+                PORTFOLIO_METRIC.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "negotiated_congestion_max_iterations" => {
+            if let SettingsEnum::Usize(val) = value {
+                NEGOTIATED_CONGESTION_MAX_ITERATIONS.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "present_congestion_initial_factor" => {
+            if let SettingsEnum::Float(val) = value {
+                PRESENT_CONGESTION_INITIAL_FACTOR.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "present_congestion_growth" => {
+            if let SettingsEnum::Float(val) = value {
+                PRESENT_CONGESTION_GROWTH.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
+        "history_congestion_increment" => {
+            if let SettingsEnum::Float(val) = value {
+                HISTORY_CONGESTION_INCREMENT.store(val, Ordering::SeqCst);
+                MyResult::Ok(())
+            } else {
+                MyResult::Err("Invalid value type".into())
+            }
+        },
         _=>{
             MyResult::Err(format!("Unknown setting: {}", setting))
         }
@@ -324,6 +1028,10 @@ pub fn get_stats(stat: &str) -> StatsEnum{
             let time_elapsed = TIME_ELAPSED.lock().unwrap().clone();
             StatsEnum::Float(time_elapsed)
         },
+        "max_diff_pair_skew" => {
+            let max_diff_pair_skew = MAX_DIFF_PAIR_SKEW.lock().unwrap().clone();
+            StatsEnum::Float(max_diff_pair_skew)
+        },
         "num_bayesian_path_finding_calls" => {
             let num_bayesian_path_finding_calls = NUM_BAYESIAN_PATH_FINDING_CALLS.load(Ordering::Relaxed);
             StatsEnum::Usize(num_bayesian_path_finding_calls)
@@ -332,10 +1040,32 @@ pub fn get_stats(stat: &str) -> StatsEnum{
             let num_naive_path_finding_calls = NUM_NAIVE_PATH_FINDING_CALLS.load(Ordering::Relaxed);
             StatsEnum::Usize(num_naive_path_finding_calls)
         },
+        "num_negotiated_path_finding_calls" => {
+            let num_negotiated_path_finding_calls = NUM_NEGOTIATED_PATH_FINDING_CALLS.load(Ordering::Relaxed);
+            StatsEnum::Usize(num_negotiated_path_finding_calls)
+        },
+        "pareto_front" => {
+            let pareto_front = PARETO_FRONT.lock().unwrap().iter().map(|entry| entry.objectives).collect();
+            StatsEnum::ParetoFront(pareto_front)
+        },
         _ => panic!("Unknown stat: {}", stat),
     }
 }
 
+/// makes a solution of the Pareto front the one that is saved, and shows its length, vias and skew in the stats
+#[tauri::command]
+pub fn select_pareto_solution(index: usize) -> MyResult<(), String> {
+    let pareto_front = PARETO_FRONT.lock().unwrap();
+    let Some(entry) = pareto_front.get(index) else {
+        return MyResult::Err(format!("No solution {} in the Pareto front", index));
+    };
+    *SES_STRING.lock().unwrap() = Some(entry.ses_string.clone());
+    *TOTAL_LENGTH.lock().unwrap() = entry.objectives.total_length;
+    *NUM_VIAS.lock().unwrap() = entry.objectives.num_vias;
+    *MAX_DIFF_PAIR_SKEW.lock().unwrap() = entry.max_diff_pair_skew;
+    MyResult::Ok(())
+}
+
 #[tauri::command]
 pub fn open_file()->MyResult<(), String>{
     handle_file_open::open_file(None)
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, sync::{atomic::{AtomicBool, AtomicUsize}, Condvar, Mutex}, thread::JoinHandle};
 
 use lazy_static::lazy_static;
+use shared::pareto::SolutionObjectives;
 use tauri::AppHandle;
 
 use crate::algorithm_thread::AlgorithmThreadHandle;
@@ -11,6 +12,7 @@ pub static SUBMIT_RENDER_MODEL_MUTEX: Mutex<()> = Mutex::new(());
 pub static SUBMIT_RENDER_MODEL_CV: Condvar = Condvar::new();
 
 pub static USE_BAYESIAN: AtomicBool = AtomicBool::new(true);
+pub static SOLVER_ALGORITHM: AtomicUsize = AtomicUsize::new(0); // 0 backtracking by USE_BAYESIAN, 1 negotiated congestion, 2 exact selection (falls back to the Bayesian backtrack), 3 Monte Carlo order
 
 pub static SUBMISSION_INTERVAL_MILLIS: AtomicUsize = AtomicUsize::new(300);
 pub static SES_STRING: Mutex<Option<String>> = Mutex::new(None);
@@ -18,6 +20,15 @@ pub static SES_STRING: Mutex<Option<String>> = Mutex::new(None);
 pub static TOTAL_LENGTH: Mutex<f64> = Mutex::new(0.0);
 pub static NUM_VIAS: Mutex<usize> = Mutex::new(0);
 pub static TIME_ELAPSED: Mutex<f64> = Mutex::new(0.0);
+pub static MAX_DIFF_PAIR_SKEW: Mutex<f64> = Mutex::new(0.0);
+pub static PARETO_FRONT: Mutex<Vec<ParetoEntry>> = Mutex::new(Vec::new()); // empty unless the last solve collected a Pareto front
+
+/// a solution of the Pareto front of the last solve, the one picked on the stats page is the one saved
+pub struct ParetoEntry {
+    pub objectives: SolutionObjectives,
+    pub max_diff_pair_skew: f64,
+    pub ses_string: String,
+}
 
 pub static CLEANUP_EMIT_CALLS: Mutex<Option<Box<dyn Fn(&AppHandle) + Send>>> = Mutex::new(None);
 
@@ -41,6 +41,7 @@ pub fn run() {
             get_settings,
             set_settings,
             get_stats,
+            select_pareto_solution,
             open_file,
         ])
         .on_window_event(|window, window_event| {
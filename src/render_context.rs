@@ -1,5 +1,6 @@
 use std::{cell::RefCell, sync::Arc};
 
+use shared::vec2::FloatVec2;
 use web_sys::HtmlCanvasElement;
 use wgpu::{util::DeviceExt, CompositeAlphaMode, PollType, SurfaceTarget};
 
@@ -335,3 +336,38 @@ pub fn create_line_mesh(device: &wgpu::Device) -> Arc<ShapeMesh> {
     };
     Arc::new(shape_mesh)
 }
+
+/// a convex polygon already in board coordinates, drawn with an identity instance
+pub fn create_polygon_mesh(device: &wgpu::Device, polygon_vertices: &[FloatVec2]) -> Arc<ShapeMesh> {
+    let vertices: Vec<Vertex> = polygon_vertices
+        .iter()
+        .map(|vertex| Vertex {
+            position: [vertex.x, vertex.y, 0.0],
+            tex_coords: [0.0, 0.0],
+            normal: [0.0, 0.0, 1.0],
+        })
+        .collect();
+    // a triangle fan, the polygon is convex
+    let mut indices: Vec<u16> = Vec::new();
+    for i in 1..vertices.len().saturating_sub(1) as u16 {
+        indices.push(0);
+        indices.push(i);
+        indices.push(i + 1);
+    }
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Polygon Vertex Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Polygon Index Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    let shape_mesh = ShapeMesh {
+        vertex_buffer,
+        index_buffer,
+        num_indices: indices.len() as u32,
+    };
+    Arc::new(shape_mesh)
+}
@@ -6,12 +6,12 @@ use std::{
 use cgmath::{Euler, Quaternion};
 use shared::{
     pcb_render_model::{PcbRenderModel, ShapeRenderable},
-    prim_shape::{CircleShape, Line, PrimShape, RectangleShape},
+    prim_shape::{CircleShape, Line, PolygonShape, PrimShape, RectangleShape},
 };
 
 use crate::{
     line_pipeline::LineShapeBatch, orthographic_camera::OrthographicCamera,
-    render_context::RenderContext, shape_instance::ShapeInstance, shape_mesh::ShapeMesh,
+    render_context::{create_polygon_mesh, RenderContext}, shape_instance::ShapeInstance, shape_mesh::ShapeMesh,
     transparent_pipeline::TransparentShapeBatch,
 };
 
@@ -73,7 +73,7 @@ pub fn render_model_to_submissions(
     let rect_mesh = render_context.square_mesh.clone();
     let line_mesh = render_context.line_mesh.clone();
     let (transparent_submissions, line_submissions) =
-        pcb_render_model_to_shape_submissions(&pcb_render_model, circle_mesh, rect_mesh, line_mesh);
+        pcb_render_model_to_shape_submissions(&pcb_render_model, &render_context.device, circle_mesh, rect_mesh, line_mesh);
     // self.transparent_shape_submissions = Some(transparent_submissions);
     // self.line_shape_submissions = Some(line_submissions);
     RenderSubmissions {
@@ -82,8 +82,23 @@ pub fn render_model_to_submissions(
         camera,
     }
 }
+/// a polygon gets a mesh of its own, its vertices are already in place
+fn polygon_instance(color: [f32; 4]) -> ShapeInstance {
+    ShapeInstance {
+        position: [0.0, 0.0, 0.0].into(),
+        rotation: Quaternion::from(Euler::new(
+            cgmath::Deg(0.0),
+            cgmath::Deg(0.0),
+            cgmath::Deg(0.0),
+        )),
+        scale: cgmath::Vector3::new(1.0, 1.0, 1.0),
+        color,
+    }
+}
+
 pub fn pcb_render_model_to_shape_submissions(
     pcb_render_model: &PcbRenderModel,
+    device: &wgpu::Device, // polygon meshes are built on the fly
     circle_mesh: Arc<ShapeMesh>,
     rect_mesh: Arc<ShapeMesh>,
     line_mesh: Arc<ShapeMesh>,
@@ -111,6 +126,7 @@ pub fn pcb_render_model_to_shape_submissions(
     for trace in &pcb_render_model.trace_shape_renderables {
         let mut circle_instances: Vec<ShapeInstance> = Vec::new();
         let mut rect_instances: Vec<ShapeInstance> = Vec::new();
+        let mut polygon_meshes: Vec<(Arc<ShapeMesh>, Vec<ShapeInstance>)> = Vec::new();
         for renderable in &trace.0 {
             let color = renderable.color;
             match &renderable.shape {
@@ -148,6 +164,9 @@ pub fn pcb_render_model_to_shape_submissions(
                     rect_instances.push(rect_instance);
                 }
 
+                PrimShape::Polygon(PolygonShape { vertices }) => {
+                    polygon_meshes.push((create_polygon_mesh(device, vertices), vec![polygon_instance(color)]));
+                }
                 PrimShape::Line(_) => panic!("Line shapes are not supported in trace renderables"),
             }
         }
@@ -158,6 +177,7 @@ pub fn pcb_render_model_to_shape_submissions(
         if !rect_instances.is_empty() {
             batch_contents.push((rect_mesh.clone(), rect_instances));
         }
+        batch_contents.extend(polygon_meshes);
         if !batch_contents.is_empty() {
             let trace_batch = TransparentShapeBatch(batch_contents);
             transparent_submissions.push(trace_batch);
@@ -224,6 +244,11 @@ pub fn pcb_render_model_to_shape_submissions(
                 let line_batch = LineShapeBatch(vec![(line_mesh.clone(), vec![line_instance])]);
                 line_submissions.push(line_batch);
             }
+            PrimShape::Polygon(PolygonShape { vertices }) => {
+                let polygon_batch =
+                    TransparentShapeBatch(vec![(create_polygon_mesh(device, vertices), vec![polygon_instance(color)])]);
+                transparent_submissions.push(polygon_batch);
+            }
         }
     };
     // Add pads
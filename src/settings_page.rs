@@ -10,15 +10,27 @@ use tauri_sys::core::invoke;
 // trace score causing probability halved   f64 >=0.1  recommended 10
 // opportunity cost causing probability halved  f64 >= 0.1 recommended 0.5
 // max trace generation attempts usize >= 1, recommended 4
-// first iteration prior probability f64 > 0.0, recommended 0.5
-// second iteration prior probability f64 > 0.0, recommended 0.4
-// second iteration num traces usize >= 1, recommended 3
+// sampling schedule, comma separated num_traces:prior per round, num_traces >= 1, 0.0 < prior <= 1.0, recommended 1:0.8, 3:0.6
 // via cost (mm) f64 >= 0.0, recommended 5.0
+// blind via cost (mm) f64 >= 0.0, recommended 4.0
+// buried via cost (mm) f64 >= 0.0, recommended 4.0
+// micro via cost (mm) f64 >= 0.0, recommended 3.0
 
 // bayesian inference related:
 // num top ranked to try     usize >= 1, recommended 3
-// sample iterations (1 or 2) usize 1 or 2 recommended 2   
+// schedule stable tolerance f64 >= 0.0, 0 runs every round, recommended 0.01
 // update probability skip stride usize >= 1, recommended 2
+// sampling threads usize >= 0, 0 uses all cores
+// use exact selection bool, takes precedence over bayesian inference and falls back to it
+// exact selection max nodes usize >= 1, recommended 200000
+// exact selection extra rounds usize >= 0, recommended 3
+
+// negotiated congestion related:
+// use negotiated congestion bool, takes precedence over bayesian inference
+// negotiated congestion max iterations usize >= 1, recommended 30
+// present congestion initial factor f64 >= 0.0, recommended 0.5
+// present congestion growth f64 >= 1.0, recommended 1.5
+// history congestion increment f64 >= 0.0, recommended 1.0
 
 #[component]
 pub fn SettingsPage() -> impl IntoView {
@@ -31,15 +43,73 @@ pub fn SettingsPage() -> impl IntoView {
     let (astar_max_expansions, set_astar_max_expansions) = signal::<usize>(0);
     let (astar_stride, set_astar_stride) = signal::<f64>(0.0);
     let (trace_score_halved, set_trace_score_halved) = signal::<f64>(0.0);
+    let (score_length_weight, set_score_length_weight) = signal::<f64>(0.0);
+    let (score_via_weight, set_score_via_weight) = signal::<f64>(0.0);
+    let (score_bend_weight, set_score_bend_weight) = signal::<f64>(0.0);
+    let (score_clearance_weight, set_score_clearance_weight) = signal::<f64>(0.0);
+    let (score_clearance_margin, set_score_clearance_margin) = signal::<f64>(0.0);
     let (opportunity_cost_halved, set_opportunity_cost_halved) = signal::<f64>(0.0);
+    let (inference_engine, set_inference_engine) = signal::<usize>(0);
+    let (inference_max_iterations, set_inference_max_iterations) = signal::<usize>(0);
+    let (inference_tolerance, set_inference_tolerance) = signal::<f64>(0.0);
+    let (fixed_point_iterations, set_fixed_point_iterations) = signal::<usize>(0);
     let (max_trace_generation_attempts, set_max_trace_generation_attempts) = signal::<usize>(0);
-    let (first_iteration_prior_probability, set_first_iteration_prior_probability) = signal::<f64>(0.0);
-    let (second_iteration_prior_probability, set_second_iteration_prior_probability) = signal::<f64>(0.0);
-    let (second_iteration_num_traces, set_second_iteration_num_traces) = signal::<usize>(0);
+    let (diversity_penalty, set_diversity_penalty) = signal::<f64>(0.0);
+    let (diversity_alternatives, set_diversity_alternatives) = signal::<usize>(0);
+    let (diversity_min_distance, set_diversity_min_distance) = signal::<f64>(0.0);
+    let (sampling_schedule, set_sampling_schedule) = signal::<String>(String::new());
     let (via_cost, set_via_cost) = signal::<f64>(0.0);
+    let (blind_via_cost, set_blind_via_cost) = signal::<f64>(0.0);
+    let (buried_via_cost, set_buried_via_cost) = signal::<f64>(0.0);
+    let (micro_via_cost, set_micro_via_cost) = signal::<f64>(0.0);
+    let (diff_pair_max_uncoupled_length, set_diff_pair_max_uncoupled_length) = signal::<f64>(0.0);
+    let (meander_max_amplitude, set_meander_max_amplitude) = signal::<f64>(0.0);
+    let (use_teardrops, set_use_teardrops) = signal(false);
+    let (teardrop_length_ratio, set_teardrop_length_ratio) = signal::<f64>(0.0);
+    let (teardrop_width_ratio, set_teardrop_width_ratio) = signal::<f64>(0.0);
+    let (use_fanout, set_use_fanout) = signal(false);
+    let (fanout_max_pitch, set_fanout_max_pitch) = signal::<f64>(0.0);
+    let (zone_fill_resolution, set_zone_fill_resolution) = signal::<f64>(0.0);
+    let (zone_min_width, set_zone_min_width) = signal::<f64>(0.0);
+    let (arc_corner_radius, set_arc_corner_radius) = signal::<f64>(0.0);
+    let (shove_max_depth, set_shove_max_depth) = signal::<usize>(0);
+    let (shove_max_distance, set_shove_max_distance) = signal::<f64>(0.0);
+    let (solve_time_limit, set_solve_time_limit) = signal::<f64>(0.0);
+    let (solve_max_expansions, set_solve_max_expansions) = signal::<usize>(0);
+    let (solve_max_backtrack_nodes, set_solve_max_backtrack_nodes) = signal::<usize>(0);
+    let (checkpoint_interval, set_checkpoint_interval) = signal::<f64>(0.0);
     let (num_top_ranked_to_try, set_num_top_ranked_to_try) = signal::<usize>(0);
-    let (sample_iterations, set_sample_iterations) = signal::<usize>(0);
+    let (use_backjumping, set_use_backjumping) = signal(false);
+    let (max_bayesian_backjumps, set_max_bayesian_backjumps) = signal::<usize>(0);
+    let (search_strategy, set_search_strategy) = signal::<usize>(0);
+    let (search_max_children, set_search_max_children) = signal::<usize>(0);
+    let (search_max_nodes, set_search_max_nodes) = signal::<usize>(0);
+    let (beam_width, set_beam_width) = signal::<usize>(0);
+    let (lds_max_discrepancies, set_lds_max_discrepancies) = signal::<usize>(0);
+    let (schedule_stable_tolerance, set_schedule_stable_tolerance) = signal::<f64>(0.0);
     let (update_probability_skip_stride, set_update_probability_skip_stride) = signal::<usize>(0);
+    let (sampling_threads, set_sampling_threads) = signal::<usize>(0);
+    let (random_seed, set_random_seed) = signal::<usize>(0);
+    let (portfolio_num_seeds, set_portfolio_num_seeds) = signal::<usize>(0);
+    let (portfolio_threads, set_portfolio_threads) = signal::<usize>(0);
+    let (portfolio_metric, set_portfolio_metric) = signal::<usize>(0);
+    let (solver_algorithm, set_solver_algorithm) = signal::<usize>(0);
+    let (exact_selection_max_nodes, set_exact_selection_max_nodes) = signal::<usize>(0);
+    let (exact_selection_extra_rounds, set_exact_selection_extra_rounds) = signal::<usize>(0);
+    let (mcts_iterations, set_mcts_iterations) = signal::<usize>(0);
+    let (mcts_exploration, set_mcts_exploration) = signal::<f64>(0.0);
+    let (mcts_rollout_randomness, set_mcts_rollout_randomness) = signal::<f64>(0.0);
+    let (use_annealing, set_use_annealing) = signal(false);
+    let (annealing_iterations, set_annealing_iterations) = signal::<usize>(0);
+    let (annealing_time_limit, set_annealing_time_limit) = signal::<f64>(0.0);
+    let (annealing_start_temperature, set_annealing_start_temperature) = signal::<f64>(0.0);
+    let (annealing_cooling, set_annealing_cooling) = signal::<f64>(0.0);
+    let (annealing_max_ripup, set_annealing_max_ripup) = signal::<usize>(0);
+    let (use_pareto_front, set_use_pareto_front) = signal(false);
+    let (negotiated_congestion_max_iterations, set_negotiated_congestion_max_iterations) = signal::<usize>(0);
+    let (present_congestion_initial_factor, set_present_congestion_initial_factor) = signal::<f64>(0.0);
+    let (present_congestion_growth, set_present_congestion_growth) = signal::<f64>(0.0);
+    let (history_congestion_increment, set_history_congestion_increment) = signal::<f64>(0.0);
 
     let on_back_clicked = move |_|{
         let navigate = navigate.clone();
@@ -52,24 +122,140 @@ pub fn SettingsPage() -> impl IntoView {
             web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("trace_score_halved".into(), SettingsEnum::Float(trace_score_halved.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("score_length_weight".into(), SettingsEnum::Float(score_length_weight.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("score_via_weight".into(), SettingsEnum::Float(score_via_weight.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("score_bend_weight".into(), SettingsEnum::Float(score_bend_weight.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("score_clearance_weight".into(), SettingsEnum::Float(score_clearance_weight.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("score_clearance_margin".into(), SettingsEnum::Float(score_clearance_margin.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("opportunity_cost_halved".into(), SettingsEnum::Float(opportunity_cost_halved.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("inference_engine".into(), SettingsEnum::Usize(inference_engine.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("inference_max_iterations".into(), SettingsEnum::Usize(inference_max_iterations.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("inference_tolerance".into(), SettingsEnum::Float(inference_tolerance.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("fixed_point_iterations".into(), SettingsEnum::Usize(fixed_point_iterations.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("max_trace_generation_attempts".into(), SettingsEnum::Usize(max_trace_generation_attempts.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
-            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("first_iteration_prior_probability".into(), SettingsEnum::Float(first_iteration_prior_probability.get_untracked()))).await;
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("diversity_penalty".into(), SettingsEnum::Float(diversity_penalty.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
-            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("second_iteration_prior_probability".into(), SettingsEnum::Float(second_iteration_prior_probability.get_untracked()))).await;
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("diversity_alternatives".into(), SettingsEnum::Usize(diversity_alternatives.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
-            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("second_iteration_num_traces".into(), SettingsEnum::Usize(second_iteration_num_traces.get_untracked()))).await;
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("diversity_min_distance".into(), SettingsEnum::Float(diversity_min_distance.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("sampling_schedule".into(), SettingsEnum::Text(sampling_schedule.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("via_cost".into(), SettingsEnum::Float(via_cost.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("blind_via_cost".into(), SettingsEnum::Float(blind_via_cost.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("buried_via_cost".into(), SettingsEnum::Float(buried_via_cost.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("micro_via_cost".into(), SettingsEnum::Float(micro_via_cost.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("diff_pair_max_uncoupled_length".into(), SettingsEnum::Float(diff_pair_max_uncoupled_length.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("meander_max_amplitude".into(), SettingsEnum::Float(meander_max_amplitude.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("use_teardrops".into(), SettingsEnum::Bool(use_teardrops.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("teardrop_length_ratio".into(), SettingsEnum::Float(teardrop_length_ratio.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("teardrop_width_ratio".into(), SettingsEnum::Float(teardrop_width_ratio.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("use_fanout".into(), SettingsEnum::Bool(use_fanout.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("fanout_max_pitch".into(), SettingsEnum::Float(fanout_max_pitch.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("zone_fill_resolution".into(), SettingsEnum::Float(zone_fill_resolution.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("zone_min_width".into(), SettingsEnum::Float(zone_min_width.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("arc_corner_radius".into(), SettingsEnum::Float(arc_corner_radius.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("shove_max_depth".into(), SettingsEnum::Usize(shove_max_depth.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("shove_max_distance".into(), SettingsEnum::Float(shove_max_distance.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("solve_time_limit".into(), SettingsEnum::Float(solve_time_limit.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("solve_max_expansions".into(), SettingsEnum::Usize(solve_max_expansions.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("solve_max_backtrack_nodes".into(), SettingsEnum::Usize(solve_max_backtrack_nodes.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("checkpoint_interval".into(), SettingsEnum::Float(checkpoint_interval.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("num_top_ranked_to_try".into(), SettingsEnum::Usize(num_top_ranked_to_try.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
-            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("sample_iterations".into(), SettingsEnum::Usize(sample_iterations.get_untracked()))).await;
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("use_backjumping".into(), SettingsEnum::Bool(use_backjumping.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("max_bayesian_backjumps".into(), SettingsEnum::Usize(max_bayesian_backjumps.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("search_strategy".into(), SettingsEnum::Usize(search_strategy.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("search_max_children".into(), SettingsEnum::Usize(search_max_children.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("search_max_nodes".into(), SettingsEnum::Usize(search_max_nodes.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("beam_width".into(), SettingsEnum::Usize(beam_width.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("lds_max_discrepancies".into(), SettingsEnum::Usize(lds_max_discrepancies.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("schedule_stable_tolerance".into(), SettingsEnum::Float(schedule_stable_tolerance.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
             let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("update_probability_skip_stride".into(), SettingsEnum::Usize(update_probability_skip_stride.get_untracked()))).await;
             web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("sampling_threads".into(), SettingsEnum::Usize(sampling_threads.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("random_seed".into(), SettingsEnum::Usize(random_seed.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("portfolio_num_seeds".into(), SettingsEnum::Usize(portfolio_num_seeds.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("portfolio_threads".into(), SettingsEnum::Usize(portfolio_threads.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("portfolio_metric".into(), SettingsEnum::Usize(portfolio_metric.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("solver_algorithm".into(), SettingsEnum::Usize(solver_algorithm.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("exact_selection_max_nodes".into(), SettingsEnum::Usize(exact_selection_max_nodes.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("exact_selection_extra_rounds".into(), SettingsEnum::Usize(exact_selection_extra_rounds.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("mcts_iterations".into(), SettingsEnum::Usize(mcts_iterations.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("mcts_exploration".into(), SettingsEnum::Float(mcts_exploration.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("mcts_rollout_randomness".into(), SettingsEnum::Float(mcts_rollout_randomness.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("use_annealing".into(), SettingsEnum::Bool(use_annealing.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("annealing_iterations".into(), SettingsEnum::Usize(annealing_iterations.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("annealing_time_limit".into(), SettingsEnum::Float(annealing_time_limit.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("annealing_start_temperature".into(), SettingsEnum::Float(annealing_start_temperature.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("annealing_cooling".into(), SettingsEnum::Float(annealing_cooling.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("annealing_max_ripup".into(), SettingsEnum::Usize(annealing_max_ripup.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("use_pareto_front".into(), SettingsEnum::Bool(use_pareto_front.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("negotiated_congestion_max_iterations".into(), SettingsEnum::Usize(negotiated_congestion_max_iterations.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("present_congestion_initial_factor".into(), SettingsEnum::Float(present_congestion_initial_factor.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("present_congestion_growth".into(), SettingsEnum::Float(present_congestion_growth.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
+            let result = invoke::<MyResult<(), String>>("set_settings", SetSettingsArg::new("history_congestion_increment".into(), SettingsEnum::Float(history_congestion_increment.get_untracked()))).await;
+            web_sys::console::log_1(&format!("result: {:?}", result).into());
             navigate("/pcb", Default::default());
         });        
     };
@@ -90,24 +276,140 @@ pub fn SettingsPage() -> impl IntoView {
                 set_astar_stride.set(result.as_float().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("trace_score_halved".into())).await;
                 set_trace_score_halved.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("score_length_weight".into())).await;
+                set_score_length_weight.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("score_via_weight".into())).await;
+                set_score_via_weight.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("score_bend_weight".into())).await;
+                set_score_bend_weight.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("score_clearance_weight".into())).await;
+                set_score_clearance_weight.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("score_clearance_margin".into())).await;
+                set_score_clearance_margin.set(result.as_float().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("opportunity_cost_halved".into())).await;
                 set_opportunity_cost_halved.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("inference_engine".into())).await;
+                set_inference_engine.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("inference_max_iterations".into())).await;
+                set_inference_max_iterations.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("inference_tolerance".into())).await;
+                set_inference_tolerance.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("fixed_point_iterations".into())).await;
+                set_fixed_point_iterations.set(result.as_usize().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("max_trace_generation_attempts".into())).await;
                 set_max_trace_generation_attempts.set(result.as_usize().unwrap());
-                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("first_iteration_prior_probability".into())).await;
-                set_first_iteration_prior_probability.set(result.as_float().unwrap());
-                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("second_iteration_prior_probability".into())).await;
-                set_second_iteration_prior_probability.set(result.as_float().unwrap());
-                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("second_iteration_num_traces".into())).await;
-                set_second_iteration_num_traces.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("diversity_penalty".into())).await;
+                set_diversity_penalty.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("diversity_alternatives".into())).await;
+                set_diversity_alternatives.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("diversity_min_distance".into())).await;
+                set_diversity_min_distance.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("sampling_schedule".into())).await;
+                set_sampling_schedule.set(result.as_text().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("via_cost".into())).await;
                 set_via_cost.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("blind_via_cost".into())).await;
+                set_blind_via_cost.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("buried_via_cost".into())).await;
+                set_buried_via_cost.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("micro_via_cost".into())).await;
+                set_micro_via_cost.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("diff_pair_max_uncoupled_length".into())).await;
+                set_diff_pair_max_uncoupled_length.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("meander_max_amplitude".into())).await;
+                set_meander_max_amplitude.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("use_teardrops".into())).await;
+                set_use_teardrops.set(result.as_bool().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("teardrop_length_ratio".into())).await;
+                set_teardrop_length_ratio.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("teardrop_width_ratio".into())).await;
+                set_teardrop_width_ratio.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("use_fanout".into())).await;
+                set_use_fanout.set(result.as_bool().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("fanout_max_pitch".into())).await;
+                set_fanout_max_pitch.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("zone_fill_resolution".into())).await;
+                set_zone_fill_resolution.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("zone_min_width".into())).await;
+                set_zone_min_width.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("arc_corner_radius".into())).await;
+                set_arc_corner_radius.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("shove_max_depth".into())).await;
+                set_shove_max_depth.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("shove_max_distance".into())).await;
+                set_shove_max_distance.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("solve_time_limit".into())).await;
+                set_solve_time_limit.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("solve_max_expansions".into())).await;
+                set_solve_max_expansions.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("solve_max_backtrack_nodes".into())).await;
+                set_solve_max_backtrack_nodes.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("checkpoint_interval".into())).await;
+                set_checkpoint_interval.set(result.as_float().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("num_top_ranked_to_try".into())).await;
                 set_num_top_ranked_to_try.set(result.as_usize().unwrap());
-                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("sample_iterations".into())).await;
-                set_sample_iterations.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("use_backjumping".into())).await;
+                set_use_backjumping.set(result.as_bool().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("max_bayesian_backjumps".into())).await;
+                set_max_bayesian_backjumps.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("search_strategy".into())).await;
+                set_search_strategy.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("search_max_children".into())).await;
+                set_search_max_children.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("search_max_nodes".into())).await;
+                set_search_max_nodes.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("beam_width".into())).await;
+                set_beam_width.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("lds_max_discrepancies".into())).await;
+                set_lds_max_discrepancies.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("schedule_stable_tolerance".into())).await;
+                set_schedule_stable_tolerance.set(result.as_float().unwrap());
                 let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("update_probability_skip_stride".into())).await;
                 set_update_probability_skip_stride.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("sampling_threads".into())).await;
+                set_sampling_threads.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("random_seed".into())).await;
+                set_random_seed.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("portfolio_num_seeds".into())).await;
+                set_portfolio_num_seeds.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("portfolio_threads".into())).await;
+                set_portfolio_threads.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("portfolio_metric".into())).await;
+                set_portfolio_metric.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("solver_algorithm".into())).await;
+                set_solver_algorithm.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("exact_selection_max_nodes".into())).await;
+                set_exact_selection_max_nodes.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("exact_selection_extra_rounds".into())).await;
+                set_exact_selection_extra_rounds.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("mcts_iterations".into())).await;
+                set_mcts_iterations.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("mcts_exploration".into())).await;
+                set_mcts_exploration.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("mcts_rollout_randomness".into())).await;
+                set_mcts_rollout_randomness.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("use_annealing".into())).await;
+                set_use_annealing.set(result.as_bool().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("annealing_iterations".into())).await;
+                set_annealing_iterations.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("annealing_time_limit".into())).await;
+                set_annealing_time_limit.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("annealing_start_temperature".into())).await;
+                set_annealing_start_temperature.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("annealing_cooling".into())).await;
+                set_annealing_cooling.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("annealing_max_ripup".into())).await;
+                set_annealing_max_ripup.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("use_pareto_front".into())).await;
+                set_use_pareto_front.set(result.as_bool().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("negotiated_congestion_max_iterations".into())).await;
+                set_negotiated_congestion_max_iterations.set(result.as_usize().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("present_congestion_initial_factor".into())).await;
+                set_present_congestion_initial_factor.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("present_congestion_growth".into())).await;
+                set_present_congestion_growth.set(result.as_float().unwrap());
+                let result: SettingsEnum = invoke("get_settings", GetSettingsArg::new("history_congestion_increment".into())).await;
+                set_history_congestion_increment.set(result.as_float().unwrap());
             });
         }
     });
@@ -146,14 +448,32 @@ pub fn SettingsPage() -> impl IntoView {
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "A* Max Expansions"
+                                "Solver Algorithm (0 = backtracking, 1 = negotiated congestion, 2 = exact selection, 3 = Monte Carlo tree search over routing order)"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 3000"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
                             <input
-                                value=astar_max_expansions
+                                value=solver_algorithm
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_astar_max_expansions.set(input.parse().unwrap_or(0));
+                                    set_solver_algorithm.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                max="3"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Exact Selection Max Nodes"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 200000"</label>
+                            <input
+                                value=exact_selection_max_nodes
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_exact_selection_max_nodes.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
                                 min="1"
@@ -163,111 +483,117 @@ pub fn SettingsPage() -> impl IntoView {
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "A* Stride"
+                                "Exact Selection Extra Rounds"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 1.27"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 3"</label>
                             <input
-                                value=astar_stride
+                                value=exact_selection_extra_rounds
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_astar_stride.set(input.parse().unwrap_or(0.0));
+                                    set_exact_selection_extra_rounds.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
-                                step="0.01"
-                                min="0.01"
+                                min="0"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Trace Score Prob. Halved"
+                                "MCTS Iterations"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 10"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 200"</label>
                             <input
-                                value=trace_score_halved
+                                value=mcts_iterations
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_trace_score_halved.set(input.parse().unwrap_or(0.0));
+                                    set_mcts_iterations.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
-                                step="0.01"
-                                min="0.1"
+                                min="1"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Opportunity Cost Prob. Halved"
+                                "MCTS Exploration"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.4"</label>
                             <input
-                                value=opportunity_cost_halved
+                                value=mcts_exploration
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_opportunity_cost_halved.set(input.parse().unwrap_or(0.0));
+                                    set_mcts_exploration.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
+                                min="0.0"
                                 step="0.01"
-                                min="0.1"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Max Trace Generation Attempts"
+                                "MCTS Rollout Randomness"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 4"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.1"</label>
                             <input
-                                value=max_trace_generation_attempts
+                                value=mcts_rollout_randomness
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_max_trace_generation_attempts
-                                        .set(input.parse().unwrap_or(0));
+                                    set_mcts_rollout_randomness.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
-                                min="1"
+                                min="0.0"
+                                step="0.01"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
+                        <label class="flex items-center gap-2">
+                            <input
+                                checked=use_annealing
+                                on:change=move |ev| {
+                                    let input = event_target_checked(&ev);
+                                    set_use_annealing.set(input);
+                                }
+                                type="checkbox"
+                                class="form-checkbox"
+                            />
+                            "Use Annealing"
+                        </label>
+
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "First Iteration Prior Probability"
+                                "Annealing Iterations"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 300"</label>
                             <input
-                                value=first_iteration_prior_probability
+                                value=annealing_iterations
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_first_iteration_prior_probability
-                                        .set(input.parse().unwrap_or(0.0));
+                                    set_annealing_iterations.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
-                                min="0.01"
-                                max="1"
-                                step="0.01"
+                                min="0"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Second Iteration Prior Probability"
+                                "Annealing Time Limit (s, 0 = no limit)"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 0.4"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 30.0"</label>
                             <input
-                                value=second_iteration_prior_probability
+                                value=annealing_time_limit
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_second_iteration_prior_probability
-                                        .set(input.parse().unwrap_or(0.0));
+                                    set_annealing_time_limit.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
-                                min="0.01"
-                                max="1"
+                                min="0.0"
                                 step="0.01"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
@@ -275,55 +601,51 @@ pub fn SettingsPage() -> impl IntoView {
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Second Iteration Num Traces"
+                                "Annealing Start Temperature"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 3"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.0"</label>
                             <input
-                                value=second_iteration_num_traces
+                                value=annealing_start_temperature
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_second_iteration_num_traces.set(input.parse().unwrap_or(0));
+                                    set_annealing_start_temperature.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
-                                min="1"
+                                min="0.0"
+                                step="0.01"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Via Cost (mm)"
+                                "Annealing Cooling"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 5.0"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.99"</label>
                             <input
-                                value=via_cost
+                                value=annealing_cooling
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_via_cost.set(input.parse().unwrap_or(0.0));
+                                    set_annealing_cooling.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
                                 min="0.0"
+                                max="1.0"
                                 step="0.01"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
-                    </div>
-                </div>
 
-                // Bayesian Section
-                <div>
-                    <h2 class="text-xl font-semibold mb-4">Bayesian Inference Options</h2>
-                    <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Num Top Ranked to Try"
+                                "Annealing Max Rip-up"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 3"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 2"</label>
                             <input
-                                value=num_top_ranked_to_try
+                                value=annealing_max_ripup
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_num_top_ranked_to_try.set(input.parse().unwrap_or(0));
+                                    set_annealing_max_ripup.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
                                 min="1"
@@ -331,38 +653,989 @@ pub fn SettingsPage() -> impl IntoView {
                             />
                         </div>
 
+                        <label class="flex items-center gap-2">
+                            <input
+                                checked=use_pareto_front
+                                on:change=move |ev| {
+                                    let input = event_target_checked(&ev);
+                                    set_use_pareto_front.set(input);
+                                }
+                                type="checkbox"
+                                class="form-checkbox"
+                            />
+                            "Keep Pareto Front"
+                        </label>
+
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Sample Iterations (1 or 2)"
+                                "A* Max Expansions"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 2"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 3000"</label>
                             <input
-                                value=sample_iterations
+                                value=astar_max_expansions
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_sample_iterations.set(input.parse().unwrap_or(0));
+                                    set_astar_max_expansions.set(input.parse().unwrap_or(0));
                                 }
                                 type="number"
                                 min="1"
-                                max="2"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
 
                         <div>
                             <label class="block text-sm font-medium text-gray-700">
-                                "Update Prob. Skip Stride"
+                                "A* Stride"
                             </label>
-                            <label class="block text-sm text-gray-500">"Recommended: 2"</label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.27"</label>
                             <input
-                                value=update_probability_skip_stride
+                                value=astar_stride
                                 on:change=move |ev| {
                                     let input = event_target_value(&ev);
-                                    set_update_probability_skip_stride
-                                        .set(input.parse().unwrap_or(0));
+                                    set_astar_stride.set(input.parse().unwrap_or(0.0));
                                 }
                                 type="number"
-                                min="1"
+                                step="0.01"
+                                min="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Trace Score Prob. Halved"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 10"</label>
+                            <input
+                                value=trace_score_halved
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_trace_score_halved.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                step="0.01"
+                                min="0.1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Score: Weight per Unit Length"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1"</label>
+                            <input
+                                value=score_length_weight
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_score_length_weight.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Score: Weight per Via"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=score_via_weight
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_score_via_weight.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Score: Weight per Bend"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=score_bend_weight
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_score_bend_weight.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Score: Weight per Pad within Margin"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=score_clearance_weight
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_score_clearance_weight.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Score: Clearance Margin"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.1"</label>
+                            <input
+                                value=score_clearance_margin
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_score_clearance_margin.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Opportunity Cost Prob. Halved"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <input
+                                value=opportunity_cost_halved
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_opportunity_cost_halved.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                step="0.01"
+                                min="0.1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Inference Engine (0 = fixed point, 1 = belief propagation, 2 = Gibbs, 3 = mean field)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=inference_engine
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_inference_engine.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                max="3"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Inference Max Iterations (Gibbs: sweeps)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 100"</label>
+                            <input
+                                value=inference_max_iterations
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_inference_max_iterations.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Inference Tolerance"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.0001"</label>
+                            <input
+                                value=inference_tolerance
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_inference_tolerance.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.0001"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Fixed Point Iterations"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 10"</label>
+                            <input
+                                value=fixed_point_iterations
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_fixed_point_iterations.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Max Trace Generation Attempts"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 4"</label>
+                            <input
+                                value=max_trace_generation_attempts
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_max_trace_generation_attempts
+                                        .set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Diversity Penalty"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.0"</label>
+                            <input
+                                value=diversity_penalty
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_diversity_penalty.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Diversity Alternatives"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=diversity_alternatives
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_diversity_alternatives.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Diversity Min Distance (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.2"</label>
+                            <input
+                                value=diversity_min_distance
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_diversity_min_distance.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Sampling Schedule (traces:prior per round)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1:0.8, 3:0.6"</label>
+                            <input
+                                value=sampling_schedule
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_sampling_schedule.set(input);
+                                }
+                                type="text"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Via Cost (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 5.0"</label>
+                            <input
+                                value=via_cost
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_via_cost.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Blind Via Cost (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 4.0"</label>
+                            <input
+                                value=blind_via_cost
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_blind_via_cost.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Buried Via Cost (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 4.0"</label>
+                            <input
+                                value=buried_via_cost
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_buried_via_cost.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Micro Via Cost (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 3.0"</label>
+                            <input
+                                value=micro_via_cost
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_micro_via_cost.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Differential Pair Max Uncoupled Length (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 2.0"</label>
+                            <input
+                                value=diff_pair_max_uncoupled_length
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_diff_pair_max_uncoupled_length.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Meander Max Amplitude (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.0"</label>
+                            <input
+                                value=meander_max_amplitude
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_meander_max_amplitude.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <label class="flex items-center gap-2">
+                            <input
+                                checked=use_teardrops
+                                on:change=move |ev| {
+                                    let input = event_target_checked(&ev);
+                                    set_use_teardrops.set(input);
+                                }
+                                type="checkbox"
+                                class="form-checkbox"
+                            />
+                            "Use Teardrops"
+                        </label>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Teardrop Length Ratio"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <input
+                                value=teardrop_length_ratio
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_teardrop_length_ratio.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Teardrop Width Ratio"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.9"</label>
+                            <input
+                                value=teardrop_width_ratio
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_teardrop_width_ratio.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                max="1.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <label class="flex items-center gap-2">
+                            <input
+                                checked=use_fanout
+                                on:change=move |ev| {
+                                    let input = event_target_checked(&ev);
+                                    set_use_fanout.set(input);
+                                }
+                                type="checkbox"
+                                class="form-checkbox"
+                            />
+                            "Use Fanout"
+                        </label>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Fanout Max Pitch (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.0"</label>
+                            <input
+                                value=fanout_max_pitch
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_fanout_max_pitch.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Zone Fill Resolution (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.1"</label>
+                            <input
+                                value=zone_fill_resolution
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_zone_fill_resolution.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Zone Min Width (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.2"</label>
+                            <input
+                                value=zone_min_width
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_zone_min_width.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Arc Corner Radius (mm, 0 = sharp corners)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.0"</label>
+                            <input
+                                value=arc_corner_radius
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_arc_corner_radius.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Shove Max Depth (0 = no shoving)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0, or 2 to shove"</label>
+                            <input
+                                value=shove_max_depth
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_shove_max_depth.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Shove Max Distance (mm)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <input
+                                value=shove_max_distance
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_shove_max_distance.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Solve Time Limit (s, 0 = no limit)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=solve_time_limit
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_solve_time_limit.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Solve Max A* Expansions (0 = no limit)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=solve_max_expansions
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_solve_max_expansions.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Max Backtrack Nodes (0 = no limit)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=solve_max_backtrack_nodes
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_solve_max_backtrack_nodes.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Checkpoint Interval in Seconds (0 = only when stopped)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 60"</label>
+                            <input
+                                value=checkpoint_interval
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_checkpoint_interval.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                    </div>
+                </div>
+
+                // Bayesian Section
+                <div>
+                    <h2 class="text-xl font-semibold mb-4">Bayesian Inference Options</h2>
+                    <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Num Top Ranked to Try"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 3"</label>
+                            <input
+                                value=num_top_ranked_to_try
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_num_top_ranked_to_try.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <label class="flex items-center gap-2">
+                            <input
+                                checked=use_backjumping
+                                on:change=move |ev| {
+                                    let input = event_target_checked(&ev);
+                                    set_use_backjumping.set(input);
+                                }
+                                type="checkbox"
+                                class="form-checkbox"
+                            />
+                            "Use Backjumping"
+                        </label>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Max Bayesian Backjumps"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 20"</label>
+                            <input
+                                value=max_bayesian_backjumps
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_max_bayesian_backjumps.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Search Strategy (0 = depth first, 1 = beam, 2 = best first, 3 = limited discrepancy)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=search_strategy
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_search_strategy.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                max="3"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Search Max Children"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 3"</label>
+                            <input
+                                value=search_max_children
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_search_max_children.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Search Max Nodes"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 2000"</label>
+                            <input
+                                value=search_max_nodes
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_search_max_nodes.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Beam Width"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 4"</label>
+                            <input
+                                value=beam_width
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_beam_width.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "LDS Max Discrepancies"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 2"</label>
+                            <input
+                                value=lds_max_discrepancies
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_lds_max_discrepancies.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Schedule Stable Tolerance"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.01"</label>
+                            <input
+                                value=schedule_stable_tolerance
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_schedule_stable_tolerance.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.001"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Update Prob. Skip Stride"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 2"</label>
+                            <input
+                                value=update_probability_skip_stride
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_update_probability_skip_stride
+                                        .set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Sampling Threads"
+                            </label>
+                            <label class="block text-sm text-gray-500">"0 uses all cores"</label>
+                            <input
+                                value=sampling_threads
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_sampling_threads.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Random Seed"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 42"</label>
+                            <input
+                                value=random_seed
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_random_seed.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Portfolio Seeds (1 = single run)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1"</label>
+                            <input
+                                value=portfolio_num_seeds
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_portfolio_num_seeds.set(input.parse().unwrap_or(1));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Portfolio Threads"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1"</label>
+                            <input
+                                value=portfolio_threads
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_portfolio_threads.set(input.parse().unwrap_or(1));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Portfolio Metric (0 = completion, 1 = length, 2 = vias)"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0"</label>
+                            <input
+                                value=portfolio_metric
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_portfolio_metric.set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="0"
+                                max="2"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+                    </div>
+                </div>
+
+                // Negotiated Congestion Section
+                <div>
+                    <h2 class="text-xl font-semibold mb-4">Negotiated Congestion Options</h2>
+                    <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Max Rip-up Iterations"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 30"</label>
+                            <input
+                                value=negotiated_congestion_max_iterations
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_negotiated_congestion_max_iterations
+                                        .set(input.parse().unwrap_or(0));
+                                }
+                                type="number"
+                                min="1"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Present Congestion Initial Factor"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 0.5"</label>
+                            <input
+                                value=present_congestion_initial_factor
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_present_congestion_initial_factor
+                                        .set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "Present Congestion Growth"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.5"</label>
+                            <input
+                                value=present_congestion_growth
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_present_congestion_growth.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="1.0"
+                                step="0.01"
+                                class="mt-1 block w-full rounded border-gray-300 shadow-sm"
+                            />
+                        </div>
+
+                        <div>
+                            <label class="block text-sm font-medium text-gray-700">
+                                "History Congestion Increment"
+                            </label>
+                            <label class="block text-sm text-gray-500">"Recommended: 1.0"</label>
+                            <input
+                                value=history_congestion_increment
+                                on:change=move |ev| {
+                                    let input = event_target_value(&ev);
+                                    set_history_congestion_increment.set(input.parse().unwrap_or(0.0));
+                                }
+                                type="number"
+                                min="0.0"
+                                step="0.01"
                                 class="mt-1 block w-full rounded border-gray-300 shadow-sm"
                             />
                         </div>
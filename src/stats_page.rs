@@ -1,7 +1,7 @@
 use leptos::{component, prelude::{signal, Effect}, reactive::spawn_local, view, IntoView};
 use leptos::prelude::*;
 use leptos_router::hooks::use_navigate;
-use shared::stats_enum::{StatsArgs, StatsEnum};
+use shared::{my_result::MyResult, pareto::SolutionObjectives, stats_enum::{SelectParetoSolutionArg, StatsArgs, StatsEnum}};
 use tauri_sys::core::invoke;
 
 
@@ -10,8 +10,12 @@ pub fn StatsPage() -> impl IntoView {
     let (total_length, set_total_length) = signal::<f64>(0.0);
     let (num_vias, set_num_vias) = signal::<usize>(0);
     let (time_elapsed, set_time_elapsed) = signal::<f64>(0.0);
+    let (max_diff_pair_skew, set_max_diff_pair_skew) = signal::<f64>(0.0);
     let (num_bayesian_path_finding_calls, set_num_bayesian_path_finding_calls) = signal::<usize>(0);
     let (num_naive_path_finding_calls, set_num_naive_path_finding_calls) = signal::<usize>(0);
+    let (num_negotiated_path_finding_calls, set_num_negotiated_path_finding_calls) = signal::<usize>(0);
+    let (pareto_front, set_pareto_front) = signal::<Vec<SolutionObjectives>>(Vec::new());
+    let (selected_pareto_solution, set_selected_pareto_solution) = signal::<Option<usize>>(None);
     let (initialized, set_initialized) = signal::<bool>(false);
     Effect::new(move || {
         if !initialized.get() {
@@ -24,15 +28,41 @@ pub fn StatsPage() -> impl IntoView {
                 set_num_vias.set(result.as_usize().unwrap_or(0));
                 let result: StatsEnum = invoke("get_stats", StatsArgs::new("time_elapsed".to_string())).await;
                 set_time_elapsed.set(result.as_float().unwrap_or(0.0));
+                let result: StatsEnum = invoke("get_stats", StatsArgs::new("max_diff_pair_skew".to_string())).await;
+                set_max_diff_pair_skew.set(result.as_float().unwrap_or(0.0));
                 let result: StatsEnum = invoke("get_stats", StatsArgs::new("num_bayesian_path_finding_calls".to_string())).await;
                 set_num_bayesian_path_finding_calls.set(result.as_usize().unwrap_or(0));
                 let result: StatsEnum = invoke("get_stats", StatsArgs::new("num_naive_path_finding_calls".to_string())).await;
                 set_num_naive_path_finding_calls.set(result.as_usize().unwrap_or(0));
+                let result: StatsEnum = invoke("get_stats", StatsArgs::new("num_negotiated_path_finding_calls".to_string())).await;
+                set_num_negotiated_path_finding_calls.set(result.as_usize().unwrap_or(0));
+                let result: StatsEnum = invoke("get_stats", StatsArgs::new("pareto_front".to_string())).await;
+                set_pareto_front.set(result.as_pareto_front().unwrap_or_default());
             });
             // Here you would typically fetch the statistics from your application state or context
             // For demonstration, we will just set some dummy values            
         }        
     });
+    // the picked solution is the one saved, and its length, vias and skew replace the ones above
+    let on_use_pareto_solution = move |index: usize| {
+        spawn_local(async move {
+            let result: MyResult<(), String> = invoke("select_pareto_solution", SelectParetoSolutionArg::new(index)).await;
+            match result {
+                MyResult::Ok(_) => {
+                    set_selected_pareto_solution.set(Some(index));
+                    let result: StatsEnum = invoke("get_stats", StatsArgs::new("total_length".to_string())).await;
+                    set_total_length.set(result.as_float().unwrap_or(0.0));
+                    let result: StatsEnum = invoke("get_stats", StatsArgs::new("num_vias".to_string())).await;
+                    set_num_vias.set(result.as_usize().unwrap_or(0));
+                    let result: StatsEnum = invoke("get_stats", StatsArgs::new("max_diff_pair_skew".to_string())).await;
+                    set_max_diff_pair_skew.set(result.as_float().unwrap_or(0.0));
+                },
+                MyResult::Err(e) => {
+                    web_sys::console::error_1(&format!("Failed to select Pareto solution: {}", e).into());
+                }
+            }
+        });
+    };
     let navigate = use_navigate();
     let on_back_clicked = move |_|{
         navigate("/pcb", Default::default());
@@ -45,9 +75,54 @@ pub fn StatsPage() -> impl IntoView {
                 <StatCardF64 label="Total Length (mm)" value=total_length />
                 <StatCardUsize label="Number of Vias" value=num_vias />
                 <StatCardF64 label="Time Elapsed (s)" value=time_elapsed />
+                <StatCardF64 label="Max Differential Pair Skew (mm)" value=max_diff_pair_skew />
                 <StatCardUsize label="Bayesian Pathfinding Calls" value=num_bayesian_path_finding_calls />
                 <StatCardUsize label="Naive Pathfinding Calls" value=num_naive_path_finding_calls />
+                <StatCardUsize label="Negotiated Pathfinding Calls" value=num_negotiated_path_finding_calls />
             </div>
+
+            <Show when=move || !pareto_front.get().is_empty()>
+                <h2 class="text-xl font-bold">"Pareto Front"</h2>
+                <table class="w-full text-left bg-white dark:bg-gray-800 shadow-md rounded-xl">
+                    <thead>
+                        <tr class="text-sm text-gray-500 dark:text-gray-400">
+                            <th class="p-2">"Total Length (mm)"</th>
+                            <th class="p-2">"Vias"</th>
+                            <th class="p-2">"Bends"</th>
+                            <th class="p-2">"Min Clearance Slack (mm)"</th>
+                            <th class="p-2"></th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {move || {
+                            pareto_front
+                                .get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(index, objectives)| {
+                                    view! {
+                                        <tr class="text-gray-900 dark:text-white">
+                                            <td class="p-2">{format!("{:.2}", objectives.total_length)}</td>
+                                            <td class="p-2">{objectives.num_vias}</td>
+                                            <td class="p-2">{objectives.num_bends}</td>
+                                            <td class="p-2">{format!("{:.3}", objectives.min_clearance_slack)}</td>
+                                            <td class="p-2">
+                                                <button
+                                                    on:click=move |_| on_use_pareto_solution(index)
+                                                    disabled=move || selected_pareto_solution.get() == Some(index)
+                                                    class="bg-blue-500 hover:bg-blue-700 disabled:bg-gray-400 text-white font-bold py-1 px-3 rounded"
+                                                >
+                                                    {move || if selected_pareto_solution.get() == Some(index) { "Saved" } else { "Use" }}
+                                                </button>
+                                            </td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()
+                        }}
+                    </tbody>
+                </table>
+            </Show>
             <button
                 on:click=on_back_clicked
                 class="bg-blue-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded mb-4"